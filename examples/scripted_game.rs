@@ -0,0 +1,80 @@
+//! Plays a seeded expert board on its own: whatever the solver can prove next is played, checking safe spaces and
+//! flagging mines, and when nothing is certain the space least likely to be a mine is checked. The game is recorded as a
+//! replay, verified, and printed as JSON. Run with `cargo run --example scripted_game [seed]`.
+
+use minesweeper::engine::{Board, GameState, ManualClock};
+use minesweeper::generation::GenerationOptions;
+use minesweeper::render::{Renderer, TextRenderer};
+use minesweeper::replay::{ActionKind, Replay};
+use minesweeper::solver;
+use std::time::Duration;
+
+fn main() {
+    let seed = std::env::args().nth(1).and_then(|s| s.parse().ok()).unwrap_or(1);
+    let clock = ManualClock::new();
+    // Made the way the game makes boards, with the first check always opening up
+    let mut replay = Replay::new(30, 16, 99, 0, seed, false, false);
+    replay.generation = GenerationOptions { clear_opening: true, ..GenerationOptions::default() };
+    let mut board = replay.new_board(Box::new(clock.clone())).unwrap();
+    let start = (15, 8);
+    replay.generation.generate(&mut board, start);
+    let mut moves = vec![(ActionKind::Reveal, start.0, start.1)];
+    while board.state() == GameState::Starting || board.state() == GameState::Running {
+        if moves.is_empty() {
+            // Flags only ever go on proven mines here, so the solver can take them as read
+            if let Some(deduction) = board.get_solver().trusting_flags().next_deduction() {
+                moves.extend(deduction.mines.into_iter().filter(|&(x, y)| !board.is_flagged(x, y)).map(|(x, y)| (ActionKind::Flag, x, y)));
+                moves.extend(deduction.safe.into_iter().map(|(x, y)| (ActionKind::Reveal, x, y)));
+            }
+        }
+        if moves.is_empty() {
+            match safest(&board) {
+                Some((x, y)) => moves.push((ActionKind::Reveal, x, y)),
+                None => break,
+            }
+        }
+        let (kind, x, y) = moves.remove(0);
+        // Spaces an earlier move in the batch already opened up
+        if board.is_uncovered(x, y) {
+            continue;
+        }
+        // A second a move, so the replay's times are easy to read
+        clock.advance(Duration::from_secs(1));
+        match kind {
+            ActionKind::Flag => drop(board.flag(x, y)),
+            _ => drop(board.reveal(x, y)),
+        }
+        replay.record(kind, x, y, board.elapsed());
+    }
+    replay.finish(board.state(), board.elapsed());
+    TextRenderer::new(std::io::stdout()).draw(&board.visible_board());
+    match replay.verify() {
+        Ok(verification) => println!("Verified: {:?} in {:?} over {} moves", verification.result, verification.time, replay.actions.len()),
+        Err(e) => println!("The replay doesn't hold up: {}", e),
+    }
+    println!("{}", serde_json::to_string(&replay).unwrap());
+}
+
+///
+/// The covered, unflagged space least likely to be a mine. When there are too many ways the mines could lie for
+/// the solver to work the chances out, it's the first such space
+///
+fn safest(board: &Board) -> Option<(i16, i16)> {
+    let chances = match solver::probabilities(board) {
+        Some(chances) => chances,
+        None => {
+            let mut spaces = (0..board.height()).flat_map(|y| (0..board.width()).map(move |x| (x, y)));
+            return spaces.find(|&(x, y)| !board.is_uncovered(x, y) && !board.is_flagged(x, y));
+        }
+    };
+    let mut best: Option<((i16, i16), f64)> = None;
+    for (y, row) in chances.iter().enumerate() {
+        for (x, chance) in row.iter().enumerate() {
+            let space = (x as i16, y as i16);
+            if let Some(chance) = chance.filter(|&chance| !board.is_flagged(space.0, space.1) && best.is_none_or(|(_, lowest)| chance < lowest)) {
+                best = Some((space, chance));
+            }
+        }
+    }
+    best.map(|(space, _)| space)
+}
@@ -0,0 +1,54 @@
+//! A whole frontend in a few lines: the board is drawn as text with its columns and rows numbered, and moves are
+//! typed in as `c x y` to check, `f x y` to flag, or `s x y` to chord. Run with
+//! `cargo run --example text_frontend [seed]`.
+
+use minesweeper::engine::{Board, GameState, SeedRng, VisibleBoard};
+use minesweeper::render::{self, Renderer};
+use std::io::BufRead;
+
+///
+/// Draws the board the way `render::text` does, with the numbers of its columns above and its rows beside it
+///
+struct Numbered;
+
+impl Renderer for Numbered {
+    fn draw(&mut self, board: &VisibleBoard) {
+        let columns: String = (0..board.width).map(|x| (x % 10).to_string()).collect();
+        println!("   {}", columns);
+        for (y, line) in render::text(board).lines().enumerate() {
+            if y < board.height as usize {
+                println!("{:>2} {}", y, line);
+            } else {
+                println!("{}", line);
+            }
+        }
+    }
+}
+
+fn main() {
+    let seed = std::env::args().nth(1).and_then(|s| s.parse().ok()).unwrap_or(1);
+    let mut board = Board::seeded(9, 9, 10, 0, seed, SeedRng::Pcg);
+    let mut renderer = Numbered;
+    renderer.draw(&board.visible_board());
+    for line in std::io::stdin().lock().lines() {
+        let line = line.unwrap();
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let (x, y) = match words.get(1..3).map(|xy| (xy[0].parse::<i16>(), xy[1].parse::<i16>())) {
+            Some((Ok(x), Ok(y))) => (x, y),
+            _ => {
+                println!("Moves are `c x y` to check, `f x y` to flag, or `s x y` to chord");
+                continue;
+            }
+        };
+        match words[0] {
+            "c" => drop(board.reveal(x, y)),
+            "f" => drop(board.flag(x, y)),
+            "s" => drop(board.chord(x, y)),
+            _ => continue,
+        }
+        renderer.draw(&board.visible_board());
+        if board.state() == GameState::Win || board.state() == GameState::Loss {
+            break;
+        }
+    }
+}
@@ -0,0 +1,54 @@
+//! Drives the engine with action sequences decoded from the fuzzer's bytes, on a deterministic board,
+//! checking that nothing panics, every uncovered space is on the board, and the board stays consistent.
+//!
+//! Layout of the input: width, height, mines, gamemode, rules, topology, 8 bytes of seed,
+//! then 3 bytes per action (kind, x, y). Coordinates wrap onto the board.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use minesweeper::engine::{Board, GameState, ManualClock};
+use minesweeper::{rules, topology};
+
+const RULES: [&str; 6] = ["standard", "lives:3", "liar", "multi", "anti", "treasure"];
+const TOPOLOGIES: [&str; 6] = ["square8", "square4", "hex", "knight", "torus", "masked"];
+
+fuzz_target!(|data: &[u8]| {
+    if data.len() < 14 {
+        return;
+    }
+    let width = (data[0] % 30) as i16 + 1;
+    let height = (data[1] % 30) as i16 + 1;
+    let mines = (data[2] as i16) % (width * height);
+    let gamemode = data[3] % 2;
+    let seed = u64::from_le_bytes(data[6..14].try_into().unwrap());
+    let mut board = Board::deterministic(width, height, mines, gamemode, seed, ManualClock::new());
+    board.set_pre_start_flags(data[3] & 2 != 0, data[3] & 4 != 0);
+    board.set_rules(rules::from_name(RULES[data[4] as usize % RULES.len()]).unwrap());
+    board.set_topology(topology::from_name(TOPOLOGIES[data[5] as usize % TOPOLOGIES.len()]).unwrap());
+    assert_eq!(board.debug_validate(), Ok(()));
+
+    for action in data[14..].chunks_exact(3) {
+        let x = action[1] as i16 % width;
+        let y = action[2] as i16 % height;
+        let before = board.state();
+        let uncovered = match action[0] % 3 {
+            0 => board.reveal(x, y),
+            1 => {
+                board.flag(x, y);
+                vec![]
+            }
+            _ => board.chord(x, y),
+        };
+        for space in &uncovered {
+            assert!(space.0 >= 0 && space.0 < width && space.1 >= 0 && space.1 < height, "uncovered {:?} is off the board", space);
+        }
+        if before == GameState::Win || before == GameState::Loss {
+            assert_eq!(board.state(), before);
+            assert!(uncovered.is_empty());
+        }
+        if let Err(e) = board.debug_validate() {
+            panic!("board went bad after {:?} at ({}, {}): {}", action[0] % 3, x, y, e);
+        }
+    }
+});
@@ -0,0 +1,256 @@
+use crate::cast::{self, Frame, Theme};
+use crate::engine::Tile;
+use crate::replay::Replay;
+use crate::topology::{self, Topology};
+
+use gif::{Encoder, Repeat};
+use std::borrow::Cow;
+
+// Pixels per character column; a space is 3 columns wide, and drawn as a square
+const COLUMN: usize = 6;
+const CELL: usize = 3 * COLUMN;
+// Thickness of the border around the board
+const BORDER: usize = 6;
+// Background of uncovered spaces and holes, the terminal's usual black
+const BACKGROUND: [u8; 3] = [12, 12, 12];
+
+// Digits and a minus sign, 3 pixels wide by 5 tall, a row per entry with the leftmost pixel in the highest bit
+const GLYPHS: [[u8; 5]; 11] = [
+    [0b111, 0b101, 0b101, 0b101, 0b111],
+    [0b010, 0b110, 0b010, 0b010, 0b111],
+    [0b111, 0b001, 0b111, 0b100, 0b111],
+    [0b111, 0b001, 0b111, 0b001, 0b111],
+    [0b101, 0b101, 0b111, 0b001, 0b001],
+    [0b111, 0b100, 0b111, 0b001, 0b111],
+    [0b111, 0b100, 0b111, 0b101, 0b111],
+    [0b111, 0b001, 0b010, 0b010, 0b010],
+    [0b111, 0b101, 0b111, 0b101, 0b111],
+    [0b111, 0b101, 0b111, 0b001, 0b111],
+    [0b000, 0b000, 0b111, 0b000, 0b000],
+];
+
+///
+/// The color an SGR code sets, the way a typical terminal shows it. Takes the last color in the code,
+/// so "1;34" is blue. Understands the 16 basic colors and the 256 color and true color forms
+///
+fn sgr_color(code: &str) -> Option<[u8; 3]> {
+    const BASIC: [[u8; 3]; 16] = [
+        [12, 12, 12], [197, 15, 31], [19, 161, 14], [193, 156, 0], [0, 55, 218], [136, 23, 152], [58, 150, 221], [204, 204, 204],
+        [118, 118, 118], [231, 72, 86], [22, 198, 12], [249, 241, 165], [59, 120, 255], [180, 0, 158], [97, 214, 214], [242, 242, 242],
+    ];
+    let parts: Vec<u16> = code.split(';').filter_map(|p| p.trim().parse::<u16>().ok()).collect();
+    let mut color: Option<[u8; 3]> = None;
+    let mut i = 0;
+    while i < parts.len() {
+        match parts[i] {
+            n @ (30..=37 | 40..=47) => color = Some(BASIC[(n % 10) as usize]),
+            n @ (90..=97 | 100..=107) => color = Some(BASIC[(n % 10 + 8) as usize]),
+            38 | 48 => {
+                match parts.get(i + 1) {
+                    Some(5) => {
+                        if let Some(&n) = parts.get(i + 2) {
+                            color = Some(palette_256(n.min(255) as u8, &BASIC));
+                        }
+                        i += 2;
+                    }
+                    Some(2) => {
+                        if let [r, g, b] = parts[(i + 2).min(parts.len())..(i + 5).min(parts.len())] {
+                            color = Some([r.min(255) as u8, g.min(255) as u8, b.min(255) as u8]);
+                        }
+                        i += 4;
+                    }
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    color
+}
+
+///
+/// The xterm 256 color palette: the basic 16, a 6x6x6 color cube, then 24 grays
+///
+fn palette_256(n: u8, basic: &[[u8; 3]; 16]) -> [u8; 3] {
+    if n < 16 {
+        return basic[n as usize];
+    }
+    if n < 232 {
+        let level = |v: u8| if v == 0 { 0 } else { 55 + v * 40 };
+        let n = n - 16;
+        return [level(n / 36), level(n / 6 % 6), level(n % 6)];
+    }
+    let gray = 8 + (n - 232) * 10;
+    [gray, gray, gray]
+}
+
+///
+/// An image being drawn with a handful of colors, which become the GIF's palette
+///
+struct Canvas {
+    width: usize,
+    height: usize,
+    pixels: Vec<u8>,
+    palette: Vec<[u8; 3]>,
+}
+
+impl Canvas {
+    fn new(width: usize, height: usize) -> Canvas {
+        Canvas { width, height, pixels: vec![0; width * height], palette: vec![BACKGROUND] }
+    }
+
+    ///
+    /// Index of a color in the palette, adding it if it's new
+    ///
+    fn index(&mut self, color: [u8; 3]) -> u8 {
+        if let Some(i) = self.palette.iter().position(|&c| c == color) {
+            return i as u8;
+        }
+        // The board only ever uses a few dozen colors, so this never gets near the 256 a GIF allows
+        self.palette.push(color);
+        (self.palette.len() - 1) as u8
+    }
+
+    fn fill(&mut self, x: usize, y: usize, width: usize, height: usize, color: [u8; 3]) {
+        let index = self.index(color);
+        for row in y..(y + height).min(self.height) {
+            for column in x..(x + width).min(self.width) {
+                self.pixels[row * self.width + column] = index;
+            }
+        }
+    }
+
+    ///
+    /// Draws a number centered in the space whose corner is at (x, y), with each glyph pixel 2x2
+    ///
+    fn number(&mut self, x: usize, y: usize, n: i16, color: [u8; 3]) {
+        let text = n.to_string();
+        let width = text.len() * 8 - 2;
+        let left = x + CELL.saturating_sub(width) / 2;
+        let top = y + (CELL - 10) / 2;
+        for (i, c) in text.chars().enumerate() {
+            let glyph = match c.to_digit(10) {
+                Some(d) => GLYPHS[d as usize],
+                None => GLYPHS[10],
+            };
+            for (row, bits) in glyph.iter().enumerate() {
+                for column in 0..3 {
+                    if bits & (0b100 >> column) != 0 {
+                        self.fill(left + i * 8 + column * 2, top + row * 2, 2, 2, color);
+                    }
+                }
+            }
+        }
+    }
+}
+
+///
+/// Draws a frame as an image, laid out like the board in the terminal: one square per space inside a border
+///
+fn render_image(frame: &Frame, topology: &dyn Topology, theme: &Theme) -> Canvas {
+    let board = &frame.board;
+    let inner_width = topology.screen_width(board.width, board.height).max(0) as usize * COLUMN;
+    let mut canvas = Canvas::new(inner_width + 2 * BORDER, board.height as usize * CELL + 2 * BORDER);
+    let color = |code: &str, fallback: [u8; 3]| sgr_color(code).unwrap_or(fallback);
+    let border = color(&theme.border_fg, [204, 204, 204]);
+    let covered = color(&theme.inner_bg, [118, 118, 118]);
+    let flagged = color(&theme.flag_bg, covered);
+    let outline = color(&theme.inner_fg, [204, 204, 204]);
+    let highlight = color(&theme.inner_highlight, [242, 242, 242]);
+    let (width, height) = (canvas.width, canvas.height);
+    canvas.fill(0, 0, width, BORDER, border);
+    canvas.fill(0, height - BORDER, width, BORDER, border);
+    canvas.fill(0, 0, BORDER, height, border);
+    canvas.fill(width - BORDER, 0, BORDER, height, border);
+    for y in 0..board.height {
+        for x in 0..board.width {
+            if !topology.contains(x, y, board.width, board.height) {
+                continue;
+            }
+            let (column, row) = topology.screen_offset(x, y);
+            let left = BORDER + column.max(0) as usize * COLUMN;
+            let top = BORDER + row.max(0) as usize * CELL;
+            let tile = board.tiles[y as usize][x as usize];
+            if tile != Tile::Flagged && frame.mines.contains(&(x, y)) {
+                canvas.fill(left + 1, top + 1, CELL - 2, CELL - 2, sgr_color("100").unwrap());
+                canvas.fill(left + 6, top + 4, CELL - 12, CELL - 8, highlight);
+                canvas.fill(left + 4, top + 6, CELL - 8, CELL - 12, highlight);
+                continue;
+            }
+            match tile {
+                Tile::Covered | Tile::Flagged => {
+                    canvas.fill(left + 1, top + 1, CELL - 2, CELL - 2, outline);
+                    canvas.fill(left + 2, top + 2, CELL - 4, CELL - 4, if tile == Tile::Flagged { flagged } else { covered });
+                    if tile == Tile::Flagged {
+                        // A pole with a pennant off the top of it
+                        canvas.fill(left + 10, top + 4, 2, 10, highlight);
+                        canvas.fill(left + 5, top + 4, 5, 5, highlight);
+                        canvas.fill(left + 6, top + 14, 8, 2, highlight);
+                    }
+                }
+                Tile::Revealed(0) => {}
+                Tile::Revealed(n) => {
+                    // Variant rules can show numbers past 8 and below 0
+                    let code = &theme.m_count_fg[(n.abs().clamp(1, 8) - 1) as usize];
+                    canvas.number(left, top, n, color(code, [242, 242, 242]));
+                }
+            }
+        }
+    }
+    canvas
+}
+
+///
+/// Renders a replay as an animated GIF, a frame per move drawn with the theme's colors. Characters in the theme
+/// aren't used, since spaces are drawn as pictures. `speed` above 1 plays the game back faster than it was played
+///
+pub fn to_gif(replay: &Replay, theme: &Theme, speed: f64) -> Result<Vec<u8>, String> {
+    let topology = match topology::from_name(&replay.topology) {
+        Some(topology) => topology,
+        None => return Err(format!("Unknown topology \"{}\"", replay.topology)),
+    };
+    let frames = cast::frames(replay)?;
+    let images: Vec<Canvas> = frames.iter().map(|frame| render_image(frame, topology.as_ref(), theme)).collect();
+    let (width, height) = match images.first() {
+        Some(image) => (image.width, image.height),
+        None => return Err(String::from("Nothing to draw")),
+    };
+    if width > u16::MAX as usize || height > u16::MAX as usize {
+        return Err(format!("A {}x{} board is too big for a GIF", replay.width, replay.height));
+    }
+    // Every frame shares one palette, so colors are gathered up front
+    let mut palette: Vec<[u8; 3]> = vec![];
+    for image in &images {
+        for &color in &image.palette {
+            if !palette.contains(&color) {
+                palette.push(color);
+            }
+        }
+    }
+    let flat: Vec<u8> = palette.iter().flatten().copied().collect();
+    let mut out: Vec<u8> = vec![];
+    {
+        let mut encoder = Encoder::new(&mut out, width as u16, height as u16, &flat).map_err(|e| e.to_string())?;
+        encoder.set_repeat(Repeat::Infinite).map_err(|e| e.to_string())?;
+        // Delays are in hundredths of a second, rounded off from the start so they don't drift over a long game
+        let centis = |time_ms: u64| (time_ms as f64 / 10. / speed).round() as u64;
+        for (i, image) in images.iter().enumerate() {
+            let delay = match frames.get(i + 1) {
+                Some(next) => centis(next.time_ms) - centis(frames[i].time_ms),
+                None => (cast::HOLD_END * 100.) as u64,
+            };
+            let remap: Vec<u8> = image.palette.iter().map(|c| palette.iter().position(|p| p == c).unwrap() as u8).collect();
+            let buffer: Vec<u8> = image.pixels.iter().map(|&p| remap[p as usize]).collect();
+            let frame = gif::Frame {
+                width: width as u16,
+                height: height as u16,
+                delay: delay.min(u16::MAX as u64) as u16,
+                buffer: Cow::Owned(buffer),
+                ..gif::Frame::default()
+            };
+            encoder.write_frame(&frame).map_err(|e| e.to_string())?;
+        }
+    }
+    Ok(out)
+}
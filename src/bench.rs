@@ -0,0 +1,97 @@
+use crate::logic::MinesweeperGame;
+use crate::term;
+use minesweeper::engine::{Board, ManualClock};
+#[cfg(feature = "solver")]
+use minesweeper::grade;
+
+use std::io::IsTerminal;
+use std::time::{Duration, Instant};
+
+///
+/// Runs `f` `iterations` times and returns the average time taken per run
+///
+fn time_average<F: FnMut(u64)>(iterations: u64, mut f: F) -> Duration {
+    let start = Instant::now();
+    for i in 0..iterations {
+        f(i);
+    }
+    start.elapsed() / iterations as u32
+}
+
+///
+/// Measures the solver and the grader on expert boards after the opening
+///
+#[cfg(feature = "solver")]
+fn bench_solver(results: &mut Vec<(String, Duration)>) {
+    // Solver throughput
+    let mut boards: Vec<Board> = vec![];
+    for seed in 0..20 {
+        let mut board = Board::deterministic(30, 16, 99, 0, seed, ManualClock::new());
+        board.reveal(15, 8);
+        boards.push(board);
+    }
+    let average = time_average(boards.len() as u64, |i| {
+        let solver = boards[i as usize].get_solver();
+        solver.certain_safe();
+        solver.certain_mines();
+    });
+    results.push((String::from("solver certain_safe + certain_mines, expert"), average));
+
+    // Grading from the opening
+    let average = time_average(boards.len() as u64, |i| {
+        grade::grade(&boards[i as usize], (15, 8));
+    });
+    results.push((String::from("grade, expert"), average));
+}
+
+///
+/// Measures board generation, flood fill, the solver, and rendering, then prints a table of the results.
+/// Run with `minesweeper bench`
+///
+pub fn run() {
+    let mut results: Vec<(String, Duration)> = vec![];
+
+    // Board generation at various densities
+    for (width, height) in [(30, 16), (100, 100)] {
+        for density in [0.10, 0.20, 0.30] {
+            let mines = ((width * height) as f32 * density) as i16;
+            let average = time_average(50, |i| {
+                let mut board = Board::deterministic(width, height, mines, 0, i, ManualClock::new());
+                board.generate((0, 0));
+            });
+            results.push((format!("generate {}x{} @ {:.0}%", width, height, density * 100.), average));
+        }
+    }
+
+    // Flood fill across a near-empty board
+    for size in [100, 250] {
+        let average = time_average(20, |i| {
+            let mut board = Board::deterministic(size, size, 1, 0, i, ManualClock::new());
+            board.reveal(0, 0);
+        });
+        results.push((format!("flood fill {}x{}, 1 mine", size, size), average));
+    }
+
+    #[cfg(feature = "solver")]
+    bench_solver(&mut results);
+
+    // Full frame render of a fully uncovered expert board, skipped when piped since it writes escape sequences
+    if std::io::stdout().is_terminal() {
+        let average = MinesweeperGame::bench_render(30, 16, 99, 20);
+        results.push((String::from("render full frame, expert"), average));
+    }
+
+    // Report, as plain lines when piped so the output can be scripted
+    if std::io::stdout().is_terminal() {
+        term::clear();
+        print!("{:<45} {:>12}\r\n", "benchmark", "avg time");
+        for (name, average) in results {
+            print!("{:<45} {:>12.3?}\r\n", name, average);
+        }
+    } else {
+        println!("{:<45} {:>12}", "benchmark", "avg time");
+        for (name, average) in results {
+            println!("{:<45} {:>12.3?}", name, average);
+        }
+    }
+}
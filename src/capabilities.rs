@@ -0,0 +1,121 @@
+use crate::saves::Save;
+use crate::term::{self, Multiplexer};
+use minesweeper::cast::Theme;
+
+use crossterm::{
+    cursor,
+    style,
+    terminal::{self, disable_raw_mode, enable_raw_mode},
+};
+
+use std::io::{IsTerminal, Write};
+
+// Characters whose width gets measured: braille from the overview, and one that's wide in East Asian fonts
+const SAMPLE_CHARS: [(&str, &str); 2] = [("braille (overview)", "⣿"), ("ambiguous width", "±")];
+
+///
+/// Prints what the terminal supports and how the game will draw in it, for tracking down drawing problems.
+/// Run with `minesweeper --capability-report`. Checks that need to ask the terminal are skipped when
+/// stdin or stdout isn't one
+///
+pub(crate) fn report(save: &Save) {
+    let interactive = std::io::stdin().is_terminal() && std::io::stdout().is_terminal();
+    let term_var = std::env::var("TERM").unwrap_or_default();
+    println!("Terminal");
+    println!("  TERM: {}", if term_var.is_empty() { "(not set)" } else { &term_var });
+    match terminal::size() {
+        Ok((columns, rows)) if columns > 0 && rows > 0 => println!("  Size: {}x{}", columns, rows),
+        _ => println!("  Size: unknown"),
+    }
+    println!("  Multiplexer: {}", match term::multiplexer() {
+        Multiplexer::None => "none",
+        Multiplexer::Tmux => "tmux",
+        Multiplexer::Screen => "screen",
+    });
+    let colors = match style::available_color_count() {
+        u16::MAX => String::from("24-bit"),
+        n => n.to_string(),
+    };
+    println!("  Colors: {}{}", colors, if term::no_color() { " (NO_COLOR is set, so the game leaves colors out)" } else { "" });
+    // Mouse reporting can't be asked about, only seen in use
+    let mouse = match term_var.as_str() {
+        "" | "dumb" | "linux" => "unlikely",
+        _ => "likely, going by TERM",
+    };
+    println!("  Mouse reporting: {}", mouse);
+    if interactive {
+        let kitty = match terminal::supports_keyboard_enhancement() {
+            Ok(true) => "yes",
+            Ok(false) => "no",
+            Err(_) => "didn't answer",
+        };
+        println!("  Kitty keyboard protocol: {}", kitty);
+    } else {
+        println!("  Kitty keyboard protocol: not checked (not a terminal)");
+    }
+    println!();
+
+    println!("Character widths (each should be 1 column)");
+    let theme = save.theme();
+    let chars = [("covered space", theme.tile_char.as_str()), ("flag", theme.flag_char.as_str()), ("mine", theme.mine_char.as_str())];
+    for (name, text) in chars.into_iter().chain(SAMPLE_CHARS) {
+        let width = if interactive { measure_width(text) } else { None };
+        match width {
+            Some(width) => println!("  {} {}: {}{}", name, text, width, if width != 1 { " - this will break the board's columns" } else { "" }),
+            None => println!("  {} {}: not measured", name, text),
+        }
+    }
+    println!();
+
+    println!("What the game will do");
+    println!("  Drawing: {}", renderer());
+    println!("  Synchronized output: {}", if term::multiplexer() == Multiplexer::Screen { "not sent, since screen doesn't know it" } else { "sent around every frame" });
+    println!("  Cursor shape: {}", if term::supports_cursor_style() { "steady block" } else { "left alone" });
+    println!("  Theme: {}", theme_name(&theme));
+    println!("  Numbers: {}", if term::no_color() || save.number_marks { "each between its own brackets" } else { "told apart by color" });
+    println!("  Board view: {}", match save.large_print {
+        2 | 3 => format!("large print, {} rows a space", save.large_print),
+        _ => String::from("normal"),
+    });
+}
+
+///
+/// How the game gets colors and cursor moves to the terminal
+///
+fn renderer() -> &'static str {
+    #[cfg(windows)]
+    if !crossterm::ansi_support::supports_ansi() {
+        return "Windows console API (legacy console without ANSI support)";
+    }
+    "ANSI escape codes"
+}
+
+///
+/// The built-in theme the save's characters and colors match, if any
+///
+fn theme_name(theme: &Theme) -> String {
+    match Theme::gallery().into_iter().find(|(_, t)| t == theme) {
+        Some((name, _)) => String::from(name),
+        None => String::from("custom"),
+    }
+}
+
+///
+/// How many columns the terminal moves the cursor for `text`, found by printing it and asking where the cursor went.
+/// Returns `None` if the terminal doesn't say
+///
+fn measure_width(text: &str) -> Option<u16> {
+    enable_raw_mode().ok()?;
+    let mut stdout = std::io::stdout();
+    print!("\r");
+    stdout.flush().ok();
+    let start = cursor::position().ok().map(|p| p.0);
+    print!("{}", text);
+    stdout.flush().ok();
+    let end = cursor::position().ok().map(|p| p.0);
+    print!("\r");
+    term::clear_line();
+    stdout.flush().ok();
+    let _ = disable_raw_mode();
+    Some(end?.saturating_sub(start?))
+}
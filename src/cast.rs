@@ -0,0 +1,243 @@
+use crate::engine::{GameState, Tile, VisibleBoard};
+use crate::replay::Replay;
+use crate::topology::{self, Topology};
+
+use serde_json::json;
+
+// How long the last frame stays up before a cast ends, in seconds
+pub(crate) const HOLD_END: f64 = 2.0;
+
+///
+/// How a rendered board looks: the characters for covered spaces, flags, and mines,
+/// and colors as SGR codes, the same way the game's save stores them
+///
+#[derive(PartialEq, Clone, Debug)]
+pub struct Theme {
+    pub tile_char: String,
+    pub flag_char: String,
+    pub mine_char: String,
+    pub border_fg: String,
+    pub border_bg: String,
+    pub inner_fg: String,
+    pub inner_bg: String,
+    pub inner_highlight: String,
+    pub m_count_fg: Vec<String>, // Colors of the numbers 1 to 8
+    pub m_count_chars: Vec<String>, // Characters shown for the numbers 1 to 8, or empty for plain digits
+    pub flag_bg: String,         // Background of flagged spaces
+    pub revealed: String,        // Colors of uncovered spaces' brackets and background
+    pub empty_char: String,      // Shown on uncovered spaces with no number
+    pub number_brackets: Vec<String>, // Bracket pairs around the numbers 1 to 8 when they can't be told apart by color
+}
+
+impl Default for Theme {
+    ///
+    /// The default save's colors, with plain characters that show up in any font
+    ///
+    fn default() -> Theme {
+        Theme {
+            tile_char: String::from("#"),
+            flag_char: String::from("F"),
+            mine_char: String::from("*"),
+            border_fg: String::from("37"),
+            border_bg: String::from("40"),
+            inner_fg: String::from("37"),
+            inner_bg: String::from("100"),
+            inner_highlight: String::from("97"),
+            m_count_fg: ["34", "32", "31", "35", "33", "36", "37", "30"].iter().map(|c| c.to_string()).collect(),
+            m_count_chars: vec![],
+            flag_bg: String::from("100"),
+            revealed: String::from("0;30"),
+            empty_char: String::from(" "),
+            number_brackets: default_number_brackets(),
+        }
+    }
+}
+
+///
+/// A different pair of brackets for each number from 1 to 8, so they can be told apart without color
+///
+pub fn default_number_brackets() -> Vec<String> {
+    ["()", "[]", "{}", "<>", "||", "//", "::", "##"].iter().map(|b| b.to_string()).collect()
+}
+
+impl Theme {
+    ///
+    /// The built-in themes, by name: `classic` is the game's own look with Nerd Font icons, `plain` sticks to
+    /// characters any font has, and `high-contrast` tells covered, flagged, and uncovered spaces apart by both
+    /// how bright their background is and what's on them
+    ///
+    pub fn gallery() -> Vec<(&'static str, Theme)> {
+        let classic = Theme {
+            tile_char: String::from("󰆢"),
+            flag_char: String::from("󰈿"),
+            mine_char: String::from("󰷚"),
+            ..Theme::default()
+        };
+        let high_contrast = Theme {
+            border_fg: String::from("97"),
+            border_bg: String::from("40"),
+            inner_fg: String::from("30"),
+            inner_bg: String::from("107"),
+            inner_highlight: String::from("97"),
+            m_count_fg: ["96", "92", "91", "95", "93", "97", "94", "37"].iter().map(|c| c.to_string()).collect(),
+            flag_bg: String::from("41"),
+            revealed: String::from("0;37;40"),
+            empty_char: String::from("."),
+            ..Theme::default()
+        };
+        vec![("classic", classic), ("plain", Theme::default()), ("high-contrast", high_contrast)]
+    }
+}
+
+///
+/// How a number is shown: the theme's character for it if it has one, otherwise its digits.
+/// Only 1 to 8 can have characters, since variant rules' other numbers come up too rarely to theme
+///
+pub fn number_text(m_count_chars: &[String], n: i16) -> String {
+    if (1..=8).contains(&n) && let Some(text) = m_count_chars.get((n - 1) as usize).filter(|c| !c.is_empty()) {
+        return text.clone();
+    }
+    n.to_string()
+}
+
+///
+/// The brackets a number goes between when colors can't tell it apart: the pair from `number_brackets` for it,
+/// if it has one of exactly two characters, otherwise the usual square brackets
+///
+pub fn number_brackets(number_brackets: &[String], n: i16) -> (String, String) {
+    if (1..=8).contains(&n) && let Some(pair) = number_brackets.get((n - 1) as usize) {
+        let mut chars = pair.chars();
+        if let (Some(open), Some(close), None) = (chars.next(), chars.next(), chars.next()) {
+            return (open.to_string(), close.to_string());
+        }
+    }
+    (String::from("["), String::from("]"))
+}
+
+///
+/// One step of a game being played back: the board as the player saw it, and when
+///
+#[derive(PartialEq, Clone, Debug)]
+pub struct Frame {
+    pub time_ms: u64,
+    pub board: VisibleBoard,
+    pub mines: Vec<(i16, i16)>, // Mines to show, which is only ever done after a loss
+}
+
+///
+/// Plays a replay back as frames: the board before the first move, then after each move at the time it was made.
+/// A lost game's last frame also shows where the mines were
+///
+pub fn frames(replay: &Replay) -> Result<Vec<Frame>, String> {
+    let positions = replay.positions()?;
+    let mut frames: Vec<Frame> = vec![];
+    for (i, position) in positions.into_iter().enumerate() {
+        let time_ms = if i == 0 { 0 } else { replay.actions[i - 1].time_ms };
+        frames.push(Frame { time_ms, board: position, mines: vec![] });
+    }
+    if let Some(last) = frames.last_mut() && last.board.state == GameState::Loss {
+        let board = replay.simulate(replay.actions.len())?;
+        for y in 0..replay.height {
+            for x in 0..replay.width {
+                if board.is_mine(x, y) {
+                    last.mines.push((x, y));
+                }
+            }
+        }
+    }
+    Ok(frames)
+}
+
+///
+/// Wraps text in SGR color codes
+///
+fn paint(codes: &[&str], text: &str) -> String {
+    format!("\x1b[{}m{}\x1b[0m", codes.join(";"), text)
+}
+
+///
+/// Draws a frame as lines of colored text, laid out the way the game draws its board:
+/// the flag counter and time, the board in its border, and the result once the game is over
+///
+pub fn render_text(frame: &Frame, topology: &dyn Topology, theme: &Theme) -> Vec<String> {
+    let board = &frame.board;
+    let border = [theme.border_fg.as_str(), theme.border_bg.as_str()];
+    let inner_width = topology.screen_width(board.width, board.height);
+    let mut lines: Vec<String> = vec![];
+    lines.push(format!("FLAGS LEFT: {:<5} TIME: {}", board.m_count - board.f_count, frame.time_ms / 1000));
+    lines.push(paint(&border, &format!("╔{}╗", "═".repeat(inner_width as usize))));
+    for y in 0..board.height {
+        let mut line = paint(&border, "║");
+        // Some shapes shift spaces over, so pad up to wherever each one goes
+        let mut column: i16 = 0;
+        for x in 0..board.width {
+            let offset = topology.screen_offset(x, y).0;
+            line.push_str(&" ".repeat((offset - column).max(0) as usize));
+            column = offset + 3;
+            let tile = board.tiles[y as usize][x as usize];
+            let text = if !topology.contains(x, y, board.width, board.height) {
+                // Hole in the board
+                String::from("   ")
+            } else if tile != Tile::Flagged && frame.mines.contains(&(x, y)) {
+                paint(&[&theme.inner_highlight, "100"], &format!("[{}]", theme.mine_char))
+            } else {
+                match tile {
+                    Tile::Covered => paint(&[&theme.inner_fg, &theme.inner_bg], &format!("[{}]", theme.tile_char)),
+                    Tile::Flagged => {
+                        let colors = [theme.inner_fg.as_str(), theme.flag_bg.as_str()];
+                        format!("{}{}{}", paint(&colors, "["), paint(&[&theme.inner_highlight, &theme.flag_bg], &theme.flag_char), paint(&colors, "]"))
+                    }
+                    Tile::Revealed(0) => paint(&[&theme.revealed], &format!("[{}]", theme.empty_char)),
+                    Tile::Revealed(n) => {
+                        // Variant rules can show numbers past 8 and below 0
+                        let color = &theme.m_count_fg[(n.abs().clamp(1, 8) - 1) as usize];
+                        format!("{}{}{}", paint(&[&theme.revealed], "["), paint(&[&theme.revealed, "1", color], &number_text(&theme.m_count_chars, n)), paint(&[&theme.revealed], "]"))
+                    }
+                }
+            };
+            line.push_str(&text);
+        }
+        line.push_str(&" ".repeat((inner_width - column).max(0) as usize));
+        line.push_str(&paint(&border, "║"));
+        lines.push(line);
+    }
+    lines.push(paint(&border, &format!("╚{}╝", "═".repeat(inner_width as usize))));
+    lines.push(String::from(match board.state {
+        GameState::Win => "Congrats! You won!",
+        GameState::Loss => "Sorry! You lose.",
+        _ => "",
+    }));
+    lines
+}
+
+///
+/// Renders a replay as an asciinema cast (version 2): a header, then one timed event per frame that redraws the board.
+/// `speed` above 1 plays the game back faster than it was played
+///
+pub fn to_cast(replay: &Replay, theme: &Theme, title: &str, speed: f64) -> Result<String, String> {
+    let topology = match topology::from_name(&replay.topology) {
+        Some(topology) => topology,
+        None => return Err(format!("Unknown topology \"{}\"", replay.topology)),
+    };
+    let frames = frames(replay)?;
+    let columns = (topology.screen_width(replay.width, replay.height) + 2).max(40);
+    let rows = replay.height + 4;
+    let header = json!({
+        "version": 2,
+        "width": columns,
+        "height": rows,
+        "title": title,
+        "env": { "TERM": "xterm-256color" },
+    });
+    let mut lines: Vec<String> = vec![header.to_string()];
+    let mut time: f64 = 0.;
+    for (i, frame) in frames.iter().enumerate() {
+        // The first frame starts from a clear screen; the rest draw over it
+        let home = if i == 0 { "\x1b[2J\x1b[H" } else { "\x1b[H" };
+        let text = format!("{}{}", home, render_text(frame, topology.as_ref(), theme).join("\x1b[K\r\n"));
+        time = frame.time_ms as f64 / 1000. / speed;
+        lines.push(json!([time, "o", text]).to_string());
+    }
+    lines.push(json!([time + HOLD_END, "o", ""]).to_string());
+    Ok(lines.join("\n") + "\n")
+}
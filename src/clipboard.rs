@@ -0,0 +1,38 @@
+//! The system clipboard, for copying seeds, join codes, and results out of the game and pasting seeds in.
+//! Only built with the `clipboard` feature.
+
+use arboard::Clipboard;
+use std::cell::RefCell;
+
+thread_local! {
+    // Opened on first use, then kept for the rest of the session. On X11 and Wayland the copied text
+    // only stays on the clipboard while whoever copied it is still around to hand it out
+    static CLIPBOARD: RefCell<Option<Clipboard>> = const { RefCell::new(None) };
+}
+
+///
+/// Runs `f` with the clipboard, opening it first if this is the first time
+///
+fn with_clipboard<T, F: FnOnce(&mut Clipboard) -> Result<T, arboard::Error>>(f: F) -> Result<T, String> {
+    CLIPBOARD.with(|cell| {
+        let mut clipboard = cell.borrow_mut();
+        if clipboard.is_none() {
+            *clipboard = Some(Clipboard::new().map_err(|e| e.to_string())?);
+        }
+        f(clipboard.as_mut().unwrap()).map_err(|e| e.to_string())
+    })
+}
+
+///
+/// Puts text on the clipboard
+///
+pub(crate) fn copy(text: &str) -> Result<(), String> {
+    with_clipboard(|clipboard| clipboard.set_text(text))
+}
+
+///
+/// Whatever text is on the clipboard
+///
+pub(crate) fn paste() -> Result<String, String> {
+    with_clipboard(|clipboard| clipboard.get_text())
+}
@@ -0,0 +1,304 @@
+//! Replays packed down for storage: the board's settings and seed, then the moves as a stream of small deltas,
+//! so a finished Expert game comes to a few hundred bytes. With the `zstd` feature the result is compressed too.
+//!
+//! The layout is the magic bytes, then varints throughout (signed values zigzagged first), except the seed, which
+//! is 8 bytes since it's random anyway. Each move packs its kind into the low bits of its column delta, followed by
+//! its row delta and how many milliseconds came after the move before it.
+
+use crate::engine::GameState;
+use crate::generation::GenerationOptions;
+use crate::layout::Layout;
+use crate::replay::{ActionKind, Badges, Replay, ReplayAction};
+
+// "MSR" and the format's version
+const MAGIC: [u8; 4] = [b'M', b'S', b'R', 1];
+// What every zstd frame starts with
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xB5, 0x2F, 0xFD];
+// The most a compressed replay may inflate to, far past any real game, so a crafted file can't use up memory
+#[cfg(feature = "zstd")]
+const MAX_UNPACKED: usize = 1 << 20;
+
+// Bits of the flags byte
+const PRE_START_FLAGS: u8 = 1 << 0;
+const PRE_FLAGS_SAFE: u8 = 1 << 1;
+const ASSIST_FREE: u8 = 1 << 2;
+const NO_FLAGS: u8 = 1 << 3;
+const NO_GUESS: u8 = 1 << 4;
+const ZERO_GUESS: u8 = 1 << 5;
+const HAS_LAYOUT: u8 = 1 << 6;
+const CLEAR_OPENING: u8 = 1 << 7;
+
+///
+/// Whether some bytes look like a packed replay, compressed or not, as opposed to JSON
+///
+pub fn is_compact(bytes: &[u8]) -> bool {
+    bytes.starts_with(&MAGIC) || bytes.starts_with(&ZSTD_MAGIC)
+}
+
+///
+/// Packs a replay into bytes, compressed with zstd when the feature is on
+///
+pub fn encode(replay: &Replay) -> Vec<u8> {
+    compress(pack(replay))
+}
+
+///
+/// Packs a replay into bytes without compressing them, so the same replay always comes out the same
+///
+pub fn pack(replay: &Replay) -> Vec<u8> {
+    let mut out: Vec<u8> = MAGIC.to_vec();
+    write_signed(&mut out, replay.width as i64);
+    write_signed(&mut out, replay.height as i64);
+    write_signed(&mut out, replay.m_count as i64);
+    out.push(replay.gamemode);
+    out.extend_from_slice(&replay.seed.to_le_bytes());
+    let mut flags: u8 = 0;
+    for (bit, set) in [
+        (PRE_START_FLAGS, replay.pre_start_flags),
+        (PRE_FLAGS_SAFE, replay.pre_flags_safe),
+        (ASSIST_FREE, replay.badges.assist_free),
+        (NO_FLAGS, replay.badges.no_flags),
+        (NO_GUESS, replay.badges.no_guess),
+        (ZERO_GUESS, replay.badges.zero_guess),
+        (HAS_LAYOUT, replay.layout.is_some()),
+        (CLEAR_OPENING, replay.generation.clear_opening),
+    ] {
+        if set {
+            flags |= bit;
+        }
+    }
+    out.push(flags);
+    write_varint(&mut out, replay.generation.hard_samples as u64);
+    write_varint(&mut out, replay.generation.opening_min as u64);
+    write_varint(&mut out, replay.generation.opening_max as u64);
+    for text in [&replay.rules, &replay.topology, &replay.rng] {
+        write_varint(&mut out, text.len() as u64);
+        out.extend_from_slice(text.as_bytes());
+    }
+    out.push(match replay.result {
+        GameState::Starting => 0,
+        GameState::Running => 1,
+        GameState::Win => 2,
+        GameState::Loss => 3,
+    });
+    write_varint(&mut out, replay.time_ms);
+    if let Some(layout) = &replay.layout {
+        write_signed(&mut out, layout.width as i64);
+        write_signed(&mut out, layout.height as i64);
+        write_varint(&mut out, layout.mines.len() as u64);
+        for &(x, y) in &layout.mines {
+            write_signed(&mut out, x as i64);
+            write_signed(&mut out, y as i64);
+        }
+    }
+    write_varint(&mut out, replay.actions.len() as u64);
+    // Moves tend to be near the one before them, so only the differences are stored
+    let (mut x, mut y, mut time_ms) = (0i64, 0i64, 0u64);
+    for action in &replay.actions {
+        let kind: u64 = match action.kind {
+            ActionKind::Reveal => 0,
+            ActionKind::Flag => 1,
+            ActionKind::Chord => 2,
+        };
+        write_varint(&mut out, zigzag(action.x as i64 - x) << 2 | kind);
+        write_signed(&mut out, action.y as i64 - y);
+        // Time only goes forward in a real game, but a hand-edited replay could say otherwise
+        write_signed(&mut out, action.time_ms.wrapping_sub(time_ms) as i64);
+        (x, y, time_ms) = (action.x as i64, action.y as i64, action.time_ms);
+    }
+    out
+}
+
+///
+/// Unpacks a replay from `encode`'s bytes, decompressing them first if needed
+///
+pub fn decode(bytes: &[u8]) -> Result<Replay, String> {
+    let bytes = decompress(bytes)?;
+    let mut reader = Reader { bytes: &bytes, pos: 0 };
+    if reader.take(MAGIC.len())? != MAGIC {
+        return Err(String::from("Not a packed replay, or one from a newer version"));
+    }
+    let width = reader.small()?;
+    let height = reader.small()?;
+    let m_count = reader.small()?;
+    let gamemode = reader.byte()?;
+    let seed = u64::from_le_bytes(reader.take(8)?.try_into().unwrap());
+    let flags = reader.byte()?;
+    let generation = GenerationOptions {
+        hard_samples: u32::try_from(reader.varint()?).map_err(|_| String::from("Too many hard samples"))?,
+        opening_min: u16::try_from(reader.varint()?).map_err(|_| String::from("Opening minimum out of range"))?,
+        opening_max: u16::try_from(reader.varint()?).map_err(|_| String::from("Opening maximum out of range"))?,
+        clear_opening: flags & CLEAR_OPENING != 0,
+    };
+    let rules = reader.text()?;
+    let topology = reader.text()?;
+    let rng = reader.text()?;
+    let result = match reader.byte()? {
+        0 => GameState::Starting,
+        1 => GameState::Running,
+        2 => GameState::Win,
+        3 => GameState::Loss,
+        n => return Err(format!("Unknown result {}", n)),
+    };
+    let time_ms = reader.varint()?;
+    let mut layout: Option<Layout> = None;
+    if flags & HAS_LAYOUT != 0 {
+        let layout_width = reader.small()?;
+        let layout_height = reader.small()?;
+        let count = reader.varint()?;
+        let mut mines: Vec<(i16, i16)> = vec![];
+        for _ in 0..count {
+            mines.push((reader.small()?, reader.small()?));
+        }
+        layout = Some(Layout { width: layout_width, height: layout_height, mines });
+    }
+    let count = reader.varint()?;
+    let mut actions: Vec<ReplayAction> = vec![];
+    let (mut x, mut y, mut time) = (0i64, 0i64, 0u64);
+    for _ in 0..count {
+        let packed = reader.varint()?;
+        let kind = match packed & 3 {
+            0 => ActionKind::Reveal,
+            1 => ActionKind::Flag,
+            2 => ActionKind::Chord,
+            _ => return Err(String::from("Unknown kind of move")),
+        };
+        let (dx, dy) = (unzigzag(packed >> 2), reader.signed()?);
+        (x, y) = match (x.checked_add(dx), y.checked_add(dy)) {
+            (Some(x), Some(y)) => (x, y),
+            _ => return Err(String::from("A move is off the board")),
+        };
+        time = time.wrapping_add(reader.signed()? as u64);
+        let (ax, ay) = match (i16::try_from(x), i16::try_from(y)) {
+            (Ok(ax), Ok(ay)) => (ax, ay),
+            _ => return Err(String::from("A move is off the board")),
+        };
+        actions.push(ReplayAction { kind, x: ax, y: ay, time_ms: time });
+    }
+    if reader.pos != bytes.len() {
+        return Err(String::from("Extra bytes after the last move"));
+    }
+    Ok(Replay {
+        width,
+        height,
+        m_count,
+        gamemode,
+        seed,
+        pre_start_flags: flags & PRE_START_FLAGS != 0,
+        pre_flags_safe: flags & PRE_FLAGS_SAFE != 0,
+        generation,
+        rules,
+        topology,
+        rng,
+        layout,
+        badges: Badges {
+            assist_free: flags & ASSIST_FREE != 0,
+            no_flags: flags & NO_FLAGS != 0,
+            no_guess: flags & NO_GUESS != 0,
+            zero_guess: flags & ZERO_GUESS != 0,
+        },
+        actions,
+        result,
+        time_ms,
+    })
+}
+
+#[cfg(feature = "zstd")]
+fn compress(bytes: Vec<u8>) -> Vec<u8> {
+    // Replays are small, so the slowest level still takes no time at all
+    zstd::bulk::compress(&bytes, 19).unwrap_or(bytes)
+}
+
+#[cfg(not(feature = "zstd"))]
+fn compress(bytes: Vec<u8>) -> Vec<u8> {
+    bytes
+}
+
+#[cfg(feature = "zstd")]
+fn decompress(bytes: &[u8]) -> Result<Vec<u8>, String> {
+    if bytes.starts_with(&ZSTD_MAGIC) {
+        return zstd::bulk::decompress(bytes, MAX_UNPACKED).map_err(|e| e.to_string());
+    }
+    Ok(bytes.to_vec())
+}
+
+#[cfg(not(feature = "zstd"))]
+fn decompress(bytes: &[u8]) -> Result<Vec<u8>, String> {
+    if bytes.starts_with(&ZSTD_MAGIC) {
+        return Err(String::from("This replay is compressed, which needs a build with the zstd feature"));
+    }
+    Ok(bytes.to_vec())
+}
+
+///
+/// Maps signed numbers to unsigned ones with small magnitudes staying small: 0, -1, 1, -2, ... to 0, 1, 2, 3, ...
+///
+fn zigzag(n: i64) -> u64 {
+    ((n << 1) ^ (n >> 63)) as u64
+}
+
+fn unzigzag(n: u64) -> i64 {
+    (n >> 1) as i64 ^ -((n & 1) as i64)
+}
+
+///
+/// Writes a number 7 bits at a time, lowest first, with the top bit of each byte set if more follow
+///
+fn write_varint(out: &mut Vec<u8>, mut n: u64) {
+    while n >= 0x80 {
+        out.push((n as u8 & 0x7F) | 0x80);
+        n >>= 7;
+    }
+    out.push(n as u8);
+}
+
+fn write_signed(out: &mut Vec<u8>, n: i64) {
+    write_varint(out, zigzag(n));
+}
+
+///
+/// Reads a packed replay from the front, running out with an error rather than a panic
+///
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl Reader<'_> {
+    fn take(&mut self, n: usize) -> Result<&[u8], String> {
+        if self.bytes.len() - self.pos < n {
+            return Err(String::from("The replay ends early"));
+        }
+        self.pos += n;
+        Ok(&self.bytes[self.pos - n..self.pos])
+    }
+    fn byte(&mut self) -> Result<u8, String> {
+        Ok(self.take(1)?[0])
+    }
+    fn varint(&mut self) -> Result<u64, String> {
+        let mut n: u64 = 0;
+        for shift in (0..64).step_by(7) {
+            let byte = self.byte()?;
+            n |= ((byte & 0x7F) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(n);
+            }
+        }
+        Err(String::from("A number in the replay is too long"))
+    }
+    fn signed(&mut self) -> Result<i64, String> {
+        Ok(unzigzag(self.varint()?))
+    }
+    ///
+    /// A signed number that has to fit a board coordinate or size
+    ///
+    fn small(&mut self) -> Result<i16, String> {
+        let n = self.signed()?;
+        i16::try_from(n).map_err(|_| format!("{} is out of range", n))
+    }
+    fn text(&mut self) -> Result<String, String> {
+        let len = self.varint()?;
+        let len = usize::try_from(len).map_err(|_| String::from("The replay ends early"))?;
+        String::from_utf8(self.take(len)?.to_vec()).map_err(|e| e.to_string())
+    }
+}
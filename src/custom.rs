@@ -0,0 +1,308 @@
+use crossterm::{
+    cursor::{MoveTo, Hide, Show},
+    event::{self, Event, KeyCode, KeyEventKind},
+    terminal::{disable_raw_mode, enable_raw_mode},
+    execute
+};
+
+#[cfg(feature = "clipboard")]
+use crate::clipboard;
+use crate::term;
+#[cfg(feature = "clipboard")]
+use crossterm::event::KeyModifiers;
+#[cfg(feature = "solver")]
+use minesweeper::engine::{Board, ManualClock};
+#[cfg(feature = "solver")]
+use minesweeper::grade::{self, Grade};
+use minesweeper::seed_code::SeedCode;
+
+use std::io::Write;
+#[cfg(feature = "solver")]
+use std::time::{Duration, Instant};
+
+///
+/// The settings picked in the custom game form
+///
+pub struct CustomGame {
+    pub width: i16,
+    pub height: i16,
+    pub mines: i16,
+    pub seed: Option<u64>,
+}
+
+// Smallest width / height allowed, so there is room for a full opening around the first check
+const MIN_SIZE: i16 = 3;
+// Spaces kept free of mines around the first check
+const SAFE_OPENING: i16 = 9;
+
+// Field indices
+const WIDTH: usize = 0;
+const HEIGHT: usize = 1;
+const MINES: usize = 2;
+const DENSITY: usize = 3;
+const SEED: usize = 4;
+
+// How long the density advisor gets to play out boards, and the most it plays
+#[cfg(feature = "solver")]
+const ANALYZE_TIME: Duration = Duration::from_millis(1500);
+#[cfg(feature = "solver")]
+const ANALYZE_SAMPLES: u64 = 200;
+
+///
+/// Editable form for setting up a custom game, with live validation.
+/// Mines and density are kept in sync with each other as either one is edited
+///
+struct CustomForm {
+    labels: [&'static str; 5],
+    values: [String; 5],
+    selected: usize,
+    notice: Option<String>, // What happened on the last paste, if it went wrong
+    analysis: Option<((i16, i16, i16), f64)>, // The density advisor's last size and mine count, and how many of those boards it could solve
+}
+
+impl CustomForm {
+    fn new() -> CustomForm {
+        CustomForm {
+            labels: ["Width", "Height", "Mines", "Density %", "Seed or code"],
+            values: [String::from("16"), String::from("16"), String::from("40"), String::from("15.6"), String::new()],
+            selected: WIDTH,
+            notice: None,
+            analysis: None,
+        }
+    }
+    ///
+    /// Checks the form, returning the game it describes or a message explaining what is wrong
+    ///
+    fn validate(&self) -> Result<CustomGame, String> {
+        let width = self.values[WIDTH].parse::<i16>().map_err(|_| String::from("Width must be a positive number"))?;
+        let height = self.values[HEIGHT].parse::<i16>().map_err(|_| String::from("Height must be a positive number"))?;
+        let mines = self.values[MINES].parse::<i16>().map_err(|_| String::from("Mines must be a positive number"))?;
+        if width < MIN_SIZE || height < MIN_SIZE {
+            return Err(format!("Width and height must be at least {}", MIN_SIZE));
+        }
+        let space_n = width.checked_mul(height).ok_or(String::from("Board is too large"))?;
+        // Check (by numerical constraints) if it is valid, leaving room for the opening
+        if mines > space_n - SAFE_OPENING {
+            return Err(format!("Too many mines for the given space count ({} mines in {} spaces, max {})", mines, space_n, space_n - SAFE_OPENING));
+        }
+        let seed = if self.values[SEED].is_empty() {
+            None
+        } else if let Some(code) = SeedCode::parse(&self.values[SEED]) {
+            Some(code.seed)
+        } else {
+            Some(self.values[SEED].parse::<u64>().map_err(|_| String::from("Seed must be a number, or a seed code like 16x16-40-3F9K2Q7"))?)
+        };
+        Ok(CustomGame { width, height, mines, seed })
+    }
+    ///
+    /// Recalculates whichever of mines / density wasn't just edited
+    ///
+    fn sync_density(&mut self) {
+        let width = self.values[WIDTH].parse::<f32>().unwrap_or(0.);
+        let height = self.values[HEIGHT].parse::<f32>().unwrap_or(0.);
+        let area = width * height;
+        if area <= 0. {
+            return;
+        }
+        if self.selected == DENSITY {
+            if let Ok(density) = self.values[DENSITY].parse::<f32>() {
+                self.values[MINES] = ((area * density / 100.).round() as i64).to_string();
+            }
+        } else if let Ok(mines) = self.values[MINES].parse::<f32>() {
+            self.values[DENSITY] = format!("{:.1}", mines / area * 100.);
+        }
+    }
+    ///
+    /// Fills in the size and mine count from a seed code in the seed field, so the code makes the board it was given for
+    ///
+    fn sync_code(&mut self) {
+        if let Some(code) = SeedCode::parse(&self.values[SEED]) {
+            self.values[WIDTH] = code.width.to_string();
+            self.values[HEIGHT] = code.height.to_string();
+            self.values[MINES] = code.mines.to_string();
+            self.sync_density();
+        }
+    }
+    ///
+    /// Fills the selected field with the last number on the clipboard, so a seed copied from anywhere
+    /// (even as part of a longer message) can be pasted straight in. A seed code anywhere on it goes in the seed field instead,
+    /// along with the board it's for
+    ///
+    #[cfg(feature = "clipboard")]
+    fn paste(&mut self) {
+        self.notice = match clipboard::paste() {
+            Ok(text) => {
+                if let Some(code) = SeedCode::find(&text) {
+                    self.values[SEED] = code.to_string();
+                    self.sync_code();
+                    self.notice = None;
+                    return;
+                }
+                match text.split(|c: char| !c.is_ascii_digit()).rfind(|n| !n.is_empty()) {
+                    Some(number) if number.len() <= 20 => {
+                        self.values[self.selected] = number.to_string();
+                        self.sync_density();
+                        None
+                    }
+                    _ => Some(String::from("There's no number on the clipboard")),
+                }
+            }
+            Err(e) => Some(format!("Couldn't paste: {}", e)),
+        };
+    }
+    ///
+    /// Plays out random boards of the form's size and mine count from the middle, the way a game's first check would go,
+    /// for as long as `ANALYZE_TIME` allows, and works out how many could be cleared without guessing
+    ///
+    #[cfg(feature = "solver")]
+    fn analyze(&mut self) {
+        let game = match self.validate() {
+            Ok(game) => game,
+            Err(_) => return,
+        };
+        print!("\r\nAnalyzing...");
+        std::io::stdout().flush().ok();
+        let start = (game.width / 2, game.height / 2);
+        let deadline = Instant::now() + ANALYZE_TIME;
+        let (mut played, mut solved) = (0u64, 0u64);
+        while played < ANALYZE_SAMPLES && (played == 0 || Instant::now() < deadline) {
+            let mut board = Board::deterministic(game.width, game.height, game.mines, 0, played, ManualClock::new());
+            board.reveal(start.0, start.1);
+            if grade::grade(&board, start).is_some_and(|g| g != Grade::Expert) {
+                solved += 1;
+            }
+            played += 1;
+        }
+        self.analysis = Some(((game.width, game.height, game.mines), solved as f64 / played as f64));
+    }
+    ///
+    /// Draws the whole form along with the current validation message
+    ///
+    fn draw(&self) {
+        term::clear();
+        print!("Custom game\r\n");
+        let paste = if cfg!(feature = "clipboard") { " | ctrl+v - paste" } else { "" };
+        let analyze = if cfg!(feature = "solver") { " | a - analyze" } else { "" };
+        print!("up/down - select | type to edit{}{} | enter - start | esc - back\r\n\r\n", paste, analyze);
+        for i in 0..self.labels.len() {
+            let marker = if i == self.selected { ">" } else { " " };
+            print!("{} {:<16} {}\r\n", marker, self.labels[i], self.values[i]);
+        }
+        match self.validate() {
+            Ok(game) => {
+                print!("\r\n");
+                term::paint(&["0;32"], &format!("OK: {}x{} with {} mines", game.width, game.height, game.mines));
+                print!("\r\n");
+                if let Some(warning) = size_warning(game.width, game.height) {
+                    term::paint(&["0;33"], &format!("! {}", warning));
+                    print!("\r\n");
+                }
+                // Only shown while the size and mine count are still the ones that were analyzed
+                if let Some((_, rate)) = self.analysis.filter(|a| a.0 == (game.width, game.height, game.mines)) {
+                    let (color, advice) = if rate >= 0.5 {
+                        ("0;32", "mostly logic")
+                    } else if rate >= 0.1 {
+                        ("0;33", "expect to guess along the way")
+                    } else {
+                        ("0;31", "mostly luck - a lower density makes it winnable by logic")
+                    };
+                    term::paint(&[color], &format!("~ {:.0}% of boards like this can be cleared without guessing: {}", rate * 100., advice));
+                    print!("\r\n");
+                }
+            }
+            Err(e) => {
+                print!("\r\n");
+                term::paint(&["0;31"], &format!("X {}", e));
+                print!("\r\n");
+            }
+        }
+        if let Some(notice) = &self.notice {
+            term::paint(&["0;33"], &format!("! {}", notice));
+            print!("\r\n");
+        }
+        // Leave the cursor at the end of the field being edited
+        execute!(std::io::stdout(), MoveTo((19 + self.values[self.selected].len()) as u16, (3 + self.selected) as u16)).ok();
+        std::io::stdout().flush().ok();
+    }
+}
+
+///
+/// Warns if a board of the given size won't fit in the terminal
+///
+fn size_warning(width: i16, height: i16) -> Option<String> {
+    let (columns, rows) = crossterm::terminal::size().ok()?;
+    // Each space is 3 characters wide plus the borders, with the header and messages above and below
+    let needed_columns = (width as i32) * 3 + 2;
+    let needed_rows = (height as i32) + 8;
+    if needed_columns > columns as i32 || needed_rows > rows as i32 {
+        return Some(format!("This board needs a {}x{} terminal but yours is {}x{}, so it will need scrolling", needed_columns, needed_rows, columns, rows));
+    }
+    None
+}
+
+///
+/// Shows the custom game form until the player starts a valid game or backs out with Esc
+///
+// A key's arm takes it even when the field is full, so e.g. a full seed doesn't fall through to `a` for analyze
+#[allow(clippy::collapsible_match)]
+pub fn custom_game_form() -> Result<Option<CustomGame>, std::io::Error> {
+    let _ = enable_raw_mode();
+    execute!(std::io::stdout(), Show).ok();
+    let mut form = CustomForm::new();
+    let result = loop {
+        form.draw();
+        match event::read()? {
+            Event::Key(key_event) if key_event.kind == KeyEventKind::Press => {
+                match key_event.code {
+                    KeyCode::Up => {
+                        form.selected = (form.selected + form.labels.len() - 1) % form.labels.len();
+                    }
+                    KeyCode::Down | KeyCode::Tab => {
+                        form.selected = (form.selected + 1) % form.labels.len();
+                    }
+                    #[cfg(feature = "clipboard")]
+                    KeyCode::Char('v') if key_event.modifiers.contains(KeyModifiers::CONTROL) => {
+                        form.paste();
+                    }
+                    // Seed codes have letters and dashes in them too
+                    KeyCode::Char(c) if form.selected == SEED && (c.is_ascii_alphanumeric() || c == '-') => {
+                        if form.values[SEED].len() < 40 {
+                            form.values[SEED].push(c);
+                            form.sync_code();
+                        }
+                    }
+                    KeyCode::Char(c) if c.is_ascii_digit() || (c == '.' && form.selected == DENSITY) => {
+                        if form.values[form.selected].len() < 20 {
+                            form.values[form.selected].push(c);
+                            form.sync_density();
+                        }
+                    }
+                    KeyCode::Backspace => {
+                        form.values[form.selected].pop();
+                        form.sync_density();
+                        if form.selected == SEED {
+                            form.sync_code();
+                        }
+                    }
+                    #[cfg(feature = "solver")]
+                    KeyCode::Char('a') => {
+                        form.analyze();
+                    }
+                    KeyCode::Enter => {
+                        if let Ok(game) = form.validate() {
+                            break Some(game);
+                        }
+                    }
+                    KeyCode::Esc => {
+                        break None;
+                    }
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    };
+    execute!(std::io::stdout(), Hide).ok();
+    let _ = disable_raw_mode();
+    Ok(result)
+}
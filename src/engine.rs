@@ -0,0 +1,1022 @@
+use crate::layout::Layout;
+use crate::rules::{RuleSet, Standard};
+use crate::topology::{SquareEight, Topology};
+#[cfg(feature = "solver")]
+use crate::solver::Solver;
+
+use rand::{Rng, RngCore, SeedableRng};
+use rand::rngs::StdRng;
+use rand_pcg::Pcg32;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+///
+/// Source of time for the engine. Swapped out for a `ManualClock` in deterministic mode
+///
+pub trait Clock: Send {
+    ///
+    /// Time passed since the clock was created
+    ///
+    fn now(&self) -> Duration;
+}
+
+///
+/// Clock backed by the system's monotonic time
+///
+pub struct SystemClock {
+    start: Instant,
+}
+
+impl SystemClock {
+    pub fn new() -> SystemClock {
+        SystemClock { start: Instant::now() }
+    }
+}
+
+impl Default for SystemClock {
+    fn default() -> SystemClock {
+        SystemClock::new()
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> Duration {
+        self.start.elapsed()
+    }
+}
+
+///
+/// Clock that only moves when told to. Clones share the same time, so a test can keep one
+/// and hand the other to the engine
+///
+#[derive(Clone, Default)]
+pub struct ManualClock {
+    millis: Arc<AtomicU64>,
+}
+
+impl ManualClock {
+    pub fn new() -> ManualClock {
+        ManualClock::default()
+    }
+    ///
+    /// Moves the clock forward
+    ///
+    pub fn advance(&self, by: Duration) {
+        self.millis.fetch_add(by.as_millis() as u64, Ordering::Relaxed);
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Duration {
+        Duration::from_millis(self.millis.load(Ordering::Relaxed))
+    }
+}
+
+// PCG stream used for seeded boards. Part of what a seed means, so it can never change
+const PCG_STREAM: u64 = 0x6D69_6E65_7377_6565;
+
+///
+/// Which generator turns a board's seed into mines. `Std` is rand's `StdRng`, which is fast but can change
+/// from one rand version to the next; `Pcg` is PCG32 (XSH RR) from the exactly pinned `rand_pcg`, so a seed
+/// gives the same board in every version of the game, on every platform
+///
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum SeedRng {
+    Std,
+    Pcg,
+}
+
+impl SeedRng {
+    ///
+    /// Looks a generator up by the name replays store it under: "std" or "pcg"
+    ///
+    pub fn from_name(name: &str) -> Option<SeedRng> {
+        match name {
+            "std" => Some(SeedRng::Std),
+            "pcg" => Some(SeedRng::Pcg),
+            _ => None,
+        }
+    }
+    pub fn name(&self) -> String {
+        String::from(match self {
+            SeedRng::Std => "std",
+            SeedRng::Pcg => "pcg",
+        })
+    }
+    ///
+    /// A generator started from `seed`
+    ///
+    pub fn rng(&self, seed: u64) -> Box<dyn RngCore + Send> {
+        match self {
+            SeedRng::Std => Box::new(StdRng::seed_from_u64(seed)),
+            SeedRng::Pcg => Box::new(Pcg32::new(seed, PCG_STREAM)),
+        }
+    }
+}
+
+#[derive(PartialEq, Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum GameState {
+    Starting,
+    Running,
+    Win,
+    Loss
+}
+
+///
+/// The rules of a game of minesweeper, without any rendering or terminal IO.
+/// The TUI drives a `Board` and draws whatever it reports back
+///
+pub struct Board {
+    // Info
+    width: i16,       // Board width
+    height: i16,      // Board height
+    m_count: i16,     // Number of mines on the board
+    f_count: i16,     // Number of flags on the board
+    gamemode: u8,     // Gamemode from the save, which affects generation
+    state: GameState, // Where the game is at
+    generated: bool,  // Whether or not the mines have been placed yet
+    pre_start_flags: bool, // Whether or not flags can be placed before the first reveal
+    avoid_flags: bool,     // Whether or not generation keeps pre-start flags free of mines
+    rules: Box<dyn RuleSet>,       // What mines are worth and what happens when one is checked
+    survived: Vec<(i16, i16)>,     // Mines checked without losing, which stay flagged
+    topology: Box<dyn Topology>,   // Which spaces exist and which ones are next to each other
+
+    // Maps
+    mine_map: Vec<Vec<i16>>,      // 0 = no mine, otherwise how much the mine adds to the numbers around it (1 normally)
+    flag_map: Vec<Vec<i16>>,      // 0 = no flag, otherwise the kind of flag, from 1
+    m_count_map: Vec<Vec<i16>>,   // Each space has the number it shows, normally the # of mines around it
+    uncovered_map: Vec<Vec<i16>>, // 0 = covered, 1 = uncovered. Uncovered tiles cannot be flagged.
+    reveal_times: Vec<Vec<Option<Duration>>>, // When each uncovered space was uncovered, by `clock_time`
+
+    // Injected sources, so the engine can run deterministically
+    rng: Box<dyn RngCore + Send>,
+    clock: Box<dyn Clock>,
+    started_at: Option<Duration>, // Clock time the timer last started running at, or `None` while it is paused or stopped
+    accumulated: Duration,        // Game time from before the timer last started running
+    credit: Duration,             // Time taken off the game's time by bonuses
+}
+
+///
+/// What the player is able to see of a single space
+///
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum Tile {
+    Covered,
+    Flagged,
+    Revealed(i16)
+}
+
+///
+/// Everything a player is allowed to know about a board: its size, counters, state, and what each space looks like.
+/// Mine locations are never part of it, so it is safe to hand to bots, network peers, and spectators
+///
+#[derive(PartialEq, Clone, Debug, Serialize, Deserialize)]
+pub struct VisibleBoard {
+    pub width: i16,
+    pub height: i16,
+    pub m_count: i16,
+    pub f_count: i16,
+    pub state: GameState,
+    pub tiles: Vec<Vec<Tile>>, // Indexed [y][x]
+}
+
+///
+/// The parts of a board that moves change, taken by `checkpoint` so `restore` can put the board back how it was
+///
+pub struct Checkpoint {
+    state: GameState,
+    f_count: i16,
+    flag_map: Vec<Vec<i16>>,
+    uncovered_map: Vec<Vec<i16>>,
+    reveal_times: Vec<Vec<Option<Duration>>>,
+    survived: Vec<(i16, i16)>,
+    rules: Box<dyn RuleSet>, // Rules can change during a game too, like lives running down
+    credit: Duration,
+}
+
+// Initialization
+impl Board {
+    ///
+    /// Creates a new board using OS randomness and the system clock
+    ///
+    pub fn new(width: i16, height: i16, m_count: i16, gamemode: u8) -> Board {
+        Board::with_sources(width, height, m_count, gamemode, Box::new(StdRng::from_os_rng()), Box::new(SystemClock::new()))
+    }
+    ///
+    /// Creates a board whose mines are generated from `seed` by `rng`, so the same seed gives the same board
+    ///
+    pub fn seeded(width: i16, height: i16, m_count: i16, gamemode: u8, seed: u64, rng: SeedRng) -> Board {
+        Board::with_sources(width, height, m_count, gamemode, rng.rng(seed), Box::new(SystemClock::new()))
+    }
+    ///
+    /// Creates a board for tests and fuzzing: the same seed always generates the same mines (through PCG, like
+    /// seeded boards), and time only passes when `clock` is advanced
+    ///
+    pub fn deterministic(width: i16, height: i16, m_count: i16, gamemode: u8, seed: u64, clock: ManualClock) -> Board {
+        Board::with_sources(width, height, m_count, gamemode, SeedRng::Pcg.rng(seed), Box::new(clock))
+    }
+    ///
+    /// Creates a board with its mines already laid out, for puzzles, tests, and examples: standard rules on the usual
+    /// grid, with the numbers worked out from the mines. Nothing is generated, so the first check can hit a mine.
+    /// Time stands still until `set_clock` gives the board a clock
+    ///
+    pub fn from_layout(layout: &Layout) -> Result<Board, String> {
+        layout.validate()?;
+        let mut board = Board::deterministic(layout.width, layout.height, layout.mines.len() as i16, 0, 0, ManualClock::new());
+        board.set_mine_map(layout.mine_map());
+        Ok(board)
+    }
+    ///
+    /// Creates a board with the given random number generator and clock.
+    /// Degenerate sizes are clamped: the board is at least 1x1, and always has room for the first check
+    ///
+    pub fn with_sources(width: i16, height: i16, m_count: i16, gamemode: u8, rng: Box<dyn RngCore + Send>, clock: Box<dyn Clock>) -> Board {
+        let width = width.max(1);
+        let height = height.max(1);
+        let m_count = m_count.clamp(0, width.saturating_mul(height) - 1);
+        let started_at = clock.now();
+        Board {
+            width,
+            height,
+            m_count,
+            f_count: 0,
+            gamemode,
+            state: GameState::Starting,
+            generated: false,
+            pre_start_flags: false,
+            avoid_flags: false,
+            rules: Box::new(Standard),
+            survived: vec![],
+            topology: Box::new(SquareEight),
+
+            mine_map: vec![vec![0; width as usize]; height as usize],
+            flag_map: vec![vec![0; width as usize]; height as usize],
+            m_count_map: vec![vec![0; width as usize]; height as usize],
+            uncovered_map: vec![vec![0; width as usize]; height as usize],
+            reveal_times: vec![vec![None; width as usize]; height as usize],
+
+            rng,
+            clock,
+            started_at: Some(started_at),
+            accumulated: Duration::ZERO,
+            credit: Duration::ZERO,
+        }
+    }
+    ///
+    /// Lets flags be placed before the board is generated. If `avoid_flags` is set,
+    /// generation also keeps those flagged spaces free of mines
+    ///
+    pub fn set_pre_start_flags(&mut self, allowed: bool, avoid_flags: bool) {
+        self.pre_start_flags = allowed;
+        self.avoid_flags = avoid_flags;
+    }
+    ///
+    /// Plays the board by the given rules instead of standard ones. Must be set before the mines are placed
+    ///
+    pub fn set_rules(&mut self, rules: Box<dyn RuleSet>) {
+        self.rules = rules;
+    }
+    ///
+    /// Lays the board out on the given topology instead of the usual grid. Must be set before the mines are placed.
+    /// If the topology leaves fewer spaces than there are mines, the mine count is cut down to fit
+    ///
+    pub fn set_topology(&mut self, topology: Box<dyn Topology>) {
+        self.topology = topology;
+        self.m_count = self.m_count.min((self.space_count() - 1).max(0));
+    }
+    ///
+    /// Places the mines, keeping `start` (where the player selected) clear
+    ///
+    pub fn generate(&mut self, start: (i16, i16)) {
+        self.generate_avoiding(start, &[]);
+    }
+    ///
+    /// Places the mines, keeping `start` and the spaces in `clear` free of them, as long as the mines still fit
+    ///
+    pub fn generate_avoiding(&mut self, start: (i16, i16), clear: &[(i16, i16)]) {
+        let _span = tracing::info_span!("generate", width = self.width, height = self.height, mines = self.m_count).entered();
+        let mut avoid: Vec<(i16, i16)> = vec![start];
+        // Holes in the board never get mines
+        for i in 0..self.height {
+            for j in 0..self.width {
+                if !self.is_space(j, i) {
+                    avoid.push((j, i));
+                }
+            }
+        }
+        // Only keep the extra spaces clear if the mines still fit
+        if self.space_count() as i32 - (1 + clear.len() as i32) >= self.m_count as i32 {
+            avoid.extend(clear.iter().filter(|s| **s != start));
+        }
+        let required = avoid.len();
+        if self.avoid_flags {
+            for i in 0..self.height {
+                for j in 0..self.width {
+                    if self.is_flagged(j, i) && !avoid.contains(&(j, i)) {
+                        avoid.push((j, i));
+                    }
+                }
+            }
+            // Only keep flags clear if the mines still fit
+            // Worked out in i32, since the whole board's space count can be past what an i16 holds
+            if (self.width as i32) * (self.height as i32) - (avoid.len() as i32) < self.m_count as i32 {
+                avoid.truncate(required);
+            }
+        }
+        let mine_map = Board::generate_mine_map(self.width, self.height, self.m_count, self.gamemode, &avoid, &mut *self.rng);
+        self.set_mine_map(mine_map);
+        tracing::info!(x = start.0, y = start.1, kept_clear = avoid.len(), "mines placed");
+    }
+    ///
+    /// Uses an already generated mine map, e.g. one made on a background thread.
+    /// Any space that isn't 0 gets a mine, worth whatever the rules say
+    ///
+    pub fn set_mine_map(&mut self, mine_map: Vec<Vec<i16>>) {
+        let mut mines: Vec<(i16, i16)> = vec![];
+        for i in 0..self.height {
+            for j in 0..self.width {
+                if mine_map[i as usize][j as usize] != 0 {
+                    mines.push((j, i));
+                }
+            }
+        }
+        let weights = self.rules.arrange(self.width, self.height, &*self.topology, &mines, &mut *self.rng);
+        let mut mine_map = vec![vec![0; self.width as usize]; self.height as usize];
+        for (mine, weight) in mines.iter().zip(weights) {
+            mine_map[mine.1 as usize][mine.0 as usize] = weight;
+        }
+        let mut m_count_map = Board::generate_m_count_map(&mine_map, &*self.topology);
+        for i in 0..self.height {
+            for j in 0..self.width {
+                m_count_map[i as usize][j as usize] = self.rules.shown_count((j, i), m_count_map[i as usize][j as usize]);
+            }
+        }
+        self.m_count_map = m_count_map;
+        self.mine_map = mine_map;
+        self.generated = true;
+    }
+    ///
+    /// Generates a mine map without touching any board, so it can also be done on a background thread.
+    /// No mines are placed on the spaces in `avoid`.
+    /// This function behaves differently based on the selected gamemode
+    ///
+    pub fn generate_mine_map(width: i16, height: i16, m_count: i16, gamemode: u8, avoid: &[(i16, i16)], rng: &mut dyn RngCore) -> Vec<Vec<i16>> {
+        let mut mine_map = vec![vec![0; width as usize]; height as usize];
+        let is_corner = |x: i16, y: i16| (x == 0 || x == width - 1) && (y == 0 || y == height - 1);
+        // Narrow boards can run out of room once the corners are kept clear too, so then they aren't
+        let mut avoided = vec![vec![false; width as usize]; height as usize];
+        for space in avoid {
+            avoided[space.1 as usize][space.0 as usize] = true;
+        }
+        let mut open: i16 = 0;
+        let mut open_corners: i16 = 0;
+        for i in 0..height {
+            for j in 0..width {
+                if !avoided[i as usize][j as usize] {
+                    open = open.saturating_add(1);
+                    if is_corner(j, i) {
+                        open_corners += 1;
+                    }
+                }
+            }
+        }
+        let gamemode = if gamemode == 1 && open - open_corners < m_count { 0 } else { gamemode };
+        for _ in 0..m_count {
+            loop {
+                let rand_y = rng.random_range(0..height);
+                let rand_x = rng.random_range(0..width);
+                // First check - don't double up on mine or place where player selected
+                if mine_map[rand_y as usize][rand_x as usize] != 1 && !avoided[rand_y as usize][rand_x as usize] {
+                    // Second check based on gamemode
+                    match gamemode {
+                        1 => {
+                            // QOL gamemode - guarantee no corners
+                            if !is_corner(rand_x, rand_y) {
+                                mine_map[rand_y as usize][rand_x as usize] = 1;
+                                break;
+                            }
+                        }
+                        _ => {
+                            // Normal gamemode or No guessing - do nothing special
+                            mine_map[rand_y as usize][rand_x as usize] = 1;
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+        mine_map
+    }
+    ///
+    /// Builds the mine count map for a mine map laid out on the given topology
+    ///
+    pub fn generate_m_count_map(mine_map: &[Vec<i16>], topology: &dyn Topology) -> Vec<Vec<i16>> {
+        let mut m_count_map = vec![vec![0; mine_map[0].len()]; mine_map.len()];
+        for (i, row) in m_count_map.iter_mut().enumerate() {
+            for (j, count) in row.iter_mut().enumerate() {
+                *count = Board::get_mine_count(mine_map, topology, j as i16, i as i16);
+            }
+        }
+        m_count_map
+    }
+    ///
+    /// Get the number of mines surrounding the given position
+    ///
+    fn get_mine_count(mine_map: &[Vec<i16>], topology: &dyn Topology, x: i16, y: i16) -> i16 {
+        let height = mine_map.len() as i16;
+        let width = mine_map[0].len() as i16;
+        let mut mine_count = 0;
+        for (nx, ny) in topology.neighbors(x, y, width, height) {
+            mine_count += mine_map[ny as usize][nx as usize];
+        }
+        mine_count
+    }
+}
+
+// Queries
+impl Board {
+    pub fn width(&self) -> i16 {
+        self.width
+    }
+    pub fn height(&self) -> i16 {
+        self.height
+    }
+    pub fn m_count(&self) -> i16 {
+        self.m_count
+    }
+    pub fn f_count(&self) -> i16 {
+        self.f_count
+    }
+    pub fn state(&self) -> GameState {
+        self.state
+    }
+    pub fn is_generated(&self) -> bool {
+        self.generated
+    }
+    pub fn is_mine(&self, x: i16, y: i16) -> bool {
+        self.mine_map[y as usize][x as usize] != 0
+    }
+    ///
+    /// How many flags can be down at once: one for every mine, or as many as the player could think there are
+    /// when the rules hide the total
+    ///
+    pub fn flag_limit(&self) -> i16 {
+        match self.rules.mine_range(self.m_count) {
+            Some((_, most)) => most,
+            None => self.space_count(),
+        }
+    }
+    ///
+    /// The rules the board is played by
+    ///
+    pub fn rules(&self) -> &dyn RuleSet {
+        &*self.rules
+    }
+    ///
+    /// The shape of the board
+    ///
+    pub fn topology(&self) -> &dyn Topology {
+        &*self.topology
+    }
+    ///
+    /// Whether or not (x, y) is part of the board, rather than a hole in it
+    ///
+    pub fn is_space(&self, x: i16, y: i16) -> bool {
+        self.topology.contains(x, y, self.width, self.height)
+    }
+    ///
+    /// Number of spaces on the board, not counting holes. Like the mine count, it tops out at what an `i16` holds
+    ///
+    pub fn space_count(&self) -> i16 {
+        let mut spaces: i16 = 0;
+        for i in 0..self.height {
+            for j in 0..self.width {
+                if self.is_space(j, i) {
+                    spaces = spaces.saturating_add(1);
+                }
+            }
+        }
+        spaces
+    }
+    ///
+    /// Whether or not checking the given space opens up the spaces around it, which happens when none of them are mines.
+    /// Normally that's the same as the space showing a 0, but some rules' numbers don't say so
+    ///
+    fn opens_up(&self, x: i16, y: i16) -> bool {
+        !self.get_surrounding(x, y).iter().any(|s| self.is_mine(s.0, s.1))
+    }
+    pub fn is_flagged(&self, x: i16, y: i16) -> bool {
+        self.flag_map[y as usize][x as usize] != 0
+    }
+    pub fn is_uncovered(&self, x: i16, y: i16) -> bool {
+        self.uncovered_map[y as usize][x as usize] == 1
+    }
+    ///
+    /// When a space was uncovered, by `clock_time` like the moves in a replay, or `None` if it's still covered.
+    /// Every space a flood opens up shares the time of the check that started it
+    ///
+    pub fn revealed_at(&self, x: i16, y: i16) -> Option<Duration> {
+        if !self.is_uncovered(x, y) {
+            return None;
+        }
+        self.reveal_times[y as usize][x as usize]
+    }
+    ///
+    /// Number of mines around the given position
+    ///
+    pub fn mine_count_at(&self, x: i16, y: i16) -> i16 {
+        self.m_count_map[y as usize][x as usize]
+    }
+    ///
+    /// How long the game has been going, or how long it lasted if it is over, less whatever time bonuses took off
+    ///
+    pub fn elapsed(&self) -> Duration {
+        self.clock_time().saturating_sub(self.credit)
+    }
+    ///
+    /// How long the timer has run, without the time bonuses took off. Moves are timed by this, so they always come in order
+    ///
+    pub fn clock_time(&self) -> Duration {
+        match self.started_at {
+            Some(started_at) => self.accumulated + (self.clock.now() - started_at),
+            None => self.accumulated,
+        }
+    }
+    ///
+    /// How much time bonuses have taken off the game's time
+    ///
+    pub fn bonus_time(&self) -> Duration {
+        self.credit
+    }
+    ///
+    /// Covered spaces with a time bonus that can still be had
+    ///
+    pub fn bonuses(&self) -> Vec<(i16, i16)> {
+        self.rules.bonuses(self.clock_time()).into_iter().filter(|s| !self.is_uncovered(s.0, s.1)).collect()
+    }
+    ///
+    /// Whether the timer is stopped on a game that isn't over yet
+    ///
+    pub fn is_paused(&self) -> bool {
+        self.started_at.is_none() && self.state != GameState::Win && self.state != GameState::Loss
+    }
+    ///
+    /// Gets the spaces next to a given coordinate as a `Vec<(i16, i16)>`, going by the board's topology. Holes have none
+    ///
+    pub fn get_surrounding(&self, x: i16, y: i16) -> Vec<(i16, i16)> {
+        if !self.is_space(x, y) {
+            return vec![];
+        }
+        self.topology.neighbors(x, y, self.width, self.height).into_iter().filter(|s| self.is_space(s.0, s.1)).collect()
+    }
+    ///
+    /// Calculates the 3BV of the board: the minimum number of clicks needed to clear it.
+    /// Each opening (connected area of zeroes) counts once, as does every numbered space not bordering an opening
+    ///
+    pub fn get_3bv(&self) -> u32 {
+        self.get_3bv_progress().1
+    }
+    ///
+    /// How much of the board's 3BV has been done so far, and the 3BV itself. An opening counts as done once all of it,
+    /// border included, is uncovered, and a numbered space on its own once it is
+    ///
+    pub fn get_3bv_progress(&self) -> (u32, u32) {
+        let mut marked = vec![vec![false; self.width as usize]; self.height as usize];
+        let mut bbbv: u32 = 0;
+        let mut done: u32 = 0;
+        // 1. Count openings, marking every space that gets cleared by them
+        for i in 0..self.height {
+            for j in 0..self.width {
+                if marked[i as usize][j as usize] || !self.is_space(j, i) || self.is_mine(j, i) || !self.opens_up(j, i) {
+                    continue;
+                }
+                bbbv += 1;
+                let mut cleared = self.is_uncovered(j, i);
+                let mut to_visit: Vec<(i16, i16)> = vec![(j, i)];
+                marked[i as usize][j as usize] = true;
+                while let Some(space) = to_visit.pop() {
+                    for next in self.get_surrounding(space.0, space.1) {
+                        if !marked[next.1 as usize][next.0 as usize] {
+                            marked[next.1 as usize][next.0 as usize] = true;
+                            cleared &= self.is_uncovered(next.0, next.1);
+                            if self.opens_up(next.0, next.1) {
+                                to_visit.push(next);
+                            }
+                        }
+                    }
+                }
+                if cleared {
+                    done += 1;
+                }
+            }
+        }
+        // 2. Count the remaining safe spaces, which each need their own click
+        for i in 0..self.height {
+            for j in 0..self.width {
+                if !marked[i as usize][j as usize] && self.is_space(j, i) && !self.is_mine(j, i) {
+                    bbbv += 1;
+                    if self.is_uncovered(j, i) {
+                        done += 1;
+                    }
+                }
+            }
+        }
+        (done, bbbv)
+    }
+    ///
+    /// Counts how many spaces checking `start` on the generated board would uncover: just the one for a number,
+    /// or the whole opening and its border for a zero
+    ///
+    pub fn opening_size(&self, start: (i16, i16)) -> usize {
+        if self.is_mine(start.0, start.1) {
+            return 0;
+        }
+        let mut marked = vec![vec![false; self.width as usize]; self.height as usize];
+        let mut to_visit: Vec<(i16, i16)> = vec![start];
+        marked[start.1 as usize][start.0 as usize] = true;
+        let mut size: usize = 0;
+        while let Some(space) = to_visit.pop() {
+            size += 1;
+            if !self.opens_up(space.0, space.1) {
+                continue;
+            }
+            for next in self.get_surrounding(space.0, space.1) {
+                if !marked[next.1 as usize][next.0 as usize] {
+                    marked[next.1 as usize][next.0 as usize] = true;
+                    to_visit.push(next);
+                }
+            }
+        }
+        size
+    }
+    ///
+    /// Copies where the mines are, in the same form `set_mine_map` takes
+    ///
+    pub(crate) fn mine_layout(&self) -> Vec<Vec<i16>> {
+        self.mine_map.clone()
+    }
+    ///
+    /// Gets the board as the player sees it: revealed numbers, flags, and covered spaces, with no mine locations
+    ///
+    pub fn visible_board(&self) -> VisibleBoard {
+        let mut tiles = vec![vec![Tile::Covered; self.width as usize]; self.height as usize];
+        for i in 0..self.height {
+            for j in 0..self.width {
+                if self.uncovered_map[i as usize][j as usize] == 1 {
+                    tiles[i as usize][j as usize] = Tile::Revealed(self.m_count_map[i as usize][j as usize]);
+                } else if self.flag_map[i as usize][j as usize] != 0 {
+                    tiles[i as usize][j as usize] = Tile::Flagged;
+                }
+            }
+        }
+        VisibleBoard {
+            width: self.width,
+            height: self.height,
+            m_count: self.m_count,
+            f_count: self.f_count,
+            state: self.state,
+            tiles,
+        }
+    }
+    ///
+    /// Creates a solver that only knows what the player can see, including only as much of the mine total as the rules show
+    ///
+    #[cfg(feature = "solver")]
+    pub fn get_solver(&self) -> Solver {
+        let view = self.visible_board();
+        let solver = Solver::new(view.width, view.height, view.m_count, view.tiles);
+        match self.rules.mine_range(self.m_count) {
+            Some((fewest, most)) => solver.with_mine_range(fewest, most),
+            None => solver.with_mine_range(0, self.space_count()),
+        }
+    }
+}
+
+// Actions
+impl Board {
+    ///
+    /// Uncovers a space, flooding outward through any zeroes. Generates the mines first if needed.
+    /// Returns every space that was uncovered, or just the mine if one was hit
+    ///
+    pub fn reveal(&mut self, x: i16, y: i16) -> Vec<(i16, i16)> {
+        if self.state == GameState::Win || self.state == GameState::Loss || !self.is_space(x, y) {
+            return vec![];
+        }
+        if !self.generated {
+            self.generate((x, y));
+        }
+        self.state = GameState::Running;
+        if self.is_flagged(x, y) || self.is_uncovered(x, y) {
+            return vec![];
+        }
+        // See if there is a mine where we checked. If so, we lose, unless the rules let it slide
+        if self.is_mine(x, y) {
+            if self.rules.survive_mine() {
+                self.survived.push((x, y));
+                if !self.is_flagged(x, y) {
+                    self.flag_map[y as usize][x as usize] = 1;
+                    self.f_count += 1;
+                }
+                tracing::info!(x = x, y = y, "mine hit, but the rules let it slide");
+            } else {
+                self.state = GameState::Loss;
+                self.stop_timer();
+                tracing::info!(x = x, y = y, time_ms = self.elapsed().as_millis() as u64, "mine hit, game lost");
+            }
+            return vec![(x, y)];
+        }
+        // Otherwise uncover it, and keep going through any zeroes
+        let _span = tracing::info_span!("flood_fill").entered();
+        let time = self.clock_time();
+        let mut uncovered: Vec<(i16, i16)> = vec![];
+        let mut to_check: Vec<(i16, i16)> = vec![(x, y)];
+        self.uncovered_map[y as usize][x as usize] = 1;
+        while let Some(space) = to_check.pop() {
+            uncovered.push(space);
+            self.reveal_times[space.1 as usize][space.0 as usize] = Some(time);
+            if !self.opens_up(space.0, space.1) {
+                continue;
+            }
+            for next in self.get_surrounding(space.0, space.1) {
+                if !self.is_uncovered(next.0, next.1) && !self.is_flagged(next.0, next.1) {
+                    self.uncovered_map[next.1 as usize][next.0 as usize] = 1;
+                    to_check.push(next);
+                }
+            }
+        }
+        // Bonuses go by the clock, so taking one doesn't hold the others up for longer
+        for space in &uncovered {
+            self.credit += self.rules.time_bonus(*space, time);
+        }
+        tracing::debug!(x = x, y = y, opened = uncovered.len(), "uncovered");
+        // Every way of opening spaces goes through here, so this is where the game gets won
+        if uncovered.iter().any(|s| self.rules.wins_on(*s)) {
+            self.win();
+        } else {
+            self.check_win_condition();
+        }
+        uncovered
+    }
+    ///
+    /// Toggles a flag on a covered space. Returns whether or not anything changed
+    ///
+    pub fn flag(&mut self, x: i16, y: i16) -> bool {
+        let can_flag = self.state == GameState::Running || (self.state == GameState::Starting && self.pre_start_flags);
+        if !can_flag || !self.is_space(x, y) || self.is_uncovered(x, y) || self.survived.contains(&(x, y)) {
+            return false;
+        }
+        if !self.is_flagged(x, y) && self.f_count < self.flag_limit() {
+            self.flag_map[y as usize][x as usize] = 1;
+            self.f_count += 1;
+            tracing::debug!(x = x, y = y, flags = self.f_count, "flagged");
+            return true;
+        } else if self.is_flagged(x, y) {
+            self.flag_map[y as usize][x as usize] = 0;
+            self.f_count -= 1;
+            tracing::debug!(x = x, y = y, flags = self.f_count, "unflagged");
+            return true;
+        }
+        false
+    }
+    ///
+    /// The kind of flag on a space, or 0 if it isn't flagged. Plain flags are kind 1
+    ///
+    pub fn flag_kind(&self, x: i16, y: i16) -> u8 {
+        self.flag_map[y as usize][x as usize] as u8
+    }
+    ///
+    /// Changes the kind of a flag that's already down. Kinds only tell flags apart for the player:
+    /// every kind counts as a flag for chording and winning. Returns whether there was a flag to change
+    ///
+    pub fn set_flag_kind(&mut self, x: i16, y: i16, kind: u8) -> bool {
+        if kind == 0 || !self.is_space(x, y) || !self.is_flagged(x, y) {
+            return false;
+        }
+        self.flag_map[y as usize][x as usize] = kind as i16;
+        true
+    }
+    ///
+    /// Counts the flags around a space
+    ///
+    pub fn flags_around(&self, x: i16, y: i16) -> i16 {
+        self.get_surrounding(x, y).iter().filter(|s| self.is_flagged(s.0, s.1)).count() as i16
+    }
+    ///
+    /// Chords a space: if its number of surrounding flags matches its mine count, uncover everything else around it.
+    /// Chording a covered space just checks it
+    ///
+    pub fn chord(&mut self, x: i16, y: i16) -> Vec<(i16, i16)> {
+        // If we are trying to chord on an unchecked space, jk jk, just check
+        if !self.is_uncovered(x, y) {
+            return self.reveal(x, y);
+        }
+        let mut uncovered: Vec<(i16, i16)> = vec![];
+        if self.flags_around(x, y) == self.mine_count_at(x, y) {
+            for space in self.get_surrounding(x, y) {
+                if self.state == GameState::Loss {
+                    break;
+                }
+                uncovered.extend(self.reveal(space.0, space.1));
+            }
+        }
+        uncovered
+    }
+    ///
+    /// Stops the timer until `resume`, so time spent away from the game doesn't count
+    ///
+    pub fn pause(&mut self) {
+        self.stop_timer();
+    }
+    ///
+    /// Starts the timer again after a `pause`. Does nothing once the game is over
+    ///
+    pub fn resume(&mut self) {
+        if self.is_paused() {
+            self.started_at = Some(self.clock.now());
+        }
+    }
+    ///
+    /// Sets how much time the game has taken so far, e.g. when picking a suspended game back up.
+    /// A running timer carries on from there
+    ///
+    pub fn set_elapsed(&mut self, elapsed: Duration) {
+        self.accumulated = elapsed;
+        self.credit = Duration::ZERO;
+        if self.started_at.is_some() {
+            self.started_at = Some(self.clock.now());
+        }
+    }
+    ///
+    /// Moves the game over to another clock, e.g. to play on from a position rebuilt on a manual one.
+    /// The time taken so far is kept
+    ///
+    pub fn set_clock(&mut self, clock: Box<dyn Clock>) {
+        let elapsed = self.clock_time();
+        self.clock = clock;
+        self.accumulated = elapsed;
+        if self.started_at.is_some() {
+            self.started_at = Some(self.clock.now());
+        }
+    }
+    ///
+    /// Takes down everything a move could change, for undoing it later with `restore`
+    ///
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            state: self.state,
+            f_count: self.f_count,
+            flag_map: self.flag_map.clone(),
+            uncovered_map: self.uncovered_map.clone(),
+            reveal_times: self.reveal_times.clone(),
+            survived: self.survived.clone(),
+            rules: self.rules.copy(),
+            credit: self.credit,
+        }
+    }
+    ///
+    /// Puts the board back how it was when `checkpoint` was taken. The mines stay where they are,
+    /// and the timer keeps counting from now on, so taking a move back never takes time back with it
+    ///
+    pub fn restore(&mut self, checkpoint: &Checkpoint) {
+        self.state = checkpoint.state;
+        self.f_count = checkpoint.f_count;
+        self.flag_map = checkpoint.flag_map.clone();
+        self.uncovered_map = checkpoint.uncovered_map.clone();
+        self.reveal_times = checkpoint.reveal_times.clone();
+        self.survived = checkpoint.survived.clone();
+        self.rules = checkpoint.rules.copy();
+        self.credit = checkpoint.credit;
+        if self.state == GameState::Running && self.started_at.is_none() {
+            self.started_at = Some(self.clock.now());
+        }
+        tracing::debug!(state = ?self.state, flags = self.f_count, "checkpoint restored");
+    }
+    ///
+    /// Check win condition after clearing a space.
+    /// Win condition is defined as: every position that does NOT have a mine is checked
+    ///
+    fn check_win_condition(&mut self) -> bool {
+        if self.state != GameState::Running {
+            return self.state == GameState::Win;
+        }
+        for i in 0..self.height {
+            for j in 0..self.width {
+                if self.is_space(j, i) && !self.is_mine(j, i) && self.uncovered_map[i as usize][j as usize] != 1 {
+                    return false;
+                }
+            }
+        }
+        self.win();
+        true
+    }
+    ///
+    /// Ends the game in a win, covering every mine with a flag and taking the rest away
+    ///
+    fn win(&mut self) {
+        for i in 0..self.height {
+            for j in 0..self.width {
+                // Flags already on mines keep their kind
+                self.flag_map[i as usize][j as usize] = if self.is_mine(j, i) { self.flag_map[i as usize][j as usize].max(1) } else { 0 };
+            }
+        }
+        self.f_count = self.m_count;
+        self.state = GameState::Win;
+        self.stop_timer();
+        tracing::info!(time_ms = self.elapsed().as_millis() as u64, "board cleared, game won");
+    }
+    ///
+    /// Stops the timer, keeping the time it ran for
+    ///
+    fn stop_timer(&mut self) {
+        if let Some(started_at) = self.started_at.take() {
+            self.accumulated += self.clock.now() - started_at;
+        }
+    }
+}
+
+// Debugging
+impl Board {
+    ///
+    /// Checks that the maps, counters, and game state all agree with each other.
+    /// Returns a description of the first problem found. Used by the property tests
+    ///
+    pub fn debug_validate(&self) -> Result<(), String> {
+        let maps = [&self.mine_map, &self.flag_map, &self.m_count_map, &self.uncovered_map];
+        if maps.iter().any(|m| m.len() != self.height as usize || m.iter().any(|row| row.len() != self.width as usize)) {
+            return Err(String::from("map dimensions don't match the board size"));
+        }
+        let mut mines: i16 = 0;
+        let mut flags: i16 = 0;
+        let mut uncovered: i16 = 0;
+        for i in 0..self.height {
+            for j in 0..self.width {
+                if self.is_mine(j, i) {
+                    mines += 1;
+                    if !self.is_space(j, i) {
+                        return Err(format!("mine at ({}, {}) is in a hole", j, i));
+                    }
+                }
+                if self.is_flagged(j, i) {
+                    flags += 1;
+                }
+                if self.is_uncovered(j, i) {
+                    uncovered += 1;
+                    if !self.is_space(j, i) {
+                        return Err(format!("hole at ({}, {}) was uncovered", j, i));
+                    }
+                    if self.is_flagged(j, i) {
+                        return Err(format!("({}, {}) is both flagged and uncovered", j, i));
+                    }
+                    if self.is_mine(j, i) {
+                        return Err(format!("mine at ({}, {}) was uncovered", j, i));
+                    }
+                    if self.revealed_at(j, i).is_none_or(|time| time > self.clock_time()) {
+                        return Err(format!("({}, {}) was uncovered without a time, or later than now", j, i));
+                    }
+                }
+                if self.generated && self.m_count_map[i as usize][j as usize] != self.rules.shown_count((j, i), Board::get_mine_count(&self.mine_map, &*self.topology, j, i)) {
+                    return Err(format!("mine count at ({}, {}) is wrong", j, i));
+                }
+            }
+        }
+        if flags != self.f_count {
+            return Err(format!("f_count is {} but {} flags are placed", self.f_count, flags));
+        }
+        // Mines that were lived through get flagged past the limit if they have to
+        if self.f_count > self.flag_limit() + self.survived.len() as i16 {
+            return Err(format!("{} flags placed for {} mines", self.f_count, self.flag_limit()));
+        }
+        if self.generated && mines != self.m_count {
+            return Err(format!("{} mines placed, expected {}", mines, self.m_count));
+        }
+        if !self.generated && (mines != 0 || uncovered != 0 || self.state != GameState::Starting) {
+            return Err(String::from("board was played before the mines were generated"));
+        }
+        let safe = self.space_count() - self.m_count;
+        match self.state {
+            GameState::Starting | GameState::Running => {
+                if self.generated && uncovered == safe {
+                    return Err(String::from("every safe space is uncovered but the game wasn't won"));
+                }
+            }
+            GameState::Win => {
+                let treasure = (0..self.height).any(|i| (0..self.width).any(|j| self.is_uncovered(j, i) && self.rules.wins_on((j, i))));
+                if uncovered != safe && !treasure {
+                    return Err(String::from("won without uncovering every safe space"));
+                }
+                if self.started_at.is_some() {
+                    return Err(String::from("won game's timer is still running"));
+                }
+            }
+            GameState::Loss => {
+                if self.started_at.is_some() {
+                    return Err(String::from("lost game's timer is still running"));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
@@ -0,0 +1,80 @@
+use crate::engine::Board;
+#[cfg(feature = "solver")]
+use crate::grade;
+
+use serde::{Deserialize, Serialize};
+
+// Boards tried before settling for the one whose opening came closest
+const OPENING_ATTEMPTS: u32 = 500;
+
+///
+/// How a board gets generated once the first check is in. Stored in replays, so the same seed
+/// and options always give the same board
+///
+#[derive(PartialEq, Clone, Copy, Debug, Default, Serialize, Deserialize)]
+pub struct GenerationOptions {
+    #[serde(default)]
+    pub hard_samples: u32, // Candidate boards sampled by the hard generator, or 0 for a normal board
+    #[serde(default)]
+    pub opening_min: u16,  // Fewest spaces the first check should uncover, or 0 for no minimum
+    #[serde(default)]
+    pub opening_max: u16,  // Most spaces the first check should uncover, or 0 for no maximum
+    #[serde(default)]
+    pub clear_opening: bool, // Keep the spaces around the first check free of mines too, so it always opens up. Off in replays from before it was added
+}
+
+impl GenerationOptions {
+    ///
+    /// Places the board's mines around the first check at `start`. Hard boards always get a clear opening,
+    /// so the opening limits only apply to normal boards. Hard boards need the solver, and are generated as normal boards without it
+    ///
+    pub fn generate(&self, board: &mut Board, start: (i16, i16)) {
+        let surrounding = board.get_surrounding(start.0, start.1);
+        let _span = tracing::info_span!("generation", hard_samples = self.hard_samples, opening_min = self.opening_min, opening_max = self.opening_max).entered();
+        #[cfg(feature = "solver")]
+        if self.hard_samples > 0 {
+            grade::generate_hard(board, start, self.hard_samples);
+            return;
+        }
+        if self.opening_min == 0 && self.opening_max == 0 {
+            board.generate_avoiding(start, if self.clear_opening { &surrounding } else { &[] });
+            return;
+        }
+        // Anything past a single space needs the spaces around the start to be clear. A clear opening is skipped
+        // when the maximum is too small to hold one
+        let clear = self.opening_min > 1 || (self.clear_opening && (self.opening_max == 0 || self.opening_max as usize > surrounding.len()));
+        // Resample until the opening fits, keeping whichever board came closest in case none do
+        let mut best: Option<(usize, Vec<Vec<i16>>)> = None;
+        for _ in 0..OPENING_ATTEMPTS {
+            if clear {
+                board.generate_avoiding(start, &surrounding);
+            } else {
+                board.generate(start);
+            }
+            let distance = self.distance(board.opening_size(start));
+            if distance == 0 {
+                return;
+            }
+            if best.as_ref().is_none_or(|b| distance < b.0) {
+                best = Some((distance, board.mine_layout()));
+            }
+        }
+        if let Some((_, mine_map)) = best {
+            board.set_mine_map(mine_map);
+        }
+    }
+    ///
+    /// How far an opening of `size` spaces is from the allowed range
+    ///
+    fn distance(&self, size: usize) -> usize {
+        let min = self.opening_min as usize;
+        let max = self.opening_max as usize;
+        if size < min {
+            return min - size;
+        }
+        if max > 0 && size > max {
+            return size - max;
+        }
+        0
+    }
+}
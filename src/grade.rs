@@ -0,0 +1,128 @@
+#[cfg(feature = "solver")]
+use crate::engine::{Board, GameState, ManualClock};
+#[cfg(feature = "solver")]
+use crate::solver::{Rule, Solver, Tile};
+
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+
+///
+/// How hard a board is to solve, going by the most involved rule needed to clear it from the first check
+///
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum Grade {
+    Trivial, // Single numbers are enough
+    Easy,    // Needs subsets of numbers
+    Hard,    // Needs full enumeration
+    Expert,  // Needs at least one guess
+}
+
+impl Display for Grade {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
+        match *self {
+            Grade::Trivial => {
+                write!(f, "trivial")
+            }
+            Grade::Easy => {
+                write!(f, "easy")
+            }
+            Grade::Hard => {
+                write!(f, "hard")
+            }
+            Grade::Expert => {
+                write!(f, "expert")
+            }
+        }
+    }
+}
+
+///
+/// A board's grade, along with how many steps needed more than single numbers. Higher scores are trickier boards
+///
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug)]
+pub struct Analysis {
+    pub grade: Grade,
+    pub advanced_steps: u32,
+}
+
+///
+/// Grades a generated board by solving a copy of it from `start`, always using the simplest rule that makes progress.
+/// Returns `None` if the board hasn't been generated yet
+///
+#[cfg(feature = "solver")]
+pub fn grade(board: &Board, start: (i16, i16)) -> Option<Grade> {
+    analyze(board, start).map(|a| a.grade)
+}
+
+///
+/// Solves a copy of a generated board from `start`, keeping track of the hardest rule needed
+/// and how often anything past single numbers was needed
+///
+#[cfg(feature = "solver")]
+pub fn analyze(board: &Board, start: (i16, i16)) -> Option<Analysis> {
+    let _span = tracing::info_span!("analyze").entered();
+    if !board.is_generated() {
+        return None;
+    }
+    let width = board.width();
+    let height = board.height();
+    let mut copy = Board::deterministic(width, height, board.m_count(), 0, 0, ManualClock::new());
+    copy.set_mine_map(board.mine_layout());
+    copy.reveal(start.0, start.1);
+    // Proven mines are tracked here rather than flagged, so the flag limit never gets in the way
+    let mut known_mines: Vec<(i16, i16)> = vec![];
+    let mut hardest = Rule::SingleCell;
+    let mut advanced_steps: u32 = 0;
+    while copy.state() == GameState::Running {
+        let mut tiles = copy.visible_board().tiles;
+        for mine in &known_mines {
+            tiles[mine.1 as usize][mine.0 as usize] = Tile::Flagged;
+        }
+        let solver = Solver::new(width, height, copy.m_count(), tiles).trusting_flags();
+        let deduction = match solver.next_deduction() {
+            Some(d) => d,
+            None => return Some(Analysis { grade: Grade::Expert, advanced_steps }),
+        };
+        hardest = hardest.max(deduction.rule);
+        advanced_steps += match deduction.rule {
+            Rule::SingleCell => 0,
+            Rule::Subset => 1,
+            Rule::Enumeration => 2,
+        };
+        known_mines.extend(deduction.mines);
+        for space in deduction.safe {
+            copy.reveal(space.0, space.1);
+        }
+    }
+    let grade = match hardest {
+        Rule::SingleCell => Grade::Trivial,
+        Rule::Subset => Grade::Easy,
+        Rule::Enumeration => Grade::Hard,
+    };
+    Some(Analysis { grade, advanced_steps })
+}
+
+///
+/// Generates `samples` candidate boards with a clear opening around `start` and keeps the trickiest one
+/// that can still be solved without guessing. If every candidate needs a guess, the last one is kept.
+/// Uses only the board's own random number generator, so a seeded board always ends up the same
+///
+#[cfg(feature = "solver")]
+pub fn generate_hard(board: &mut Board, start: (i16, i16), samples: u32) {
+    let _span = tracing::info_span!("generate_hard", samples = samples).entered();
+    let opening = board.get_surrounding(start.0, start.1);
+    let mut best: Option<(Analysis, Vec<Vec<i16>>)> = None;
+    for _ in 0..samples.max(1) {
+        board.generate_avoiding(start, &opening);
+        let analysis = match analyze(board, start) {
+            Some(a) if a.grade != Grade::Expert => a,
+            _ => continue,
+        };
+        if best.as_ref().is_none_or(|b| analysis > b.0) {
+            best = Some((analysis, board.mine_layout()));
+        }
+    }
+    if let Some((_, mine_map)) = best {
+        board.set_mine_map(mine_map);
+    }
+}
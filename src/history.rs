@@ -0,0 +1,164 @@
+use crate::engine::{Board, GameState};
+use crate::replay::{Replay, ReplayAction};
+
+///
+/// One move in a move tree, along with the moves that have been tried after it
+///
+#[derive(PartialEq, Clone, Debug)]
+struct MoveNode {
+    action: Option<ReplayAction>, // Only the root has no move
+    parent: Option<usize>,
+    children: Vec<usize>,
+    selected: Option<usize>, // Child that redo follows
+}
+
+///
+/// Every line of play tried from a board, as a tree of moves with a current position in it.
+/// Undoing steps back toward the start, redoing steps forward along the most recently used line,
+/// and playing a different move after undoing branches off a new line instead of losing the old one
+///
+#[derive(PartialEq, Clone, Debug)]
+pub struct MoveTree {
+    header: Replay, // Board settings and seed, with no moves or result
+    nodes: Vec<MoveNode>,
+    current: usize,
+}
+
+impl MoveTree {
+    ///
+    /// Starts an empty tree for a board with the same settings and seed as `header`
+    ///
+    pub fn new(header: &Replay) -> MoveTree {
+        let mut header = header.clone();
+        header.actions.clear();
+        header.result = GameState::Starting;
+        header.time_ms = 0;
+        let root = MoveNode { action: None, parent: None, children: vec![], selected: None };
+        MoveTree { header, nodes: vec![root], current: 0 }
+    }
+    ///
+    /// Builds a tree holding a replay's moves as a single line, positioned at the end of the game
+    ///
+    pub fn from_replay(replay: &Replay) -> MoveTree {
+        let mut tree = MoveTree::new(replay);
+        for action in &replay.actions {
+            tree.push(*action);
+        }
+        tree
+    }
+    ///
+    /// Plays a move from the current position. If the same move was already tried from here its line is reused,
+    /// otherwise it starts a new line
+    ///
+    pub fn push(&mut self, action: ReplayAction) {
+        let existing = self.nodes[self.current].children.iter().copied().find(|&child| {
+            let tried = self.nodes[child].action.unwrap();
+            tried.kind == action.kind && tried.x == action.x && tried.y == action.y
+        });
+        let next = match existing {
+            Some(child) => child,
+            None => {
+                self.nodes.push(MoveNode { action: Some(action), parent: Some(self.current), children: vec![], selected: None });
+                let child = self.nodes.len() - 1;
+                self.nodes[self.current].children.push(child);
+                child
+            }
+        };
+        self.nodes[self.current].selected = Some(next);
+        self.current = next;
+    }
+    ///
+    /// Steps back one move. Returns false if already at the start
+    ///
+    pub fn undo(&mut self) -> bool {
+        match self.nodes[self.current].parent {
+            Some(parent) => {
+                self.current = parent;
+                true
+            }
+            None => false,
+        }
+    }
+    ///
+    /// Steps forward one move along the selected line. Returns false if there's nothing to redo
+    ///
+    pub fn redo(&mut self) -> bool {
+        match self.nodes[self.current].selected {
+            Some(child) => {
+                self.current = child;
+                true
+            }
+            None => false,
+        }
+    }
+    ///
+    /// Steps back to before the first move
+    ///
+    pub fn to_start(&mut self) {
+        self.current = 0;
+    }
+    ///
+    /// Steps forward to the end of the selected line
+    ///
+    pub fn to_end(&mut self) {
+        while self.redo() {}
+    }
+    ///
+    /// Moves that have been tried from the current position, oldest first
+    ///
+    pub fn variations(&self) -> Vec<ReplayAction> {
+        self.nodes[self.current].children.iter().map(|&child| self.nodes[child].action.unwrap()).collect()
+    }
+    ///
+    /// Makes the `index`th variation from the current position the one redo follows. Returns false if there isn't one
+    ///
+    pub fn select_variation(&mut self, index: usize) -> bool {
+        match self.nodes[self.current].children.get(index) {
+            Some(&child) => {
+                self.nodes[self.current].selected = Some(child);
+                true
+            }
+            None => false,
+        }
+    }
+    ///
+    /// The moves leading from the start to the current position
+    ///
+    pub fn line(&self) -> Vec<ReplayAction> {
+        let mut line = vec![];
+        let mut node = self.current;
+        while let Some(parent) = self.nodes[node].parent {
+            line.push(self.nodes[node].action.unwrap());
+            node = parent;
+        }
+        line.reverse();
+        line
+    }
+    ///
+    /// How many moves into the game the current position is
+    ///
+    pub fn depth(&self) -> usize {
+        let mut depth = 0;
+        let mut node = self.current;
+        while let Some(parent) = self.nodes[node].parent {
+            depth += 1;
+            node = parent;
+        }
+        depth
+    }
+    ///
+    /// The current position's line as a replay, with no result claimed yet
+    ///
+    pub fn to_replay(&self) -> Replay {
+        let mut replay = self.header.clone();
+        replay.actions = self.line();
+        replay
+    }
+    ///
+    /// Rebuilds the board at the current position by re-playing its line from the seed
+    ///
+    pub fn board(&self) -> Result<Board, String> {
+        let replay = self.to_replay();
+        replay.simulate(replay.actions.len())
+    }
+}
@@ -0,0 +1,167 @@
+//! Keys the save binds to actions, on top of the usual ones, and the screen for changing them in a game.
+//! Bindings are kept in the save's `keys` as an action's name and a key's name, e.g. "flag": "x" or "check": "space".
+
+use crate::saves::Save;
+use crate::term;
+use crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use std::collections::HashMap;
+use std::io::Write;
+
+///
+/// Actions that can be bound: each one's name in the save, the usual key for it, and what it does
+///
+pub(crate) const ACTIONS: [(&str, KeyCode, &str); 9] = [
+    ("check", KeyCode::Char('q'), "Check a space"),
+    ("flag", KeyCode::Char('w'), "Flag a space"),
+    ("chord", KeyCode::Char(' '), "Chord a number"),
+    ("up", KeyCode::Up, "Move up"),
+    ("down", KeyCode::Down, "Move down"),
+    ("left", KeyCode::Left, "Move left"),
+    ("right", KeyCode::Right, "Move right"),
+    ("reset", KeyCode::Char('r'), "Start over"),
+    ("menu", KeyCode::Char('m'), "Back to the menu"),
+];
+
+///
+/// The key a name in the save stands for: a single character, or one of "space", "enter", "tab", and the arrows
+///
+pub(crate) fn parse(name: &str) -> Option<KeyCode> {
+    match name {
+        "space" => Some(KeyCode::Char(' ')),
+        "enter" => Some(KeyCode::Enter),
+        "tab" => Some(KeyCode::Tab),
+        "up" => Some(KeyCode::Up),
+        "down" => Some(KeyCode::Down),
+        "left" => Some(KeyCode::Left),
+        "right" => Some(KeyCode::Right),
+        _ => {
+            let mut chars = name.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => Some(KeyCode::Char(c)),
+                _ => None,
+            }
+        }
+    }
+}
+
+///
+/// The name a key goes by in the save, or `None` for keys that can't be bound
+///
+pub(crate) fn name(key: KeyCode) -> Option<String> {
+    match key {
+        KeyCode::Char(' ') => Some(String::from("space")),
+        KeyCode::Char(c) => Some(c.to_string()),
+        KeyCode::Enter => Some(String::from("enter")),
+        KeyCode::Tab => Some(String::from("tab")),
+        KeyCode::Up => Some(String::from("up")),
+        KeyCode::Down => Some(String::from("down")),
+        KeyCode::Left => Some(String::from("left")),
+        KeyCode::Right => Some(String::from("right")),
+        _ => None,
+    }
+}
+
+///
+/// The key the game knows a pressed key's action by, if the save binds it to one. Keys that aren't bound
+/// to anything come through as they are, so the usual keys keep working alongside the save's
+///
+pub(crate) fn bound_key(keys: &HashMap<String, String>, pressed: KeyCode) -> KeyCode {
+    for (action, name) in keys {
+        if parse(name) != Some(pressed) {
+            continue;
+        }
+        if let Some((_, usual, _)) = ACTIONS.iter().find(|(a, _, _)| a == action) {
+            return *usual;
+        }
+    }
+    pressed
+}
+
+///
+/// The key a pressed key stands for with `vim_keys` on. h, j, k, and l move, and y, u, b, and n move diagonally,
+/// the same as the keypad's Home, Page Up, End, and Page Down. The keys they take over move to K for the key screen,
+/// B for the overview, Y for copying, H for the heat replay, and z for undo. Anything else comes through as it is
+///
+pub(crate) fn vim_key(pressed: KeyCode) -> KeyCode {
+    match pressed {
+        KeyCode::Char('h') => KeyCode::Left,
+        KeyCode::Char('j') => KeyCode::Down,
+        KeyCode::Char('k') => KeyCode::Up,
+        KeyCode::Char('l') => KeyCode::Right,
+        KeyCode::Char('y') => KeyCode::Home,
+        KeyCode::Char('u') => KeyCode::PageUp,
+        KeyCode::Char('b') => KeyCode::End,
+        KeyCode::Char('n') => KeyCode::PageDown,
+        KeyCode::Char('K') => KeyCode::Char('k'),
+        KeyCode::Char('B') => KeyCode::Char('b'),
+        KeyCode::Char('Y') => KeyCode::Char('y'),
+        KeyCode::Char('H') => KeyCode::Char('h'),
+        KeyCode::Char('z') => KeyCode::Char('u'),
+        other => other,
+    }
+}
+
+///
+/// Shows every action with the key bound to it, and lets the player pick new ones. Up and down pick an action,
+/// Enter waits for its new key, Backspace takes the binding off, and Esc is done. The save is changed as keys
+/// are picked, and left for the caller to write. `other` is listed below, for the keys that can't be rebound
+///
+pub(crate) fn rebind(save: &mut Save, other: &[String]) -> Result<(), std::io::Error> {
+    let mut selected: usize = 0;
+    let mut waiting = false; // Waiting for the new key for the selected action
+    let mut notice = String::new();
+    loop {
+        term::clear();
+        print!("Keys - bound keys work on top of the usual ones\r\n\r\n");
+        for (i, (action, usual, what)) in ACTIONS.iter().enumerate() {
+            let bound = save.keys.get(*action).cloned().unwrap_or_else(|| String::from("-"));
+            let pointer = if i == selected { ">" } else { " " };
+            print!("{} {:<18} {:<8} (usual: {})\r\n", pointer, what, bound, name(*usual).unwrap_or_default());
+        }
+        print!("\r\nOther keys\r\n");
+        for key in other {
+            print!("  {}\r\n", key);
+        }
+        print!("\r\n");
+        if waiting {
+            print!("Press the new key for \"{}\" | Esc - cancel\r\n", ACTIONS[selected].2);
+        } else {
+            print!("up/down - pick | enter - rebind | backspace - unbind | Esc - done\r\n");
+        }
+        print!("{}", notice);
+        std::io::stdout().flush()?;
+        let key = match event::read()? {
+            Event::Key(key_event) if key_event.kind == KeyEventKind::Press => key_event.code,
+            _ => continue,
+        };
+        notice.clear();
+        let action = ACTIONS[selected].0;
+        if waiting {
+            waiting = false;
+            if key == KeyCode::Esc {
+                continue;
+            }
+            match name(key) {
+                Some(name) => {
+                    // A key does one thing, so it comes off whatever it was bound to before
+                    save.keys.retain(|_, bound| *bound != name);
+                    save.keys.insert(action.to_string(), name);
+                }
+                None => notice = String::from("That key can't be bound"),
+            }
+            continue;
+        }
+        match key {
+            KeyCode::Up => selected = selected.saturating_sub(1),
+            KeyCode::Down => selected = (selected + 1).min(ACTIONS.len() - 1),
+            KeyCode::Enter => waiting = true,
+            KeyCode::Backspace | KeyCode::Delete => {
+                save.keys.remove(action);
+            }
+            KeyCode::Esc => break,
+            _ => {}
+        }
+    }
+    term::clear();
+    Ok(())
+}
@@ -0,0 +1,157 @@
+use crate::engine::Board;
+
+use serde::{Deserialize, Serialize};
+
+///
+/// A board's mines at fixed spots, instead of generated from a seed. Layouts are read and written in the
+/// Minesweeper Board Format (.mbf) other clones and tools use: a byte each for the width and height,
+/// the mine count as 2 big-endian bytes, then a byte each for every mine's x and y
+///
+#[derive(PartialEq, Clone, Debug, Serialize, Deserialize)]
+pub struct Layout {
+    pub width: i16,
+    pub height: i16,
+    pub mines: Vec<(i16, i16)>,
+}
+
+impl Layout {
+    ///
+    /// Reads a layout from the bytes of an .mbf file, checking that it describes a board that can be played
+    ///
+    pub fn from_mbf(bytes: &[u8]) -> Result<Layout, String> {
+        if bytes.len() < 4 {
+            return Err(format!("An .mbf file starts with 4 bytes of header, but this one is only {} bytes", bytes.len()));
+        }
+        let count = u16::from_be_bytes([bytes[2], bytes[3]]) as usize;
+        if bytes.len() != 4 + 2 * count {
+            return Err(format!("The header says there are {} mines, which takes {} bytes, but the file is {} bytes", count, 4 + 2 * count, bytes.len()));
+        }
+        let layout = Layout {
+            width: bytes[0] as i16,
+            height: bytes[1] as i16,
+            mines: bytes[4..].chunks(2).map(|m| (m[0] as i16, m[1] as i16)).collect(),
+        };
+        layout.validate()?;
+        Ok(layout)
+    }
+    ///
+    /// Reads a layout from rows of spaces, `true` for a mine. Every row has to be the same length
+    ///
+    pub fn from_grid(rows: &[Vec<bool>]) -> Result<Layout, String> {
+        let width = rows.first().map_or(0, |row| row.len());
+        if let Some(y) = rows.iter().position(|row| row.len() != width) {
+            return Err(format!("Row {} is {} spaces long, but the first row is {}", y + 1, rows[y].len(), width));
+        }
+        if width > i16::MAX as usize || rows.len() > i16::MAX as usize {
+            return Err(format!("A {}x{} board is too big", width, rows.len()));
+        }
+        let mut mines: Vec<(i16, i16)> = vec![];
+        for (y, row) in rows.iter().enumerate() {
+            for (x, &mine) in row.iter().enumerate() {
+                if mine {
+                    mines.push((x as i16, y as i16));
+                }
+            }
+        }
+        let layout = Layout { width: width as i16, height: rows.len() as i16, mines };
+        layout.validate()?;
+        Ok(layout)
+    }
+    ///
+    /// Reads a layout drawn as text, a line per row: `*` for a mine and `.` for a safe space, e.g. `"..*\n*.."`.
+    /// Spaces around each line and blank lines are ignored, so the art can be indented
+    ///
+    /// ```
+    /// use minesweeper::engine::{Board, GameState};
+    /// use minesweeper::layout::Layout;
+    ///
+    /// let layout = Layout::from_art("
+    ///     ..*
+    ///     *..
+    /// ").unwrap();
+    /// assert_eq!(layout.mines, vec![(2, 0), (0, 1)]);
+    /// let mut board = Board::from_layout(&layout).unwrap();
+    /// assert_eq!(board.mine_count_at(1, 0), 2);
+    /// board.reveal(0, 1);
+    /// assert_eq!(board.state(), GameState::Loss);
+    /// ```
+    ///
+    pub fn from_art(art: &str) -> Result<Layout, String> {
+        let mut rows: Vec<Vec<bool>> = vec![];
+        for line in art.lines().map(str::trim).filter(|line| !line.is_empty()) {
+            let row = line.chars().map(|c| match c {
+                '*' => Ok(true),
+                '.' => Ok(false),
+                c => Err(format!("Unexpected '{}' in row {}: spaces are '.' or '*'", c, rows.len() + 1)),
+            }).collect::<Result<Vec<bool>, String>>()?;
+            rows.push(row);
+        }
+        Layout::from_grid(&rows)
+    }
+    ///
+    /// Writes the layout as the bytes of an .mbf file. Fails if it's too big for the format
+    ///
+    pub fn to_mbf(&self) -> Result<Vec<u8>, String> {
+        self.validate()?;
+        if self.width > u8::MAX as i16 || self.height > u8::MAX as i16 {
+            return Err(format!("An .mbf board can be at most 255x255, not {}x{}", self.width, self.height));
+        }
+        let mut bytes = vec![self.width as u8, self.height as u8];
+        bytes.extend((self.mines.len() as u16).to_be_bytes());
+        for &(x, y) in &self.mines {
+            bytes.push(x as u8);
+            bytes.push(y as u8);
+        }
+        Ok(bytes)
+    }
+    ///
+    /// The layout of a board whose mines have been placed, or `None` if they haven't been yet.
+    /// Only where the mines are is kept, not what variant rules made them worth
+    ///
+    pub fn from_board(board: &Board) -> Option<Layout> {
+        if !board.is_generated() {
+            return None;
+        }
+        let mut mines: Vec<(i16, i16)> = vec![];
+        for y in 0..board.height() {
+            for x in 0..board.width() {
+                if board.is_mine(x, y) {
+                    mines.push((x, y));
+                }
+            }
+        }
+        Some(Layout { width: board.width(), height: board.height(), mines })
+    }
+    ///
+    /// The layout as a mine map for `Board::set_mine_map`
+    ///
+    pub fn mine_map(&self) -> Vec<Vec<i16>> {
+        let mut mine_map = vec![vec![0; self.width as usize]; self.height as usize];
+        for &(x, y) in &self.mines {
+            mine_map[y as usize][x as usize] = 1;
+        }
+        mine_map
+    }
+    ///
+    /// Checks that every mine is on the board, no space has two, and at least one space is left safe
+    ///
+    pub fn validate(&self) -> Result<(), String> {
+        if self.width < 1 || self.height < 1 {
+            return Err(format!("Impossible board size {}x{}", self.width, self.height));
+        }
+        let mut seen = vec![vec![false; self.width as usize]; self.height as usize];
+        for &(x, y) in &self.mines {
+            if x < 0 || y < 0 || x >= self.width || y >= self.height {
+                return Err(format!("The mine at ({}, {}) is off the {}x{} board", x, y, self.width, self.height));
+            }
+            if seen[y as usize][x as usize] {
+                return Err(format!("There are two mines at ({}, {})", x, y));
+            }
+            seen[y as usize][x as usize] = true;
+        }
+        if self.mines.len() as i32 >= (self.width as i32) * (self.height as i32) {
+            return Err(format!("{} mines leave no safe spaces on a {}x{} board", self.mines.len(), self.width, self.height));
+        }
+        Ok(())
+    }
+}
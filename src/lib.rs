@@ -0,0 +1,60 @@
+//! Terminal minesweeper's game engine: the rules of the game, a solver, replay verification, compact storage, and export (as asciinema casts, or GIFs with the `gif` feature), snapshots of lost games, par time medals, .mbf board layouts, settings imported from other clients, move history, shareable seed codes, locale-aware number and time formatting, the network protocol, and a `Renderer` trait for other frontends, with no terminal IO.
+//!
+//! A game is a `Board` driven by moves, with a `Replay` recording them so the game can be checked and stored:
+//!
+//! ```
+//! use std::time::Duration;
+//! use minesweeper::engine::{Board, GameState, ManualClock};
+//! use minesweeper::replay::{ActionKind, Replay};
+//!
+//! // The same seed always makes the same board, and time only passes when the clock is told to
+//! let clock = ManualClock::new();
+//! let mut board = Board::deterministic(9, 9, 10, 0, 42, clock.clone());
+//! let mut replay = Replay::new(9, 9, 10, 0, 42, false, false);
+//!
+//! // Check the middle, then keep checking whatever isn't a mine until the board is cleared
+//! let mut moves = vec![(4, 4)];
+//! while let Some((x, y)) = moves.pop() {
+//!     clock.advance(Duration::from_millis(250));
+//!     board.reveal(x, y);
+//!     replay.record(ActionKind::Reveal, x, y, board.elapsed());
+//!     if moves.is_empty() && board.state() == GameState::Running {
+//!         let covered = (0..9).flat_map(|y| (0..9).map(move |x| (x, y)));
+//!         moves.extend(covered.filter(|&(x, y)| !board.is_uncovered(x, y) && !board.is_mine(x, y)).take(1));
+//!     }
+//! }
+//! assert_eq!(board.state(), GameState::Win);
+//! assert_eq!(board.visible_board().m_count, 10);
+//!
+//! // The replay holds up when it's played back, and survives being stored, as JSON or packed
+//! replay.finish(board.state(), board.elapsed());
+//! assert_eq!(replay.verify().unwrap().result, GameState::Win);
+//! let json = serde_json::to_string(&replay).unwrap();
+//! assert_eq!(serde_json::from_str::<Replay>(&json).unwrap(), replay);
+//! let packed = minesweeper::compact::encode(&replay);
+//! assert_eq!(minesweeper::compact::decode(&packed).unwrap(), replay);
+//! ```
+
+#[cfg(feature = "gif")]
+pub mod animation;
+pub mod cast;
+pub mod compact;
+pub mod engine;
+pub mod generation;
+pub mod grade;
+pub mod history;
+pub mod layout;
+pub mod locale;
+pub mod medal;
+pub mod migrate;
+pub mod protocol;
+pub mod render;
+pub mod replay;
+#[cfg(feature = "solver")]
+pub mod review;
+pub mod rules;
+pub mod seed_code;
+pub mod snapshot;
+#[cfg(feature = "solver")]
+pub mod solver;
+pub mod topology;
@@ -0,0 +1,120 @@
+//! Formatting numbers and times for the player's locale: the characters that group thousands and mark decimals,
+//! and whether times read as seconds (`65.3s`) or on a clock (`1:05.3`).
+
+use std::time::Duration;
+
+///
+/// How numbers and times are written out
+///
+#[derive(PartialEq, Clone, Debug)]
+pub struct Locale {
+    pub decimal: char,           // Separates whole numbers from decimals
+    pub thousands: Option<char>, // Groups the digits of large numbers, if they're grouped at all
+    pub clock: bool,             // Times as minutes and seconds instead of a number of seconds
+}
+
+impl Default for Locale {
+    fn default() -> Locale {
+        Locale { decimal: '.', thousands: Some(','), clock: false }
+    }
+}
+
+impl Locale {
+    ///
+    /// The locale for a name like `de`, `fr_FR`, or `pt_BR.UTF-8`, going by its language (and region, for the few
+    /// languages that write numbers differently between regions). Unknown names get English formatting
+    ///
+    pub fn from_name(name: &str) -> Locale {
+        let name = name.split(['.', '@']).next().unwrap_or("").replace('-', "_").to_lowercase();
+        let language = name.split('_').next().unwrap_or("");
+        let (decimal, thousands) = match (language, name.as_str()) {
+            ("de", "de_ch") | ("it", "it_ch") => ('.', Some('\'')),
+            ("de" | "es" | "it" | "nl" | "pt" | "da" | "id" | "tr" | "el" | "ro", _) => (',', Some('.')),
+            ("fr" | "ru" | "pl" | "cs" | "sk" | "sv" | "fi" | "nb" | "no" | "uk" | "hu" | "bg", _) => (',', Some(' ')),
+            _ => ('.', Some(',')),
+        };
+        Locale { decimal, thousands, clock: false }
+    }
+    ///
+    /// The locale the environment asks for, from `LC_ALL`, `LC_NUMERIC`, or `LANG`, whichever is set first
+    ///
+    pub fn from_env() -> Locale {
+        let name = ["LC_ALL", "LC_NUMERIC", "LANG"].iter()
+            .filter_map(|var| std::env::var(var).ok())
+            .find(|value| !value.is_empty())
+            .unwrap_or_default();
+        Locale::from_name(&name)
+    }
+    ///
+    /// Writes out a whole number, with its digits grouped in threes
+    ///
+    pub fn number(&self, n: u64) -> String {
+        let digits = n.to_string();
+        let separator = match self.thousands {
+            Some(c) => c,
+            None => return digits,
+        };
+        let mut out = String::new();
+        for (i, digit) in digits.chars().enumerate() {
+            if i > 0 && (digits.len() - i).is_multiple_of(3) {
+                out.push(separator);
+            }
+            out.push(digit);
+        }
+        out
+    }
+    ///
+    /// Writes out a number with `places` decimals
+    ///
+    pub fn decimal(&self, value: f64, places: usize) -> String {
+        let text = format!("{:.*}", places, value.abs());
+        let (whole, fraction) = text.split_once('.').unwrap_or((&text, ""));
+        let sign = if value < 0. && text.chars().any(|c| c.is_ascii_digit() && c != '0') { "-" } else { "" };
+        let whole = self.number(whole.parse().unwrap_or(0));
+        if fraction.is_empty() {
+            return format!("{}{}", sign, whole);
+        }
+        format!("{}{}{}{}", sign, whole, self.decimal, fraction)
+    }
+    ///
+    /// Writes out a length of time to `places` decimals of a second: `65.3s`, or `1:05.3` on a clock
+    ///
+    pub fn time(&self, time: Duration, places: usize) -> String {
+        self.seconds(time.as_secs_f64(), places)
+    }
+    ///
+    /// Writes out a number of seconds, which may be negative, the same way as `time`
+    ///
+    pub fn seconds(&self, seconds: f64, places: usize) -> String {
+        if !self.clock {
+            return format!("{}s", self.decimal(seconds, places));
+        }
+        // Rounded first, so e.g. 59.96 seconds comes out as 1:00.0 rather than 0:60.0
+        let scale = 10f64.powi(places as i32);
+        let total = (seconds.abs() * scale).round() / scale;
+        let minutes = (total / 60.).floor();
+        let rest = total - minutes * 60.;
+        let width = if places > 0 { places + 3 } else { 2 };
+        let rest = format!("{:0width$.places$}", rest, width = width, places = places).replace('.', &self.decimal.to_string());
+        let sign = if seconds < 0. && total > 0. { "-" } else { "" };
+        format!("{}{}:{}", sign, self.number(minutes as u64), rest)
+    }
+}
+
+///
+/// A day, counted from 1970-01-01, as YYYY-MM-DD. Dates are always written this way, whatever the locale,
+/// so they sort the same as they read
+///
+pub fn date(days: u64) -> String {
+    // Howard Hinnant's `civil_from_days`
+    let z = days as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
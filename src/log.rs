@@ -0,0 +1,88 @@
+//! Logging: events from the engine, the solver, drawing, and races, as `tracing` events. The last few are kept in
+//! memory for the in-game console, and `--log-file <path>` writes every one out as it happens, so a bug report about
+//! a desync, a drawing glitch, or a solver mistake can come with what led up to it.
+
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+use std::fs::File;
+use std::io::Write;
+use std::sync::Mutex;
+use std::time::Instant;
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::layer::{Context, Layer};
+
+// Lines the console keeps. Older ones are only in the log file, if there is one
+const CONSOLE_LINES: usize = 200;
+
+// The newest lines, oldest first
+static LINES: Mutex<VecDeque<String>> = Mutex::new(VecDeque::new());
+
+///
+/// Turns events into lines for the console, and the log file if there is one
+///
+pub(crate) struct LogLayer {
+    started: Instant,
+    file: Option<Mutex<File>>,
+}
+
+impl LogLayer {
+    ///
+    /// A layer that keeps lines for the console, and writes them to `path` too if it's given
+    ///
+    pub(crate) fn new(path: Option<&str>) -> Result<LogLayer, std::io::Error> {
+        let file = match path {
+            Some(path) => Some(Mutex::new(File::create(path)?)),
+            None => None,
+        };
+        Ok(LogLayer { started: Instant::now(), file })
+    }
+}
+
+impl<S: Subscriber> Layer<S> for LogLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let metadata = event.metadata();
+        // Trace events are for the profiler
+        if *metadata.level() == Level::TRACE {
+            return;
+        }
+        let mut fields = Fields::default();
+        event.record(&mut fields);
+        let line = format!("{:>8.3} {:<5} {}: {}{}", self.started.elapsed().as_secs_f64(), metadata.level(), metadata.target(), fields.message, fields.rest);
+        if let Some(file) = &self.file && let Ok(mut file) = file.lock() {
+            writeln!(file, "{}", line).ok();
+        }
+        let mut lines = LINES.lock().unwrap();
+        if lines.len() == CONSOLE_LINES {
+            lines.pop_front();
+        }
+        lines.push_back(line);
+    }
+}
+
+///
+/// An event's message, and its other fields written out as `name=value`
+///
+#[derive(Default)]
+struct Fields {
+    message: String,
+    rest: String,
+}
+
+impl Visit for Fields {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "message" {
+            write!(self.message, "{:?}", value).ok();
+        } else {
+            write!(self.rest, " {}={:?}", field.name(), value).ok();
+        }
+    }
+}
+
+///
+/// The newest `count` lines, oldest first
+///
+pub(crate) fn last_lines(count: usize) -> Vec<String> {
+    let lines = LINES.lock().unwrap();
+    lines.iter().skip(lines.len().saturating_sub(count)).cloned().collect()
+}
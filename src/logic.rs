@@ -1,750 +1,3884 @@
-use crate::saves::Save;
-
-use rand::Rng;
-use std::time::{Duration, Instant};
-
-use crossterm::{
-    cursor::{
-        MoveTo, Hide, Show
-    },
-    event::{self, Event, KeyCode, KeyEventKind},
-    terminal::{disable_raw_mode, enable_raw_mode},
-    execute
-};
-
-///
-/// Struct that acts as a game of minesweeper. Created / managed by the TUI
-/// 
-pub struct MinesweeperGame {
-    // Info
-    x: i16,          // Current x position
-    y: i16,          // Current y position
-    width: i16,      // Board width 
-    height: i16,     // Board height
-    m_count: i16,    // Number of mines on the board
-    f_count: i16,    // Number of flags on the board
-    state: MSGState, // Whether or not the game is over
-    reset: bool,     // Whether or not to reset the game
-    time: Instant,   // Represents the instant that the game started, for getting game length
-    clicks: u64,     // Number of checks / chords done in the game
-
-    // Records
-    // Contains stats and visual info, stored in `save.json`
-    save: Save, 
-
-    // Maps
-    mine_map: Vec<Vec<i16>>,      // 0 = no mine, 1 = mine
-    flag_map: Vec<Vec<i16>>,      // 0 = no flag, 1 = flag
-    m_count_map: Vec<Vec<i16>>,   // Each space has the # of mines around it
-    uncovered_map: Vec<Vec<i16>>, // 0 = covered, 1 = uncovered. Uncovered tiles cannot be flagged.
-}
-
-#[derive(PartialEq)]
-enum MSGState {
-    Starting,
-    Running,
-    Win,
-    Loss,
-    Done
-}
-
-use std::fmt::Display;
-use std::fmt::Formatter;
-impl Display for MSGState {
-    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
-        match self {
-            &MSGState::Starting => {
-                write!(f, "Starting")
-            }
-            &MSGState::Running => {
-                write!(f, "Running")
-            }
-            &MSGState::Loss => {
-                write!(f, "Loss")
-            }
-            &MSGState::Win => {
-                write!(f, "Win")
-            }
-            &MSGState::Done => {
-                write!(f, "Done")
-            }
-        }
-    }
-}
-
-// Initialization
-impl MinesweeperGame {
-    ///
-    /// Creates a new instance of the game
-    /// 
-    fn new(width: i16, height: i16, m_count: i16) -> MinesweeperGame {
-        MinesweeperGame {
-            x: 0,
-            y: 0,
-            width: width,
-            height: height,
-            m_count: m_count,
-            f_count: 0,
-            state: MSGState::Starting,
-            reset: false,
-            time: Instant::now(),
-            clicks: 0,
-
-            save: Save::read_save(),
-
-            mine_map: vec![vec![0; width as usize]; height as usize],
-            flag_map: vec![vec![0; width as usize]; height as usize],
-            m_count_map: vec![vec![0; width as usize]; height as usize],
-            uncovered_map: vec![vec![0; width as usize]; height as usize],
-        }
-    }
-    ///
-    /// Populate the mines on the board by updating `mine_map`
-    /// This function behaves differently based on the selected gamemode
-    /// 
-    fn populate_mine_map(&mut self) {
-        for _ in 0..self.m_count {
-            let mut rng = rand::rng();
-            loop {
-                let rand_y = rng.random_range(0..self.height);
-                let rand_x = rng.random_range(0..self.width);
-                // First check - don't double up on mine or place where player selected
-                if self.mine_map[rand_y as usize][rand_x as usize] != 1 && !(self.x == rand_x && self.y == rand_y) {
-                    // Second check based on gamemode
-                    match self.save.gamemode {
-                        0 | 2 => {
-                            // Normal gamemode or No guessing - do nothing special
-                            self.mine_map[rand_y as usize][rand_x as usize] = 1;
-                            break;
-                        }
-                        1 => {
-                            // QOL gamemode - guarantee no corners
-                            if !((rand_x == 0 || rand_x == self.width - 1) && (rand_y == 0 || rand_y == self.height - 1)) {
-                                self.mine_map[rand_y as usize][rand_x as usize] = 1;
-                                break;
-                            }
-                        }
-                        _ => {
-                            // Should not occur
-                            print!("How did we get here?\r\n");
-                            std::process::exit(1);
-                        }
-                    }
-                }
-            }
-        }
-    }
-    /// 
-    /// Populate the mine counts on the board by updating `m_count_map`
-    /// 
-    fn populate_m_count_map(&mut self) {
-        for i in 0..self.height {
-            for j in 0..self.width {
-                let mine_count = Self::get_mine_count(self, j, i);
-                self.m_count_map[i as usize][j as usize] = mine_count;
-            }
-        }
-    }
-    ///
-    /// Get the number of mines surrounding the given position
-    /// 
-    fn get_mine_count(&mut self, x: i16, y: i16) -> i16 {
-        let surrounding = self.get_surrounding(x, y);
-        let mut mine_count = 0;
-        for coord in surrounding {
-            if coord.0 >= 0 && coord.0 < self.width && coord.1 >= 0 && coord.1 < self.height {
-                mine_count += self.mine_map[coord.1 as usize][coord.0 as usize];
-            }
-        }
-        return mine_count;
-    }
-}
-
-// Visualization
-impl MinesweeperGame {
-    ///
-    /// Prints the board with a map of the mines. 
-    /// Used for visualizing mine generation in testing.
-    /// "M" = mine, " " = no mine
-    /// 
-    fn _print_board_mine_map(&self) {
-        execute!(std::io::stdout(), MoveTo(0, 0)).ok();
-        print!("{}[2J", 27 as char);
-
-        print!("╔");
-        for _ in 0..(self.width*3) {
-            print!("═");
-        }
-        print!("╗\r\n");
-        for i in 0..self.height {
-            print!("║");
-            for j in 0..(self.width) {
-                if self.mine_map[i as usize][j as usize] == 1 {
-                    print!("[{}]", self.save.mine_char);
-                } else {
-                    print!("[{}]", self.save.tile_char);
-                }
-            }
-            print!("║\r\n");
-        }
-        print!("╚");
-        for _ in 0..(self.width*3) {
-            print!("═");
-        }
-        print!("╝\r\n");
-    }
-    ///
-    /// Prints the board with the calculated neighboring mine count of each position.
-    /// If a position contains a mine, it prints "M" instead.
-    /// Used for testing the `get_mine_count` algorithm
-    ///
-    fn _print_board_m_count_map(&self) {
-        execute!(std::io::stdout(), MoveTo(0, 0)).ok();
-        print!("{}[2J", 27 as char);
-
-        print!("╔");
-        for _ in 0..(self.width*3) {
-            print!("═");
-        }
-        print!("╗\r\n");
-        for i in 0..self.height {
-            print!("║");
-            for j in 0..(self.width) {
-                if self.mine_map[i as usize][j as usize] == 1 {
-                    print!("[{}]", self.save.mine_char);
-                } else {
-                    print!("[{}]", self.m_count_map[i as usize][j as usize]);
-                }
-            }
-            print!("║\r\n");
-        }
-        print!("╚");
-        for _ in 0..(self.width*3) {
-            print!("═");
-        }
-        print!("╝\r\n");
-    }
-    ///
-    /// Prints a blank board with no visual information.
-    /// Used when starting an actual game to set the initial scene
-    /// 
-    fn print_board_normal(&self) {
-        execute!(std::io::stdout(), MoveTo(0, 0)).ok();
-        print!("{}[2J", 27 as char);
-        print!("q - check | w - flag | r - reset | m - menu\r\n");
-        print!("FLAGS LEFT: {}\r\n", self.m_count);
-        print!("\x1b[{};{}m╔", self.save.border_fg, self.save.border_bg);
-        for _ in 0..(self.width*3) {
-            print!("═");
-        }
-        print!("╗\x1b[0m\r\n");
-        for _ in 0..self.height {
-            print!("\x1b[{};{}m║\x1b[0m", self.save.border_fg, self.save.border_bg);
-            for _ in 0..(self.width) {
-                print!("\x1b[{};{}m[{}]\x1b[0m", self.save.inner_fg, self.save.inner_bg, self.save.tile_char);
-            }
-            print!("\x1b[{};{}m║\x1b[0m\r\n", self.save.border_fg, self.save.border_bg);
-        }
-        print!("\x1b[{};{}m╚", self.save.border_fg, self.save.border_bg);
-        for _ in 0..(self.width*3) {
-            print!("═");
-        }
-        print!("╝\x1b[0m\r\n");
-    }
-    ///
-    /// Used to visually update the colors of an entire square after checking
-    /// 
-    fn visual_update_space(&self, x: i16, y: i16, mine_count: i16) {
-        // 0. Get the canon position
-        let pos = self.get_canon_pos(x, y);
-        // 1. Move to the character before it on the x-axis
-        execute!(std::io::stdout(), MoveTo((pos.0 - 1) as u16, (pos.1) as u16)).ok();
-        // 2. Print space info based on mine count
-        if mine_count == 0 {
-            // Empty space
-            print!("\x1b[0;30m[ ]\x1b[0m");
-        } else if mine_count == -1 {
-            // Mine
-            print!("\x1b[{};100m[{}]\x1b[0m", self.save.inner_highlight, self.save.mine_char);
-        } else if mine_count == -2 {
-            // Flag
-            print!("\x1b[{};100m[\x1b[{}m{}\x1b[{};100m]\x1b[0m", self.save.inner_fg, self.save.inner_highlight, self.save.flag_char, self.save.inner_fg);
-        } else if mine_count == -3 {
-            // No guessing - place X on start location
-            print!("\x1b[{};100m[X]\x1b[0m", self.save.inner_highlight);
-        } else {
-            // Space with mine count
-            print!("\x1b[0;30m[\x1b[0m");
-            print!("\x1b[1;{}m{}\x1b[0m", self.save.m_count_fg[(mine_count - 1) as usize], mine_count);
-            print!("\x1b[0;30m]\x1b[0m");
-        }
-    }
-    ///
-    /// Shows all of the mine locations. Used for showing mines after a loss
-    /// 
-    fn show_mines(&self) {
-        for i in 0..self.height {
-            for j in 0..self.width {
-                if self.mine_map[i as usize][j as usize] == 1 {
-                    if self.flag_map[i as usize][j as usize] != 1 {
-                        self.visual_update_space(j, i, -1);
-                    }
-                }
-            }
-        }
-    }
-    ///
-    /// Update the "mines left counter" when a flag is placed
-    ///
-    fn visual_update_f_count(&self) {
-        // Jump to where it is printed and update it
-        execute!(std::io::stdout(), MoveTo(0, 1)).ok();
-        print!("FLAGS LEFT: {}     ", self.m_count - self.f_count);
-    } 
-}
-
-// Game logic
-impl MinesweeperGame {
-    ///
-    /// Handle the start of the game, in which the player has to get a check in before the mines can generate, in order to avoid random start losses 
-    /// 
-    fn handle_start(&mut self, key_code: KeyCode, ng_start: Option<(i16, i16)>) {
-        match key_code {
-            KeyCode::Up => {
-                if self.y > 0 {
-                    self.y -= 1;
-                    self.position_cursor(self.x, self.y);
-                }
-            }
-            KeyCode::Down => {
-                if self.y < self.height - 1 {
-                    self.y += 1;
-                    self.position_cursor(self.x, self.y);
-                }
-            }
-            KeyCode::Left => {
-                if self.x > 0 {
-                    self.x -= 1;
-                    self.position_cursor(self.x, self.y);
-                }
-            }
-            KeyCode::Right => {
-                if self.x < self.width - 1{
-                    self.x += 1;
-                    self.position_cursor(self.x, self.y);
-                }
-            }
-            KeyCode::Char('q') => {
-                match self.save.gamemode {
-                    0 | 1 => { // Normal gamemodes
-                        // Generate the board, update the game state
-                        self.populate_mine_map();
-                        self.populate_m_count_map();
-                        self.state = MSGState::Running;
-                    }
-                    2 => { // No guessing mode
-                        // Ensure we are on the X
-                        let start = ng_start.unwrap();
-                        if self.x == start.0 && self.y == start.1 {
-                            self.state = MSGState::Running;
-                        }
-                    }
-                    _ => {
-                        // This should not happen
-                        std::process::exit(1);
-                    }
-                }
-            }
-            _ => {}
-        }
-    }
-    ///
-    /// Handle user input for things like checking, flagging, movement, etc.
-    /// 
-    fn handle_input(&mut self, key_code: KeyCode) {
-        match key_code {
-            KeyCode::Up => {
-                if self.y > 0 {
-                    self.y -= 1;
-                    self.position_cursor(self.x, self.y);
-                }
-            }
-            KeyCode::Down => {
-                if self.y < self.height - 1 {
-                    self.y += 1;
-                    self.position_cursor(self.x, self.y);
-                }
-            }
-            KeyCode::Left => {
-                if self.x > 0 {
-                    self.x -= 1;
-                    self.position_cursor(self.x, self.y);
-                }
-            }
-            KeyCode::Right => {
-                if self.x < self.width - 1 {
-                    self.x += 1;
-                    self.position_cursor(self.x, self.y);
-                }
-            }
-            KeyCode::Char('q') => {
-                if self.state != MSGState::Win && self.state != MSGState::Loss {
-                    // Chord
-                    if self.flag_map[self.y as usize][self.x as usize] != 1 {
-                        self.clicks += 1;
-                        self.chord();
-                    }
-                    // Check for win condition
-                    self.check_win_condition();
-                }
-            }
-            KeyCode::Char('w') => {
-                if self.state != MSGState::Win && self.state != MSGState::Loss {
-                    // Flag
-                    if self.uncovered_map[self.y as usize][self.x as usize] == 0 {
-                        if self.flag_map[self.y as usize][self.x as usize] == 0 && self.f_count < (self.m_count) {
-                            self.flag_map[self.y as usize][self.x as usize] = 1;
-                            print!("\x1b[{};{}m{}\x1b[0m", self.save.inner_highlight, self.save.inner_bg, self.save.flag_char);
-                            self.f_count += 1;
-                            self.visual_update_f_count();
-                            self.position_cursor(self.x, self.y);
-                        } else if self.flag_map[self.y as usize][self.x as usize] == 1 {
-                            self.flag_map[self.y as usize][self.x as usize] = 0;
-                            print!("\x1b[{};{}m{}\x1b[0m", self.save.inner_fg, self.save.inner_bg, self.save.tile_char);
-                            self.f_count -= 1;
-                            self.visual_update_f_count();
-                            self.position_cursor(self.x, self.y);
-                        }
-                    }
-                }
-            }
-            KeyCode::Char('r') => {
-                // Reset the game
-                self.reset = true;
-                self.state = MSGState::Done;
-            }
-            KeyCode::Char('m') => {
-                // Quit to main menu
-                self.state = MSGState::Done;
-            }
-            _ => {}
-        }
-    }
-    ///
-    /// Handle the checking action
-    /// 
-    fn check(&mut self) {
-        let temp_x = self.x;
-        let temp_y = self.y;
-        // See if there is a mine where we checked. If so, we lose.
-        if self.mine_map[self.y as usize][self.x as usize] == 1 {
-            self.state = MSGState::Loss;
-            execute!(std::io::stdout(), MoveTo(0, (self.height + 4) as u16)).ok();
-            execute!(std::io::stdout(), Hide).ok();
-            print!("Sorry! You lose.\r\n");
-            print!("Game time: {}s\r\n", self.time.elapsed().as_secs());
-            self.show_mines();
-            // Update save data
-            self.save.update_save(false, self.time.elapsed().as_secs(), self.clicks);
-            self.save.write_save();
-            return;
-        }
-        // If there is not a mine, check the mine count on the current space
-        let current_mine_count = self.m_count_map[self.y as usize][self.x as usize];
-        // Mark the uncovered map so that we know we have checked this spot already
-        self.uncovered_map[self.y as usize][self.x as usize] = 1;
-        // 1. If the mine count != 0, show mine count
-        if current_mine_count != 0 {
-            self.visual_update_space(self.x, self.y, current_mine_count);
-            self.position_cursor(self.x, self.y);
-        }
-        // 2. If the mine count == 0, show mine count and check the surrounding spaces as well
-        else if current_mine_count == 0 {
-            self.visual_update_space(self.x, self.y, current_mine_count);
-            self.position_cursor(self.x, self.y);
-            // Get surrounding spaces
-            let surrounding = self.get_surrounding(self.x, self.y);
-            let mut to_check: Vec<(i16, i16)> = vec![];
-            for space in surrounding {
-                if self.uncovered_map[space.1 as usize][space.0 as usize] != 1 {
-                    if self.m_count_map[space.1 as usize][space.0 as usize] == 0 {
-                        self.visual_update_space(self.x, self.y, current_mine_count);
-                        self.position_cursor(space.0, space.1);
-                        to_check.push((space.0, space.1));
-                    } else {
-                        self.position_cursor(space.0, space.1);
-                        self.visual_update_space(space.0, space.1, self.m_count_map[space.1 as usize][space.0 as usize]);
-                        self.position_cursor(self.x, self.y);
-                    }
-                    self.uncovered_map[space.1 as usize][space.0 as usize] = 1;
-                }
-            }
-            // If we found any zeroes, check them as well
-            for space in to_check {
-                self.x = space.0;
-                self.y = space.1;
-                self.check();
-            }
-        }
-        self.position_cursor(temp_x, temp_y);
-        self.x = temp_x;
-        self.y = temp_y;
-    }
-    ///
-    /// Handle the chording action
-    /// 
-    fn chord(&mut self) {
-        // If we are trying to chord on an unchecked space, jk jk, just check
-        if self.uncovered_map[self.y as usize][self.x as usize] == 0 {
-            self.check();
-            return;
-        }
-        // Get all the surrounding and make a list of those which are flagged
-        let surrounding = self.get_surrounding(self.x, self.y);
-        let mut num_flagged: i16 = 0;
-        let mut flagged: Vec<(i16, i16)> = vec![];
-        for space in &surrounding {
-            if self.flag_map[space.1 as usize][space.0 as usize] == 1 {
-                num_flagged += 1;
-                flagged.push(*space);
-            }
-        }
-        let temp_x = self.x;
-        let temp_y = self.y;
-        // If the number of flags matches the number of surrounding mines, we can chord.
-        if num_flagged == self.m_count_map[self.y as usize][self.x as usize] {
-            for space in surrounding {
-                if !flagged.contains(&space) {
-                    self.x = space.0;
-                    self.y = space.1;
-                    self.check();
-                }
-            }
-        }
-        // Reset to initial position
-        self.x = temp_x;
-        self.y = temp_y;
-        self.position_cursor(self.x, self.y);
-    }
-    ///
-    /// Gets the surrounding spaces of a given coordinate as a `Vec<(i16, i16)>`
-    /// 
-    fn get_surrounding(&self, x: i16, y: i16) -> Vec<(i16, i16)> {
-        // TODO make this more efficient?
-        let mut surroundings: Vec<(i16, i16)> = vec![];
-        // Left space
-        if x > 0 {
-            surroundings.push((x - 1, y));
-        }
-        // Right space
-        if x < self.width - 1 {
-            surroundings.push((x + 1, y));
-        }
-        // Top space
-        if y > 0 {
-            surroundings.push((x, y - 1));
-        }
-        // Bottom space
-        if y < self.height - 1 {
-            surroundings.push((x, y + 1));
-        }
-        // Top left
-        if x > 0 && y > 0 {
-            surroundings.push((x - 1, y - 1));
-        }
-        // Top right
-        if x < self.width - 1 && y > 0 {
-            surroundings.push((x + 1, y - 1));
-        }
-        // Bottom left
-        if x > 0 && y < self.height - 1 {
-            surroundings.push((x - 1, y + 1));
-        }
-        // Bottom right
-        if x < self.width - 1 && y < self.height - 1 {
-            surroundings.push((x + 1, y + 1));
-        }
-        return surroundings;
-    }
-    ///
-    /// Position cursor relative to board position
-    /// 
-    fn position_cursor(&self, x: i16, y: i16) {
-        let coord = self.get_canon_pos(x, y);
-        execute!(std::io::stdout(), MoveTo(coord.0 as u16, coord.1 as u16)).ok();
-    }
-    ///
-    /// Gets the cursor location that position_cursor will place the cursor at.
-    /// This is split into a different function so it can be used also to fix background colors on space check
-    /// 
-    fn get_canon_pos(&self, x: i16, y: i16) -> (i16, i16) {
-        return ((3 * x) + 2, y + 3);
-    }
-    ///
-    /// Check win condition after clearing a space
-    /// 
-    fn check_win_condition(&mut self) {
-        // Win condition is defined as:
-        // Every position that does NOT have a mine is checked
-        let mut has_won: bool = true;
-        for i in 0..self.height {
-            for j in 0..self.width {
-                if self.mine_map[i as usize][j as usize] == 0 {
-                    if self.uncovered_map[i as usize][j as usize] != 1 {
-                        has_won = false;
-                    }
-                }
-            }
-        }
-        // If we got all the way through the maps and has_won is still true, we won!
-        if has_won {
-            self.state = MSGState::Win;
-            // Update the board to have flags over the remaining mines
-            for i in 0..self.height {
-                for j in 0..self.width {
-                    if self.mine_map[i as usize][j as usize] == 1 {
-                        if self.flag_map[i as usize][j as usize] != 1 {
-                            self.visual_update_space(j, i, -2);
-                        }
-                    }
-                }
-            }
-            self.f_count = self.m_count;
-            self.visual_update_f_count();
-            // Display win message
-            // TODO reconfigure this 4 to be a non-magic number
-            execute!(std::io::stdout(), MoveTo(0, (self.height + 4) as u16)).ok();
-            execute!(std::io::stdout(), Hide).ok();
-            print!("Congrats! You won!\r\n");
-            print!("Game time: {}s\r\n", self.time.elapsed().as_secs());
-            // Update save data
-            self.save.update_save(true, self.time.elapsed().as_secs(), self.clicks);
-            self.save.write_save();
-        }
-    }
-}
-
-// Game controller
-impl MinesweeperGame {
-    ///
-    /// Handles the vanilla minesweeper gamemode
-    /// 
-    pub fn run_game(width: i16, height: i16, mine_count: i16) -> Result<(), std::io::Error> {
-        // Create game object
-        execute!(std::io::stdout(), Show).ok();
-        let _ = enable_raw_mode();
-        let mut msg = MinesweeperGame::new(width, height, mine_count);
-        // Break out into no guessing mode if need be
-        if msg.save.gamemode == 2 {
-            let res = MinesweeperGame::run_game_ng(width, height, mine_count);
-            return res;
-        }
-        // Display board size
-        msg.print_board_normal();
-        // Position the cursor
-        msg.position_cursor(msg.x, msg.y);
-        // Ensure that the user gets a click in before generating the board
-        while msg.state == MSGState::Starting {
-            if event::poll(Duration::from_millis(250))? {
-                match event::read().unwrap() {
-                    Event::Key(key_event) => {
-                        if key_event.kind == KeyEventKind::Press {
-                            msg.handle_start(key_event.code, None);
-                        }
-                    }
-                    _ => {}
-                }
-            }
-        }
-        // Reset board visually
-        execute!(std::io::stdout(), MoveTo(0,0)).ok();
-        msg.print_board_normal();
-        // We have already checked the position we started at so make sure to check it when we move there
-        msg.position_cursor(msg.x, msg.y);
-        msg.check();
-        // Main game loop
-        while msg.state != MSGState::Done {
-            if event::poll(Duration::from_millis(250))? {
-                match event::read().unwrap() {
-                    Event::Key(key_event) => {
-                        if key_event.kind == KeyEventKind::Press {
-                            msg.handle_input(key_event.code);
-                        }
-                    }
-                    _ => {}
-                }
-            }
-        }
-        // Reset if need be
-        if msg.reset {
-            MinesweeperGame::run_game(width, height, mine_count)?;
-        }
-        // Clean up
-        execute!(std::io::stdout(), Hide).ok();
-        let _ = disable_raw_mode();
-        Ok(())
-    }
-    ///
-    /// Handles the no-guessing mode
-    /// 
-    fn run_game_ng(width: i16, height: i16, mine_count: i16) -> Result<(), std::io::Error> {
-        // Create game object
-        execute!(std::io::stdout(), Show).ok();
-        print!("Creating game object\r\n"); // DEBUG
-        let mut msg = MinesweeperGame::new(width, height, mine_count);
-        print!("Generating mine map\r\n"); // DEBUG
-        // Generate the mines and get the start pos
-        msg.populate_mine_map();
-        print!("Generating mine count map\r\n"); // DEBUG
-        msg.populate_m_count_map();
-        msg.print_board_normal();
-        let mut rng = rand::rng();
-        loop {
-            let rand_y = rng.random_range(0..msg.height);
-            let rand_x = rng.random_range(0..msg.width);
-            if msg.m_count_map[rand_y as usize][rand_x as usize] == 0 && msg.mine_map[rand_y as usize][rand_x as usize] == 0 {
-                let start = (rand_x, rand_y);
-                // Once we have found the valid start, print the X
-                msg.print_board_normal();
-                msg.visual_update_space(start.0, start.1, -3);
-                msg.position_cursor(msg.x, msg.y);
-                // Then handle the start
-                while msg.state == MSGState::Starting {
-                    if event::poll(Duration::from_millis(250))? {
-                        match event::read().unwrap() {
-                            Event::Key(key_event) => {
-                                if key_event.kind == KeyEventKind::Press {
-                                    msg.handle_start(key_event.code, Some(start));
-                                }
-                            }
-                            _ => {}
-                        }
-                    }
-                }
-                break;
-            }
-        }
-        // Now handle the rest as normal
-        msg.check();
-        // Main game loop
-        while msg.state != MSGState::Done {
-            if event::poll(Duration::from_millis(250))? {
-                match event::read().unwrap() {
-                    Event::Key(key_event) => {
-                        if key_event.kind == KeyEventKind::Press {
-                            msg.handle_input(key_event.code);
-                        }
-                    }
-                    _ => {}
-                }
-            }
-        }
-        // Reset if need be
-        if msg.reset {
-            MinesweeperGame::run_game(width, height, mine_count)?;
-        }
-        // Clean up
-        execute!(std::io::stdout(), Hide).ok();
-        Ok(())
-    }
-}
+#[cfg(feature = "clipboard")]
+use crate::clipboard;
+use crate::keys;
+use crate::log;
+use crate::net::{self, JoinTarget, Link, LinkEvent, LinkStatus};
+use crate::saves::{self, GameRecord, LastGame, Save};
+use crate::scripting::{self, ScriptOutput};
+use crate::term;
+use minesweeper::cast;
+use minesweeper::compact;
+use minesweeper::engine::{Board, Checkpoint, Clock, GameState, SeedRng, SystemClock};
+#[cfg(feature = "solver")]
+use minesweeper::engine::ManualClock;
+use minesweeper::generation::GenerationOptions;
+use minesweeper::grade::Grade;
+use minesweeper::layout::Layout;
+use minesweeper::locale::{self, Locale};
+use minesweeper::medal::ParTimes;
+use minesweeper::protocol::{self, MAX_CHAT, Message, Progress};
+use minesweeper::replay::{ActionKind, Badges, Replay};
+#[cfg(feature = "solver")]
+use minesweeper::replay::ReplayAction;
+use minesweeper::seed_code::SeedCode;
+use minesweeper::rules::{self, Standard};
+use minesweeper::snapshot::Snapshot;
+use minesweeper::topology::{self, SquareEight};
+#[cfg(feature = "solver")]
+use minesweeper::{engine::{Tile, VisibleBoard}, grade, history::MoveTree, review::{self, Note, Review}};
+
+use rand::{Rng, RngCore, SeedableRng};
+use rand::rngs::StdRng;
+use rand::seq::IndexedRandom;
+use ratatui::layout::Rect;
+use ratatui::widgets::{Block, BorderType, Widget};
+use std::io::Write;
+use std::sync::{Arc, mpsc};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crossterm::{
+    cursor::{
+        MoveTo, Hide, Show
+    },
+    event::{self, DisableFocusChange, DisableMouseCapture, EnableFocusChange, EnableMouseCapture, Event, KeyCode, KeyEventKind, MouseButton, MouseEvent, MouseEventKind},
+    terminal::{self, disable_raw_mode, enable_raw_mode},
+    execute
+};
+
+// Maximum number of times a second the screen is brought up to date
+const FRAME_RATE: u32 = 30;
+// Key that puts a question mark on a space, or takes it off
+const MARK_KEY: KeyCode = KeyCode::Char('e');
+// Key that switches to the braille overview of the whole board and back
+const OVERVIEW_KEY: KeyCode = KeyCode::Char('b');
+// Key that opens the board stats over the board and closes them again
+const STATS_KEY: KeyCode = KeyCode::Char('i');
+// Key that opens the screen for rebinding keys
+const KEYS_KEY: KeyCode = KeyCode::Char('k');
+// Key that shows and hides the log console
+const CONSOLE_KEY: KeyCode = KeyCode::Char('`');
+// Spaces each dot of a braille character stands for, in the order of the dots' bits
+const BRAILLE_DOTS: [(i16, i16); 8] = [(0, 0), (0, 1), (0, 2), (1, 0), (1, 1), (1, 2), (0, 3), (1, 3)];
+// Key that pauses and resumes the game
+const PAUSE_KEY: KeyCode = KeyCode::Char('p');
+// Keys that take the last move back and play it again
+const UNDO_KEY: KeyCode = KeyCode::Char('u');
+const REDO_KEY: KeyCode = KeyCode::Char('U');
+// Key that copies the seed, or the result once the game is over
+#[cfg(feature = "clipboard")]
+const COPY_KEY: KeyCode = KeyCode::Char('y');
+// Key that opens the chat line in networked games
+const CHAT_KEY: KeyCode = KeyCode::Char('t');
+// Longest name shown next to a chat message
+const CHAT_NAME_LENGTH: usize = 16;
+// Most keys held on to while the game is busy, e.g. generating a board or waiting for a race to start
+const MAX_QUEUED_KEYS: usize = 32;
+// Key that lets go of the keys held on to while the game is busy
+const DROP_QUEUED_KEY: KeyCode = KeyCode::Backspace;
+// Width and height of the warmup board played before joining a race, and its mines
+const WARMUP_SIZE: i16 = 5;
+const WARMUP_MINES: i16 = 4;
+// Players taking turns in a hot-seat race
+const HOTSEAT_PLAYERS: u8 = 2;
+// Largest board "Surprise me" picks, which the terminal is checked against while the size is still hidden
+const SURPRISE_WIDTH: i16 = 30;
+const SURPRISE_HEIGHT: i16 = 16;
+// Candidate boards the hard generator picks the trickiest of
+#[cfg(feature = "solver")]
+const HARD_SAMPLES: u32 = 30;
+// Boards the no guessing generator tries before it settles for one that needs a guess
+#[cfg(feature = "solver")]
+const NO_GUESS_ATTEMPTS: u32 = 2000;
+// Background colors the region overlay cycles through
+#[cfg(feature = "solver")]
+const REGION_COLORS: [&str; 6] = ["44", "42", "45", "46", "43", "41"];
+// Key that shows the heat replay once the game is over
+const HEAT_KEY: KeyCode = KeyCode::Char('h');
+// Background colors of the heat replay, from the spaces opened first to the ones opened last
+const HEAT_COLORS: [&str; 5] = ["44", "46", "42", "43", "41"];
+// Steps the heat replay plays the game back in
+const HEAT_STEPS: u32 = 10;
+
+///
+/// Names a practice position the same way every time it's loaded: a hash of the board's settings and the moves that
+/// lead to it, packed the way replays are stored so it doesn't change with the JSON
+///
+fn practice_key(position: &Replay) -> String {
+    // 64 bit FNV-1a
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in compact::pack(position) {
+        hash = (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3);
+    }
+    format!("{:016x}", hash)
+}
+
+///
+/// Drops repeats of an action key that arrived in the same burst, which is what key bounce and aggressive
+/// key repeat look like. Movement keys are kept, since holding an arrow to travel is intended
+///
+fn coalesce_keys(keys: Vec<KeyCode>) -> Vec<KeyCode> {
+    let mut coalesced: Vec<KeyCode> = vec![];
+    for key in keys {
+        let movement = matches!(key, KeyCode::Up | KeyCode::Down | KeyCode::Left | KeyCode::Right
+            | KeyCode::Home | KeyCode::PageUp | KeyCode::End | KeyCode::PageDown);
+        if !movement && coalesced.last() == Some(&key) {
+            continue;
+        }
+        coalesced.push(key);
+    }
+    coalesced
+}
+
+///
+/// Holds on to a key pressed while the game is busy, so it's played once the game is ready instead of being lost.
+/// The drop key lets go of everything held so far, and keys past `MAX_QUEUED_KEYS` are left out
+///
+fn queue_key(queued: &mut Vec<KeyCode>, key: KeyCode) {
+    if key == DROP_QUEUED_KEY {
+        queued.clear();
+    } else if queued.len() < MAX_QUEUED_KEYS {
+        queued.push(key);
+    }
+}
+
+///
+/// What a busy screen says about the keys it's holding on to, if there are any
+///
+fn queued_notice(queued: &[KeyCode]) -> String {
+    match queued.len() {
+        0 => String::new(),
+        1 => String::from(" | 1 key queued, backspace - drop it"),
+        n => format!(" | {} keys queued, backspace - drop them", n),
+    }
+}
+
+// Digits for large print, 3 pixels wide, a row per entry with the leftmost pixel in the highest bit.
+// Each row of characters holds two rows of pixels as half blocks, so 2 rows fit these...
+const BIG_DIGITS_4: [[u8; 4]; 10] = [
+    [0b111, 0b101, 0b101, 0b111],
+    [0b110, 0b010, 0b010, 0b111],
+    [0b110, 0b001, 0b010, 0b111],
+    [0b111, 0b011, 0b001, 0b111],
+    [0b101, 0b101, 0b111, 0b001],
+    [0b111, 0b110, 0b001, 0b110],
+    [0b100, 0b111, 0b101, 0b111],
+    [0b111, 0b001, 0b010, 0b010],
+    [0b111, 0b101, 0b111, 0b111],
+    [0b111, 0b101, 0b111, 0b001],
+];
+// ...and 3 rows fit these
+const BIG_DIGITS_5: [[u8; 5]; 10] = [
+    [0b111, 0b101, 0b101, 0b101, 0b111],
+    [0b010, 0b110, 0b010, 0b010, 0b111],
+    [0b111, 0b001, 0b111, 0b100, 0b111],
+    [0b111, 0b001, 0b111, 0b001, 0b111],
+    [0b101, 0b101, 0b111, 0b001, 0b001],
+    [0b111, 0b100, 0b111, 0b001, 0b111],
+    [0b111, 0b100, 0b111, 0b101, 0b111],
+    [0b111, 0b001, 0b010, 0b010, 0b010],
+    [0b111, 0b101, 0b111, 0b101, 0b111],
+    [0b111, 0b101, 0b111, 0b001, 0b111],
+];
+
+///
+/// A digit drawn with half blocks as `rows` lines of 3 characters, or `None` if `text` isn't a single digit
+/// or there's no font that tall
+///
+fn big_digit(text: &str, rows: i16) -> Option<Vec<String>> {
+    let mut chars = text.chars();
+    let digit = match (chars.next(), chars.next()) {
+        (Some(c), None) => c.to_digit(10)? as usize,
+        _ => return None,
+    };
+    let pixels: &[u8] = match rows {
+        2 => &BIG_DIGITS_4[digit],
+        3 => &BIG_DIGITS_5[digit],
+        _ => return None,
+    };
+    let mut lines: Vec<String> = vec![];
+    for row in 0..rows as usize {
+        let top = pixels.get(2 * row).copied().unwrap_or(0);
+        let bottom = pixels.get(2 * row + 1).copied().unwrap_or(0);
+        lines.push((0..3).map(|column| {
+            let bit = 0b100 >> column;
+            match (top & bit != 0, bottom & bit != 0) {
+                (true, true) => '█',
+                (true, false) => '▀',
+                (false, true) => '▄',
+                (false, false) => ' ',
+            }
+        }).collect());
+    }
+    Some(lines)
+}
+
+///
+/// A bar `width` characters wide, filled in for how far `done` is through `total`
+///
+#[cfg(feature = "solver")]
+fn progress_bar(done: usize, total: usize, width: usize) -> String {
+    let filled = (done.min(total) * width).checked_div(total).unwrap_or(width);
+    format!("{}{}", "█".repeat(filled), "░".repeat(width - filled))
+}
+
+///
+/// How much of the terminal each space takes up. Spaces are normally 3 columns by 1 row,
+/// and large print scales them up
+///
+#[derive(Clone, Copy)]
+struct CellGeometry {
+    width: i16,
+    height: i16,
+}
+
+impl CellGeometry {
+    ///
+    /// The geometry for a large print setting from the save: 2 or 3 makes spaces that many rows tall, anything else is normal size
+    ///
+    fn from_large_print(large_print: u8) -> CellGeometry {
+        let scale = if (2..=3).contains(&large_print) { large_print as i16 } else { 1 };
+        CellGeometry { width: 3 * scale, height: scale }
+    }
+}
+
+///
+/// Spreadsheet style name of a column: A to Z, then AA, AB, and so on
+///
+fn column_name(x: i16) -> String {
+    let mut name = String::new();
+    let mut n = x as u32 + 1;
+    while n > 0 {
+        n -= 1;
+        name.insert(0, char::from_u32('A' as u32 + n % 26).unwrap());
+        n /= 26;
+    }
+    name
+}
+
+///
+/// Where on a board a space is, for telling starts apart: "corner", "edge", or "middle"
+///
+fn start_kind(x: i16, y: i16, width: i16, height: i16) -> &'static str {
+    let (side_x, side_y) = (x == 0 || x == width - 1, y == 0 || y == height - 1);
+    match (side_x, side_y) {
+        (true, true) => "corner",
+        (false, false) => "middle",
+        _ => "edge",
+    }
+}
+
+///
+/// Paces redraws to a fixed rate using the engine's clock, independent of how often input arrives
+///
+struct FrameLimiter {
+    clock: SystemClock,
+    interval: Duration,
+    next_frame: Duration,
+    dirty: bool, // Whether something changed since the last frame
+}
+
+impl FrameLimiter {
+    fn new(frame_rate: u32) -> FrameLimiter {
+        let clock = SystemClock::new();
+        let next_frame = clock.now();
+        FrameLimiter {
+            clock,
+            interval: Duration::from_secs(1) / frame_rate,
+            next_frame,
+            dirty: true,
+        }
+    }
+    ///
+    /// How long until the next frame is due, for use as an input poll timeout
+    ///
+    fn until_next(&self) -> Duration {
+        self.next_frame.saturating_sub(self.clock.now())
+    }
+    ///
+    /// Whether it's time to draw a frame. Schedules the next one if it is
+    ///
+    fn due(&mut self) -> bool {
+        let now = self.clock.now();
+        if now < self.next_frame {
+            return false;
+        }
+        self.next_frame = now + self.interval;
+        true
+    }
+}
+
+///
+/// A race against another player over the network. Both play the same board from the same start,
+/// and only share how far along they are
+///
+struct Race {
+    link: Link,
+    hosting: bool,
+    board: Replay,              // The board being raced on, as an empty replay
+    start: (i16, i16),          // Where both players start
+    opponent: Option<Progress>, // Last heard from the other player
+    chat: Option<(String, String)>, // Last chat message, and who said it
+}
+
+///
+/// Struct that acts as a game of minesweeper. Created / managed by the TUI
+/// 
+pub struct MinesweeperGame {
+    // Info
+    x: i16,          // Current x position
+    y: i16,          // Current y position
+    width: i16,      // Board width 
+    height: i16,     // Board height
+    m_count: i16,    // Number of mines on the board
+    state: MSGState, // Whether or not the game is over
+    reset: bool,     // Whether or not to reset the game
+    next_game: Option<Category>, // Game to start once this game is left, picked from the end screen
+    clicks: u64,     // Number of checks / chords done in the game
+    category: Category, // Which menu entry the game was started from
+    assists: u32,    // Number of times the solver was asked for help during the game
+    guesses: u32,    // Checks and chords that opened a space the solver couldn't prove safe
+    forced_guesses: u32, // Guesses made when nothing on the board could be proven
+    guesses_survived: u32, // Guesses that didn't hit a mine
+    start: Option<(i16, i16)>, // Where the first check was, if the player picked it
+    ng_start: Option<(i16, i16)>, // The X to start from in no guessing mode and races
+    replay: Option<Replay>,  // Every move made, for boards that can be regenerated from their seed
+    grade: Option<Grade>,    // How hard the solver found the board, once the first check is in
+    generation: GenerationOptions, // How the board is generated around the first check
+    last_flag: Option<((i16, i16), Instant)>, // The last space flagged or unflagged, and when
+    race: Option<Race>,      // The other player, in networked races
+    focus_paused: bool,      // Paused because the terminal lost focus, so getting it back resumes the game
+    marks: Vec<(i16, i16)>,  // Spaces marked with a question mark. Only a note to the player, so the board knows nothing about them
+    geometry: CellGeometry,  // How big each space is drawn
+    locale: Locale,          // How numbers and times are written out
+    number_marks: bool,      // Numbers go between brackets of their own, for when color can't tell them apart
+    feedback_shown: bool,    // A message about why the last key did nothing is up, to be cleared by the next key
+    practice: Option<String>, // The position being practiced from, whose results go to its own stats instead of the usual ones
+    warmup: bool,            // A throwaway board before joining a race, which doesn't count for anything
+    hotseat: Option<u8>,     // The player whose turn it is, in hot-seat races. Like a warmup, it doesn't count for anything
+    history: Vec<Checkpoint>,  // The board before each move since the first check, newest last, for undo
+    undone: Vec<Checkpoint>,   // The boards moves were taken back from, newest last, for redo
+    undos: u32,              // Moves taken back. A game with any doesn't count
+    bonuses_shown: Vec<(i16, i16)>, // Spaces drawn with a time bonus on them
+    queued: Vec<KeyCode>,    // Keys pressed while the game was busy, as the game takes them, to be played in order
+
+    // Records
+    // Contains stats and visual info, stored in `save.json`
+    save: Save, 
+
+    // Rules and maps, kept free of any rendering
+    board: Board,
+}
+
+#[derive(PartialEq)]
+enum MSGState {
+    Starting,
+    Running,
+    Win,
+    Loss,
+    Done
+}
+
+///
+/// The menu category a game belongs to. Each category is tracked separately in the save
+/// 
+#[derive(PartialEq, Clone, Copy)]
+pub enum Category {
+    Beginner,
+    Intermediate,
+    Expert,
+    Custom,
+    Surprise,
+    Race
+}
+
+impl Category {
+    ///
+    /// Width, height, and mine count of the preset boards
+    ///
+    pub fn preset(&self) -> Option<(i16, i16, i16)> {
+        match self {
+            Category::Beginner => Some((9, 9, 10)),
+            Category::Intermediate => Some((16, 16, 40)),
+            Category::Expert => Some((30, 16, 99)),
+            _ => None,
+        }
+    }
+    ///
+    /// The menu category with the given name, as shown by `Display`. Races don't start from the menu, so they aren't one
+    ///
+    pub fn from_name(name: &str) -> Option<Category> {
+        match name {
+            "beginner" => Some(Category::Beginner),
+            "intermediate" => Some(Category::Intermediate),
+            "expert" => Some(Category::Expert),
+            "custom" => Some(Category::Custom),
+            "surprise" => Some(Category::Surprise),
+            _ => None,
+        }
+    }
+}
+
+use std::fmt::Display;
+use std::fmt::Formatter;
+impl Display for Category {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
+        match *self {
+            Category::Beginner => {
+                write!(f, "beginner")
+            }
+            Category::Intermediate => {
+                write!(f, "intermediate")
+            }
+            Category::Expert => {
+                write!(f, "expert")
+            }
+            Category::Custom => {
+                write!(f, "custom")
+            }
+            Category::Surprise => {
+                write!(f, "surprise")
+            }
+            Category::Race => {
+                write!(f, "race")
+            }
+        }
+    }
+}
+
+impl Display for MSGState {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
+        match *self {
+            MSGState::Starting => {
+                write!(f, "Starting")
+            }
+            MSGState::Running => {
+                write!(f, "Running")
+            }
+            MSGState::Loss => {
+                write!(f, "Loss")
+            }
+            MSGState::Win => {
+                write!(f, "Win")
+            }
+            MSGState::Done => {
+                write!(f, "Done")
+            }
+        }
+    }
+}
+
+// Initialization
+impl MinesweeperGame {
+    ///
+    /// Creates a new instance of the game
+    /// 
+    fn new(width: i16, height: i16, m_count: i16, category: Category, seed: Option<u64>) -> MinesweeperGame {
+        let save = Save::read_save();
+        // Every board gets a seed so it can be replayed and verified later. Chosen seeds are meant to be shared,
+        // so they always go to the generator that stays the same between versions
+        let rng = if seed.is_some() || save.rng == "pcg" { SeedRng::Pcg } else { SeedRng::Std };
+        let seed = seed.unwrap_or_else(rand::random);
+        let mut board = Board::seeded(width, height, m_count, save.gamemode, seed, rng);
+        board.set_pre_start_flags(save.pre_start_flags, save.pre_flags_safe);
+        // Unknown rules in the save fall back to standard ones
+        board.set_rules(rules::from_name(&save.rules).unwrap_or_else(|| Box::new(Standard)));
+        board.set_topology(topology::from_name(&save.topology).unwrap_or_else(|| Box::new(SquareEight)));
+        // Boards with holes can hold fewer mines
+        let m_count = board.m_count();
+        // Hard boards are picked by the solver, which can only read standard numbers on the usual grid
+        #[cfg(feature = "solver")]
+        let hard_samples = if save.hard_boards && board.rules().standard_numbers() && board.topology().is_plain_grid() { HARD_SAMPLES } else { 0 };
+        #[cfg(not(feature = "solver"))]
+        let hard_samples = 0;
+        let generation = GenerationOptions {
+            hard_samples,
+            opening_min: save.opening_min,
+            opening_max: save.opening_max,
+            clear_opening: true,
+        };
+        // No guessing boards come from a separate generator, so they can't be replayed from the seed
+        let replay = match save.gamemode {
+            0 | 1 => Some(Replay {
+                generation,
+                rules: board.rules().name(),
+                topology: board.topology().name(),
+                rng: rng.name(),
+                ..Replay::new(width, height, m_count, save.gamemode, seed, save.pre_start_flags, save.pre_flags_safe)
+            }),
+            _ => None,
+        };
+        MinesweeperGame {
+            x: 0,
+            y: 0,
+            width,
+            height,
+            m_count,
+            state: MSGState::Starting,
+            reset: false,
+            next_game: None,
+            clicks: 0,
+            category,
+            assists: 0,
+            guesses: 0,
+            forced_guesses: 0,
+            guesses_survived: 0,
+            start: None,
+            ng_start: None,
+            replay,
+            grade: None,
+            generation,
+            last_flag: None,
+            race: None,
+            focus_paused: false,
+            feedback_shown: false,
+            practice: None,
+            warmup: false,
+            hotseat: None,
+            history: vec![],
+            undone: vec![],
+            undos: 0,
+            bonuses_shown: vec![],
+            queued: vec![],
+            marks: vec![],
+            geometry: CellGeometry::from_large_print(save.large_print),
+            locale: save.locale(),
+            number_marks: save.number_marks || term::no_color(),
+
+            save,
+
+            board,
+        }
+    }
+}
+
+// Visualization
+impl MinesweeperGame {
+    ///
+    /// Prints the board with a map of the mines. 
+    /// Used for visualizing mine generation in testing.
+    /// "M" = mine, " " = no mine
+    /// 
+    fn _print_board_mine_map(&self) {
+        term::clear();
+
+        print!("╔");
+        for _ in 0..(self.width*3) {
+            print!("═");
+        }
+        print!("╗\r\n");
+        for i in 0..self.height {
+            print!("║");
+            for j in 0..(self.width) {
+                if self.board.is_mine(j, i) {
+                    print!("[{}]", self.save.mine_char);
+                } else {
+                    print!("[{}]", self.save.tile_char);
+                }
+            }
+            print!("║\r\n");
+        }
+        print!("╚");
+        for _ in 0..(self.width*3) {
+            print!("═");
+        }
+        print!("╝\r\n");
+    }
+    ///
+    /// Whether or not the board is still hidden, as a surprise game's is until the first check
+    ///
+    fn surprise_hidden(&self) -> bool {
+        self.category == Category::Surprise && self.state == MSGState::Starting
+    }
+    ///
+    /// Where a surprise game's first check goes: the X on no guessing boards, or else the space nearest the middle
+    ///
+    fn surprise_start(&self) -> (i16, i16) {
+        if let Some(start) = self.ng_start {
+            return start;
+        }
+        let middle = (self.width / 2, self.height / 2);
+        let spaces = (0..self.height).flat_map(|i| (0..self.width).map(move |j| (j, i)));
+        spaces.filter(|&(j, i)| self.board.is_space(j, i))
+            .min_by_key(|&(j, i)| (j - middle.0).abs() + (i - middle.1).abs())
+            .unwrap_or(middle)
+    }
+    ///
+    /// The line of keys over the board. It's kept short enough for narrow terminals, and the rest of the keys
+    /// are listed on the key screen
+    ///
+    fn help_line(&self) -> String {
+        let check = if self.save.combined_chord { "q - check / chord" } else { "q - check | space - chord" };
+        let keys = if self.save.vim_keys { "K" } else { "k" };
+        let chat = if self.race.is_some() { " | t - chat" } else { "" };
+        format!("{} | w - flag | r - reset | m - menu | {} - keys{}", check, keys, chat)
+    }
+    ///
+    /// Every key the help line leaves off, for the key screen
+    ///
+    fn other_keys(&self) -> Vec<String> {
+        // Vim keys take over b, u, and y, so those move to other keys
+        let vim = self.save.vim_keys;
+        let mut other = vec![String::from("e - question mark")];
+        if cfg!(feature = "solver") {
+            other.extend(["f - flag certain", "c - configurations", "o - regions"].map(String::from));
+        }
+        if cfg!(feature = "clipboard") {
+            other.push(String::from(if vim { "Y - copy seed code" } else { "y - copy seed code" }));
+        }
+        if !self.racing() {
+            other.push(String::from("p - pause"));
+        }
+        if self.can_undo() {
+            other.push(String::from(if vim { "z/U - undo/redo" } else { "u/U - undo/redo" }));
+        }
+        other.push(String::from(if vim { "B - overview" } else { "b - overview" }));
+        other.extend(["i - stats", "` - log"].map(String::from));
+        other
+    }
+    ///
+    /// Prints the board with the calculated neighboring mine count of each position.
+    /// If a position contains a mine, it prints "M" instead.
+    /// Used for testing the `get_mine_count` algorithm
+    ///
+    fn _print_board_m_count_map(&self) {
+        term::clear();
+
+        print!("╔");
+        for _ in 0..(self.width*3) {
+            print!("═");
+        }
+        print!("╗\r\n");
+        for i in 0..self.height {
+            print!("║");
+            for j in 0..(self.width) {
+                if self.board.is_mine(j, i) {
+                    print!("[{}]", self.save.mine_char);
+                } else {
+                    print!("[{}]", self.board.mine_count_at(j, i));
+                }
+            }
+            print!("║\r\n");
+        }
+        print!("╚");
+        for _ in 0..(self.width*3) {
+            print!("═");
+        }
+        print!("╝\r\n");
+    }
+    ///
+    /// Prints a blank board with no visual information.
+    /// Used when starting an actual game to set the initial scene
+    /// 
+    fn print_board_normal(&self) {
+        term::clear();
+        print!("{}\r\n", self.help_line());
+        if self.surprise_hidden() {
+            // Surprise games keep their parameters hidden until the first click, board size included
+            print!("FLAGS LEFT: ?\r\n\r\n");
+            print!("The board is a surprise too - q makes the first check, in the middle of it\r\n");
+            return;
+        }
+        print!("{}\r\n", self.flag_counter());
+        let border = [self.save.border_fg.as_str(), self.save.border_bg.as_str()];
+        let geometry = self.geometry;
+        let inner_width = (self.board.topology().screen_width(self.width, self.height) * geometry.width / 3) as u16;
+        let rows = (self.height * geometry.height) as u16;
+        let labels = if self.save.coordinates != 0 { 1 + self.height.to_string().len() as u16 } else { 0 };
+        // The board and its border are drawn through ratatui, from the row under the status bar
+        let area = Rect::new(0, 2, inner_width + 2 + labels, rows + 2);
+        term::board_view(area);
+        term::draw_board(area, |cells| {
+            let framed = Rect::new(0, 2, inner_width + 2, rows + 2);
+            Block::bordered().border_type(BorderType::Double).border_style(term::cell_style(&border)).render(framed, cells);
+            if self.save.coordinates != 0 {
+                // Column names go in the border, over the middle of each column
+                for j in 0..self.width {
+                    let start = self.cell_origin(j, 0).0 + geometry.width / 2;
+                    term::put(cells, start as u16, 2, &border, &column_name(j));
+                }
+                // Row numbers go to the right, so the board stays put whether or not they're shown
+                for i in 0..self.height {
+                    let line = 3 + (i * geometry.height + geometry.height / 2) as u16;
+                    term::put(cells, inner_width + 2, line, &[], &format!(" {}", self.row_number(i)));
+                }
+            }
+        });
+        // Anything printed after the board goes under it
+        execute!(std::io::stdout(), MoveTo(0, 4 + rows)).ok();
+        // The border goes around blank rows, and the spaces are drawn into them afterwards
+        for i in 0..self.height {
+            for j in 0..self.width {
+                // Holes in the board are left blank
+                if self.board.is_space(j, i) {
+                    self.visual_restore_space(j, i);
+                }
+            }
+        }
+        self.visual_update_timer();
+        if self.category == Category::Surprise {
+            self.visual_update_surprise();
+        }
+        self.visual_update_grade();
+        self.visual_update_rules();
+    }
+    ///
+    /// Updates the timer in the status bar according to the timer mode in the save.
+    /// Hidden timers are only revealed once the game is over
+    ///
+    fn visual_update_timer(&self) {
+        execute!(std::io::stdout(), MoveTo(18, 1)).ok();
+        let elapsed = self.board.elapsed().as_secs();
+        let game_over = self.state == MSGState::Win || self.state == MSGState::Loss;
+        let timer = match self.save.timer_mode {
+            1 => format!("TIME LEFT: {}", self.locale.time(Duration::from_secs(self.save.timer_target.saturating_sub(elapsed)), 0)),
+            2 if !game_over => String::from("TIME: --"),
+            _ => format!("TIME: {}", self.locale.time(Duration::from_secs(elapsed), 0)),
+        };
+        print!("{:<13}", timer);
+    }
+    ///
+    /// Reveals the parameters of a surprise game next to the flag counter
+    ///
+    fn visual_update_surprise(&self) {
+        execute!(std::io::stdout(), MoveTo(32, 1)).ok();
+        print!("SURPRISE: {}x{}, {} mines", self.width, self.height, self.m_count);
+    }
+    ///
+    /// Shows the board's grade at the end of the status bar
+    ///
+    fn visual_update_grade(&self) {
+        if let Some(grade) = self.grade {
+            execute!(std::io::stdout(), MoveTo(60, 1)).ok();
+            print!("GRADE: {}", grade);
+        }
+    }
+    ///
+    /// Shows the space under the cursor if the board is labeled, what the rules have to say (e.g. lives left),
+    /// the detailed counters if the save asks for them, and how a race is going on the row under the board
+    ///
+    fn visual_update_rules(&self) {
+        // The row would give a surprise board's height away
+        if self.surprise_hidden() {
+            return;
+        }
+        let mut parts: Vec<String> = vec![];
+        if self.save.coordinates != 0 {
+            parts.push(format!("AT: {} ({})", self.cell_name(self.x, self.y), self.describe_space(self.x, self.y)));
+        }
+        if self.save.detailed_counters {
+            parts.push(self.counters());
+        }
+        if let Some(status) = self.board.rules().status() {
+            parts.push(status);
+        }
+        if let Some(race) = self.race_status() {
+            parts.push(race);
+        }
+        if let Some(player) = self.hotseat {
+            parts.push(format!("PLAYER {}", player));
+        }
+        // Cut to the terminal's width, since a line that wraps would run into the messages under it
+        let columns = terminal::size().map_or(usize::MAX, |(columns, _)| columns as usize);
+        let line: String = parts.join(" | ").chars().take(columns).collect();
+        // Chat makes the line longer or shorter at any time, so whatever was there is cleared
+        execute!(std::io::stdout(), MoveTo(0, self.status_row())).ok();
+        print!("{}", line);
+        term::clear_line();
+    }
+    ///
+    /// Draws an uncovered space with its number. Unlike `visual_update_space`, any number works here,
+    /// including the ones past 8 and below 0 that variant rules can show
+    ///
+    fn visual_update_number(&self, x: i16, y: i16) {
+        let mine_count = self.board.mine_count_at(x, y);
+        if (1..=8).contains(&mine_count) || mine_count == 0 {
+            self.visual_update_space(x, y, mine_count);
+            return;
+        }
+        self.paint_number(x, y, mine_count);
+    }
+    ///
+    /// Used to visually update the colors of an entire square after checking
+    /// 
+    fn visual_update_space(&self, x: i16, y: i16, mine_count: i16) {
+        if mine_count == 0 {
+            // Empty space
+            self.paint_cell(x, y, &[&self.save.revealed], &[&self.save.revealed], &self.save.empty_char);
+        } else if mine_count == -1 {
+            // Mine
+            self.paint_cell(x, y, &[&self.save.inner_highlight, "100"], &[&self.save.inner_highlight, "100"], &self.save.mine_char);
+        } else if mine_count == -2 {
+            // Flag
+            self.paint_cell(x, y, &[&self.save.inner_fg, "100"], &[&self.save.inner_highlight, "100"], &self.save.flag_char);
+        } else if mine_count == -3 {
+            // No guessing - place X on start location
+            self.paint_cell(x, y, &[&self.save.inner_highlight, "100"], &[&self.save.inner_highlight, "100"], "X");
+        } else {
+            // Space with mine count
+            self.paint_number(x, y, mine_count);
+        }
+    }
+    ///
+    /// Draws an uncovered number in its color, and between its own brackets when colors are off (or the save asks for it),
+    /// so each number still looks different at a glance
+    ///
+    fn paint_number(&self, x: i16, y: i16, mine_count: i16) {
+        let color = &self.save.m_count_fg[(mine_count.abs().clamp(1, 8) - 1) as usize];
+        let text = cast::number_text(&self.save.m_count_chars, mine_count);
+        let (open, close) = self.number_brackets(mine_count);
+        self.paint_cell_between(x, y, &[&self.save.revealed], &[&self.save.revealed, "1", color], &text, (&open, &close));
+    }
+    ///
+    /// The brackets a number is drawn between
+    ///
+    fn number_brackets(&self, mine_count: i16) -> (String, String) {
+        if self.number_marks {
+            return cast::number_brackets(&self.save.number_brackets, mine_count);
+        }
+        (String::from("["), String::from("]"))
+    }
+    ///
+    /// Draws a space as a block the size of the cell geometry: brackets down each side in `frame` colors, around `symbol`
+    /// in `symbol_colors`. In large print, single digits are drawn big
+    ///
+    fn paint_cell(&self, x: i16, y: i16, frame: &[&str], symbol_colors: &[&str], symbol: &str) {
+        self.paint_cell_between(x, y, frame, symbol_colors, symbol, ("[", "]"));
+    }
+    ///
+    /// Draws a space the same way as `paint_cell`, between other brackets
+    ///
+    fn paint_cell_between(&self, x: i16, y: i16, frame: &[&str], symbol_colors: &[&str], symbol: &str, brackets: (&str, &str)) {
+        let (left, top) = self.cell_origin(x, y);
+        let inside = self.geometry.width - 2;
+        let big = big_digit(symbol, self.geometry.height);
+        // Long numbers can push the brackets sideways, so the space's whole band of rows is redrawn
+        let band = Rect::new(0, top as u16, u16::MAX, self.geometry.height as u16);
+        term::draw_board(band, |cells| {
+            for row in 0..self.geometry.height {
+                let line = (top + row) as u16;
+                let text = match &big {
+                    Some(lines) => lines[row as usize].as_str(),
+                    None if row == self.geometry.height / 2 => symbol,
+                    None => "",
+                };
+                let width = text.chars().count() as i16;
+                let mut column = left as u16;
+                // Numbers too long to fit between the brackets push the opening one out, as variant rules' two digit numbers do
+                if width <= inside {
+                    column = term::put(cells, column, line, frame, brackets.0);
+                }
+                let padding = (inside - width).max(0);
+                column = term::put(cells, column, line, frame, &" ".repeat((padding / 2) as usize));
+                column = term::put(cells, column, line, symbol_colors, text);
+                column = term::put(cells, column, line, frame, &" ".repeat((padding - padding / 2) as usize));
+                term::put(cells, column, line, frame, brackets.1);
+            }
+        });
+    }
+    ///
+    /// Redraws the whole board from the game's state, e.g. after the terminal was resized
+    /// or a multiplexer session was reattached
+    ///
+    fn redraw(&self) {
+        let _span = tracing::info_span!("redraw").entered();
+        term::begin_frame();
+        self.print_board_normal();
+        // Nothing else goes on the screen until a surprise game's size is out
+        if self.surprise_hidden() {
+            term::end_frame();
+            return;
+        }
+        // A paused game keeps its board hidden, so it can't be studied while the clock is stopped
+        if self.board.is_paused() {
+            self.visual_update_message("PAUSED - p to resume");
+            term::end_frame();
+            return;
+        }
+        if self.board.is_generated() {
+            for i in 0..self.height {
+                for j in 0..self.width {
+                    if self.board.is_uncovered(j, i) {
+                        self.visual_update_number(j, i);
+                    }
+                }
+            }
+            if self.state == MSGState::Loss {
+                self.show_mines();
+            }
+        }
+        if self.category == Category::Surprise {
+            self.visual_update_f_count();
+        }
+        if let Some(start) = self.ng_start && self.state == MSGState::Starting {
+            self.visual_update_space(start.0, start.1, -3);
+        }
+        self.position_cursor(self.x, self.y);
+        term::end_frame();
+    }
+    ///
+    /// Shows all of the mine locations. Used for showing mines after a loss
+    /// 
+    fn show_mines(&self) {
+        for i in 0..self.height {
+            for j in 0..self.width {
+                if self.board.is_mine(j, i) && !self.board.is_flagged(j, i) {
+                    self.visual_update_space(j, i, -1);
+                }
+            }
+        }
+    }
+    ///
+    /// Color of the flag on a space: the usual highlight for plain flags, or the save's color for the other kinds
+    ///
+    fn flag_color(&self, x: i16, y: i16) -> &str {
+        match self.board.flag_kind(x, y) {
+            0 | 1 => &self.save.inner_highlight,
+            kind => self.save.flag_colors.get(kind as usize - 2).unwrap_or(&self.save.inner_highlight),
+        }
+    }
+    ///
+    /// Redraws a covered space, with its flag or question mark if it has one
+    ///
+    fn visual_restore_space(&self, x: i16, y: i16) {
+        let frame = [self.save.inner_fg.as_str(), self.save.inner_bg.as_str()];
+        if self.board.is_flagged(x, y) {
+            let flag_bg = self.save.flag_bg();
+            self.paint_cell(x, y, &[&self.save.inner_fg, flag_bg], &[self.flag_color(x, y), flag_bg], &self.save.flag_char);
+        } else if self.marks.contains(&(x, y)) {
+            self.paint_cell(x, y, &frame, &[&self.save.inner_highlight, &self.save.inner_bg], "?");
+        } else if self.board.bonuses().contains(&(x, y)) {
+            self.paint_cell(x, y, &frame, &["93", &self.save.inner_bg], &self.save.bonus_char);
+        } else {
+            self.paint_cell(x, y, &frame, &frame, &self.save.tile_char);
+        }
+    }
+    ///
+    /// Prints a message in the area below the board, clearing whatever was there before
+    ///
+    fn visual_update_message(&self, message: &str) {
+        execute!(std::io::stdout(), MoveTo(0, self.message_row())).ok();
+        print!("{}", message);
+        term::clear_line();
+    }
+    ///
+    /// Says why a key did nothing, below the board. It stays up until the next key
+    ///
+    fn feedback(&mut self, message: &str) {
+        self.visual_update_message(message);
+        self.position_cursor(self.x, self.y);
+        self.feedback_shown = true;
+    }
+    ///
+    /// Takes down the last key's feedback, if it's still up
+    ///
+    fn clear_feedback(&mut self) {
+        if self.feedback_shown {
+            self.feedback_shown = false;
+            self.visual_update_message("");
+            self.position_cursor(self.x, self.y);
+        }
+    }
+    ///
+    /// The flag counter: flags left when the player knows the mine total, or just the flags placed when it's hidden
+    ///
+    fn flag_counter(&self) -> String {
+        if !self.total_known() {
+            return format!("FLAGS PLACED: {}", self.board.f_count());
+        }
+        format!("FLAGS LEFT: {}", self.m_count - self.board.f_count())
+    }
+    ///
+    /// Whether or not the rules tell the player exactly how many mines there are
+    ///
+    fn total_known(&self) -> bool {
+        self.board.rules().mine_range(self.m_count) == Some((self.m_count, self.m_count))
+    }
+    ///
+    /// Draws time bonuses that have come up and takes down the ones that went away, whether they ran out or were taken
+    ///
+    fn visual_update_bonuses(&mut self) {
+        let bonuses = self.board.bonuses();
+        if bonuses == self.bonuses_shown {
+            return;
+        }
+        for &(x, y) in self.bonuses_shown.iter().chain(&bonuses) {
+            if !self.board.is_uncovered(x, y) {
+                self.visual_restore_space(x, y);
+            }
+        }
+        self.bonuses_shown = bonuses;
+    }
+    ///
+    /// Update the "mines left counter" when a flag is placed
+    ///
+    fn visual_update_f_count(&self) {
+        // Jump to where it is printed and update it
+        execute!(std::io::stdout(), MoveTo(0, 1)).ok();
+        if self.surprise_hidden() {
+            print!("FLAGS LEFT: {:<5}", "?");
+        } else {
+            print!("{:<17}", self.flag_counter());
+        }
+        if self.save.detailed_counters {
+            self.visual_update_rules();
+        }
+    }
+    ///
+    /// The number a row is labeled with, counting from the top or the bottom as the save says
+    ///
+    fn row_number(&self, y: i16) -> i16 {
+        if self.save.coordinates == 2 { self.height - y } else { y + 1 }
+    }
+    ///
+    /// How a space is named in messages: its column and row (e.g. "C4") if the board is labeled, otherwise "(x, y)" counting from 1
+    ///
+    fn cell_name(&self, x: i16, y: i16) -> String {
+        if self.save.coordinates != 0 {
+            return format!("{}{}", column_name(x), self.row_number(y));
+        }
+        format!("({}, {})", x + 1, y + 1)
+    }
+    ///
+    /// What's on a space, in words for the line under the board: its number, or whether it's empty, covered, flagged, or marked
+    ///
+    fn describe_space(&self, x: i16, y: i16) -> String {
+        if self.board.is_uncovered(x, y) {
+            return match self.board.mine_count_at(x, y) {
+                0 => String::from("empty"),
+                n => n.to_string(),
+            };
+        }
+        if self.board.is_flagged(x, y) {
+            return String::from("flagged");
+        }
+        if self.marks.contains(&(x, y)) {
+            return String::from("marked");
+        }
+        String::from("covered")
+    }
+    ///
+    /// Flags and question marks placed, for the line under the board. In CMD's QOL mode, also points out when a number
+    /// has more flags around it than it shows, since at least one of them has to be wrong
+    ///
+    fn counters(&self) -> String {
+        let marks = self.marks.iter().filter(|m| !self.board.is_uncovered(m.0, m.1) && !self.board.is_flagged(m.0, m.1)).count();
+        let mut text = format!("FLAGS PLACED: {} | ?: {}", self.board.f_count(), marks);
+        // Variant rules can make a number disagree with the flags around it on purpose
+        if self.save.gamemode == 1 && self.board.rules().standard_numbers() && self.state == MSGState::Running {
+            let overflagged = (0..self.height).any(|i| (0..self.width).any(|j| {
+                self.board.is_uncovered(j, i) && self.board.flags_around(j, i) > self.board.mine_count_at(j, i)
+            }));
+            if overflagged {
+                text.push_str(" | TOO MANY FLAGS AROUND A NUMBER");
+            }
+        }
+        text
+    }
+}
+
+// Game logic
+impl MinesweeperGame {
+    ///
+    /// Handle the start of the game, in which the player has to get a check in before the mines can generate, in order to avoid random start losses 
+    /// 
+    // A key's arm takes it even when there's nothing for it to do, rather than letting it fall through to another
+    #[allow(clippy::collapsible_match)]
+    fn handle_start(&mut self, key_code: KeyCode) {
+        // Surprise games aren't shown until the first check, so there's nowhere to move to or flag yet
+        if self.surprise_hidden() {
+            if key_code != KeyCode::Char('q') {
+                return;
+            }
+            (self.x, self.y) = self.surprise_start();
+        }
+        match key_code {
+            KeyCode::Up => {
+                if self.y > 0 {
+                    self.y -= 1;
+                    self.position_cursor(self.x, self.y);
+                }
+            }
+            KeyCode::Down => {
+                if self.y < self.height - 1 {
+                    self.y += 1;
+                    self.position_cursor(self.x, self.y);
+                }
+            }
+            KeyCode::Left => {
+                if self.x > 0 {
+                    self.x -= 1;
+                    self.position_cursor(self.x, self.y);
+                }
+            }
+            KeyCode::Right => {
+                if self.x < self.width - 1{
+                    self.x += 1;
+                    self.position_cursor(self.x, self.y);
+                }
+            }
+            KeyCode::Home | KeyCode::PageUp | KeyCode::End | KeyCode::PageDown => {
+                self.move_diagonally(key_code);
+            }
+            KeyCode::Char('w') => {
+                // Pre-start flags, if the save allows them
+                self.toggle_flag();
+            }
+            KeyCode::Char('q') => {
+                // Holes in the board can't be checked, so they can't start the game either
+                if !self.board.is_space(self.x, self.y) {
+                    self.feedback("That's a hole in the board, not a space");
+                    return;
+                }
+                // No guessing boards and races have to start on the X
+                if let Some(start) = self.ng_start && (self.x, self.y) != start {
+                    self.feedback(&format!("This board starts from the X at {}", self.cell_name(start.0, start.1)));
+                    return;
+                }
+                // No guessing boards are already generated; everything else is generated around the first check
+                if !self.board.is_generated() {
+                    if self.generation.hard_samples > 0 {
+                        self.visual_update_message("Looking for a tricky board...");
+                        std::io::stdout().flush().ok();
+                    }
+                    self.generation.generate(&mut self.board, (self.x, self.y));
+                    self.visual_update_message("");
+                }
+                self.clicks += 1;
+                // Starts on the X weren't the player's choice
+                if self.ng_start.is_none() {
+                    self.start = Some((self.x, self.y));
+                }
+                self.state = MSGState::Running;
+                tracing::info!(category = %self.category, width = self.width, height = self.height, mines = self.m_count,
+                    seed = self.replay.as_ref().map(|r| r.seed), rules = %self.board.rules().name(), "game started");
+            }
+            _ => {}
+        }
+    }
+    ///
+    /// Moves the cursor a space diagonally, for the keypad's Home, Page Up, End, and Page Down. At an edge it
+    /// slides along it instead
+    ///
+    fn move_diagonally(&mut self, key_code: KeyCode) {
+        let (dx, dy) = match key_code {
+            KeyCode::Home => (-1, -1),
+            KeyCode::PageUp => (1, -1),
+            KeyCode::End => (-1, 1),
+            KeyCode::PageDown => (1, 1),
+            _ => return,
+        };
+        self.x = (self.x + dx).clamp(0, self.width - 1);
+        self.y = (self.y + dy).clamp(0, self.height - 1);
+        self.position_cursor(self.x, self.y);
+    }
+    ///
+    /// Handle user input for things like checking, flagging, movement, etc.
+    /// 
+    fn handle_input(&mut self, key_code: KeyCode) {
+        // Nothing on the board can be touched while the game is paused
+        if self.board.is_paused() && !matches!(key_code, PAUSE_KEY | KeyCode::Char('r') | KeyCode::Char('m')) {
+            return;
+        }
+        match key_code {
+            KeyCode::Up if self.y > 0 => {
+                self.y -= 1;
+                self.position_cursor(self.x, self.y);
+            }
+            KeyCode::Down if self.y < self.height - 1 => {
+                self.y += 1;
+                self.position_cursor(self.x, self.y);
+            }
+            KeyCode::Left if self.x > 0 => {
+                self.x -= 1;
+                self.position_cursor(self.x, self.y);
+            }
+            KeyCode::Right if self.x < self.width - 1 => {
+                self.x += 1;
+                self.position_cursor(self.x, self.y);
+            }
+            KeyCode::Home | KeyCode::PageUp | KeyCode::End | KeyCode::PageDown => {
+                self.move_diagonally(key_code);
+            }
+            KeyCode::Char('q') if self.state != MSGState::Win && self.state != MSGState::Loss => {
+                if self.board.is_flagged(self.x, self.y) {
+                    self.feedback("Flagged spaces can't be checked - unflag it first");
+                } else if self.save.combined_chord {
+                    // Check and chord share the key
+                    self.clicks += 1;
+                    self.chord();
+                } else if !self.board.is_uncovered(self.x, self.y) {
+                    self.clicks += 1;
+                    self.check();
+                } else {
+                    self.feedback("Already uncovered - space chords");
+                }
+            }
+            KeyCode::Char(' ') if self.state != MSGState::Win && self.state != MSGState::Loss => {
+                // Chord, only on numbers so a stray press never checks a covered space
+                if self.board.is_uncovered(self.x, self.y) {
+                    self.clicks += 1;
+                    self.chord();
+                } else {
+                    self.feedback("Only uncovered numbers can be chorded - q checks");
+                }
+            }
+            KeyCode::Char('w') if self.state != MSGState::Win && self.state != MSGState::Loss => {
+                // Flag
+                self.toggle_flag();
+            }
+            MARK_KEY if self.state != MSGState::Win && self.state != MSGState::Loss => {
+                self.toggle_mark();
+            }
+            #[cfg(feature = "solver")]
+            KeyCode::Char('f') => {
+                if self.state == MSGState::Running && self.solver_can_read() {
+                    self.assists += 1;
+                    self.flag_certain_mines();
+                } else if self.state == MSGState::Running {
+                    self.feedback("Hints need standard numbers on the usual grid");
+                }
+            }
+            #[cfg(feature = "solver")]
+            KeyCode::Char('c') => {
+                if self.state == MSGState::Running && self.solver_can_read() {
+                    self.show_configurations();
+                } else if self.state == MSGState::Running {
+                    self.feedback("Hints need standard numbers on the usual grid");
+                }
+            }
+            #[cfg(feature = "solver")]
+            KeyCode::Char('o') => {
+                if self.state == MSGState::Running && self.solver_can_read() {
+                    self.assists += 1;
+                    self.show_regions();
+                } else if self.state == MSGState::Running {
+                    self.feedback("Hints need standard numbers on the usual grid");
+                }
+            }
+            KEYS_KEY if self.racing() && self.state == MSGState::Running => {
+                self.feedback("Keys can't be changed mid-race");
+            }
+            KEYS_KEY if self.state == MSGState::Running => {
+                self.show_keys();
+            }
+            OVERVIEW_KEY if self.state == MSGState::Running => {
+                self.show_overview();
+            }
+            STATS_KEY if self.state == MSGState::Running => {
+                self.show_stats();
+            }
+            CONSOLE_KEY if self.state == MSGState::Running => {
+                self.show_console();
+            }
+            #[cfg(feature = "solver")]
+            KeyCode::Char('v') if (self.state == MSGState::Win || self.state == MSGState::Loss) && self.solver_can_read() => {
+                self.show_review();
+            }
+            HEAT_KEY if self.state == MSGState::Win || self.state == MSGState::Loss => {
+                self.show_heat();
+            }
+            #[cfg(feature = "clipboard")]
+            COPY_KEY => {
+                self.copy_to_clipboard();
+            }
+            UNDO_KEY if self.state == MSGState::Running || self.state == MSGState::Loss => {
+                self.undo();
+            }
+            REDO_KEY if self.state == MSGState::Running => {
+                self.redo();
+            }
+            PAUSE_KEY if !self.racing() && self.state == MSGState::Running => {
+                let paused = self.board.is_paused();
+                self.set_paused(!paused);
+            }
+            PAUSE_KEY if self.racing() && self.state == MSGState::Running => {
+                self.feedback("Races can't be paused");
+            }
+            KeyCode::Enter if (self.warmup || self.hotseat.is_some()) && (self.state == MSGState::Win || self.state == MSGState::Loss) => {
+                // Warmups go on to the race they were played before, and hot-seat turns to the next player
+                self.next_game = Some(Category::Race);
+                self.state = MSGState::Done;
+            }
+            KeyCode::Enter if !self.racing() && (self.state == MSGState::Win || self.state == MSGState::Loss) => {
+                // Straight into the next game with the same settings
+                self.reset = true;
+                self.state = MSGState::Done;
+            }
+            KeyCode::Char(c @ '1'..='3') if !self.racing() && !self.warmup && (self.state == MSGState::Win || self.state == MSGState::Loss) => {
+                // Straight into a preset game, like desktop clients' difficulty keys
+                self.next_game = Some([Category::Beginner, Category::Intermediate, Category::Expert][c as usize - '1' as usize]);
+                self.state = MSGState::Done;
+            }
+            KeyCode::Char('r') if !self.racing() => {
+                // Reset the game
+                self.reset = true;
+                self.state = MSGState::Done;
+            }
+            KeyCode::Char('r') if self.state == MSGState::Running => {
+                self.feedback("Races can't be restarted - m leaves");
+            }
+            KeyCode::Char('m') => {
+                // Quit to main menu
+                self.state = MSGState::Done;
+            }
+            _ => {}
+        }
+    }
+    ///
+    /// Stops or restarts the game's timer, hiding the board while it's stopped. Races can't be paused,
+    /// since both players are timed against each other
+    ///
+    fn set_paused(&mut self, paused: bool) {
+        if self.racing() || self.state != MSGState::Running || paused == self.board.is_paused() {
+            return;
+        }
+        if paused {
+            self.board.pause();
+        } else {
+            self.board.resume();
+        }
+        self.focus_paused = false;
+        self.redraw();
+    }
+    ///
+    /// Opens the screen for rebinding keys, with the clock stopped while it's up, then saves whatever was changed
+    ///
+    fn show_keys(&mut self) {
+        self.board.pause();
+        let other = self.other_keys();
+        if keys::rebind(&mut self.save, &other).is_ok() {
+            self.save.write_save();
+        }
+        self.board.resume();
+        self.redraw();
+    }
+    ///
+    /// Handle the flagging action
+    /// 
+    fn toggle_flag(&mut self) {
+        // Ignore a second toggle of the same space that comes in too quickly to be on purpose
+        let now = Instant::now();
+        if let Some((space, at)) = self.last_flag && space == (self.x, self.y) && now.duration_since(at) < Duration::from_millis(self.save.flag_debounce_ms) {
+            return;
+        }
+        self.last_flag = Some(((self.x, self.y), now));
+        // With more than one kind of flag, pressing again moves on to the next kind before taking the flag off.
+        // Only the player sees the kind, so the replay doesn't either
+        let kind = self.board.flag_kind(self.x, self.y);
+        if kind > 0 && (kind as usize) <= self.save.flag_colors.len() && self.board.set_flag_kind(self.x, self.y, kind + 1) {
+            self.visual_restore_space(self.x, self.y);
+            self.position_cursor(self.x, self.y);
+            return;
+        }
+        // Flag, then question mark, then clear, when the save has question marks in the cycle
+        if self.save.question_marks && self.marks.contains(&(self.x, self.y)) {
+            self.toggle_mark();
+            return;
+        }
+        let before = self.undo_point();
+        if self.board.flag(self.x, self.y) {
+            self.remember(before);
+            self.record(ActionKind::Flag, self.x, self.y);
+            // A flag takes the place of a question mark, and leaves one behind when it comes off
+            self.marks.retain(|&m| m != (self.x, self.y));
+            if self.save.question_marks && !self.board.is_flagged(self.x, self.y) {
+                self.marks.push((self.x, self.y));
+                if self.save.detailed_counters {
+                    self.visual_update_rules();
+                }
+            }
+            self.visual_restore_space(self.x, self.y);
+            self.visual_update_f_count();
+            self.position_cursor(self.x, self.y);
+            self.script_flag(self.x, self.y);
+        } else if self.state == MSGState::Starting {
+            self.feedback("Flags go down once the game starts");
+        } else if self.board.is_uncovered(self.x, self.y) {
+            self.feedback("Uncovered spaces can't be flagged");
+        } else if !self.board.is_flagged(self.x, self.y) && self.board.f_count() >= self.board.flag_limit() {
+            self.feedback(&format!("Flag limit reached - all {} flags are down", self.board.flag_limit()));
+        } else if self.board.is_space(self.x, self.y) {
+            self.feedback("The mine hit here stays flagged");
+        }
+    }
+    ///
+    /// Whether or not moves can be taken back in this game. Races and practice tries are played straight
+    ///
+    fn can_undo(&self) -> bool {
+        self.save.undo > 0 && !self.racing() && self.practice.is_none()
+    }
+    ///
+    /// Whether or not the game is timed against someone else's, over the network or in the same seat
+    ///
+    fn racing(&self) -> bool {
+        self.race.is_some() || self.hotseat.is_some()
+    }
+    ///
+    /// Whether or not the game is played without counting for anything: moves were taken back,
+    /// or the save lets mine hits be taken back
+    ///
+    fn casual(&self) -> bool {
+        self.can_undo() && (self.undos > 0 || self.save.undo >= 2)
+    }
+    ///
+    /// The board as it is before a move, if the move could be taken back
+    ///
+    fn undo_point(&self) -> Option<Checkpoint> {
+        if !self.can_undo() || self.board.state() != GameState::Running {
+            return None;
+        }
+        Some(self.board.checkpoint())
+    }
+    ///
+    /// Keeps the board from before a move that changed it, which starts a new line of play with nothing to redo
+    ///
+    fn remember(&mut self, before: Option<Checkpoint>) {
+        if let Some(before) = before {
+            self.history.push(before);
+            self.undone.clear();
+        }
+    }
+    ///
+    /// Takes the last move back. Taking back a mine hit needs `undo` at 2 in the save
+    ///
+    fn undo(&mut self) {
+        if !self.can_undo() {
+            self.feedback(if self.save.undo == 0 { "Undo is off - set undo in the save to turn it on" } else { "Moves can't be taken back here" });
+            return;
+        }
+        if self.board.state() == GameState::Loss && self.save.undo < 2 {
+            self.feedback("Mine hits can only be taken back with undo at 2 in the save");
+            return;
+        }
+        tracing::debug!(moves = self.history.len(), "taking a move back");
+        let before = match self.history.pop() {
+            Some(before) => before,
+            None => {
+                self.feedback("Nothing to undo");
+                return;
+            }
+        };
+        self.undone.push(self.board.checkpoint());
+        self.board.restore(&before);
+        self.undos += 1;
+        // The replay can't be played back once moves have been taken back, and the game won't count anyway
+        self.replay = None;
+        self.after_undo();
+    }
+    ///
+    /// Plays the last move taken back again
+    ///
+    fn redo(&mut self) {
+        if !self.can_undo() {
+            return;
+        }
+        let after = match self.undone.pop() {
+            Some(after) => after,
+            None => {
+                self.feedback("Nothing to redo");
+                return;
+            }
+        };
+        self.history.push(self.board.checkpoint());
+        self.board.restore(&after);
+        self.after_undo();
+    }
+    ///
+    /// Draws the board again after it was put back to another point in the game
+    ///
+    fn after_undo(&mut self) {
+        self.state = MSGState::Running;
+        // Flags that come back take the place of the question marks they left
+        let board = &self.board;
+        self.marks.retain(|&(x, y)| !board.is_flagged(x, y));
+        self.redraw();
+        self.visual_update_f_count();
+        // Redoing a mine hit loses the game again
+        if self.board.state() == GameState::Loss {
+            self.visual_update_uncovered(&[]);
+            return;
+        }
+        execute!(std::io::stdout(), Show).ok();
+        self.position_cursor(self.x, self.y);
+    }
+    ///
+    /// Puts a question mark on the covered space under the cursor, or takes it back off. Question marks are just
+    /// notes, so they don't stop the space being checked and aren't part of the replay
+    ///
+    fn toggle_mark(&mut self) {
+        if self.board.is_uncovered(self.x, self.y) || self.board.is_flagged(self.x, self.y) || !self.board.is_space(self.x, self.y) {
+            return;
+        }
+        if self.marks.contains(&(self.x, self.y)) {
+            self.marks.retain(|&m| m != (self.x, self.y));
+        } else {
+            self.marks.push((self.x, self.y));
+        }
+        self.visual_restore_space(self.x, self.y);
+        if self.save.detailed_counters {
+            self.visual_update_rules();
+        }
+        self.position_cursor(self.x, self.y);
+    }
+    ///
+    /// Handle the checking action
+    /// 
+    fn check(&mut self) {
+        // The first check decides the opening, so that is when the board can be graded
+        #[cfg(feature = "solver")]
+        if self.board.is_generated() && self.board.state() == GameState::Starting && self.solver_can_read() {
+            self.grade = grade::grade(&self.board, (self.x, self.y));
+            self.visual_update_grade();
+        }
+        #[cfg(feature = "solver")]
+        let guess = self.guess_note(ActionKind::Reveal);
+        let before = self.undo_point();
+        let bonus = self.board.bonus_time();
+        let uncovered = self.board.reveal(self.x, self.y);
+        if !uncovered.is_empty() {
+            self.remember(before);
+        }
+        #[cfg(feature = "solver")]
+        self.count_guess(guess, &uncovered);
+        self.record(ActionKind::Reveal, self.x, self.y);
+        self.script_move("on_check", uncovered.len());
+        self.visual_update_uncovered(&uncovered);
+        self.visual_update_bonus(bonus);
+    }
+    ///
+    /// Handle the chording action
+    /// 
+    fn chord(&mut self) {
+        // Misflag-proof chording: refuse to chord if the solver can prove one of the flags is wrong
+        #[cfg(feature = "solver")]
+        if self.save.safe_chord && self.solver_can_read() && self.board.is_uncovered(self.x, self.y)
+            && self.board.flags_around(self.x, self.y) == self.board.mine_count_at(self.x, self.y) {
+            let certain_safe = self.board.get_solver().certain_safe();
+            let wrong = self.board.get_surrounding(self.x, self.y).into_iter()
+                .find(|space| self.board.is_flagged(space.0, space.1) && certain_safe.contains(space));
+            if let Some(wrong) = wrong {
+                self.feedback(&format!("Chord blocked: the flag at {} can't be a mine", self.cell_name(wrong.0, wrong.1)));
+                return;
+            }
+        }
+        if self.board.is_uncovered(self.x, self.y) && self.board.flags_around(self.x, self.y) != self.board.mine_count_at(self.x, self.y) {
+            self.feedback(&format!("Chord blocked: {} needs {} flags around it, it has {}", self.cell_name(self.x, self.y),
+                self.board.mine_count_at(self.x, self.y), self.board.flags_around(self.x, self.y)));
+            return;
+        }
+        #[cfg(feature = "solver")]
+        let guess = self.guess_note(ActionKind::Chord);
+        let before = self.undo_point();
+        let bonus = self.board.bonus_time();
+        let uncovered = self.board.chord(self.x, self.y);
+        if !uncovered.is_empty() {
+            self.remember(before);
+        }
+        #[cfg(feature = "solver")]
+        self.count_guess(guess, &uncovered);
+        self.record(ActionKind::Chord, self.x, self.y);
+        self.script_move("on_chord", uncovered.len());
+        self.visual_update_uncovered(&uncovered);
+        self.visual_update_bonus(bonus);
+    }
+    ///
+    /// Says how much time a move's bonuses took off, given the bonus time from before it, and puts the timer right
+    ///
+    fn visual_update_bonus(&mut self, before: Duration) {
+        let bonus = self.board.bonus_time().saturating_sub(before);
+        if bonus.is_zero() || self.state != MSGState::Running {
+            return;
+        }
+        self.visual_update_timer();
+        self.visual_update_bonuses();
+        self.visual_update_message(&format!("Time bonus! {} off the clock", self.locale.time(bonus, 0)));
+        self.position_cursor(self.x, self.y);
+        self.feedback_shown = true;
+    }
+    ///
+    /// What the solver makes of a check or chord about to be made at the cursor, if it would be a guess
+    ///
+    #[cfg(feature = "solver")]
+    fn guess_note(&self, kind: ActionKind) -> Option<Note> {
+        if !self.solver_can_read() {
+            return None;
+        }
+        let action = ReplayAction { kind, x: self.x, y: self.y, time_ms: 0 };
+        review::annotate(&self.board.visible_board(), &action).filter(|note| note.is_guess())
+    }
+    ///
+    /// Counts a move `guess_note` called a guess, once it's known whether it hit a mine
+    ///
+    #[cfg(feature = "solver")]
+    fn count_guess(&mut self, guess: Option<Note>, uncovered: &[(i16, i16)]) {
+        if let Some(note) = guess {
+            self.guesses += 1;
+            if note == Note::ForcedGuess {
+                self.forced_guesses += 1;
+            }
+            if !uncovered.iter().any(|space| self.board.is_mine(space.0, space.1)) {
+                self.guesses_survived += 1;
+            }
+        }
+    }
+    ///
+    /// Adds a move to the replay, if this game has one
+    ///
+    fn record(&mut self, kind: ActionKind, x: i16, y: i16) {
+        let time = self.board.clock_time();
+        if let Some(replay) = &mut self.replay {
+            replay.record(kind, x, y, time);
+        }
+    }
+    ///
+    /// What the game so far is certified for
+    ///
+    fn badges(&self) -> Badges {
+        let flagged = match &self.replay {
+            Some(replay) => replay.actions.iter().any(|a| a.kind == ActionKind::Flag),
+            None => self.board.f_count() > 0,
+        };
+        Badges {
+            assist_free: self.assists == 0,
+            no_flags: !flagged,
+            // Only boards the solver cleared from the first check without a guess count, whatever mode made them
+            no_guess: self.grade.is_some_and(|g| g != Grade::Expert),
+            zero_guess: self.guessing_tracked() && self.guesses == 0,
+        }
+    }
+    ///
+    /// Where the game was started from, on boards where corners and edges mean the usual thing
+    ///
+    fn start_kind(&self) -> Option<&'static str> {
+        if !self.board.topology().is_plain_grid() {
+            return None;
+        }
+        self.start.map(|(x, y)| start_kind(x, y, self.width, self.height))
+    }
+    ///
+    /// Whether or not the solver could follow the game closely enough to tell its guesses apart
+    ///
+    fn guessing_tracked(&self) -> bool {
+        #[cfg(feature = "solver")]
+        return self.solver_can_read();
+        #[cfg(not(feature = "solver"))]
+        return false;
+    }
+    ///
+    /// Prints how many guesses the game took
+    ///
+    fn print_guess_result(&self) {
+        if self.guessing_tracked() && self.guesses > 0 {
+            print!("Guesses: {} ({} forced, {} survived)\r\n", self.guesses, self.forced_guesses, self.guesses_survived);
+        }
+    }
+    ///
+    /// Everything the finished game adds to the stats
+    ///
+    fn game_record(&self, won: bool) -> GameRecord {
+        let time = self.board.elapsed();
+        GameRecord {
+            category: self.stats_category(),
+            won,
+            time_ms: time.as_millis() as u64,
+            clicks: self.clicks,
+            grade: self.grade,
+            start: self.start_kind().map(String::from),
+            guesses: self.guessing_tracked().then_some([self.guesses, self.forced_guesses, self.guesses_survived]),
+            golf_par: self.save.golf.then(|| self.board.get_3bv()),
+            medal: if won { self.par_times().and_then(|par| par.medal(time)) } else { None },
+            board: self.high_score_board(),
+            bbbv: self.board.get_3bv(),
+            date: locale::date(std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map_or(0, |d| d.as_secs() / 86400)),
+            ..GameRecord::default()
+        }
+    }
+    ///
+    /// The high score table this game's win would go on: one for each preset, and one for each custom size.
+    /// Time bonuses get tables of their own, like their stats
+    ///
+    fn high_score_board(&self) -> Option<String> {
+        let board = match self.category {
+            Category::Beginner | Category::Intermediate | Category::Expert => self.category.to_string(),
+            Category::Custom => format!("{}x{}, {} mines", self.width, self.height, self.m_count),
+            _ => return None,
+        };
+        if self.board.rules().name() == "bonus" {
+            return Some(format!("{} bonus", board));
+        }
+        Some(board)
+    }
+    ///
+    /// Says where a win that was just recorded landed on its high score table, if it made it on
+    ///
+    fn print_high_score(&self) {
+        let board = match self.high_score_board() {
+            Some(board) => board,
+            None => return,
+        };
+        let time_ms = self.board.elapsed().as_millis() as u64;
+        // A tie goes below the times it matches, since those were there first
+        let rank = self.save.best_times.get(&board).and_then(|entries| entries.iter().rposition(|entry| entry.time_ms == time_ms));
+        if let Some(rank) = rank.filter(|_| self.save.stats) {
+            print!("High score: #{} on {}\r\n", rank + 1, board);
+        }
+    }
+    ///
+    /// The name the game's stats go under. Time bonuses make for faster times than the board would give otherwise,
+    /// so games with them get a category of their own, like "beginner bonus"
+    ///
+    fn stats_category(&self) -> String {
+        if self.board.rules().name() == "bonus" {
+            return format!("{} bonus", self.category);
+        }
+        self.category.to_string()
+    }
+    ///
+    /// The code that makes this board again, if its seed makes the same board everywhere: imported boards and
+    /// no guessing boards don't come from their seed, and only seeds that went through PCG come out the same on every build
+    ///
+    fn seed_code(&self) -> Option<SeedCode> {
+        let replay = self.replay.as_ref().filter(|r| r.layout.is_none() && r.rng == "pcg")?;
+        Some(SeedCode { width: replay.width, height: replay.height, mines: replay.m_count, seed: replay.seed })
+    }
+    ///
+    /// Prints the board's seed, as a code for the custom game form if it can be shared
+    ///
+    fn print_seed(&self) {
+        match (self.seed_code(), &self.replay) {
+            (Some(code), _) => print!("Seed code: {}\r\n", code),
+            (None, Some(replay)) if replay.layout.is_none() => print!("Seed: {}\r\n", replay.seed),
+            _ => {}
+        }
+    }
+    ///
+    /// Prints the stats the save asks to see after a win, a line each
+    ///
+    fn print_metrics(&self) {
+        let bbbv = self.board.get_3bv();
+        let time = self.board.elapsed().as_secs_f64();
+        for metric in &self.save.metrics {
+            match metric.as_str() {
+                "3bv" => print!("3BV: {}\r\n", bbbv),
+                "3bv/s" if time > 0. => print!("3BV/s: {}\r\n", self.locale.decimal(bbbv as f64 / time, 2)),
+                "clicks" => print!("Clicks: {}\r\n", self.locale.number(self.clicks)),
+                "efficiency" if self.clicks > 0 => print!("Efficiency: {}%\r\n", self.locale.decimal(bbbv as f64 * 100. / self.clicks as f64, 0)),
+                _ => {}
+            }
+        }
+    }
+    ///
+    /// Prints a row with every badge, lit up if the game earned it
+    ///
+    fn print_badges(&self) {
+        for (label, earned) in self.badges().labels() {
+            let colors: &[&str] = if earned { &["30", "42"] } else { &["90"] };
+            term::paint(colors, &format!(" {} ", label));
+            print!(" ");
+        }
+        print!("\r\n");
+    }
+    ///
+    /// The game's par times for medals. Only the preset boards have them, and only on the usual board
+    /// with numbers that mean the usual thing
+    ///
+    fn par_times(&self) -> Option<ParTimes> {
+        if !self.board.rules().standard_numbers() || !self.board.topology().is_plain_grid() {
+            return None;
+        }
+        ParTimes::for_category(&self.stats_category())
+    }
+    ///
+    /// Prints the par time medal a win earned, and how fast the next one up takes
+    ///
+    fn print_medal(&self) {
+        let par = match self.par_times() {
+            Some(par) => par,
+            None => return,
+        };
+        let medal = par.medal(self.board.elapsed());
+        if let Some(medal) = medal {
+            print!("Medal: {} (under {})\r\n", medal, self.locale.seconds(par.time(medal) as f64, 0));
+        }
+        if let Some(next) = ParTimes::next(medal) {
+            print!("Next medal: {} under {}\r\n", next, self.locale.seconds(par.time(next) as f64, 0));
+        }
+    }
+    ///
+    /// Records how a try from a practice position went, under that position's own stats. Nothing else is kept:
+    /// practice runs don't count toward the usual stats, and don't leave replays or snapshots behind
+    ///
+    fn finish_practice(&mut self) {
+        let key = match &self.practice {
+            Some(key) => key.clone(),
+            None => return,
+        };
+        let won = self.board.state() == GameState::Win;
+        let stats = self.save.practice_stats.entry(key).or_default();
+        stats.g_played += 1;
+        stats.total_playtime += self.board.elapsed().as_secs();
+        if won {
+            stats.g_won += 1;
+            let time_ms = self.board.elapsed().as_millis() as u64;
+            if stats.best_time_ms.is_none_or(|best| time_ms < best) {
+                stats.best_time_ms = Some(time_ms);
+            }
+        }
+        print!("Cleared this position in {} of {} tries", stats.g_won, stats.g_played);
+        match stats.best_time_ms {
+            Some(best) => print!(", fastest in {}\r\n", self.locale.time(Duration::from_millis(best), 1)),
+            None => print!("\r\n"),
+        }
+        self.save.write_save();
+        print!("enter - try the position again | m - leave\r\n");
+    }
+    ///
+    /// Ends a warmup board. Like practice, it's left out of the stats, and it doesn't leave a replay or snapshot either
+    ///
+    fn finish_warmup(&self) {
+        print!("Warmed up - this game doesn't count for anything\r\n");
+        print!("enter - on to the race | r - warm up again | m - back out\r\n");
+    }
+    ///
+    /// Ends a player's turn in a hot-seat race. The results are compared once everyone has played
+    ///
+    fn finish_hotseat(&self) {
+        print!("Clicks: {}\r\n", self.locale.number(self.clicks));
+        print!("enter - pass the keyboard on | m - stop the race\r\n");
+    }
+    ///
+    /// Ends a game that doesn't count because moves could be taken back. A mine hit still can be
+    ///
+    fn finish_casual(&self) {
+        print!("Moves can be taken back, so this game doesn't count\r\n");
+        if self.board.state() == GameState::Loss {
+            print!("u - take the mine hit back | r - play again | m - menu\r\n");
+        }
+    }
+    ///
+    /// Finishes the replay with the game's result and writes it out next to the save.
+    /// A lost game also gets a snapshot of where it ended, with `fatal` as the mine that lost it
+    ///
+    fn finish_replay(&mut self, fatal: Option<(i16, i16)>) {
+        let badges = self.badges();
+        if let Some(replay) = &mut self.replay {
+            replay.finish(self.board.state(), self.board.elapsed());
+            // Badges only count for wins
+            if replay.result == GameState::Win {
+                replay.badges = badges;
+            }
+            // Servers check the whole game before it counts for anything
+            if let Some(race) = &self.race {
+                race.link.send(Message::Finished { replay: replay.clone() });
+            }
+            if let Some(path) = saves::write_replay(replay) {
+                tracing::info!(path = %path.display(), moves = replay.actions.len(), "replay saved");
+                print!("Replay saved to {}\r\n", path.display());
+            }
+            if let Some(fatal) = fatal && let Some(path) = saves::write_snapshot(&Snapshot::new(replay, &self.board, fatal)) {
+                print!("Snapshot saved to {}\r\n", path.display());
+            }
+            #[cfg(feature = "solver")]
+            if self.solver_can_read() {
+                print!("v - review the game\r\n");
+            }
+        }
+        print!("{} - heat replay\r\n", if self.save.vim_keys { "H" } else { "h" });
+        #[cfg(feature = "clipboard")]
+        print!("y - copy the result\r\n");
+        if self.race.is_none() {
+            print!("enter - play again | 1/2/3 - new beginner/intermediate/expert game\r\n");
+        }
+    }
+    ///
+    /// Lets user scripts react to a game event. Messages from scripts go under the board,
+    /// or after the results once the game is over
+    ///
+    fn script_event(&self, event: &str, fields: &[(&str, i64)]) {
+        let output = scripting::emit(event, fields);
+        if output.is_empty() {
+            return;
+        }
+        let game_over = self.state == MSGState::Win || self.state == MSGState::Loss;
+        for item in output {
+            match item {
+                ScriptOutput::Message(text) if game_over => print!("{}\r\n", text),
+                ScriptOutput::Message(text) => self.visual_update_message(&text),
+                ScriptOutput::Beep => term::beep(),
+            }
+        }
+        if !game_over {
+            self.position_cursor(self.x, self.y);
+        }
+    }
+    ///
+    /// Tells scripts about a check or chord that opened `opened` spaces
+    ///
+    fn script_move(&self, event: &str, opened: usize) {
+        let mut safe_left: i64 = (self.width as i64) * (self.height as i64) - self.m_count as i64;
+        for i in 0..self.height {
+            for j in 0..self.width {
+                if self.board.is_uncovered(j, i) {
+                    safe_left -= 1;
+                }
+            }
+        }
+        self.script_event(event, &[
+            ("x", self.x as i64 + 1), ("y", self.y as i64 + 1), ("opened", opened as i64),
+            ("safe_left", safe_left), ("time_ms", self.board.elapsed().as_millis() as i64),
+        ]);
+    }
+    ///
+    /// Tells scripts that the flag on a space was placed or taken off
+    ///
+    fn script_flag(&self, x: i16, y: i16) {
+        let event = if self.board.is_flagged(x, y) { "on_flag" } else { "on_unflag" };
+        self.script_event(event, &[
+            ("x", x as i64 + 1), ("y", y as i64 + 1), ("flags_left", (self.m_count - self.board.f_count()) as i64),
+            ("time_ms", self.board.elapsed().as_millis() as i64),
+        ]);
+    }
+    ///
+    /// Draws spaces the board just uncovered, or the loss screen if one of them was a mine
+    /// 
+    fn visual_update_uncovered(&mut self, uncovered: &[(i16, i16)]) {
+        if self.board.state() == GameState::Loss {
+            self.state = MSGState::Loss;
+            self.visual_update_timer();
+            execute!(std::io::stdout(), MoveTo(0, self.message_row())).ok();
+            execute!(std::io::stdout(), Hide).ok();
+            print!("Sorry! You lose.\r\n");
+            print!("Game time: {}\r\n", self.locale.time(Duration::from_secs(self.board.elapsed().as_secs()), 0));
+            self.print_seed();
+            if self.practice.is_some() {
+                self.finish_practice();
+                self.show_mines();
+                return;
+            }
+            if self.warmup {
+                self.finish_warmup();
+                self.show_mines();
+                return;
+            }
+            if self.hotseat.is_some() {
+                self.finish_hotseat();
+                self.show_mines();
+                return;
+            }
+            if self.casual() {
+                self.finish_casual();
+                self.show_mines();
+                return;
+            }
+            if self.assists > 0 {
+                print!("Assists used: {}\r\n", self.assists);
+            }
+            self.print_guess_result();
+            if self.save.golf {
+                print!("Golf: no score, the ball went into a mine\r\n");
+            }
+            // Rules that let mines slide can hit a few on the way, so the last one is what lost the game
+            let fatal = uncovered.iter().rev().find(|s| self.board.is_mine(s.0, s.1)).copied();
+            self.finish_replay(fatal);
+            self.send_progress();
+            self.script_event("on_loss", &[("time_ms", self.board.elapsed().as_millis() as i64), ("clicks", self.clicks as i64)]);
+            self.show_mines();
+            // Update save data
+            let record = self.game_record(false);
+            self.save.record_game(record);
+            return;
+        }
+        for space in uncovered {
+            if self.board.is_mine(space.0, space.1) {
+                // A mine the rules let the player live through, which stays flagged
+                self.visual_update_space(space.0, space.1, -2);
+                self.visual_update_f_count();
+                self.visual_update_rules();
+                self.visual_update_message(&format!("You hit a mine at {}!", self.cell_name(space.0, space.1)));
+            } else {
+                self.visual_update_number(space.0, space.1);
+            }
+        }
+        if self.save.detailed_counters {
+            // Uncovered question marks don't count any more, and new numbers can show up a misflag
+            self.visual_update_rules();
+        }
+        self.position_cursor(self.x, self.y);
+        // The board decides when the game is won, no matter how the spaces were opened
+        if self.board.state() == GameState::Win {
+            self.show_win();
+        }
+        self.send_progress();
+    }
+    ///
+    /// Whether or not the board has standard numbers on the usual grid, which the solver (and everything built on it) needs
+    ///
+    #[cfg(feature = "solver")]
+    fn solver_can_read(&self) -> bool {
+        self.board.rules().standard_numbers() && self.board.topology().is_plain_grid()
+    }
+    ///
+    /// Flags every space the solver can prove is a mine, leaving all other spaces alone, and says why
+    ///
+    #[cfg(feature = "solver")]
+    fn flag_certain_mines(&mut self) {
+        let solver = self.board.get_solver();
+        let certain = solver.certain_mines();
+        let inferences = solver.infer();
+        let before = self.undo_point();
+        // Flags on spaces the solver can prove are safe come off first, so they don't use up the flags the mines need
+        let mut unflagged = 0;
+        for space in solver.certain_safe() {
+            if self.board.is_flagged(space.0, space.1) && self.board.flag(space.0, space.1) {
+                self.record(ActionKind::Flag, space.0, space.1);
+                self.visual_restore_space(space.0, space.1);
+                self.script_flag(space.0, space.1);
+                unflagged += 1;
+            }
+        }
+        let mut flagged = 0;
+        for space in certain {
+            if !self.board.is_mine(space.0, space.1) {
+                tracing::warn!(x = space.0, y = space.1, "the solver called a safe space a certain mine");
+            }
+            if !self.board.is_flagged(space.0, space.1) && self.board.flag(space.0, space.1) {
+                self.record(ActionKind::Flag, space.0, space.1);
+                self.visual_restore_space(space.0, space.1);
+                self.script_flag(space.0, space.1);
+                flagged += 1;
+            }
+        }
+        if flagged + unflagged > 0 {
+            self.remember(before);
+        }
+        self.visual_update_f_count();
+        self.position_cursor(self.x, self.y);
+        let wrong = if unflagged > 0 { format!("Took {} flag(s) off safe spaces. ", unflagged) } else { String::new() };
+        // The reason given is for the simplest thing that could be proven, so it's the one to learn from
+        let mine = inferences.iter().find(|i| i.proof.mine);
+        let safe = inferences.iter().find(|i| !i.proof.mine);
+        match (flagged, mine, safe) {
+            (0, _, Some(safe)) => self.feedback(&format!("Hint: {}no certain mines left to flag. {}", wrong, safe.reason)),
+            (0, _, None) => self.feedback(&format!("Hint: {}no certain mines left to flag", wrong)),
+            (_, Some(mine), _) => self.feedback(&format!("Hint: {}{}", wrong, mine.reason)),
+            _ if unflagged > 0 => self.feedback(&format!("Hint: {}", wrong.trim_end())),
+            _ => {}
+        }
+    }
+    ///
+    /// Overlay that cycles through every mine configuration consistent with the revealed numbers.
+    /// Only available once the frontier is small enough to enumerate
+    ///
+    #[cfg(feature = "solver")]
+    fn show_configurations(&mut self) {
+        let solver = self.board.get_solver();
+        let frontier = solver.get_frontier();
+        let configurations = match solver.enumerate_configurations(24, 500) {
+            Some(c) if !c.is_empty() => c,
+            _ => {
+                self.feedback("Too many possibilities to list yet - come back in the endgame");
+                return;
+            }
+        };
+        // Only counts as an assist once there's something to see
+        self.assists += 1;
+        execute!(std::io::stdout(), Hide).ok();
+        let mut index: usize = 0;
+        loop {
+            // Draw the current configuration over the frontier
+            for space in &frontier {
+                if configurations[index].contains(space) {
+                    self.visual_update_space(space.0, space.1, -1);
+                } else {
+                    self.paint_cell(space.0, space.1, &[&self.save.inner_fg, "100"], &[&self.save.inner_fg, "100"], " ");
+                }
+            }
+            self.visual_update_message(&format!("Configuration {}/{} | left/right - cycle | c - close", index + 1, configurations.len()));
+            // Wait for the player to cycle or close
+            match event::read() {
+                Ok(Event::Key(key_event)) if key_event.kind == KeyEventKind::Press => {
+                    match key_event.code {
+                        KeyCode::Left => {
+                            index = (index + configurations.len() - 1) % configurations.len();
+                        }
+                        KeyCode::Right => {
+                            index = (index + 1) % configurations.len();
+                        }
+                        KeyCode::Char('c') | KeyCode::Esc => {
+                            break;
+                        }
+                        _ => {}
+                    }
+                }
+                Ok(_) => {}
+                Err(_) => break,
+            }
+        }
+        // Put the board back the way it was
+        for space in &frontier {
+            self.visual_restore_space(space.0, space.1);
+        }
+        self.visual_update_message("");
+        execute!(std::io::stdout(), Show).ok();
+        self.position_cursor(self.x, self.y);
+    }
+    ///
+    /// Overlay that outlines each independent region of the frontier in its own color,
+    /// labelled with how many mines the region must hold
+    ///
+    #[cfg(feature = "solver")]
+    fn show_regions(&mut self) {
+        let regions = self.board.get_solver().region_mine_counts();
+        if regions.is_empty() {
+            self.feedback("No regions to show yet");
+            return;
+        }
+        execute!(std::io::stdout(), Hide).ok();
+        let mut summary: Vec<String> = vec![];
+        for (index, region) in regions.iter().enumerate() {
+            let color = REGION_COLORS[index % REGION_COLORS.len()];
+            let count = match region.mines {
+                Some((min, max)) if min == max => min.to_string(),
+                Some((min, max)) => format!("{}-{}", min, max),
+                None => String::from("?"),
+            };
+            // Label the region's first space with its count if it fits, the rest just get outlined
+            for (i, space) in region.cells.iter().enumerate() {
+                let label = if i == 0 && count.len() == 1 { count.as_str() } else { " " };
+                self.paint_cell(space.0, space.1, &["30", color], &["30", color], label);
+            }
+            summary.push(format!(" {} ", count));
+        }
+        // The summary is printed in the same colors so each count can be matched to its region
+        execute!(std::io::stdout(), MoveTo(0, self.message_row())).ok();
+        print!("Mines per region: ");
+        for (index, count) in summary.iter().enumerate() {
+            term::paint(&["30", REGION_COLORS[index % REGION_COLORS.len()]], count);
+            print!(" ");
+        }
+        print!("| o - close");
+        term::clear_line();
+        // Wait for the player to close the overlay
+        loop {
+            match event::read() {
+                Ok(Event::Key(key_event)) if key_event.kind == KeyEventKind::Press => {
+                    match key_event.code {
+                        KeyCode::Char('o') | KeyCode::Esc => {
+                            break;
+                        }
+                        _ => {}
+                    }
+                }
+                Ok(_) => {}
+                Err(_) => break,
+            }
+        }
+        // Put the board back the way it was
+        for region in &regions {
+            for space in &region.cells {
+                self.visual_restore_space(space.0, space.1);
+            }
+        }
+        self.visual_update_message("");
+        execute!(std::io::stdout(), Show).ok();
+        self.position_cursor(self.x, self.y);
+    }
+    ///
+    /// Heat replay of a finished game: every space that was opened is colored by when, from blue for the first fifth
+    /// of the time between the first check and the last to red for the last fifth, and labeled 1 to 5 the same way,
+    /// with the times the board was a quarter, half, three quarters, and all the way cleared below. Left and right play
+    /// the game back a tenth at a time
+    ///
+    fn show_heat(&mut self) {
+        let mut opened: Vec<((i16, i16), Duration)> = vec![];
+        for i in 0..self.height {
+            for j in 0..self.width {
+                if let Some(time) = self.board.revealed_at(j, i) {
+                    opened.push(((j, i), time));
+                }
+            }
+        }
+        // The splits go by how many safe spaces there are, so a lost game only gets the ones it reached
+        opened.sort_by_key(|(_, time)| *time);
+        let (start, end) = match (opened.first(), opened.last()) {
+            (Some((_, start)), Some((_, end))) => (*start, *end),
+            _ => return,
+        };
+        // Time spent before the first check doesn't say anything about how the board was played
+        let span = (end - start).max(Duration::from_millis(1));
+        let safe = (self.board.space_count() - self.board.m_count()).max(1) as usize;
+        let splits: Vec<String> = [25, 50, 75, 100].iter().map(|percent| {
+            let split = match opened.get((safe * percent).div_ceil(100) - 1) {
+                Some((_, time)) => self.locale.time(*time, 1),
+                None => String::from("-"),
+            };
+            format!("{}% {}", percent, split)
+        }).collect();
+        execute!(std::io::stdout(), Hide).ok();
+        let mut step = HEAT_STEPS;
+        loop {
+            term::begin_frame();
+            let until = start + span * step / HEAT_STEPS;
+            for &((x, y), time) in &opened {
+                if time <= until {
+                    let heat = (((time - start).as_millis() * HEAT_COLORS.len() as u128 / span.as_millis()) as usize).min(HEAT_COLORS.len() - 1);
+                    self.paint_cell(x, y, &["30", HEAT_COLORS[heat]], &["30", HEAT_COLORS[heat]], &(heat + 1).to_string());
+                } else {
+                    self.visual_restore_space(x, y);
+                }
+            }
+            self.visual_update_message(&format!("Heat replay at {} of {} | {} | left/right - step | {} - close",
+                self.locale.time(until, 1), self.locale.time(end, 1), splits.join(" | "), if self.save.vim_keys { "H" } else { "h" }));
+            term::end_frame();
+            match event::read() {
+                Ok(Event::Key(key_event)) if key_event.kind == KeyEventKind::Press => {
+                    match key_event.code {
+                        KeyCode::Left => step = step.saturating_sub(1),
+                        KeyCode::Right => step = (step + 1).min(HEAT_STEPS),
+                        KeyCode::Char('h') | KeyCode::Char('H') | KeyCode::Esc => break,
+                        _ => {}
+                    }
+                }
+                Ok(_) => {}
+                Err(_) => break,
+            }
+        }
+        // Put the board back the way the game ended
+        self.redraw();
+        self.visual_update_message(&format!("Game over | {} - heat replay | r - play again | m - menu", if self.save.vim_keys { "H" } else { "h" }));
+        std::io::stdout().flush().ok();
+    }
+    ///
+    /// Overview that packs the whole board into braille, a character for every 2x4 spaces, so even a huge board
+    /// fits on screen. Each dot is a covered space, blocks with flags in them are red, and the block the cursor
+    /// is in is inverted. The cursor moves a block at a time, and the normal view comes back wherever it ends up
+    ///
+    fn show_overview(&mut self) {
+        execute!(std::io::stdout(), Hide).ok();
+        let border = [self.save.border_fg.as_str(), self.save.border_bg.as_str()];
+        let (columns, rows) = ((self.width + 1) / 2, (self.height + 3) / 4);
+        loop {
+            term::begin_frame();
+            term::clear();
+            term::paint(&border, &format!("╔{}╗", "═".repeat(columns as usize)));
+            print!("\r\n");
+            for row in 0..rows {
+                term::paint(&border, "║");
+                for column in 0..columns {
+                    let mut dots: u32 = 0;
+                    let mut flagged = false;
+                    for (bit, &(dx, dy)) in BRAILLE_DOTS.iter().enumerate() {
+                        let (x, y) = (column * 2 + dx, row * 4 + dy);
+                        if x >= self.width || y >= self.height || !self.board.is_space(x, y) {
+                            continue;
+                        }
+                        if !self.board.is_uncovered(x, y) {
+                            dots |= 1 << bit;
+                        }
+                        flagged |= self.board.is_flagged(x, y);
+                    }
+                    let mut colors = vec![if flagged { "31" } else { self.save.inner_fg.as_str() }];
+                    if (self.x / 2, self.y / 4) == (column, row) {
+                        colors.push("7");
+                    }
+                    term::paint(&colors, &char::from_u32(0x2800 + dots).unwrap().to_string());
+                }
+                term::paint(&border, "║");
+                print!("\r\n");
+            }
+            term::paint(&border, &format!("╚{}╝", "═".repeat(columns as usize)));
+            print!("\r\n");
+            print!("Overview at {} | arrows - move | b - back to the board", self.cell_name(self.x, self.y));
+            term::clear_line();
+            term::end_frame();
+            match event::read() {
+                Ok(Event::Key(key_event)) if key_event.kind == KeyEventKind::Press => {
+                    match key_event.code {
+                        KeyCode::Up => self.y = (self.y - 4).max(0),
+                        KeyCode::Down => self.y = (self.y + 4).min(self.height - 1),
+                        KeyCode::Left => self.x = (self.x - 2).max(0),
+                        KeyCode::Right => self.x = (self.x + 2).min(self.width - 1),
+                        OVERVIEW_KEY | KeyCode::Esc => break,
+                        _ => {}
+                    }
+                }
+                Ok(_) => {}
+                Err(_) => break,
+            }
+        }
+        self.redraw();
+        execute!(std::io::stdout(), Show).ok();
+    }
+    ///
+    /// Box over the board with how the game is going: spaces uncovered, flags, 3BV done, the time, and for preset
+    /// boards the time it's on pace for next to the best one. The clock keeps running unless the player pauses it
+    ///
+    fn show_stats(&mut self) {
+        execute!(std::io::stdout(), Hide).ok();
+        let border = [self.save.border_fg.as_str(), self.save.border_bg.as_str()];
+        let best_ms = match self.category.preset() {
+            Some(_) => self.save.category_stats.get(&self.stats_category()).and_then(|stats| stats.best_time_ms),
+            None => None,
+        };
+        let mut safe: u32 = 0;
+        for i in 0..self.height {
+            for j in 0..self.width {
+                if self.board.is_space(j, i) && !self.board.is_mine(j, i) {
+                    safe += 1;
+                }
+            }
+        }
+        loop {
+            let time = self.board.elapsed().as_secs_f64();
+            let (done, bbbv) = self.board.get_3bv_progress();
+            let uncovered = (0..self.height).map(|i| (0..self.width).filter(|&j| self.board.is_uncovered(j, i)).count()).sum::<usize>();
+            let mut lines: Vec<String> = vec![
+                String::from("BOARD STATS"),
+                // Both totals would give away how many mines there are when the rules keep that hidden
+                if self.total_known() { format!("Uncovered: {}/{}", uncovered, safe) } else { format!("Uncovered: {}", uncovered) },
+                if self.total_known() { format!("Flags: {}/{}", self.board.f_count(), self.m_count) } else { format!("Flags: {}", self.board.f_count()) },
+                format!("3BV: {}/{}", done, bbbv),
+                format!("Time: {}", self.locale.seconds(time, 1)),
+            ];
+            if self.guessing_tracked() {
+                lines.push(format!("Guesses: {}", self.guesses));
+            }
+            if done > 0 && time > 0. {
+                lines.push(format!("Pace: {} 3BV/s", self.locale.decimal(done as f64 / time, 2)));
+                // The rest of the board is assumed to go as fast as the part done so far
+                let projected = time * bbbv as f64 / done as f64;
+                match best_ms {
+                    Some(best_ms) => {
+                        let best = best_ms as f64 / 1000.;
+                        let sign = if projected >= best { "+" } else { "" };
+                        lines.push(format!("On pace for {} ({}{} vs best)", self.locale.seconds(projected, 1), sign, self.locale.seconds(projected - best, 1)));
+                    }
+                    None => lines.push(format!("On pace for {}", self.locale.seconds(projected, 1))),
+                }
+            }
+            lines.push(String::new());
+            let pause = if !self.racing() { if self.board.is_paused() { " | p - resume" } else { " | p - pause" } } else { "" };
+            lines.push(format!("i - close{}", pause));
+            // Centered over the board, or at its left edge if the board is too narrow to hold it
+            let width = lines.iter().map(|line| line.chars().count()).max().unwrap_or(0);
+            let inner_width = self.board.topology().screen_width(self.width, self.height) * self.geometry.width / 3;
+            let left = (1 + (inner_width - width as i16 - 4).max(0) / 2) as u16;
+            term::begin_frame();
+            execute!(std::io::stdout(), MoveTo(left, 3)).ok();
+            term::paint(&border, &format!("╔{}╗", "═".repeat(width + 2)));
+            for (i, line) in lines.iter().enumerate() {
+                execute!(std::io::stdout(), MoveTo(left, 4 + i as u16)).ok();
+                term::paint(&border, "║");
+                print!(" {:<width$} ", line, width = width);
+                term::paint(&border, "║");
+            }
+            execute!(std::io::stdout(), MoveTo(left, 4 + lines.len() as u16)).ok();
+            term::paint(&border, &format!("╚{}╝", "═".repeat(width + 2)));
+            term::end_frame();
+            // The time keeps changing, so the box is brought up to date every so often even without a key
+            if !event::poll(Duration::from_millis(100)).unwrap_or(false) {
+                continue;
+            }
+            match event::read() {
+                Ok(Event::Key(key_event)) if key_event.kind == KeyEventKind::Press => {
+                    match key_event.code {
+                        STATS_KEY | KeyCode::Esc => break,
+                        PAUSE_KEY if !self.racing() => {
+                            if self.board.is_paused() {
+                                self.board.resume();
+                            } else {
+                                self.board.pause();
+                            }
+                            self.focus_paused = false;
+                        }
+                        _ => {}
+                    }
+                }
+                Ok(_) => {}
+                Err(_) => break,
+            }
+        }
+        self.redraw();
+        execute!(std::io::stdout(), Show).ok();
+    }
+    ///
+    /// Shows the newest log lines in a box over the board, brought up to date as more come in, until the console key
+    /// or Esc closes it. The clock keeps running underneath
+    ///
+    fn show_console(&mut self) {
+        execute!(std::io::stdout(), Hide).ok();
+        let border = [self.save.border_fg.as_str(), self.save.border_bg.as_str()];
+        let (columns, rows) = terminal::size().unwrap_or((80, 24));
+        // Below the help line and status bar, with room for the box's own border
+        let height = (rows as usize).saturating_sub(5).max(3);
+        let width = (columns as usize).saturating_sub(4).max(20);
+        loop {
+            let mut lines = log::last_lines(height - 1);
+            if lines.is_empty() {
+                lines.push(String::from("Nothing logged yet"));
+            }
+            lines.resize(height - 1, String::new());
+            lines.push(String::from("` - close"));
+            term::begin_frame();
+            execute!(std::io::stdout(), MoveTo(0, 2)).ok();
+            term::paint(&border, &format!("╔{}╗", "═".repeat(width + 2)));
+            for (i, line) in lines.iter().enumerate() {
+                let line: String = line.chars().take(width).collect();
+                execute!(std::io::stdout(), MoveTo(0, 3 + i as u16)).ok();
+                term::paint(&border, "║");
+                print!(" {:<width$} ", line, width = width);
+                term::paint(&border, "║");
+            }
+            execute!(std::io::stdout(), MoveTo(0, 3 + lines.len() as u16)).ok();
+            term::paint(&border, &format!("╚{}╝", "═".repeat(width + 2)));
+            term::end_frame();
+            if !event::poll(Duration::from_millis(100)).unwrap_or(false) {
+                continue;
+            }
+            match event::read() {
+                Ok(Event::Key(key_event)) if key_event.kind == KeyEventKind::Press => {
+                    if matches!(key_event.code, CONSOLE_KEY | KeyCode::Esc) {
+                        break;
+                    }
+                }
+                Ok(_) => {}
+                Err(_) => break,
+            }
+        }
+        self.redraw();
+        execute!(std::io::stdout(), Show).ok();
+    }
+    ///
+    /// Steps through the finished game move by move, marking moves the solver takes issue with:
+    /// yellow for forced guesses and red for mistakes
+    ///
+    #[cfg(feature = "solver")]
+    fn show_review(&mut self) {
+        let replay = match &self.replay {
+            Some(r) => r,
+            None => return,
+        };
+        let review = match Review::new(replay) {
+            Ok(r) => r,
+            Err(e) => {
+                self.visual_update_message(&format!("Can't review this game: {}", e));
+                return;
+            }
+        };
+        let mut tree = MoveTree::from_replay(replay);
+        let total_time = Duration::from_millis(replay.time_ms);
+        let bar_width = self.board.topology().screen_width(self.width, self.height) * self.geometry.width / 3;
+        let first_mistake = match review.first_mistake() {
+            Some(i) => format!("first mistake on move {}", i + 1),
+            None => String::from("no mistakes"),
+        };
+        execute!(std::io::stdout(), Hide).ok();
+        execute!(std::io::stdout(), MoveTo(0, self.message_row())).ok();
+        term::clear_below();
+        loop {
+            let depth = tree.depth();
+            term::begin_frame();
+            self.draw_position(&review.positions[depth]);
+            let line = if depth == 0 {
+                String::from("Start")
+            } else {
+                let action = review.actions[depth - 1];
+                let verb = match action.kind {
+                    ActionKind::Reveal => "check",
+                    ActionKind::Flag => "flag",
+                    ActionKind::Chord => "chord",
+                };
+                let note = review.notes[depth - 1];
+                let color = match note {
+                    Some(Note::ForcedGuess) => "43",
+                    Some(_) => "41",
+                    None => "47",
+                };
+                self.draw_tile(action.x, action.y, review.positions[depth].tiles[action.y as usize][action.x as usize], &["30", color]);
+                let mut line = format!("Move {}/{} at {}: {} ({}, {})", depth, review.actions.len(),
+                    self.locale.time(Duration::from_millis(action.time_ms), 1), verb, action.x + 1, action.y + 1);
+                if let Some(note) = note {
+                    line.push_str(&format!(" - {}", note));
+                }
+                line
+            };
+            self.visual_update_message(&line);
+            let time = match depth {
+                0 => Duration::ZERO,
+                _ => Duration::from_millis(review.actions[depth - 1].time_ms),
+            };
+            execute!(std::io::stdout(), MoveTo(0, self.message_row() + 1)).ok();
+            print!("{} {} / {}", progress_bar(depth, review.actions.len(), bar_width.max(10) as usize),
+                self.locale.time(time, 1), self.locale.time(total_time, 1));
+            term::clear_line();
+            execute!(std::io::stdout(), MoveTo(0, self.message_row() + 2)).ok();
+            print!("Review: {} | left/right - step | shift - 10 moves | home/end - jump | n - next note | v - close", first_mistake);
+            term::clear_line();
+            // The tip is about the move that lost the game, so it goes with the last position
+            execute!(std::io::stdout(), MoveTo(0, self.message_row() + 3)).ok();
+            if let Some(tip) = review.tip.as_ref().filter(|_| depth == review.actions.len()) {
+                term::paint(&["33"], &format!("Tip: {}", tip));
+            }
+            term::clear_line();
+            term::end_frame();
+            // Wait for the player to step or close
+            match event::read() {
+                Ok(Event::Key(key_event)) if key_event.kind == KeyEventKind::Press => {
+                    // Shift skips through long games 10 moves at a time
+                    let steps = if key_event.modifiers.contains(event::KeyModifiers::SHIFT) { 10 } else { 1 };
+                    match key_event.code {
+                        KeyCode::Left => {
+                            for _ in 0..steps {
+                                tree.undo();
+                            }
+                        }
+                        KeyCode::Right => {
+                            for _ in 0..steps {
+                                tree.redo();
+                            }
+                        }
+                        KeyCode::Home => {
+                            tree.to_start();
+                        }
+                        KeyCode::End => {
+                            tree.to_end();
+                        }
+                        KeyCode::Char('n') => {
+                            // Wraps back around to the first note after the last one
+                            let next = review.next_note(depth.checked_sub(1)).or(review.next_note(None));
+                            if let Some(next) = next {
+                                tree.to_start();
+                                for _ in 0..=next {
+                                    tree.redo();
+                                }
+                            }
+                        }
+                        KeyCode::Char('v') | KeyCode::Esc => {
+                            break;
+                        }
+                        _ => {}
+                    }
+                }
+                Ok(_) => {}
+                Err(_) => break,
+            }
+        }
+        // Put the board back the way the game ended
+        execute!(std::io::stdout(), MoveTo(0, self.message_row() + 1)).ok();
+        term::clear_below();
+        self.redraw();
+        self.visual_update_message("Game over | v - review | r - play again | m - menu");
+        std::io::stdout().flush().ok();
+    }
+    ///
+    /// Draws every space of a position from a review
+    ///
+    #[cfg(feature = "solver")]
+    fn draw_position(&self, position: &VisibleBoard) {
+        for i in 0..self.height {
+            for j in 0..self.width {
+                match position.tiles[i as usize][j as usize] {
+                    Tile::Revealed(n) => self.visual_update_space(j, i, n),
+                    Tile::Flagged => {
+                        let flag_bg = self.save.flag_bg();
+                        self.paint_cell(j, i, &[&self.save.inner_fg, flag_bg], &[&self.save.inner_highlight, flag_bg], &self.save.flag_char);
+                    }
+                    Tile::Covered => {
+                        let frame = [self.save.inner_fg.as_str(), self.save.inner_bg.as_str()];
+                        self.paint_cell(j, i, &frame, &frame, &self.save.tile_char);
+                    }
+                }
+            }
+        }
+        if position.state == GameState::Loss {
+            self.show_mines();
+        }
+    }
+    ///
+    /// Draws a single space in the given colors, to pick it out from the rest of the board
+    ///
+    #[cfg(feature = "solver")]
+    fn draw_tile(&self, x: i16, y: i16, tile: Tile, colors: &[&str]) {
+        let text = match tile {
+            Tile::Covered => self.save.tile_char.clone(),
+            Tile::Flagged => self.save.flag_char.clone(),
+            Tile::Revealed(0) => String::from(" "),
+            Tile::Revealed(-1) => self.save.mine_char.clone(),
+            Tile::Revealed(n) => cast::number_text(&self.save.m_count_chars, n),
+        };
+        let (open, close) = match tile {
+            Tile::Revealed(n) if n > 0 => self.number_brackets(n),
+            _ => (String::from("["), String::from("]")),
+        };
+        self.paint_cell_between(x, y, colors, colors, &text, (&open, &close));
+    }
+    ///
+    /// Position cursor relative to board position
+    /// 
+    fn position_cursor(&self, x: i16, y: i16) {
+        let coord = self.get_canon_pos(x, y);
+        execute!(std::io::stdout(), MoveTo(coord.0 as u16, coord.1 as u16)).ok();
+    }
+    ///
+    /// Gets the cursor location that position_cursor will place the cursor at: the middle of the space
+    /// 
+    fn get_canon_pos(&self, x: i16, y: i16) -> (i16, i16) {
+        let (left, top) = self.cell_origin(x, y);
+        (left + self.geometry.width / 2, top + self.geometry.height / 2)
+    }
+    ///
+    /// The space drawn at a terminal column and row, if there is one there. The inverse of `cell_origin`
+    ///
+    fn space_at(&self, column: u16, row: u16) -> Option<(i16, i16)> {
+        let (column, row) = (column as i16, row as i16);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let (left, top) = self.cell_origin(x, y);
+                if (left..left + self.geometry.width).contains(&column) && (top..top + self.geometry.height).contains(&row) {
+                    return Some((x, y));
+                }
+            }
+        }
+        None
+    }
+    ///
+    /// The space a mouse press landed on and the key it stands for: left checks, right flags, and middle chords.
+    /// Presses off the board, and anything else the mouse does, don't count
+    ///
+    fn mouse_click(&self, mouse_event: MouseEvent) -> Option<((i16, i16), KeyCode)> {
+        // The board is hidden while paused, so there's nothing to click on
+        if self.board.is_paused() {
+            return None;
+        }
+        let key = match mouse_event.kind {
+            MouseEventKind::Down(MouseButton::Left) => KeyCode::Char('q'),
+            MouseEventKind::Down(MouseButton::Right) => KeyCode::Char('w'),
+            MouseEventKind::Down(MouseButton::Middle) => KeyCode::Char(' '),
+            _ => return None,
+        };
+        let space = self.space_at(mouse_event.column, mouse_event.row)?;
+        Some((space, key))
+    }
+    ///
+    /// The top left corner of a space on the screen. The topology lays spaces out at normal size, which is scaled to the cell geometry
+    ///
+    fn cell_origin(&self, x: i16, y: i16) -> (i16, i16) {
+        let offset = self.board.topology().screen_offset(x, y);
+        (1 + offset.0 * self.geometry.width / 3, 3 + offset.1 * self.geometry.height)
+    }
+    ///
+    /// How many columns and rows of terminal a board of this size takes up, from the help line down to the message row,
+    /// including the border and any row numbers beside it. The help line has to fit on one row too
+    ///
+    fn screen_size(&self, width: i16, height: i16) -> (i32, i32) {
+        let inner_width = self.board.topology().screen_width(width, height) * self.geometry.width / 3;
+        let labels = if self.save.coordinates != 0 { 1 + height.to_string().len() as i32 } else { 0 };
+        let help = self.help_line().chars().count() as i32;
+        ((inner_width as i32 + 2 + labels).max(help), (height * self.geometry.height) as i32 + 6)
+    }
+    ///
+    /// Before the board is drawn, checks that it fits in the terminal, since one that doesn't wraps into a garbled mess.
+    /// If it doesn't, the player can resize the terminal until it does, turn large print off, play anyway, or go back
+    /// to the menu, which returns false
+    ///
+    fn fit_board(&mut self) -> Result<bool, std::io::Error> {
+        execute!(std::io::stdout(), Hide).ok();
+        let mut warned = false;
+        let fits = loop {
+            // Some terminals don't know their size and say it's 0x0, so there's nothing to check against
+            let (columns, rows) = match terminal::size() {
+                Ok((columns, rows)) if columns > 0 && rows > 0 => (columns as i32, rows as i32),
+                _ => break true,
+            };
+            // A surprise board's size can't be given away, so it has to fit the largest one there could be
+            let (width, height) = if self.surprise_hidden() { (SURPRISE_WIDTH, SURPRISE_HEIGHT) } else { (self.width, self.height) };
+            let (needed_columns, needed_rows) = self.screen_size(width, height);
+            if needed_columns <= columns && needed_rows <= rows {
+                break true;
+            }
+            tracing::info!(columns = columns, rows = rows, needed_columns = needed_columns, needed_rows = needed_rows, "board doesn't fit the terminal");
+            // Keys typed before the warning comes up, e.g. the Enter after a menu choice, aren't answers to it.
+            // When the board fits they're left for the game to play
+            if !warned {
+                while event::poll(Duration::ZERO)? {
+                    event::read()?;
+                }
+                warned = true;
+            }
+            let large_print = self.geometry.height > 1;
+            term::clear();
+            let board = if self.surprise_hidden() { String::from("A surprise board") } else { format!("This {}x{} board", width, height) };
+            term::paint(&["0;33"], &format!("! {} needs a {}x{} terminal, but this one is {}x{}", board, needed_columns, needed_rows, columns, rows));
+            print!("\r\n\r\n");
+            print!("Make the terminal bigger (or its font smaller) and the game starts on its own, or:\r\n");
+            if large_print {
+                print!("s - turn large print off\r\n");
+            }
+            print!("enter - play anyway, with b for an overview of the whole board\r\n");
+            print!("esc - back to the menu\r\n");
+            std::io::stdout().flush().ok();
+            match event::read()? {
+                Event::Key(key_event) if key_event.kind == KeyEventKind::Press => {
+                    match key_event.code {
+                        KeyCode::Char('s') if large_print => {
+                            self.save.large_print = 0;
+                            self.save.write_save();
+                            self.geometry = CellGeometry::from_large_print(0);
+                        }
+                        KeyCode::Enter => break true,
+                        KeyCode::Esc | KeyCode::Char('m') => break false,
+                        _ => {}
+                    }
+                }
+                _ => {}
+            }
+        };
+        execute!(std::io::stdout(), Show).ok();
+        Ok(fits)
+    }
+    ///
+    /// The row just below the board, where the rules, races, and counters go
+    ///
+    fn status_row(&self) -> u16 {
+        (self.height * self.geometry.height + 4) as u16
+    }
+    ///
+    /// The row under that, where messages go
+    ///
+    fn message_row(&self) -> u16 {
+        self.status_row() + 1
+    }
+    ///
+    /// Scores a won golf round against the board's par (its 3BV)
+    /// 
+    fn print_golf_result(&self) {
+        let par = self.board.get_3bv();
+        let diff = self.clicks as i64 - par as i64;
+        if diff > 0 {
+            print!("Golf: {} strokes, par {} ({} over par)\r\n", self.clicks, par, diff);
+        } else if diff < 0 {
+            print!("Golf: {} strokes, par {} ({} under par)\r\n", self.clicks, par, -diff);
+        } else {
+            print!("Golf: {} strokes, par {} (even)\r\n", self.clicks, par);
+        }
+    }
+    ///
+    /// Shows the win screen once the board has been cleared
+    /// 
+    fn show_win(&mut self) {
+        if self.state == MSGState::Running {
+            self.state = MSGState::Win;
+            // Update the board to have flags over the remaining mines
+            for i in 0..self.height {
+                for j in 0..self.width {
+                    if self.board.is_mine(j, i) {
+                        self.visual_update_space(j, i, -2);
+                    }
+                }
+            }
+            self.visual_update_f_count();
+            self.visual_update_timer();
+            // Display win message
+            // TODO reconfigure this 4 to be a non-magic number
+            execute!(std::io::stdout(), MoveTo(0, self.message_row())).ok();
+            execute!(std::io::stdout(), Hide).ok();
+            print!("Congrats! You won!\r\n");
+            print!("Game time: {}\r\n", self.locale.time(Duration::from_secs(self.board.elapsed().as_secs()), 0));
+            self.print_seed();
+            if self.practice.is_some() {
+                self.finish_practice();
+                return;
+            }
+            if self.warmup {
+                self.finish_warmup();
+                return;
+            }
+            if self.hotseat.is_some() {
+                self.finish_hotseat();
+                return;
+            }
+            if self.casual() {
+                self.finish_casual();
+                return;
+            }
+            if self.assists > 0 {
+                print!("Assists used: {}\r\n", self.assists);
+            }
+            self.print_metrics();
+            self.print_guess_result();
+            self.print_badges();
+            self.print_medal();
+            if self.save.golf {
+                self.print_golf_result();
+            }
+            self.finish_replay(None);
+            self.script_event("on_win", &[("time_ms", self.board.elapsed().as_millis() as i64), ("clicks", self.clicks as i64)]);
+            // Update save data
+            let record = self.game_record(true);
+            self.save.record_game(record);
+            self.print_high_score();
+        }
+    }
+}
+
+// Racing
+impl MinesweeperGame {
+    ///
+    /// Handles everything that came in from the other player since the last call.
+    /// Returns whether or not anything did, so the screen can be brought up to date
+    ///
+    fn poll_race(&mut self) -> bool {
+        let events = match &mut self.race {
+            Some(race) => race.link.poll(),
+            None => return false,
+        };
+        if events.is_empty() {
+            return false;
+        }
+        for event in events {
+            match event {
+                LinkEvent::Joined { .. } => {
+                    // Catch the other side up, whether it's new or coming back after a drop
+                    if let Some(race) = &self.race && race.hosting {
+                        race.link.send(Message::Start { board: race.board.clone(), start: race.start });
+                    }
+                    self.send_progress();
+                }
+                LinkEvent::Message(Message::Progress(progress)) => {
+                    if let Some(race) = &mut self.race {
+                        race.opponent = Some(progress);
+                    }
+                }
+                LinkEvent::Message(Message::Chat { name, text }) => {
+                    // Whatever the other side sent goes straight to the screen, so it's cleaned first
+                    let name = protocol::clean_text(&name, CHAT_NAME_LENGTH);
+                    let text = protocol::clean_text(&text, MAX_CHAT);
+                    if let Some(race) = &mut self.race && !text.is_empty() {
+                        race.chat = Some((if name.is_empty() { String::from("opponent") } else { name }, text));
+                    }
+                }
+                _ => {}
+            }
+        }
+        self.visual_update_rules();
+        self.position_cursor(self.x, self.y);
+        true
+    }
+    ///
+    /// Tells the other player how far along this board is
+    ///
+    fn send_progress(&self) {
+        if let Some(race) = &self.race {
+            race.link.send(Message::Progress(self.progress()));
+        }
+    }
+    ///
+    /// How far along this board is
+    ///
+    fn progress(&self) -> Progress {
+        let mut uncovered: u32 = 0;
+        for i in 0..self.height {
+            for j in 0..self.width {
+                if self.board.is_uncovered(j, i) {
+                    uncovered += 1;
+                }
+            }
+        }
+        Progress {
+            uncovered,
+            safe: (self.board.space_count() - self.board.m_count()) as u32,
+            state: self.board.state(),
+            time_ms: self.board.elapsed().as_millis() as u64,
+        }
+    }
+    ///
+    /// How the race is going, for the line under the board
+    ///
+    fn race_status(&self) -> Option<String> {
+        let race = self.race.as_ref()?;
+        let opponent = match &race.opponent {
+            None => String::from("-"),
+            Some(p) => match p.state {
+                GameState::Win => format!("done in {}", self.locale.time(Duration::from_millis(p.time_ms), 1)),
+                GameState::Loss => String::from("hit a mine"),
+                _ => format!("{}%", p.percent()),
+            },
+        };
+        let mut parts = vec![format!("OPPONENT: {}", opponent)];
+        if let Some(result) = self.race_result() {
+            parts.push(String::from(result));
+        }
+        if *race.link.status() != LinkStatus::Connected {
+            parts.push(race.link.status().to_string());
+        }
+        if let Some((name, text)) = &race.chat {
+            parts.push(format!("{}: {}", name, text));
+        }
+        Some(parts.join(" | "))
+    }
+    ///
+    /// Who won the race, once it can be told. Players are compared by game time, so it doesn't matter who started first
+    ///
+    fn race_result(&self) -> Option<&'static str> {
+        let opponent = self.race.as_ref()?.opponent?;
+        let mine = self.board.state();
+        let time = self.board.elapsed().as_millis() as u64;
+        match (mine, opponent.state) {
+            (GameState::Loss, GameState::Loss) => Some("NOBODY WON"),
+            (GameState::Loss, _) => Some("YOU LOST"),
+            (_, GameState::Loss) if mine == GameState::Win => Some("YOU WON"),
+            (GameState::Win, GameState::Win) => Some(if time <= opponent.time_ms { "YOU WON" } else { "YOU LOST" }),
+            (GameState::Win, _) if opponent.time_ms > time => Some("YOU WON"),
+            (_, GameState::Win) if time > opponent.time_ms => Some("YOU LOST"),
+            _ => None,
+        }
+    }
+    ///
+    /// Lets the player type a chat message on the terminal's bottom row, starting with any keys already typed.
+    /// Enter sends it and Esc throws it away. The race keeps being followed while the player types
+    ///
+    fn chat(&mut self, typed: Vec<KeyCode>) -> Result<(), std::io::Error> {
+        let row = match terminal::size() {
+            Ok((_, rows)) => rows.saturating_sub(1),
+            Err(_) => (self.height + 10) as u16,
+        };
+        let mut typed = typed.into_iter();
+        let mut text = String::new();
+        execute!(std::io::stdout(), Show).ok();
+        loop {
+            execute!(std::io::stdout(), MoveTo(0, row)).ok();
+            print!("SAY: {}", text);
+            term::clear_line();
+            std::io::stdout().flush()?;
+            let key = match typed.next() {
+                Some(key) => key,
+                None => {
+                    if !event::poll(Duration::from_millis(100))? {
+                        if !self.poll_race() && self.state == MSGState::Running {
+                            self.visual_update_timer();
+                        }
+                        continue;
+                    }
+                    match event::read()? {
+                        Event::Key(key_event) if key_event.kind == KeyEventKind::Press => key_event.code,
+                        _ => continue,
+                    }
+                }
+            };
+            match key {
+                KeyCode::Enter => {
+                    let text = protocol::clean_text(&text, MAX_CHAT);
+                    let name = self.save.player_name.clone();
+                    if let Some(race) = &mut self.race && !text.is_empty() {
+                        race.link.send(Message::Chat { name, text: text.clone() });
+                        race.chat = Some((String::from("you"), text));
+                    }
+                    break;
+                }
+                KeyCode::Esc => break,
+                KeyCode::Backspace => {
+                    text.pop();
+                }
+                KeyCode::Char(c) if text.chars().count() < MAX_CHAT => {
+                    text.push(c);
+                }
+                _ => {}
+            }
+        }
+        execute!(std::io::stdout(), MoveTo(0, row)).ok();
+        term::clear_line();
+        self.visual_update_rules();
+        // The cursor stays hidden once the game is over
+        if self.state == MSGState::Win || self.state == MSGState::Loss {
+            execute!(std::io::stdout(), Hide).ok();
+        } else {
+            self.position_cursor(self.x, self.y);
+        }
+        Ok(())
+    }
+    ///
+    /// Copies the board's seed code to the clipboard, or once the game is over, a line saying how it went.
+    /// What happened shows on the terminal's bottom row
+    ///
+    #[cfg(feature = "clipboard")]
+    fn copy_to_clipboard(&self) {
+        let seed = self.seed_code();
+        let (what, text) = match self.state {
+            MSGState::Win | MSGState::Loss => {
+                let result = if self.state == MSGState::Win { "Won" } else { "Lost" };
+                let mut text = format!("{} a {}x{} minesweeper board with {} mines in {:.3}s", result, self.width, self.height, self.m_count, self.board.elapsed().as_secs_f64());
+                let badges = self.badges().to_string();
+                if self.state == MSGState::Win && !badges.is_empty() {
+                    text.push_str(&format!(" [{}]", badges));
+                }
+                if let Some(seed) = seed {
+                    text.push_str(&format!(" (seed code {})", seed));
+                }
+                ("result", Some(text))
+            }
+            _ => ("seed code", seed.map(|s| s.to_string())),
+        };
+        let notice = match text {
+            Some(text) => {
+                match clipboard::copy(&text) {
+                    Ok(()) => format!("Copied the {}: {}", what, text),
+                    Err(e) => format!("Couldn't copy the {}: {}", what, e),
+                }
+            }
+            None if self.replay.as_ref().is_some_and(|r| r.layout.is_none() && r.rng != "pcg") => {
+                String::from("This board's seed can't be shared - set \"rng\" to \"pcg\" in save.json for boards that can")
+            }
+            None => String::from("This board doesn't have a seed to copy"),
+        };
+        let row = match terminal::size() {
+            Ok((_, rows)) => rows.saturating_sub(1),
+            Err(_) => (self.height + 10) as u16,
+        };
+        execute!(std::io::stdout(), MoveTo(0, row)).ok();
+        print!("{}", notice);
+        term::clear_line();
+        if self.state != MSGState::Win && self.state != MSGState::Loss {
+            self.position_cursor(self.x, self.y);
+        }
+    }
+    ///
+    /// Hosts a race on `port` with a board of the given size, once someone joins.
+    /// The host's save decides the gamemode, rules, shape, and opening size, and whether to host through a relay
+    ///
+    pub fn host_race(port: u16, width: i16, height: i16, mine_count: i16) -> Result<(), std::io::Error> {
+        let relay = Save::read_save().relay;
+        let (link, title) = if relay.is_empty() {
+            match Link::host(port) {
+                Ok(link) => (link, format!("Hosting a race on port {}", port)),
+                Err(e) => {
+                    MinesweeperGame::show_link_error(&format!("Couldn't host on port {}: {}", port, e))?;
+                    return Ok(());
+                }
+            }
+        } else {
+            (Link::host_via_relay(relay.clone()), format!("Hosting a race through {}", relay))
+        };
+        let mut queued: Vec<KeyCode> = vec![];
+        let (link, _) = match MinesweeperGame::wait_for_link(link, &title, &mut queued, |event| match event {
+            LinkEvent::Joined { .. } => Some(()),
+            _ => None,
+        })? {
+            Some(joined) => joined,
+            None => return Ok(()),
+        };
+        let mut msg = MinesweeperGame::new(width, height, mine_count, Category::Race, None);
+        let board = Replay {
+            // Hard boards would need the solver on both ends, so races stick to the opening settings
+            generation: GenerationOptions { hard_samples: 0, ..msg.generation },
+            rules: msg.board.rules().name(),
+            topology: msg.board.topology().name(),
+            ..Replay::new(width, height, msg.board.m_count(), msg.save.gamemode.min(1), rand::random(), false, false)
+        };
+        // The middle of the board is never a hole, whatever the shape
+        let start = (width / 2, height / 2);
+        link.send(Message::Start { board: board.clone(), start });
+        if let Err(e) = msg.join_race(link, true, board, start) {
+            MinesweeperGame::show_link_error(&e)?;
+            return Ok(());
+        }
+        msg.queue_keys(queued);
+        msg.run_race()
+    }
+    ///
+    /// Joins the race at `target` (a join code or an address), on whatever board the host picked.
+    /// On a server, `room` picks who to race, or "daily" for the day's shared board
+    ///
+    pub fn join_race_at(target: &str, room: Option<String>) -> Result<(), std::io::Error> {
+        let save = Save::read_save();
+        if save.warmup && !MinesweeperGame::run_warmup()? {
+            return Ok(());
+        }
+        let name = if save.player_name.is_empty() { None } else { Some(save.player_name) };
+        let (link, title) = match net::parse_join_target(target, &save.relay) {
+            JoinTarget::Address(address) => match &room {
+                Some(room) => (Link::join(address.clone(), Some(room.clone()), name), format!("Joining {} at {}", room, address)),
+                None => (Link::join(address.clone(), None, name), format!("Joining the race at {}", address)),
+            },
+            JoinTarget::Lobby(code) => (Link::join_via_relay(save.relay, code.clone(), name), format!("Joining race {}", code)),
+        };
+        let mut queued: Vec<KeyCode> = vec![];
+        let (link, (board, start)) = match MinesweeperGame::wait_for_link(link, &title, &mut queued, |event| match event {
+            LinkEvent::Message(Message::Start { board, start }) => Some((board, start)),
+            _ => None,
+        })? {
+            Some(started) => started,
+            None => return Ok(()),
+        };
+        let mut msg = MinesweeperGame::new(board.width, board.height, board.m_count, Category::Race, Some(board.seed));
+        if let Err(e) = msg.join_race(link, false, board, start) {
+            MinesweeperGame::show_link_error(&e)?;
+            return Ok(());
+        }
+        msg.queue_keys(queued);
+        msg.run_race()
+    }
+    ///
+    /// Plays a small throwaway board before joining a race, as many times as the player likes.
+    /// Returns whether the player went on to the race, rather than backing out with m
+    ///
+    fn run_warmup() -> Result<bool, std::io::Error> {
+        let mut msg = MinesweeperGame::new(WARMUP_SIZE, WARMUP_SIZE, WARMUP_MINES, Category::Custom, None);
+        msg.warmup = true;
+        msg.replay = None;
+        execute!(std::io::stdout(), Show).ok();
+        let _ = enable_raw_mode();
+        if !msg.fit_board()? {
+            let _ = disable_raw_mode();
+            return Ok(false);
+        }
+        msg.redraw();
+        msg.run_loop(MinesweeperGame::handle_start, |msg| msg.state == MSGState::Starting)?;
+        msg.redraw();
+        msg.check();
+        msg.run_loop(MinesweeperGame::handle_input, |msg| msg.state != MSGState::Done)?;
+        execute!(std::io::stdout(), Hide).ok();
+        let _ = disable_raw_mode();
+        if msg.reset {
+            return MinesweeperGame::run_warmup();
+        }
+        Ok(msg.next_game == Some(Category::Race))
+    }
+    ///
+    /// Plays a hot-seat race: each player in turn plays the same board from the same start on this terminal, with the
+    /// screen cleared in between so nobody sees the board before their turn. `seed` picks the board, or a random one
+    /// if it's `None`. Once everyone has played, their times and clicks are compared
+    ///
+    pub fn run_hotseat(width: i16, height: i16, mine_count: i16, seed: Option<u64>) -> Result<(), std::io::Error> {
+        // A chosen seed goes through PCG, so the board can be shared afterwards too
+        let seed = seed.unwrap_or_else(rand::random);
+        let start = (width / 2, height / 2);
+        let mut board: Option<Replay> = None;
+        let mut results: Vec<(Progress, u64)> = vec![];
+        for player in 1..=HOTSEAT_PLAYERS {
+            if !MinesweeperGame::hand_over(player, width, height, mine_count)? {
+                return Ok(());
+            }
+            let mut msg = MinesweeperGame::new(width, height, mine_count, Category::Race, Some(seed));
+            // Set up the same way as a race's board, so the first player's save can't change it under the second
+            let board = board.get_or_insert_with(|| Replay {
+                generation: GenerationOptions { hard_samples: 0, ..msg.generation },
+                rules: msg.board.rules().name(),
+                topology: msg.board.topology().name(),
+                ..Replay::new(width, height, msg.board.m_count(), msg.save.gamemode.min(1), seed, false, false)
+            });
+            msg.hotseat = Some(player);
+            if let Err(e) = msg.take_seat(board, start) {
+                MinesweeperGame::show_link_error(&e)?;
+                return Ok(());
+            }
+            execute!(std::io::stdout(), Show).ok();
+            let _ = enable_raw_mode();
+            if !msg.fit_board()? {
+                let _ = disable_raw_mode();
+                return Ok(());
+            }
+            msg.redraw();
+            msg.run_loop(MinesweeperGame::handle_start, |msg| msg.state == MSGState::Starting)?;
+            msg.redraw();
+            msg.check();
+            msg.run_loop(MinesweeperGame::handle_input, |msg| msg.state != MSGState::Done)?;
+            execute!(std::io::stdout(), Hide).ok();
+            let _ = disable_raw_mode();
+            // Leaving with m calls the whole race off
+            if msg.next_game != Some(Category::Race) {
+                return Ok(());
+            }
+            results.push((msg.progress(), msg.clicks));
+        }
+        MinesweeperGame::show_hotseat_results(&results)
+    }
+    ///
+    /// Clears the screen before a hot-seat turn, and waits for the player to take the keyboard.
+    /// Returns whether they did, rather than calling the race off with Esc
+    ///
+    fn hand_over(player: u8, width: i16, height: i16, mine_count: i16) -> Result<bool, std::io::Error> {
+        let _ = enable_raw_mode();
+        term::clear();
+        print!("Hot-seat race on a {}x{} board with {} mines\r\n\r\n", width, height, mine_count);
+        print!("Player {}'s turn - everyone else, look away!\r\n", player);
+        print!("enter - start | Esc - call the race off");
+        std::io::stdout().flush()?;
+        let ready = loop {
+            if let Event::Key(key_event) = event::read()? && key_event.kind == KeyEventKind::Press {
+                match key_event.code {
+                    KeyCode::Enter => break true,
+                    KeyCode::Esc => break false,
+                    _ => {}
+                }
+            }
+        };
+        term::clear();
+        let _ = disable_raw_mode();
+        Ok(ready)
+    }
+    ///
+    /// Shows how each player's turn went and who won, until a key is pressed. Like races, a win beats a loss,
+    /// and between wins the faster time does
+    ///
+    fn show_hotseat_results(results: &[(Progress, u64)]) -> Result<(), std::io::Error> {
+        let locale = Save::read_save().locale();
+        let _ = enable_raw_mode();
+        term::clear();
+        print!("Hot-seat race results\r\n\r\n");
+        for (i, (progress, clicks)) in results.iter().enumerate() {
+            let result = match progress.state {
+                GameState::Win => format!("cleared the board in {}", locale.time(Duration::from_millis(progress.time_ms), 1)),
+                _ => format!("hit a mine after {} with {}% uncovered", locale.time(Duration::from_millis(progress.time_ms), 1), progress.percent()),
+            };
+            print!("Player {}: {}, {} clicks\r\n", i + 1, result, locale.number(*clicks));
+        }
+        let fastest = results.iter().filter(|(p, _)| p.state == GameState::Win).map(|(p, _)| p.time_ms).min();
+        let winners: Vec<usize> = results.iter().enumerate()
+            .filter(|(_, (p, _))| p.state == GameState::Win && Some(p.time_ms) == fastest)
+            .map(|(i, _)| i + 1)
+            .collect();
+        match winners.as_slice() {
+            [] => print!("\r\nNobody won\r\n"),
+            [winner] => print!("\r\nPlayer {} wins!\r\n", winner),
+            _ => print!("\r\nIt's a tie\r\n"),
+        }
+        print!("Press any key to go back");
+        std::io::stdout().flush()?;
+        loop {
+            if let Event::Key(key_event) = event::read()? && key_event.kind == KeyEventKind::Press {
+                break;
+            }
+        }
+        let _ = disable_raw_mode();
+        Ok(())
+    }
+    ///
+    /// Sets the game up to race on `board` (an empty replay) from `start`
+    ///
+    fn join_race(&mut self, link: Link, hosting: bool, board: Replay, start: (i16, i16)) -> Result<(), String> {
+        self.take_seat(&board, start)?;
+        self.race = Some(Race { link, hosting, board, start, opponent: None, chat: None });
+        Ok(())
+    }
+    ///
+    /// Sets the game up to play `board` (an empty replay) from `start`, the same as everyone else racing on it
+    ///
+    fn take_seat(&mut self, board: &Replay, start: (i16, i16)) -> Result<(), String> {
+        self.board = board.new_board(Box::new(SystemClock::new()))?;
+        if !self.board.is_space(start.0, start.1) {
+            return Err(format!("The race starts from ({}, {}), which isn't on the board", start.0 + 1, start.1 + 1));
+        }
+        self.m_count = self.board.m_count();
+        self.generation = board.generation;
+        self.replay = Some(board.clone());
+        self.ng_start = Some(start);
+        self.x = start.0;
+        self.y = start.1;
+        Ok(())
+    }
+    ///
+    /// Plays a race once both players are in
+    ///
+    fn run_race(mut self) -> Result<(), std::io::Error> {
+        execute!(std::io::stdout(), Show).ok();
+        let _ = enable_raw_mode();
+        // Backing out here leaves the race, the same as m would once it's started
+        if !self.fit_board()? {
+            let _ = disable_raw_mode();
+            return Ok(());
+        }
+        self.redraw();
+        self.send_progress();
+        self.run_loop(MinesweeperGame::handle_start, |msg| msg.state == MSGState::Starting)?;
+        self.redraw();
+        self.script_event("on_start", &[("width", self.width as i64), ("height", self.height as i64), ("mines", self.m_count as i64)]);
+        self.check();
+        self.run_loop(MinesweeperGame::handle_input, |msg| msg.state != MSGState::Done)?;
+        execute!(std::io::stdout(), Hide).ok();
+        let _ = disable_raw_mode();
+        Ok(())
+    }
+    ///
+    /// Shows `title` and the link's status until `until` picks out the event being waited for.
+    /// Returns `None` if the player gives up with Esc or the link closes. Other keys pressed while waiting go in
+    /// `queued`, to be played once the race starts
+    ///
+    fn wait_for_link<T, F: FnMut(LinkEvent) -> Option<T>>(mut link: Link, title: &str, queued: &mut Vec<KeyCode>, mut until: F) -> Result<Option<(Link, T)>, std::io::Error> {
+        let _ = enable_raw_mode();
+        execute!(std::io::stdout(), Hide).ok();
+        term::clear();
+        #[cfg_attr(not(feature = "clipboard"), allow(unused_mut))]
+        let mut copied: Option<String> = None; // What happened when the join code was last copied
+        let result = loop {
+            let mut events = link.poll().into_iter();
+            while let Some(event) = events.next() {
+                if let Some(found) = until(event) {
+                    // Whatever came in right behind it is for the game
+                    link.put_back(events.collect());
+                    let _ = disable_raw_mode();
+                    return Ok(Some((link, found)));
+                }
+            }
+            if let LinkStatus::Closed(reason) = link.status() {
+                break Some(reason.clone());
+            }
+            execute!(std::io::stdout(), MoveTo(0, 0)).ok();
+            print!("{} ({}) | Esc - cancel{}", title, link.status(), queued_notice(queued));
+            term::clear_line();
+            if let Some(code) = link.code() {
+                let copy = if cfg!(feature = "clipboard") { " | y - copy" } else { "" };
+                print!("\r\nJoin code: {} (the other player runs `minesweeper join {}`){}", code, code, copy);
+                term::clear_line();
+            }
+            if let Some(notice) = &copied {
+                print!("\r\n{}", notice);
+                term::clear_line();
+            }
+            std::io::stdout().flush()?;
+            if event::poll(Duration::from_millis(100))? && let Event::Key(key_event) = event::read()? {
+                if key_event.kind == KeyEventKind::Press && key_event.code == KeyCode::Esc {
+                    break None;
+                }
+                #[cfg(feature = "clipboard")]
+                if key_event.kind == KeyEventKind::Press && key_event.code == COPY_KEY && let Some(code) = link.code() {
+                    copied = Some(match clipboard::copy(code) {
+                        Ok(()) => String::from("Copied the join code"),
+                        Err(e) => format!("Couldn't copy the join code: {}", e),
+                    });
+                    continue;
+                }
+                if key_event.kind == KeyEventKind::Press {
+                    queue_key(queued, key_event.code);
+                }
+            }
+        };
+        let _ = disable_raw_mode();
+        if let Some(reason) = result {
+            MinesweeperGame::show_link_error(&format!("{}: {}", title, reason))?;
+        }
+        Ok(None)
+    }
+    ///
+    /// Shows why a race couldn't go ahead, until a key is pressed
+    ///
+    fn show_link_error(message: &str) -> Result<(), std::io::Error> {
+        let _ = enable_raw_mode();
+        term::clear();
+        print!("{}\r\nPress any key to go back", message);
+        std::io::stdout().flush()?;
+        loop {
+            if let Event::Key(key_event) = event::read()? && key_event.kind == KeyEventKind::Press {
+                break;
+            }
+        }
+        let _ = disable_raw_mode();
+        Ok(())
+    }
+}
+
+// Game controller
+impl MinesweeperGame {
+    ///
+    /// Handles the vanilla minesweeper gamemode
+    /// 
+    pub fn run_game(width: i16, height: i16, mine_count: i16, category: Category, seed: Option<u64>) -> Result<(), std::io::Error> {
+        // Create game object
+        execute!(std::io::stdout(), Show).ok();
+        let _ = enable_raw_mode();
+        let mut msg = MinesweeperGame::new(width, height, mine_count, category, seed);
+        if !msg.fit_board()? {
+            let _ = disable_raw_mode();
+            return Ok(());
+        }
+        // Playing a board the same size again, e.g. after a reset, picks up with the cursor where it was left.
+        // No guessing games start on their X instead
+        let cursor = msg.save.last_game.as_ref()
+            .filter(|last| last.width == width && last.height == height)
+            .and_then(|last| last.cursor)
+            .filter(|&(x, y)| x < width && y < height);
+        if let Some((x, y)) = cursor.filter(|_| msg.save.gamemode != 2) {
+            msg.x = x;
+            msg.y = y;
+        }
+        // Remember how the game was set up, so it can be started again straight from the menu
+        msg.save.last_game = Some(LastGame {
+            category: category.to_string(),
+            width,
+            height,
+            mines: mine_count,
+            gamemode: msg.save.gamemode,
+            rules: msg.save.rules.clone(),
+            topology: msg.save.topology.clone(),
+            cursor,
+        });
+        msg.save.write_save();
+        // Break out into no guessing mode if need be
+        if msg.save.gamemode == 2 {
+            let res = MinesweeperGame::run_game_ng(width, height, mine_count, category, seed);
+            let _ = disable_raw_mode();
+            return res;
+        }
+        // Display board size and position the cursor
+        msg.redraw();
+        // Ensure that the user gets a click in before generating the board
+        msg.run_loop(MinesweeperGame::handle_start, |msg| msg.state == MSGState::Starting)?;
+        // Reset board visually
+        msg.redraw();
+        msg.script_event("on_start", &[("width", width as i64), ("height", height as i64), ("mines", mine_count as i64)]);
+        // We have already checked the position we started at so make sure to check it when we move there
+        msg.check();
+        // Main game loop
+        msg.run_loop(MinesweeperGame::handle_input, |msg| msg.state != MSGState::Done)?;
+        if let Some(last) = &mut msg.save.last_game {
+            last.cursor = Some((msg.x, msg.y));
+            msg.save.write_save();
+        }
+        // Reset if need be
+        if msg.reset {
+            MinesweeperGame::run_game(width, height, mine_count, category, seed)?;
+        }
+        msg.start_next_game()?;
+        // Clean up
+        execute!(std::io::stdout(), Hide).ok();
+        let _ = disable_raw_mode();
+        Ok(())
+    }
+    ///
+    /// Starts the preset game picked from the end screen, if there was one
+    ///
+    fn start_next_game(&self) -> Result<(), std::io::Error> {
+        if let Some((width, height, mine_count)) = self.next_game.and_then(|c| c.preset()) {
+            MinesweeperGame::run_game(width, height, mine_count, self.next_game.unwrap(), None)?;
+        }
+        Ok(())
+    }
+    ///
+    /// Plays a board with its mines already laid out, e.g. one imported from an .mbf file. It counts as a custom game,
+    /// and is played on the usual grid with nothing generated, so the first check can hit a mine
+    ///
+    pub fn run_layout(layout: &Layout) -> Result<(), std::io::Error> {
+        let (width, height, mine_count) = (layout.width, layout.height, layout.mines.len() as i16);
+        let mut msg = MinesweeperGame::new(width, height, mine_count, Category::Custom, None);
+        let replay = Replay {
+            rules: msg.board.rules().name(),
+            layout: Some(layout.clone()),
+            ..Replay::new(width, height, mine_count, 0, 0, msg.save.pre_start_flags, msg.save.pre_flags_safe)
+        };
+        msg.board = replay.new_board(Box::new(SystemClock::new())).map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        msg.m_count = mine_count;
+        msg.generation = GenerationOptions::default();
+        msg.replay = Some(replay);
+        execute!(std::io::stdout(), Show).ok();
+        let _ = enable_raw_mode();
+        if !msg.fit_board()? {
+            let _ = disable_raw_mode();
+            return Ok(());
+        }
+        msg.redraw();
+        msg.run_loop(MinesweeperGame::handle_start, |msg| msg.state == MSGState::Starting)?;
+        msg.redraw();
+        msg.script_event("on_start", &[("width", width as i64), ("height", height as i64), ("mines", mine_count as i64)]);
+        msg.check();
+        msg.run_loop(MinesweeperGame::handle_input, |msg| msg.state != MSGState::Done)?;
+        if msg.reset {
+            MinesweeperGame::run_layout(layout)?;
+        }
+        msg.start_next_game()?;
+        execute!(std::io::stdout(), Hide).ok();
+        let _ = disable_raw_mode();
+        Ok(())
+    }
+    ///
+    /// Plays on from a position in a replay, after its first `moves` moves, as many times as the player likes.
+    /// The clock starts from zero at the position, and each try is tracked under the position
+    ///
+    pub fn run_practice(replay: &Replay, moves: usize) -> Result<(), std::io::Error> {
+        let mut msg = MinesweeperGame::new(replay.width, replay.height, replay.m_count, Category::Custom, None);
+        msg.board = replay.simulate(moves).map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        if msg.board.state() != GameState::Running {
+            return Err(std::io::Error::other(format!("the game isn't underway after {} moves", moves)));
+        }
+        msg.board.set_clock(Box::new(SystemClock::new()));
+        msg.board.set_elapsed(Duration::ZERO);
+        let mut practiced = replay.clone();
+        practiced.actions.truncate(moves);
+        practiced.finish(GameState::Starting, Duration::ZERO);
+        practiced.badges = Badges::default();
+        msg.practice = Some(practice_key(&practiced));
+        msg.m_count = replay.m_count;
+        msg.generation = replay.generation;
+        msg.replay = Some(practiced);
+        msg.state = MSGState::Running;
+        // Start where the move that came next in the game was made, since that's usually the one being drilled
+        if let Some(next) = replay.actions.get(moves) {
+            msg.x = next.x;
+            msg.y = next.y;
+        }
+        execute!(std::io::stdout(), Show).ok();
+        let _ = enable_raw_mode();
+        if !msg.fit_board()? {
+            let _ = disable_raw_mode();
+            return Ok(());
+        }
+        msg.redraw();
+        msg.run_loop(MinesweeperGame::handle_input, |msg| msg.state != MSGState::Done)?;
+        if msg.reset {
+            MinesweeperGame::run_practice(replay, moves)?;
+        }
+        msg.start_next_game()?;
+        execute!(std::io::stdout(), Hide).ok();
+        let _ = disable_raw_mode();
+        Ok(())
+    }
+    ///
+    /// Opens a lost game's snapshot in the review, starting from the move that lost it. Only games the solver
+    /// can read can be reviewed
+    ///
+    #[cfg(feature = "solver")]
+    pub fn run_snapshot(snapshot: &Snapshot) -> Result<(), std::io::Error> {
+        let replay = &snapshot.replay;
+        let mut msg = MinesweeperGame::new(replay.width, replay.height, replay.m_count, Category::Custom, None);
+        msg.board = replay.simulate(replay.actions.len()).map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        if !msg.solver_can_read() {
+            return Err(std::io::Error::other("only games with standard numbers on the usual grid can be reviewed"));
+        }
+        msg.m_count = replay.m_count;
+        msg.replay = Some(replay.clone());
+        msg.state = MSGState::Loss;
+        let _ = enable_raw_mode();
+        if !msg.fit_board()? {
+            let _ = disable_raw_mode();
+            return Ok(());
+        }
+        msg.redraw();
+        msg.show_review();
+        let _ = disable_raw_mode();
+        Ok(())
+    }
+    ///
+    /// A key as the game takes it. Keys the player bound come first, so they win over vim keys
+    ///
+    fn mapped_key(&self, key: KeyCode) -> KeyCode {
+        let key = keys::bound_key(&self.save.keys, key);
+        if self.save.vim_keys { keys::vim_key(key) } else { key }
+    }
+    ///
+    /// Holds on to keys pressed while the game was busy, for the next `run_loop` to play before anything else
+    ///
+    fn queue_keys(&mut self, keys: Vec<KeyCode>) {
+        let keys: Vec<KeyCode> = keys.into_iter().map(|key| self.mapped_key(key)).collect();
+        self.queued.extend(keys);
+        self.queued.truncate(MAX_QUEUED_KEYS);
+    }
+    ///
+    /// Handles input as it comes in until `keep_going` says to stop, while only bringing the screen
+    /// up to date at most `FRAME_RATE` times a second. Key handlers draw into stdout's buffer,
+    /// and each frame updates the timer and flushes everything out at once
+    ///
+    fn run_loop<F: FnMut(&mut MinesweeperGame, KeyCode)>(&mut self, mut on_key: F, keep_going: fn(&MinesweeperGame) -> bool) -> Result<(), std::io::Error> {
+        let mut frames = FrameLimiter::new(FRAME_RATE);
+        let mut shown_time = self.board.elapsed().as_secs();
+        let mut shown_cell = (self.x, self.y, self.describe_space(self.x, self.y));
+        // Terminals that can say when they lose focus let the game pause itself
+        execute!(std::io::stdout(), EnableFocusChange, EnableMouseCapture).ok();
+        while keep_going(self) {
+            if self.poll_race() {
+                frames.dirty = true;
+            }
+            // Keys held on to while the game was busy go first, without waiting for more
+            let queued = std::mem::take(&mut self.queued);
+            if !queued.is_empty() || event::poll(frames.until_next())? {
+                // Take everything that is waiting at once, so bursts from key repeat can be coalesced
+                let mut keys: Vec<KeyCode> = vec![];
+                let mut click: Option<((i16, i16), KeyCode)> = None;
+                while event::poll(Duration::ZERO)? {
+                    match event::read()? {
+                        Event::Key(key_event) if key_event.kind == KeyEventKind::Press => {
+                            keys.push(key_event.code);
+                        }
+                        Event::Mouse(mouse_event) => {
+                            // A click goes after the keys before it, so it ends the burst
+                            click = self.mouse_click(mouse_event);
+                            if click.is_some() {
+                                break;
+                            }
+                        }
+                        Event::Resize(columns, rows) => {
+                            tracing::info!(columns = columns, rows = rows, "terminal resized");
+                            self.redraw();
+                        }
+                        Event::FocusLost if !self.board.is_paused() => {
+                            // Switching away stops the clock, and switching back starts it again
+                            self.set_paused(true);
+                            self.focus_paused = self.board.is_paused();
+                        }
+                        Event::FocusGained if self.focus_paused => {
+                            self.set_paused(false);
+                        }
+                        _ => {}
+                    }
+                }
+                // Keys after the chat key are the start of a message, so they don't get coalesced
+                let typed = match keys.iter().position(|k| *k == CHAT_KEY) {
+                    Some(i) if self.race.is_some() => keys.split_off(i),
+                    _ => vec![],
+                };
+                let keys: Vec<KeyCode> = queued.into_iter().chain(keys.into_iter().map(|key| self.mapped_key(key))).collect();
+                let mut keys = coalesce_keys(keys).into_iter();
+                while let Some(key) = keys.next() {
+                    self.clear_feedback();
+                    on_key(self, key);
+                    frames.dirty = true;
+                    if !keep_going(self) {
+                        // Keys typed ahead of the first check are for the game it starts
+                        if self.state == MSGState::Running {
+                            self.queued = keys.collect();
+                        }
+                        break;
+                    }
+                }
+                if !typed.is_empty() && keep_going(self) {
+                    self.chat(typed[1..].to_vec())?;
+                    frames.dirty = true;
+                }
+                // Clicks move the cursor to the space, then act on it like the key would
+                if let Some(((x, y), key)) = click.filter(|_| keep_going(self)) {
+                    self.clear_feedback();
+                    self.x = x;
+                    self.y = y;
+                    self.position_cursor(x, y);
+                    on_key(self, key);
+                    frames.dirty = true;
+                }
+                // The line under the board names the space under the cursor on labeled boards
+                let here = (self.x, self.y, self.describe_space(self.x, self.y));
+                if self.save.coordinates != 0 && here != shown_cell && keep_going(self) {
+                    self.visual_update_rules();
+                    self.position_cursor(self.x, self.y);
+                    shown_cell = here;
+                }
+            }
+            if frames.due() {
+                let time = self.board.elapsed().as_secs();
+                if frames.dirty || time != shown_time {
+                    let _span = tracing::info_span!("frame").entered();
+                    if self.state == MSGState::Running {
+                        self.visual_update_timer();
+                        self.visual_update_bonuses();
+                        self.position_cursor(self.x, self.y);
+                    }
+                    std::io::stdout().flush()?;
+                    shown_time = time;
+                    frames.dirty = false;
+                }
+            }
+        }
+        execute!(std::io::stdout(), DisableFocusChange, DisableMouseCapture).ok();
+        Ok(())
+    }
+    ///
+    /// Handles the no-guessing mode
+    /// 
+    fn run_game_ng(width: i16, height: i16, mine_count: i16, category: Category, seed: Option<u64>) -> Result<(), std::io::Error> {
+        // Create game object
+        let mut msg = MinesweeperGame::new(width, height, mine_count, category, seed);
+        // Generate the mines and pick a start position on an opening the solver can clear the board from.
+        // Dense boards can take a while, so this runs in the background and can be cancelled
+        let gamemode = msg.save.gamemode;
+        let mine_count = msg.board.m_count();
+        let topology_name = msg.board.topology().name();
+        let avoid = (msg.x, msg.y);
+        // Boards the solver can't read are only made sure to have an opening
+        #[cfg(feature = "solver")]
+        let solver_check = msg.solver_can_read();
+        #[cfg(feature = "solver")]
+        let mut tries: u32 = 0;
+        let mut rng: Box<dyn RngCore + Send> = match seed {
+            Some(seed) => SeedRng::Pcg.rng(seed),
+            None => Box::new(StdRng::from_os_rng()),
+        };
+        let mut queued: Vec<KeyCode> = vec![];
+        let generated = MinesweeperGame::generate_in_background(msg.save.reduced_motion, &mut queued, move || {
+            let _span = tracing::info_span!("no_guess_generation", width = width, height = height, mines = mine_count).entered();
+            let topology = topology::from_name(&topology_name).unwrap_or_else(|| Box::new(SquareEight));
+            // Holes in the board never get mines, and can't be started on
+            let mut holes: Vec<(i16, i16)> = vec![avoid];
+            for i in 0..height {
+                for j in 0..width {
+                    if !topology.contains(j, i, width, height) {
+                        holes.push((j, i));
+                    }
+                }
+            }
+            let mine_map = Board::generate_mine_map(width, height, mine_count, gamemode, &holes, &mut *rng);
+            let m_count_map = Board::generate_m_count_map(&mine_map, &*topology);
+            let mut openings: Vec<(i16, i16)> = vec![];
+            for i in 0..height {
+                for j in 0..width {
+                    if m_count_map[i as usize][j as usize] == 0 && mine_map[i as usize][j as usize] == 0 && !holes.contains(&(j, i)) {
+                        openings.push((j, i));
+                    }
+                }
+            }
+            let start = *openings.choose(&mut rng)?;
+            // Keep generating until the solver can clear the board from the start without guessing
+            #[cfg(feature = "solver")]
+            if solver_check {
+                tries += 1;
+                let mut board = Board::deterministic(width, height, mine_count, 0, 0, ManualClock::new());
+                board.set_mine_map(mine_map.clone());
+                if grade::grade(&board, start) == Some(Grade::Expert) && tries < NO_GUESS_ATTEMPTS {
+                    return None;
+                }
+            }
+            Some((mine_map, start))
+        })?;
+        let start: (i16, i16) = match generated {
+            Some((mine_map, start)) => {
+                msg.board.set_mine_map(mine_map);
+                start
+            }
+            None => {
+                // Cancelled, go back to the menu
+                execute!(std::io::stdout(), Hide).ok();
+                return Ok(());
+            }
+        };
+        execute!(std::io::stdout(), Show).ok();
+        msg.queue_keys(queued);
+        // Once we have found the valid start, print the X
+        msg.ng_start = Some(start);
+        msg.redraw();
+        // Then handle the start
+        msg.run_loop(MinesweeperGame::handle_start, |msg| msg.state == MSGState::Starting)?;
+        // Some rules only have something to show once the mines are down, like the range a hidden total falls in
+        msg.visual_update_rules();
+        // Surprise games show their board once the first click is in
+        if msg.category == Category::Surprise {
+            msg.redraw();
+        }
+        // Now handle the rest as normal
+        msg.script_event("on_start", &[("width", width as i64), ("height", height as i64), ("mines", mine_count as i64)]);
+        msg.check();
+        // Main game loop
+        msg.run_loop(MinesweeperGame::handle_input, |msg| msg.state != MSGState::Done)?;
+        // Reset if need be
+        if msg.reset {
+            MinesweeperGame::run_game(width, height, mine_count, category, seed)?;
+        }
+        msg.start_next_game()?;
+        // Clean up
+        execute!(std::io::stdout(), Hide).ok();
+        Ok(())
+    }
+    ///
+    /// Repeatedly runs `attempt` on a background thread until it produces a board, showing a spinner
+    /// (unless `reduced_motion` is set) with the number of attempts so far. Returns `None` if the player cancels with Esc.
+    /// Any other keys pressed in the meantime go in `queued`, to be played on the board once it's up
+    /// 
+    fn generate_in_background<T, F>(reduced_motion: bool, queued: &mut Vec<KeyCode>, mut attempt: F) -> Result<Option<T>, std::io::Error>
+    where
+        T: Send + 'static,
+        F: FnMut() -> Option<T> + Send + 'static,
+    {
+        let attempts = Arc::new(AtomicU64::new(0));
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let (sender, receiver) = mpsc::channel();
+        let worker = {
+            let attempts = Arc::clone(&attempts);
+            let cancelled = Arc::clone(&cancelled);
+            thread::spawn(move || {
+                while !cancelled.load(Ordering::Relaxed) {
+                    attempts.fetch_add(1, Ordering::Relaxed);
+                    if let Some(result) = attempt() {
+                        sender.send(result).ok();
+                        return;
+                    }
+                }
+            })
+        };
+        // Show a spinner until the worker finishes or the player gives up
+        execute!(std::io::stdout(), Hide).ok();
+        term::clear();
+        let spinner = ['|', '/', '-', '\\'];
+        let mut frame: usize = 0;
+        let result = loop {
+            if let Ok(result) = receiver.try_recv() {
+                break Some(result);
+            }
+            execute!(std::io::stdout(), MoveTo(0, 0)).ok();
+            let icon = if reduced_motion { String::new() } else { format!("{} ", spinner[frame % spinner.len()]) };
+            print!("{}Generating board... attempts: {} | Esc - cancel{}", icon, attempts.load(Ordering::Relaxed), queued_notice(queued));
+            term::clear_line();
+            std::io::stdout().flush()?;
+            frame += 1;
+            if event::poll(Duration::from_millis(100))? && let Event::Key(key_event) = event::read()? && key_event.kind == KeyEventKind::Press {
+                if key_event.code == KeyCode::Esc {
+                    cancelled.store(true, Ordering::Relaxed);
+                    break None;
+                }
+                queue_key(queued, key_event.code);
+            }
+        };
+        worker.join().ok();
+        Ok(result)
+    }
+    ///
+    /// Draws a fully uncovered board `frames` times and returns the average time per frame.
+    /// Used by `minesweeper bench`
+    /// 
+    pub fn bench_render(width: i16, height: i16, mine_count: i16, frames: u32) -> Duration {
+        let mut msg = MinesweeperGame::new(width, height, mine_count, Category::Custom, None);
+        msg.board.generate((0, 0));
+        let start = std::time::Instant::now();
+        for _ in 0..frames {
+            msg.print_board_normal();
+            for i in 0..height {
+                for j in 0..width {
+                    if msg.board.is_mine(j, i) {
+                        msg.visual_update_space(j, i, -1);
+                    } else {
+                        msg.visual_update_space(j, i, msg.board.mine_count_at(j, i));
+                    }
+                }
+            }
+            std::io::stdout().flush().ok();
+        }
+        start.elapsed() / frames
+    }
+    ///
+    /// Picks a random board size and density, then starts a game with it.
+    /// Densities are weighted toward the playable 12-18% range
+    /// 
+    pub fn run_surprise() -> Result<(), std::io::Error> {
+        let mut rng = rand::rng();
+        let densities: [(f32, u32); 6] = [(0.10, 1), (0.12, 3), (0.14, 4), (0.16, 4), (0.18, 3), (0.20, 1)];
+        let density = densities.choose_weighted(&mut rng, |d| d.1).unwrap().0;
+        let width = rng.random_range(9..=SURPRISE_WIDTH);
+        let height = rng.random_range(9..=SURPRISE_HEIGHT);
+        let mine_count = ((width * height) as f32 * density).round().max(1.) as i16;
+        MinesweeperGame::run_game(width, height, mine_count, Category::Surprise, None)
+    }
+    ///
+    /// Starts a game with the same settings as the last one started from the menu, putting its gamemode, rules,
+    /// and shape back if they've been changed since. Does nothing if there hasn't been one yet
+    ///
+    pub fn run_last() -> Result<(), std::io::Error> {
+        let mut save = Save::read_save();
+        let last = match save.last_game.clone() {
+            Some(last) => last,
+            None => return Ok(()),
+        };
+        save.gamemode = last.gamemode;
+        save.rules = last.rules;
+        save.topology = last.topology;
+        save.write_save();
+        match Category::from_name(&last.category) {
+            Some(Category::Surprise) => MinesweeperGame::run_surprise(),
+            Some(category) => MinesweeperGame::run_game(last.width, last.height, last.mines, category, None),
+            None => Ok(()),
+        }
+    }
+}
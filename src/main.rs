@@ -1,128 +1,846 @@
+pub(crate) mod bench;
+pub(crate) mod capabilities;
+#[cfg(feature = "clipboard")]
+pub(crate) mod clipboard;
+pub(crate) mod custom;
+pub(crate) mod keys;
+pub(crate) mod log;
 pub(crate) mod logic;
+pub(crate) mod net;
+pub(crate) mod onboarding;
+#[cfg(feature = "profile")]
+pub(crate) mod profile;
+pub(crate) mod relay;
 pub(crate) mod saves;
+pub(crate) mod scores;
+pub(crate) mod scripting;
+pub(crate) mod server;
+pub(crate) mod term;
+#[cfg(feature = "update-check")]
+pub(crate) mod update;
 
 use saves::Save;
-use logic::MinesweeperGame;
+use minesweeper::cast::{self, Theme};
+use minesweeper::compact;
+use minesweeper::engine::GameState;
+use minesweeper::layout::Layout;
+use minesweeper::migrate;
+use minesweeper::replay::Replay;
+use minesweeper::seed_code::SeedCode;
+use minesweeper::snapshot::Snapshot;
+use logic::{Category, MinesweeperGame};
 
 use crossterm::{
     cursor::{
-        MoveTo, SetCursorStyle, Hide, Show
+        SetCursorStyle, Hide, Show
     },
     event::{self, Event, KeyCode, KeyEventKind},
     execute
 };
 
+use std::io::IsTerminal;
+use tracing_subscriber::Registry;
+use tracing_subscriber::layer::{Layer, SubscriberExt};
 use std::time::Duration;
-use std::io::Write;
 use std::fs;
 
 fn do_splash_text() {
     //? Shoutout Patrick Gillespie: https://patorjk.com/software/taag
-    execute!(std::io::stdout(), MoveTo(0, 0)).ok();
-    print!("{}[2J", 27 as char);
+    term::clear();
     print!(" _____    _____               _____                           \r\n");
     print!("| | | |  |_   _|___ ___ _____|   __|_ _ _ ___ ___ ___ ___ ___ \r\n");
     print!("|-   -|    | | | -_|  _|     |__   | | | | -_| -_| . | -_|  _|\r\n");
     print!("|_|_|_|    |_| |___|_| |_|_|_|_____|_____|___|___|  _|___|_|  \r\n");
     print!("                                                 |_|          \r\n\r\n");
 
+    let save = Save::read_save();
+    if let Some(last) = &save.last_game {
+        print!("0. Play again ({})\r\n", last);
+    }
     print!("1. Beginner (9x9, 10 mines)\r\n");
     print!("2. Intermediate (16x16, 40 mines)\r\n");
     print!("3. Expert (30x16, 99 mines)\r\n");
     print!("4. Custom\r\n");
-    print!("5. Exit\r\n");
+    print!("5. Surprise me\r\n");
+    print!("6. High scores\r\n");
+    print!("7. Exit\r\n");
 
-    let save = Save::read_save();
-    print!("\x1b[0;90m\r\nGames Played: {}\r\nGames Won: {}\r\nWin %: {}\r\nMinutes played: {}\r\nClicks: {}\x1b[0m\r\n", save.g_played, save.g_won, (save.g_won as f32 / save.g_played as f32) * 100., save.total_playtime / 60, save.total_clicks);
+    if save.stats {
+        print_stats(&save);
+    }
+    #[cfg(feature = "update-check")]
+    if let Some(notice) = update::notice() {
+        term::style(&["33"]);
+        print!("\r\n\r\n{}", notice);
+    }
+    term::reset();
+    print!("\r\n");
+}
+
+///
+/// The menu's stats: games played and won, guesses, win rates by start, medals, and best times with bonuses
+///
+fn print_stats(save: &Save) {
+    term::style(&["0;90"]);
+    let locale = save.locale();
+    print!("\r\nGames Played: {}\r\nGames Won: {}\r\nWin %: {}\r\nMinutes played: {}\r\nClicks: {}",
+        locale.number(save.g_played as u64), locale.number(save.g_won as u64), locale.decimal(save.g_won as f64 / save.g_played as f64 * 100., 1),
+        locale.number(save.total_playtime / 60), locale.number(save.total_clicks));
+    // Guesses show how much of the record is down to luck rather than logic
+    let guess_stats = &save.guess_stats;
+    if guess_stats.g_played > 0 {
+        let survived = if guess_stats.guesses > 0 { guess_stats.survived as f32 / guess_stats.guesses as f32 * 100. } else { 100. };
+        print!("\r\nGuesses a game: {} ({} forced)\r\nGuesses survived: {}%\r\nWins without a guess: {} of {}",
+            locale.decimal(guess_stats.guesses as f64 / guess_stats.g_played as f64, 1), locale.decimal(guess_stats.forced as f64 / guess_stats.g_played as f64, 1),
+            locale.decimal(survived as f64, 0), locale.number(guess_stats.zero_guess_wins as u64), locale.number(guess_stats.g_won as u64));
+    }
+    // Win rates by where the first check was, e.g. whether corner starts pay off
+    let starts: Vec<String> = ["corner", "edge", "middle"].iter()
+        .filter_map(|kind| save.start_stats.get(*kind).filter(|stats| stats.g_played > 0).map(|stats| (kind, stats)))
+        .map(|(kind, stats)| format!("{} starts {}% ({})", kind, locale.decimal(stats.g_won as f64 / stats.g_played as f64 * 100., 0), locale.number(stats.g_played as u64)))
+        .collect();
+    if !starts.is_empty() {
+        print!("\r\nWin % by start: {}", starts.join(" | "));
+    }
+    // Par time medals won on each preset board
+    let medals: Vec<String> = ["beginner", "intermediate", "expert"].iter()
+        .filter_map(|category| save.medal_stats.get(*category).map(|stats| (category, stats)))
+        .map(|(category, stats)| format!("{} {}/{}/{}", category, stats.gold, stats.silver, stats.bronze))
+        .collect();
+    if !medals.is_empty() {
+        print!("\r\nMedals (gold/silver/bronze): {}", medals.join(" | "));
+    }
+    // Time bonuses make for faster times, so games with them keep best times of their own
+    let bonus_bests: Vec<String> = ["beginner", "intermediate", "expert", "custom"].iter()
+        .filter_map(|category| save.category_stats.get(&format!("{} bonus", category)).and_then(|stats| stats.best_time_ms).map(|best| (category, best)))
+        .map(|(category, best)| format!("{} {}", category, locale.time(Duration::from_millis(best), 1)))
+        .collect();
+    if !bonus_bests.is_empty() {
+        print!("\r\nBest times with bonuses: {}", bonus_bests.join(" | "));
+    }
+    term::reset();
+}
+
+///
+/// Re-plays a replay file and reports whether it holds up. Returns the exit code.
+/// Run with `minesweeper verify <replay>`
+///
+fn verify_replay(path: Option<String>) -> i32 {
+    let path = match path {
+        Some(p) => p,
+        None => {
+            eprintln!("Usage: minesweeper verify <replay>");
+            return 2;
+        }
+    };
+    let replay = match read_replay(&path) {
+        Ok(r) => r,
+        Err(e) => {
+            eprintln!("Couldn't read replay {}: {}", path, e);
+            return 2;
+        }
+    };
+    match replay.verify() {
+        Ok(verification) => {
+            println!("OK: {}x{} with {} mines, seed {}", replay.width, replay.height, replay.m_count, replay.seed);
+            println!("Result: {:?} in {:.3}s over {} moves", verification.result, verification.time.as_secs_f64(), replay.actions.len());
+            if replay.badges != Default::default() {
+                println!("Badges: {}", replay.badges);
+            }
+            0
+        }
+        Err(e) => {
+            println!("INVALID: {}", e);
+            1
+        }
+    }
+}
+
+///
+/// Reads a replay file, packed or as JSON, or says why it couldn't
+///
+fn read_replay(path: &str) -> Result<Replay, String> {
+    let bytes = fs::read(path).map_err(|e| e.to_string())?;
+    if compact::is_compact(&bytes) {
+        return compact::decode(&bytes);
+    }
+    let text = String::from_utf8(bytes).map_err(|e| e.to_string())?;
+    // Snapshots of lost games carry their replay, so they work anywhere a replay does
+    serde_json::from_str::<Replay>(&text)
+        .or_else(|e| serde_json::from_str::<Snapshot>(&text).map(|s| s.replay).map_err(|_| e.to_string()))
+}
+
+///
+/// Reads the arguments shared by the replay exports: `<replay> [out] [--speed N]`.
+/// Returns the replay's path, where to write, and the speed, or the exit code to quit with
+///
+fn export_args(args: &[String], usage: &str, extension: &str) -> Result<(String, std::path::PathBuf, f64), i32> {
+    let mut paths: Vec<&String> = vec![];
+    let mut speed: f64 = 1.;
+    let mut i = 0;
+    while i < args.len() {
+        if args[i] == "--speed" {
+            match args.get(i + 1).map(|s| s.parse::<f64>()) {
+                Some(Ok(s)) if s > 0. && s.is_finite() => speed = s,
+                _ => {
+                    eprintln!("{}", usage);
+                    return Err(2);
+                }
+            }
+            i += 2;
+        } else {
+            paths.push(&args[i]);
+            i += 1;
+        }
+    }
+    match paths.as_slice() {
+        [path] => Ok((path.to_string(), std::path::Path::new(path).with_extension(extension), speed)),
+        [path, out] => Ok((path.to_string(), std::path::PathBuf::from(out), speed)),
+        _ => {
+            eprintln!("{}", usage);
+            Err(2)
+        }
+    }
+}
+
+///
+/// The save's characters and colors, for drawing replays the way the game looks
+///
+fn save_theme() -> Theme {
+    Save::read_save().theme()
+}
+
+///
+/// Lists the built-in themes with a sample of each, or switches the save over to one. Returns the exit code.
+/// Run with `minesweeper theme [name]`
+///
+fn pick_theme(name: Option<String>) -> i32 {
+    let gallery = Theme::gallery();
+    let name = match name {
+        Some(name) => name,
+        None => {
+            for (name, theme) in &gallery {
+                println!("{}", name);
+                for line in onboarding::sample(theme) {
+                    println!("  {}", line);
+                }
+            }
+            println!("Switch with `minesweeper theme <name>`");
+            return 0;
+        }
+    };
+    match gallery.into_iter().find(|(n, _)| *n == name) {
+        Some((_, theme)) => {
+            let mut save = Save::read_save();
+            save.set_theme(theme);
+            save.write_save();
+            println!("Switched to the {} theme", name);
+            0
+        }
+        None => {
+            eprintln!("Unknown theme \"{}\". Run `minesweeper theme` to see them all", name);
+            2
+        }
+    }
+}
+
+///
+/// Brings keys, colors, and stats to show over from another client's ini file into the save. Returns the exit code.
+/// Run with `minesweeper migrate <settings.ini>`
+///
+fn migrate_settings(path: Option<String>) -> i32 {
+    let path = match path {
+        Some(path) => path,
+        None => {
+            eprintln!("Usage: minesweeper migrate <settings.ini>");
+            return 2;
+        }
+    };
+    // Older clients write their ini files in the Windows code page, so anything that isn't UTF-8 is read byte for byte
+    let text = match fs::read(&path) {
+        Ok(bytes) => String::from_utf8(bytes).unwrap_or_else(|e| e.into_bytes().iter().map(|&b| b as char).collect()),
+        Err(e) => {
+            eprintln!("Couldn't read {}: {}", path, e);
+            return 2;
+        }
+    };
+    let imported = migrate::import_ini(&text);
+    if imported.keys.is_empty() && imported.colors.is_empty() && imported.metrics.is_empty() {
+        eprintln!("Nothing in {} could be brought over", path);
+        return 1;
+    }
+    let mut save = Save::read_save();
+    save.import(&imported);
+    save.write_save();
+    for (action, key) in &imported.keys {
+        println!("Bound {} to {}", action, key);
+    }
+    for (field, code) in &imported.colors {
+        println!("Set {} to {}", field, code);
+    }
+    if !imported.metrics.is_empty() {
+        println!("Showing {} after wins", imported.metrics.join(", "));
+    }
+    if !imported.skipped.is_empty() {
+        println!("Skipped {} setting(s) with nothing to match here: {}", imported.skipped.len(), imported.skipped.join(", "));
+    }
+    0
+}
+
+///
+/// Turns a replay into an asciinema cast, drawn with the save's characters and colors. Returns the exit code.
+/// Run with `minesweeper cast <replay> [out.cast] [--speed N]`; the cast goes next to the replay by default
+///
+fn export_cast(args: &[String]) -> i32 {
+    let (path, out, speed) = match export_args(args, "Usage: minesweeper cast <replay> [out.cast] [--speed N]", "cast") {
+        Ok(parsed) => parsed,
+        Err(code) => return code,
+    };
+    let replay = match read_replay(&path) {
+        Ok(r) => r,
+        Err(e) => {
+            eprintln!("Couldn't read replay {}: {}", path, e);
+            return 2;
+        }
+    };
+    let title = format!("Minesweeper {}x{}, {} mines: {:?} in {:.3}s", replay.width, replay.height, replay.m_count, replay.result, replay.time_ms as f64 / 1000.);
+    let text = match cast::to_cast(&replay, &save_theme(), &title, speed) {
+        Ok(text) => text,
+        Err(e) => {
+            eprintln!("Couldn't play back {}: {}", path, e);
+            return 1;
+        }
+    };
+    if let Err(e) = fs::write(&out, text) {
+        eprintln!("Couldn't write {}: {}", out.display(), e);
+        return 1;
+    }
+    println!("Wrote {}", out.display());
+    0
+}
+
+///
+/// Turns a replay into an animated GIF, drawn with the save's colors. Returns the exit code.
+/// Run with `minesweeper gif <replay> [out.gif] [--speed N]`; the GIF goes next to the replay by default
+///
+#[cfg(feature = "gif")]
+fn export_gif(args: &[String]) -> i32 {
+    let (path, out, speed) = match export_args(args, "Usage: minesweeper gif <replay> [out.gif] [--speed N]", "gif") {
+        Ok(parsed) => parsed,
+        Err(code) => return code,
+    };
+    let replay = match read_replay(&path) {
+        Ok(r) => r,
+        Err(e) => {
+            eprintln!("Couldn't read replay {}: {}", path, e);
+            return 2;
+        }
+    };
+    let bytes = match minesweeper::animation::to_gif(&replay, &save_theme(), speed) {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            eprintln!("Couldn't play back {}: {}", path, e);
+            return 1;
+        }
+    };
+    if let Err(e) = fs::write(&out, bytes) {
+        eprintln!("Couldn't write {}: {}", out.display(), e);
+        return 1;
+    }
+    println!("Wrote {}", out.display());
+    0
+}
+
+#[cfg(not(feature = "gif"))]
+fn export_gif(_args: &[String]) -> i32 {
+    eprintln!("This build can't make GIFs. Rebuild with `cargo build --features gif`, or use `minesweeper cast` instead.");
+    1
+}
+
+///
+/// Writes where a replay's mines were as an .mbf file, for other minesweeper tools. Returns the exit code.
+/// Run with `minesweeper export <replay> [out.mbf]`; the file goes next to the replay by default
+///
+fn export_mbf(args: &[String]) -> i32 {
+    let (path, out) = match args {
+        [path] => (path.to_string(), std::path::Path::new(path).with_extension("mbf")),
+        [path, out] => (path.to_string(), std::path::PathBuf::from(out)),
+        _ => {
+            eprintln!("Usage: minesweeper export <replay> [out.mbf]");
+            return 2;
+        }
+    };
+    let replay = match read_replay(&path) {
+        Ok(r) => r,
+        Err(e) => {
+            eprintln!("Couldn't read replay {}: {}", path, e);
+            return 2;
+        }
+    };
+    if replay.topology != "square8" {
+        eprintln!("Only boards on the usual grid can be written as .mbf, not {} boards", replay.topology);
+        return 1;
+    }
+    let layout = match replay.simulate(replay.actions.len()) {
+        Ok(board) => Layout::from_board(&board),
+        Err(e) => {
+            eprintln!("Couldn't play back {}: {}", path, e);
+            return 1;
+        }
+    };
+    let bytes = match layout.map(|layout| layout.to_mbf()) {
+        Some(Ok(bytes)) => bytes,
+        Some(Err(e)) => {
+            eprintln!("Couldn't write the board as .mbf: {}", e);
+            return 1;
+        }
+        None => {
+            eprintln!("The game in {} ended before the mines were placed", path);
+            return 1;
+        }
+    };
+    if let Err(e) = fs::write(&out, bytes) {
+        eprintln!("Couldn't write {}: {}", out.display(), e);
+        return 1;
+    }
+    println!("Wrote {}", out.display());
+    0
+}
+
+///
+/// Hosts a race. Run with `minesweeper host [port] [beginner|intermediate|expert]`; the board defaults to intermediate.
+/// With a relay in the save, the race is hosted through it and the port isn't used
+///
+fn host_race(port: Option<String>, board: Option<String>) -> Result<(), std::io::Error> {
+    let port = match port.map(|p| p.parse::<u16>()) {
+        Some(Ok(port)) => port,
+        Some(Err(_)) => {
+            eprintln!("Usage: minesweeper host [port] [beginner|intermediate|expert]");
+            std::process::exit(2);
+        }
+        None => net::DEFAULT_PORT,
+    };
+    let (width, height, mines) = match board.as_deref() {
+        Some("beginner") => (9, 9, 10),
+        Some("intermediate") | None => (16, 16, 40),
+        Some("expert") => (30, 16, 99),
+        Some(_) => {
+            eprintln!("Usage: minesweeper host [port] [beginner|intermediate|expert]");
+            std::process::exit(2);
+        }
+    };
+    MinesweeperGame::host_race(port, width, height, mines)
+}
+
+///
+/// Plays a hot-seat race, with players taking turns on the same board. Run with
+/// `minesweeper hotseat [beginner|intermediate|expert|<seed code>]`
+///
+fn hotseat(board: Option<String>) -> Result<(), std::io::Error> {
+    let (width, height, mines, seed) = match board.as_deref() {
+        Some("beginner") => (9, 9, 10, None),
+        Some("intermediate") | None => (16, 16, 40, None),
+        Some("expert") => (30, 16, 99, None),
+        Some(text) => match SeedCode::parse(text) {
+            Some(code) => (code.width, code.height, code.mines, Some(code.seed)),
+            None => {
+                eprintln!("Usage: minesweeper hotseat [beginner|intermediate|expert|<seed code>]");
+                std::process::exit(2);
+            }
+        },
+    };
+    MinesweeperGame::run_hotseat(width, height, mines, seed)
+}
+
+///
+/// Opens a lost game's snapshot in the review. Run with `minesweeper review <snapshot.json>`
+///
+#[cfg(feature = "solver")]
+fn review_snapshot(path: Option<String>) -> Result<(), std::io::Error> {
+    let path = match path {
+        Some(p) => p,
+        None => {
+            eprintln!("Usage: minesweeper review <snapshot.json>");
+            std::process::exit(2);
+        }
+    };
+    let snapshot = match fs::read_to_string(&path).map_err(|e| e.to_string()).and_then(|s| serde_json::from_str::<Snapshot>(&s).map_err(|e| e.to_string())) {
+        Ok(s) => s,
+        Err(e) => {
+            eprintln!("Couldn't read snapshot {}: {}", path, e);
+            std::process::exit(2);
+        }
+    };
+    // A snapshot that doesn't match its own moves is worth a bug report more than a review
+    if let Err(e) = snapshot.check() {
+        eprintln!("Snapshot {} doesn't add up: {}", path, e);
+        std::process::exit(1);
+    }
+    if let Err(e) = MinesweeperGame::run_snapshot(&snapshot) {
+        eprintln!("Couldn't review {}: {}", path, e);
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+#[cfg(not(feature = "solver"))]
+fn review_snapshot(_path: Option<String>) -> Result<(), std::io::Error> {
+    eprintln!("This build can't review games. Rebuild without `--no-default-features` to get the solver back.");
+    std::process::exit(1);
+}
+
+///
+/// Prints every move of a replay or snapshot with what the solver could prove before it and why. Returns the exit code.
+/// Run with `minesweeper analyze <replay>`
+///
+#[cfg(feature = "solver")]
+fn analyze_replay(path: Option<String>) -> i32 {
+    let path = match path {
+        Some(p) => p,
+        None => {
+            eprintln!("Usage: minesweeper analyze <replay>");
+            return 2;
+        }
+    };
+    let replay = match read_replay(&path) {
+        Ok(r) => r,
+        Err(e) => {
+            eprintln!("Couldn't read replay {}: {}", path, e);
+            return 2;
+        }
+    };
+    match minesweeper::review::Review::new(&replay) {
+        Ok(review) => {
+            print!("{}", review.analysis());
+            0
+        }
+        Err(e) => {
+            eprintln!("Couldn't analyze {}: {}", path, e);
+            1
+        }
+    }
+}
+
+#[cfg(not(feature = "solver"))]
+fn analyze_replay(_path: Option<String>) -> i32 {
+    eprintln!("This build can't analyze games. Rebuild without `--no-default-features` to get the solver back.");
+    1
+}
+
+///
+/// Plays on from a position in a replay or snapshot, over and over. Run with `minesweeper practice <replay> [move]`,
+/// where the position is the one after that many moves. It defaults to just before the last move of a finished game,
+/// which for a lost one is the move that lost it
+///
+fn practice(path: Option<String>, moves: Option<String>) -> Result<(), std::io::Error> {
+    let usage = "Usage: minesweeper practice <replay> [move]";
+    let path = match path {
+        Some(p) => p,
+        None => {
+            eprintln!("{}", usage);
+            std::process::exit(2);
+        }
+    };
+    let replay = match read_replay(&path) {
+        Ok(r) => r,
+        Err(e) => {
+            eprintln!("Couldn't read replay {}: {}", path, e);
+            std::process::exit(2);
+        }
+    };
+    let finished = replay.result == GameState::Win || replay.result == GameState::Loss;
+    let moves = match moves.map(|m| m.parse::<usize>()) {
+        Some(Ok(m)) if m <= replay.actions.len() => m,
+        Some(_) => {
+            eprintln!("{} (the replay has {} moves)", usage, replay.actions.len());
+            std::process::exit(2);
+        }
+        None if finished => replay.actions.len().saturating_sub(1),
+        None => replay.actions.len(),
+    };
+    if let Err(e) = MinesweeperGame::run_practice(&replay, moves) {
+        eprintln!("Couldn't practice from move {} of {}: {}", moves, path, e);
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+///
+/// Runs a server for many games at once. Run with `minesweeper serve [--port N] [--leaderboard path]`;
+/// the leaderboard defaults to `leaderboard.json` next to the save. Returns the exit code
+///
+fn serve(args: &[String]) -> i32 {
+    let usage = "Usage: minesweeper serve [--port N] [--leaderboard path]";
+    let mut port = net::DEFAULT_PORT;
+    let mut leaderboard = std::env::current_exe().unwrap().parent().unwrap().join("leaderboard.json");
+    let mut i = 0;
+    while i < args.len() {
+        match (args[i].as_str(), args.get(i + 1)) {
+            ("--port", Some(value)) => match value.parse::<u16>() {
+                Ok(p) => port = p,
+                Err(_) => {
+                    eprintln!("{}", usage);
+                    return 2;
+                }
+            },
+            ("--leaderboard", Some(value)) => leaderboard = value.into(),
+            _ => {
+                eprintln!("{}", usage);
+                return 2;
+            }
+        }
+        i += 2;
+    }
+    match server::run(port, leaderboard) {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("Couldn't serve on port {}: {}", port, e);
+            1
+        }
+    }
 }
 
 fn main() -> Result<(), std::io::Error> {
+    // `--profile` and `--log-file <path>` can go anywhere, so they're taken out before the subcommands look at the arguments
+    let mut args: Vec<String> = std::env::args().filter(|a| a != "--profile").collect();
+    let profiling = std::env::args().any(|a| a == "--profile");
+    let log_file = match args.iter().position(|a| a == "--log-file") {
+        Some(i) if i + 1 < args.len() => {
+            args.remove(i);
+            Some(args.remove(i))
+        }
+        Some(_) => {
+            eprintln!("Usage: minesweeper [...] --log-file <path>");
+            std::process::exit(2);
+        }
+        None => None,
+    };
+    let mut layers: Vec<Box<dyn Layer<Registry> + Send + Sync>> = vec![];
+    match log::LogLayer::new(log_file.as_deref()) {
+        Ok(layer) => layers.push(Box::new(layer)),
+        Err(e) => {
+            eprintln!("Couldn't open the log file {}: {}", log_file.unwrap_or_default(), e);
+            std::process::exit(1);
+        }
+    }
+    #[cfg(feature = "profile")]
+    let _profile = match profiling {
+        true => match profile::start() {
+            Some((layer, profile)) => {
+                layers.push(layer);
+                Some(profile)
+            }
+            None => {
+                eprintln!("Couldn't start the profile");
+                std::process::exit(1);
+            }
+        },
+        false => None,
+    };
+    #[cfg(not(feature = "profile"))]
+    if profiling {
+        eprintln!("This build can't profile. Rebuild with `cargo build --features profile`.");
+        std::process::exit(1);
+    }
+    tracing::subscriber::set_global_default(tracing_subscriber::registry().with(layers)).ok();
+    // The game needs a real terminal on both ends; subcommands like `bench` don't
+    let interactive = std::io::stdin().is_terminal() && std::io::stdout().is_terminal();
     // Terminal setup
-    execute!(std::io::stdout(), SetCursorStyle::SteadyBlock).ok();
-    execute!(std::io::stdout(), Hide).ok();
-    // Check for save file and make sure it exists
+    if interactive {
+        term::init();
+        if term::supports_cursor_style() {
+            execute!(std::io::stdout(), SetCursorStyle::SteadyBlock).ok();
+        }
+        execute!(std::io::stdout(), Hide).ok();
+    }
+    // Check for save file and make sure it exists. The very first launch asks a few questions to fill it in,
+    // if there's a terminal to ask them in
     let save_path = std::env::current_exe().unwrap().parent().unwrap().to_str().unwrap().to_owned();
-    if !fs::exists(format!("{}\\save.json", save_path)).unwrap() {
-        match fs::write(format!("{}\\save.json", save_path), 
-        "{\"g_played\": 0, \"g_won\": 0, \"total_playtime\": 0, \"total_clicks\": 0,
-        \"inner_fg\": \"37\", \"inner_bg\": \"100\", \"border_fg\": \"37\", \"border_bg\": \"40\",
-        \"inner_highlight\": \"97\", \"flag_char\": \"󰈿\", \"mine_char\": \"󰷚\", \"tile_char\": \"󰆢\",
-        \"m_count_fg\": [\"34\", \"32\", \"31\", \"35\", \"33\", \"36\", \"37\", \"30\"],
-        \"gamemode\": 0}"
-    )   {
-            Ok(_) => {}
-            Err(e) => {
-                print!("Error creating save file: {}\r\n", e);
-                return Ok(())
+    let save_file = format!("{}\\save.json", save_path);
+    if !fs::exists(&save_file).unwrap_or(false) {
+        let contents = if interactive {
+            serde_json::to_string(&onboarding::run()?).unwrap_or_else(|_| String::from(onboarding::DEFAULT_SAVE))
+        } else {
+            String::from(onboarding::DEFAULT_SAVE)
+        };
+        if let Err(e) = fs::write(&save_file, contents) {
+            // Without a save there's nothing to play with, so say where it should have gone and put the terminal back
+            if interactive {
+                execute!(std::io::stdout(), Show).ok();
+            }
+            eprintln!("Couldn't create the save file {}: {}", save_file, e);
+            eprintln!("The game keeps its save next to the binary, so it needs to be somewhere it can write to.");
+            std::process::exit(1);
+        }
+    }
+    // `--capability-report` also goes anywhere, and takes the place of whatever else was asked for
+    if args.iter().any(|a| a == "--capability-report") {
+        capabilities::report(&Save::read_save());
+        if interactive {
+            execute!(std::io::stdout(), Show).ok();
+        }
+        return Ok(());
+    }
+    // Subcommands
+    if args.get(1).map(String::as_str) == Some("bench") {
+        bench::run();
+        if interactive {
+            execute!(std::io::stdout(), Show).ok();
+        }
+        return Ok(());
+    }
+    if args.get(1).map(String::as_str) == Some("verify") {
+        std::process::exit(verify_replay(args.get(2).cloned()));
+    }
+    if args.get(1).map(String::as_str) == Some("analyze") {
+        std::process::exit(analyze_replay(args.get(2).cloned()));
+    }
+    if args.get(1).map(String::as_str) == Some("relay") {
+        let port = match args.get(2).map(|p| p.parse::<u16>()) {
+            Some(Ok(port)) => port,
+            Some(Err(_)) => {
+                eprintln!("Usage: minesweeper relay [port]");
+                std::process::exit(2);
             }
+            None => net::DEFAULT_RELAY_PORT,
+        };
+        if let Err(e) = relay::run(port) {
+            eprintln!("Couldn't run a relay on port {}: {}", port, e);
+            std::process::exit(1);
         }
+        return Ok(());
+    }
+    if args.get(1).map(String::as_str) == Some("cast") {
+        std::process::exit(export_cast(&args[2..]));
+    }
+    if args.get(1).map(String::as_str) == Some("gif") {
+        std::process::exit(export_gif(&args[2..]));
+    }
+    if args.get(1).map(String::as_str) == Some("export") {
+        std::process::exit(export_mbf(&args[2..]));
+    }
+    if args.get(1).map(String::as_str) == Some("theme") {
+        std::process::exit(pick_theme(args.get(2).cloned()));
+    }
+    if args.get(1).map(String::as_str) == Some("migrate") {
+        std::process::exit(migrate_settings(args.get(2).cloned()));
+    }
+    if args.get(1).map(String::as_str) == Some("serve") {
+        std::process::exit(serve(&args[2..]));
+    }
+    if !interactive {
+        eprintln!("minesweeper needs an interactive terminal to play; stdin or stdout is not a TTY.");
+        eprintln!("Run it directly in a terminal, or use `minesweeper bench` / `minesweeper verify <replay>` / `minesweeper analyze <replay>` / `minesweeper relay [port]` / `minesweeper serve` / `minesweeper cast <replay>` / `minesweeper gif <replay>` / `minesweeper export <replay>` / `minesweeper theme` / `minesweeper migrate <settings.ini>` for a non-interactive run.");
+        std::process::exit(1);
+    }
+    // From here on the game is played, so its writes go to a thread of their own, and are finished before it quits
+    let _writer = saves::Writer::start();
+    if args.get(1).map(String::as_str) == Some("host") {
+        host_race(args.get(2).cloned(), args.get(3).cloned())?;
+        execute!(std::io::stdout(), Show).ok();
+        term::clear();
+        return Ok(());
     }
+    if args.get(1).map(String::as_str) == Some("hotseat") {
+        hotseat(args.get(2).cloned())?;
+        execute!(std::io::stdout(), Show).ok();
+        term::clear();
+        return Ok(());
+    }
+    if args.get(1).map(String::as_str) == Some("review") {
+        review_snapshot(args.get(2).cloned())?;
+        execute!(std::io::stdout(), Show).ok();
+        term::clear();
+        return Ok(());
+    }
+    if args.get(1).map(String::as_str) == Some("practice") {
+        practice(args.get(2).cloned(), args.get(3).cloned())?;
+        execute!(std::io::stdout(), Show).ok();
+        term::clear();
+        return Ok(());
+    }
+    if args.get(1).map(String::as_str) == Some("join") {
+        match args.get(2) {
+            Some(target) => {
+                MinesweeperGame::join_race_at(target, args.get(3).cloned())?;
+            }
+            None => {
+                eprintln!("Usage: minesweeper join <code | host[:port]> [room]");
+                std::process::exit(2);
+            }
+        }
+        execute!(std::io::stdout(), Show).ok();
+        term::clear();
+        return Ok(());
+    }
+    if args.get(1).map(String::as_str) == Some("import") {
+        let path = match args.get(2).cloned() {
+            Some(path) => path,
+            None => {
+                eprintln!("Usage: minesweeper import <board.mbf>");
+                std::process::exit(2);
+            }
+        };
+        let layout = match fs::read(&path).map_err(|e| e.to_string()).and_then(|bytes| Layout::from_mbf(&bytes)) {
+            Ok(layout) => layout,
+            Err(e) => {
+                eprintln!("Couldn't read board {}: {}", path, e);
+                std::process::exit(2);
+            }
+        };
+        MinesweeperGame::run_layout(&layout)?;
+        execute!(std::io::stdout(), Show).ok();
+        term::clear();
+        return Ok(());
+    }
+    #[cfg(feature = "update-check")]
+    update::start(&Save::read_save());
     // Show start text and begin input loop
     do_splash_text();
+    #[cfg(feature = "update-check")]
+    let mut update_shown = update::notice().is_some();
     loop {
-        if event::poll(Duration::from_millis(500))? {
-            match event::read().unwrap() {
-                Event::Key(key_event) => {
-                    if key_event.kind == KeyEventKind::Press {
-                        match key_event.code {
-                            KeyCode::Char('1') => {
-                                MinesweeperGame::run_game(9, 9, 10)?;
-                            }
-                            KeyCode::Char('2') => {
-                                MinesweeperGame::run_game(16, 16, 40)?;
-                            }
-                            KeyCode::Char('3') => {
-                                MinesweeperGame::run_game(30, 16, 99)?;
-                            }
-                            KeyCode::Char('4') => {
-                                execute!(std::io::stdout(), Show).ok();
-                                // Get user input
-                                let mut width: String = String::new();
-                                let mut height: String = String::new();
-                                let mut mines: String = String::new();
-                                print!("\r\n> Width: "); std::io::stdout().flush()?;
-                                std::io::stdin().read_line(&mut width)?;
-                                print!("> Height: "); std::io::stdout().flush()?;
-                                std::io::stdin().read_line(&mut height)?;
-                                print!("> Mines: "); std::io::stdout().flush()?;
-                                std::io::stdin().read_line(&mut mines)?;
-                                // Check if it is valid
-                                let width_n = width.trim().parse::<i16>();
-                                let height_n = height.trim().parse::<i16>();
-                                let mines_n = mines.trim().parse::<i16>();
-                                if width_n.is_err() || height_n.is_err() || mines_n.is_err() {
-                                    print!("\r\nX Error while reading input\r\n");
-                                    print!("{:?}\r\n{:?}\r\n{:?}\r\n\r\n", width_n, height_n, mines_n);
-                                    continue;
-                                }
-                                if width_n.clone().unwrap() < 0 || height_n.clone().unwrap() < 0 || mines_n.clone().unwrap() < 0 {
-                                    print!("\r\nX Please enter valid positive numbers\r\n");
-                                    continue;
-                                }
-                                // Check (by numerical constraints) if it is valid
-                                let space_n = width_n.clone().unwrap() * height_n.clone().unwrap();
-                                if mines_n.clone().unwrap() >= space_n - 1 {
-                                    print!("\r\nX Too many mines for the given space count ({} mines in {} spaces)\r\n", mines_n.clone().unwrap(), space_n);
-                                    continue;
-                                }
-
-                                // If valid, run the game
-                                MinesweeperGame::run_game(width_n.unwrap(), height_n.unwrap(), mines_n.unwrap())?;
-                            }
-                            KeyCode::Char('5') => {
-                                break;
-                            }
-                            _ => {}
-                        }
-                        do_splash_text();
+        // The update check finishes in the background, and the menu is drawn again when it has something to say
+        #[cfg(feature = "update-check")]
+        if !update_shown && update::notice().is_some() {
+            update_shown = true;
+            do_splash_text();
+        }
+        if event::poll(Duration::from_millis(500))? && let Event::Key(key_event) = event::read().unwrap() && key_event.kind == KeyEventKind::Press {
+            match key_event.code {
+                KeyCode::Char('1') => {
+                    MinesweeperGame::run_game(9, 9, 10, Category::Beginner, None)?;
+                }
+                KeyCode::Char('2') => {
+                    MinesweeperGame::run_game(16, 16, 40, Category::Intermediate, None)?;
+                }
+                KeyCode::Char('3') => {
+                    MinesweeperGame::run_game(30, 16, 99, Category::Expert, None)?;
+                }
+                KeyCode::Char('4') => {
+                    // If valid, run the game
+                    if let Some(game) = custom::custom_game_form()? {
+                        MinesweeperGame::run_game(game.width, game.height, game.mines, Category::Custom, game.seed)?;
                     }
                 }
+                KeyCode::Char('5') => {
+                    MinesweeperGame::run_surprise()?;
+                }
+                KeyCode::Char('6') => {
+                    scores::show(&Save::read_save())?;
+                }
+                KeyCode::Char('7') => {
+                    break;
+                }
+                KeyCode::Char('0') => {
+                    MinesweeperGame::run_last()?;
+                }
                 _ => {}
             }
+            do_splash_text();
         }
     }
-    execute!(std::io::stdout(), MoveTo(0,0)).ok();
     execute!(std::io::stdout(), Show).ok();
-    print!("{}[2J", 27 as char);
+    term::clear();
     Ok(())
 }
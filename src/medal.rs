@@ -0,0 +1,86 @@
+//! Par time medals: bronze, silver, and gold times for the preset boards, so a win can be measured against
+//! something other than the player's own best.
+
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use std::time::Duration;
+
+///
+/// A medal for winning under one of a board's par times
+///
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum Medal {
+    Bronze,
+    Silver,
+    Gold,
+}
+
+impl Display for Medal {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
+        match *self {
+            Medal::Bronze => {
+                write!(f, "bronze")
+            }
+            Medal::Silver => {
+                write!(f, "silver")
+            }
+            Medal::Gold => {
+                write!(f, "gold")
+            }
+        }
+    }
+}
+
+///
+/// The times, in seconds, to win under for each medal, from bronze to gold
+///
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub struct ParTimes {
+    pub bronze: u64,
+    pub silver: u64,
+    pub gold: u64,
+}
+
+impl ParTimes {
+    ///
+    /// Par times for a preset board, by its category name. Other boards don't have any
+    ///
+    pub fn for_category(category: &str) -> Option<ParTimes> {
+        match category {
+            "beginner" => Some(ParTimes { bronze: 40, silver: 20, gold: 10 }),
+            "intermediate" => Some(ParTimes { bronze: 150, silver: 90, gold: 50 }),
+            "expert" => Some(ParTimes { bronze: 400, silver: 250, gold: 150 }),
+            _ => None,
+        }
+    }
+    ///
+    /// The best medal a win in `time` earns, if any
+    ///
+    pub fn medal(&self, time: Duration) -> Option<Medal> {
+        let time = time.as_secs_f64();
+        [(Medal::Gold, self.gold), (Medal::Silver, self.silver), (Medal::Bronze, self.bronze)].into_iter()
+            .find(|(_, par)| time < *par as f64)
+            .map(|(medal, _)| medal)
+    }
+    ///
+    /// The par time for a medal
+    ///
+    pub fn time(&self, medal: Medal) -> u64 {
+        match medal {
+            Medal::Bronze => self.bronze,
+            Medal::Silver => self.silver,
+            Medal::Gold => self.gold,
+        }
+    }
+    ///
+    /// The next medal up from `medal` (or bronze, for a win without one), unless it's already gold
+    ///
+    pub fn next(medal: Option<Medal>) -> Option<Medal> {
+        match medal {
+            None => Some(Medal::Bronze),
+            Some(Medal::Bronze) => Some(Medal::Silver),
+            Some(Medal::Silver) => Some(Medal::Gold),
+            Some(Medal::Gold) => None,
+        }
+    }
+}
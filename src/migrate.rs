@@ -0,0 +1,166 @@
+//! Settings brought over from other minesweeper clients. Minesweeper X, Arbiter, and clones like them keep their
+//! options in ini files; the ones that mean something here (keys, colors, and which stats to show after a win)
+//! are picked out and translated, and everything else is listed as skipped.
+
+///
+/// What could be made of another client's settings
+///
+#[derive(PartialEq, Clone, Debug, Default)]
+pub struct Imported {
+    pub keys: Vec<(String, String)>,   // Action ("check", "flag", "chord", "up", ...) and the name of the key for it
+    pub colors: Vec<(String, String)>, // Save field ("border_fg", "m_count_fg3", ...) and the SGR code for it
+    pub metrics: Vec<String>,          // Stats to show after a win: "3bv", "3bv/s", "clicks", or "efficiency"
+    pub skipped: Vec<String>,          // Entries that don't mean anything here, as "[section] key"
+}
+
+///
+/// Reads an ini file's entries in order as (section, key, value), with sections and keys lowercased.
+/// Comments start with `;` or `#`, and lines that aren't entries are left out
+///
+pub fn parse_ini(text: &str) -> Vec<(String, String, String)> {
+    let mut entries: Vec<(String, String, String)> = vec![];
+    let mut section = String::new();
+    for line in text.lines() {
+        let line = line.trim().trim_start_matches('\u{feff}');
+        if line.is_empty() || line.starts_with(';') || line.starts_with('#') {
+            continue;
+        }
+        if line.starts_with('[') && line.ends_with(']') {
+            section = line[1..line.len() - 1].trim().to_lowercase();
+            continue;
+        }
+        if let Some((key, value)) = line.split_once('=') {
+            let value = value.trim().trim_matches('"');
+            entries.push((section.clone(), key.trim().to_lowercase(), value.to_string()));
+        }
+    }
+    entries
+}
+
+///
+/// Translates another client's ini settings. Keys are matched by name whatever section they're in,
+/// since every client files them differently
+///
+pub fn import_ini(text: &str) -> Imported {
+    let mut imported = Imported::default();
+    for (section, key, value) in parse_ini(text) {
+        let understood = match key.as_str() {
+            "open" | "reveal" | "check" | "leftclick" => bind(&mut imported, "check", &value),
+            "flag" | "mark" | "rightclick" => bind(&mut imported, "flag", &value),
+            "chord" | "openaround" | "middleclick" | "bothclick" => bind(&mut imported, "chord", &value),
+            "up" | "moveup" => bind(&mut imported, "up", &value),
+            "down" | "movedown" => bind(&mut imported, "down", &value),
+            "left" | "moveleft" => bind(&mut imported, "left", &value),
+            "right" | "moveright" => bind(&mut imported, "right", &value),
+            "show3bv" => show(&mut imported, "3bv", &value),
+            "show3bvs" | "show3bv/s" => show(&mut imported, "3bv/s", &value),
+            "showclicks" | "showclickcount" => show(&mut imported, "clicks", &value),
+            "showioe" | "showefficiency" => show(&mut imported, "efficiency", &value),
+            "bordercolor" | "framecolor" => paint(&mut imported, "border_fg", &value, false),
+            "backgroundcolor" | "background" | "coveredcolor" => paint(&mut imported, "inner_bg", &value, true),
+            "flagcolor" => paint(&mut imported, "inner_highlight", &value, false),
+            _ => match key.strip_prefix("color").and_then(|n| n.parse::<u8>().ok()) {
+                // Number colors, usually Color1 to Color8
+                Some(n @ 1..=8) => paint(&mut imported, &format!("m_count_fg{}", n), &value, false),
+                _ => false,
+            },
+        };
+        if !understood {
+            imported.skipped.push(format!("[{}] {}", section, key));
+        }
+    }
+    imported
+}
+
+fn bind(imported: &mut Imported, action: &str, value: &str) -> bool {
+    match key_name(value) {
+        Some(name) => {
+            imported.keys.retain(|(a, _)| a != action);
+            imported.keys.push((action.to_string(), name));
+            true
+        }
+        None => false,
+    }
+}
+
+fn show(imported: &mut Imported, metric: &str, value: &str) -> bool {
+    let on = match value.to_lowercase().as_str() {
+        "1" | "true" | "yes" | "on" => true,
+        "0" | "false" | "no" | "off" => false,
+        _ => return false,
+    };
+    imported.metrics.retain(|m| m != metric);
+    if on {
+        imported.metrics.push(metric.to_string());
+    }
+    true
+}
+
+fn paint(imported: &mut Imported, field: &str, value: &str, background: bool) -> bool {
+    match color_rgb(value) {
+        Some([r, g, b]) => {
+            let code = format!("{};2;{};{};{}", if background { 48 } else { 38 }, r, g, b);
+            imported.colors.retain(|(f, _)| f != field);
+            imported.colors.push((field.to_string(), code));
+            true
+        }
+        None => false,
+    }
+}
+
+///
+/// The name the save uses for a key, from how another client wrote it: a single character, a name like "Space",
+/// or a Windows virtual key code like 32
+///
+pub fn key_name(value: &str) -> Option<String> {
+    let lower = value.trim().to_lowercase();
+    if let Ok(code) = lower.parse::<u32>() {
+        return match code {
+            0..=9 => Some(lower),
+            13 => Some(String::from("enter")),
+            32 => Some(String::from("space")),
+            37 => Some(String::from("left")),
+            38 => Some(String::from("up")),
+            39 => Some(String::from("right")),
+            40 => Some(String::from("down")),
+            48..=57 | 65..=90 => char::from_u32(code).map(|c| c.to_ascii_lowercase().to_string()),
+            _ => None,
+        };
+    }
+    match lower.as_str() {
+        "space" | "spacebar" | " " => Some(String::from("space")),
+        "enter" | "return" => Some(String::from("enter")),
+        "up" | "down" | "left" | "right" | "tab" => Some(lower),
+        _ if lower.chars().count() == 1 && !lower.starts_with(char::is_whitespace) => Some(lower),
+        _ => None,
+    }
+}
+
+///
+/// A color as red, green, and blue, from "#RRGGBB", "R,G,B", or a Windows COLORREF number (0x00BBGGRR, in decimal or hex)
+///
+pub fn color_rgb(value: &str) -> Option<[u8; 3]> {
+    let value = value.trim();
+    if let Some(hex) = value.strip_prefix('#') {
+        if hex.len() != 6 {
+            return None;
+        }
+        let n = u32::from_str_radix(hex, 16).ok()?;
+        return Some([(n >> 16) as u8, (n >> 8) as u8, n as u8]);
+    }
+    if value.contains(',') {
+        let parts: Vec<u8> = value.split(',').map(|p| p.trim().parse::<u8>()).collect::<Result<_, _>>().ok()?;
+        return match parts[..] {
+            [r, g, b] => Some([r, g, b]),
+            _ => None,
+        };
+    }
+    let n = match value.strip_prefix("0x").or(value.strip_prefix("0X")) {
+        Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+        None => value.parse::<u32>().ok()?,
+    };
+    if n > 0xFF_FFFF {
+        return None;
+    }
+    Some([n as u8, (n >> 8) as u8, (n >> 16) as u8])
+}
@@ -0,0 +1,663 @@
+use minesweeper::protocol::{self, Message, PROTOCOL_VERSION};
+
+use std::fmt::{Display, Formatter};
+use std::io::ErrorKind;
+use std::net::{IpAddr, Ipv4Addr, Shutdown, SocketAddr, TcpListener, TcpStream, ToSocketAddrs, UdpSocket};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender, TryRecvError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// Port used when none is given
+pub(crate) const DEFAULT_PORT: u16 = 7878;
+// Port a relay listens on when none is given
+pub(crate) const DEFAULT_RELAY_PORT: u16 = 7879;
+// How often a heartbeat goes out when nothing else has
+pub(crate) const HEARTBEAT: Duration = Duration::from_secs(1);
+// How long without hearing anything before the connection counts as dropped
+pub(crate) const TIMEOUT: Duration = Duration::from_secs(5);
+// How long a fresh connection gets to say hello
+pub(crate) const HANDSHAKE: Duration = Duration::from_secs(3);
+// How long a joining player keeps trying to get (back) in before giving up
+pub(crate) const RECONNECT_WINDOW: Duration = Duration::from_secs(60);
+// Time between connection attempts
+const RETRY: Duration = Duration::from_secs(1);
+// How often the connection thread checks for things to do while nothing is coming in
+pub(crate) const TICK: Duration = Duration::from_millis(50);
+// Characters join codes are made of. 0, O, 1, and I are left out so codes can be read out loud without mixups
+const CODE_ALPHABET: &[u8; 32] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+// Length of the codes a relay hands out
+const LOBBY_CODE_LENGTH: usize = 6;
+// Length of a code for hosting directly: an IPv4 address and port (48 bits) at 5 bits a character
+const DIRECT_CODE_LENGTH: usize = 10;
+
+///
+/// What a player asked to join: a host they can reach themselves, or a lobby code to look up on the relay
+///
+#[derive(PartialEq, Clone, Debug)]
+pub(crate) enum JoinTarget {
+    Address(String), // "host:port"
+    Lobby(String),
+}
+
+///
+/// Works out what `text` from the command line is: a direct join code, a lobby code (only if there's a `relay` to look it up on),
+/// or an address, which gets the default port if it doesn't have one. Codes aren't case sensitive
+///
+pub(crate) fn parse_join_target(text: &str, relay: &str) -> JoinTarget {
+    let code = text.trim().to_uppercase();
+    if is_code(&code) {
+        if code.len() == DIRECT_CODE_LENGTH {
+            let mut bits: u64 = 0;
+            for c in code.bytes() {
+                bits = (bits << 5) | CODE_ALPHABET.iter().position(|&a| a == c).unwrap() as u64;
+            }
+            let ip = Ipv4Addr::from(((bits >> 16) & 0xFFFF_FFFF) as u32);
+            return JoinTarget::Address(format!("{}:{}", ip, bits & 0xFFFF));
+        }
+        if code.len() == LOBBY_CODE_LENGTH && !relay.is_empty() {
+            return JoinTarget::Lobby(code);
+        }
+    }
+    if text.contains(':') {
+        return JoinTarget::Address(text.to_owned());
+    }
+    JoinTarget::Address(format!("{}:{}", text, DEFAULT_PORT))
+}
+
+///
+/// Whether or not `code` is made only of join code characters
+///
+fn is_code(code: &str) -> bool {
+    !code.is_empty() && code.bytes().all(|c| CODE_ALPHABET.contains(&c))
+}
+
+///
+/// Whether or not `code` could be one a relay handed out
+///
+pub(crate) fn is_lobby_code(code: &str) -> bool {
+    code.len() == LOBBY_CODE_LENGTH && is_code(code)
+}
+
+///
+/// A fresh random lobby code
+///
+pub(crate) fn new_lobby_code() -> String {
+    let mut code = String::new();
+    for _ in 0..LOBBY_CODE_LENGTH {
+        code.push(CODE_ALPHABET[rand::random_range(0..CODE_ALPHABET.len())] as char);
+    }
+    code
+}
+
+///
+/// The code for joining a game hosted directly on `port` of this machine, or `None` if it has no network address.
+/// It only saves typing the address; the other player still has to be able to reach this machine
+///
+pub(crate) fn direct_code(port: u16) -> Option<String> {
+    // Connecting a UDP socket sends nothing, but picks the address other machines would see this one at
+    let socket = UdpSocket::bind(("0.0.0.0", 0)).ok()?;
+    socket.connect(("192.0.2.1", 9)).ok()?;
+    let ip = match socket.local_addr().ok()?.ip() {
+        IpAddr::V4(ip) if !ip.is_unspecified() && !ip.is_loopback() => ip,
+        _ => return None,
+    };
+    let bits = ((u32::from(ip) as u64) << 16) | port as u64;
+    let mut code = String::new();
+    for i in (0..DIRECT_CODE_LENGTH).rev() {
+        code.push(CODE_ALPHABET[((bits >> (5 * i)) & 31) as usize] as char);
+    }
+    Some(code)
+}
+
+///
+/// Where a connection to the other player stands
+///
+#[derive(PartialEq, Clone, Debug)]
+pub(crate) enum LinkStatus {
+    Waiting,        // Hosting, and nobody has joined yet
+    Connecting,     // Joining for the first time
+    Connected,
+    Reconnecting,   // Dropped; trying to get back in, or for the host, waiting for the player to come back
+    Closed(String), // Over for good, and why
+}
+
+impl Display for LinkStatus {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
+        match self {
+            &LinkStatus::Waiting => {
+                write!(f, "waiting for a player")
+            }
+            &LinkStatus::Connecting => {
+                write!(f, "connecting")
+            }
+            &LinkStatus::Connected => {
+                write!(f, "connected")
+            }
+            &LinkStatus::Reconnecting => {
+                write!(f, "reconnecting")
+            }
+            LinkStatus::Closed(reason) => {
+                write!(f, "disconnected: {}", reason)
+            }
+        }
+    }
+}
+
+///
+/// Something that happened on a link since it was last polled
+///
+#[derive(PartialEq, Clone, Debug)]
+pub(crate) enum LinkEvent {
+    Status(LinkStatus),
+    Joined { rejoined: bool }, // A connection (re)started, so the other side needs to be caught up on everything
+    Lobby { code: String },    // The relay handed out the code for the other player to join with
+    Message(Message),
+}
+
+///
+/// A connection to the other player, kept up by a background thread. Dropped connections are picked back up
+/// automatically; messages sent while disconnected are dropped, which is why `Joined` says when to send everything again
+///
+pub(crate) struct Link {
+    outgoing: Sender<Message>,
+    incoming: Receiver<LinkEvent>,
+    status: LinkStatus,
+    code: Option<String>, // Code the other player can join with, when hosting
+    put_back: Vec<LinkEvent>, // Events handed back to be polled again
+}
+
+///
+/// The connection thread's side of a link
+///
+struct Ends {
+    outgoing: Receiver<Message>,
+    incoming: Sender<LinkEvent>,
+}
+
+///
+/// What a joining player tells the host about themselves besides the session
+///
+struct Greeting {
+    room: Option<String>,
+    name: Option<String>,
+}
+
+///
+/// Why a connection stopped
+///
+enum Ended {
+    Dropped, // Lost without a goodbye, so it might come back
+    Bye,     // The other side left for good
+    Quit,    // This side's game is gone
+}
+
+impl Link {
+    ///
+    /// Starts listening for a player on `port`
+    ///
+    pub(crate) fn host(port: u16) -> Result<Link, std::io::Error> {
+        let listener = TcpListener::bind(("0.0.0.0", port))?;
+        listener.set_nonblocking(true)?;
+        let (mut link, ends) = Link::pair(LinkStatus::Waiting);
+        link.code = direct_code(port);
+        thread::spawn(move || {
+            run_host(ends, |_| match listener.accept() {
+                Ok((stream, _)) => Ok(Some(stream)),
+                Err(_) => {
+                    thread::sleep(TICK);
+                    Ok(None)
+                }
+            })
+        });
+        Ok(link)
+    }
+    ///
+    /// Starts hosting through the relay at `relay`, which hands out a code for the other player to join with.
+    /// Neither player needs to be reachable from outside, since both only connect out to the relay
+    ///
+    pub(crate) fn host_via_relay(relay: String) -> Link {
+        let (link, ends) = Link::pair(LinkStatus::Connecting);
+        thread::spawn(move || {
+            let mut code: Option<String> = None;
+            let give_up_at = Instant::now() + RECONNECT_WINDOW;
+            run_host(ends, |ends| {
+                let waiting = open_lobby(&relay, &mut code, ends);
+                if code.is_none() && Instant::now() > give_up_at {
+                    return Err(format!("couldn't get a code from the relay at {}", relay));
+                }
+                waiting
+            })
+        });
+        link
+    }
+    ///
+    /// Starts joining the player (or server) hosting at `address`. Servers put the player in `room`
+    /// and rank them as `name`
+    ///
+    pub(crate) fn join(address: String, room: Option<String>, name: Option<String>) -> Link {
+        let (link, ends) = Link::pair(LinkStatus::Connecting);
+        thread::spawn(move || run_join(address, None, Greeting { room, name }, ends));
+        link
+    }
+    ///
+    /// Starts joining the player hosting under `code` on the relay at `relay`
+    ///
+    pub(crate) fn join_via_relay(relay: String, code: String, name: Option<String>) -> Link {
+        let (link, ends) = Link::pair(LinkStatus::Connecting);
+        thread::spawn(move || run_join(relay, Some(code), Greeting { room: None, name }, ends));
+        link
+    }
+    ///
+    /// Keeps up a connection that has already said hello, for servers. When it drops the status goes to
+    /// `Reconnecting` (it's up to the player to come back on a new connection), or `Closed` if they said goodbye
+    ///
+    pub(crate) fn accepted(stream: TcpStream) -> Link {
+        let (link, ends) = Link::pair(LinkStatus::Connected);
+        thread::spawn(move || {
+            let status = match pump(stream, &ends) {
+                Ended::Quit => return,
+                Ended::Bye => LinkStatus::Closed(String::from("the player left")),
+                Ended::Dropped => LinkStatus::Reconnecting,
+            };
+            ends.incoming.send(LinkEvent::Status(status)).ok();
+        });
+        link
+    }
+    fn pair(status: LinkStatus) -> (Link, Ends) {
+        let (outgoing_sender, outgoing_receiver) = mpsc::channel();
+        let (incoming_sender, incoming_receiver) = mpsc::channel();
+        let link = Link { outgoing: outgoing_sender, incoming: incoming_receiver, status, code: None, put_back: vec![] };
+        (link, Ends { outgoing: outgoing_receiver, incoming: incoming_sender })
+    }
+    ///
+    /// Sends a message to the other player, if connected
+    ///
+    pub(crate) fn send(&self, message: Message) {
+        self.outgoing.send(message).ok();
+    }
+    ///
+    /// Takes everything that happened since the last poll, keeping `status` up to date
+    ///
+    pub(crate) fn poll(&mut self) -> Vec<LinkEvent> {
+        let mut events: Vec<LinkEvent> = std::mem::take(&mut self.put_back);
+        loop {
+            match self.incoming.try_recv() {
+                Ok(event) => {
+                    match &event {
+                        LinkEvent::Status(status) => {
+                            tracing::info!(status = %status, "race link");
+                            self.status = status.clone();
+                        }
+                        LinkEvent::Lobby { code } => self.code = Some(code.clone()),
+                        _ => {}
+                    }
+                    events.push(event);
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    if !matches!(self.status, LinkStatus::Closed(_)) {
+                        tracing::warn!("race link stopped without saying why");
+                        self.status = LinkStatus::Closed(String::from("connection stopped"));
+                        events.push(LinkEvent::Status(self.status.clone()));
+                    }
+                    break;
+                }
+            }
+        }
+        events
+    }
+    ///
+    /// Hands events back so the next poll returns them first, for when a caller stops partway through a poll
+    ///
+    pub(crate) fn put_back(&mut self, events: Vec<LinkEvent>) {
+        self.put_back = events;
+    }
+    pub(crate) fn status(&self) -> &LinkStatus {
+        &self.status
+    }
+    pub(crate) fn code(&self) -> Option<&str> {
+        self.code.as_deref()
+    }
+}
+
+///
+/// Throws away anything the game sent while nobody was connected. Returns false once the game is gone
+///
+fn drain(ends: &Ends) -> bool {
+    loop {
+        match ends.outgoing.try_recv() {
+            Ok(_) => {}
+            Err(TryRecvError::Empty) => return true,
+            Err(TryRecvError::Disconnected) => return false,
+        }
+    }
+}
+
+///
+/// Hosts one other player: waits for them to join, then keeps letting them back in under the same session
+/// whenever the connection drops. `next_player` hands over the next connection to try, `None` if there isn't one yet,
+/// or why hosting can't go on
+///
+fn run_host<F: FnMut(&Ends) -> Result<Option<TcpStream>, String>>(ends: Ends, mut next_player: F) {
+    let mut session: Option<u64> = None;
+    loop {
+        if !drain(&ends) {
+            return;
+        }
+        let stream = match next_player(&ends) {
+            Ok(Some(stream)) => stream,
+            Ok(None) => continue,
+            Err(reason) => {
+                ends.incoming.send(LinkEvent::Status(LinkStatus::Closed(reason))).ok();
+                return;
+            }
+        };
+        let rejoined = match host_handshake(&stream, &mut session) {
+            Ok(rejoined) => rejoined,
+            Err(_) => continue,
+        };
+        if ends.incoming.send(LinkEvent::Status(LinkStatus::Connected)).is_err()
+            || ends.incoming.send(LinkEvent::Joined { rejoined }).is_err() {
+            return;
+        }
+        match pump(stream, &ends) {
+            Ended::Quit => return,
+            Ended::Bye => {
+                ends.incoming.send(LinkEvent::Status(LinkStatus::Closed(String::from("the other player left")))).ok();
+                return;
+            }
+            Ended::Dropped => {
+                if ends.incoming.send(LinkEvent::Status(LinkStatus::Reconnecting)).is_err() {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+///
+/// Checks a new connection's hello. The first player to join starts the session; after that only that player
+/// (reconnecting with the session) gets in. Returns whether or not it was a reconnect
+///
+fn host_handshake(stream: &TcpStream, session: &mut Option<u64>) -> Result<bool, String> {
+    let mut stream = stream;
+    stream.set_nonblocking(false).map_err(|e| e.to_string())?;
+    stream.set_read_timeout(Some(HANDSHAKE)).map_err(|e| e.to_string())?;
+    stream.set_write_timeout(Some(TIMEOUT)).map_err(|e| e.to_string())?;
+    let (version, asked) = match protocol::read_frame(&mut stream).map_err(|e| e.to_string())? {
+        Message::Hello { version, session, .. } => (version, session),
+        _ => return Err(String::from("expected a hello")),
+    };
+    let rejection = if version != PROTOCOL_VERSION {
+        Some(format!("version mismatch (host has {}, you have {})", PROTOCOL_VERSION, version))
+    } else if session.is_some() && asked != *session {
+        Some(String::from("the game already has two players"))
+    } else {
+        None
+    };
+    if let Some(reason) = rejection {
+        protocol::write_frame(&mut stream, &Message::Rejected { reason: reason.clone() }).ok();
+        return Err(reason);
+    }
+    let rejoined = session.is_some();
+    let id = *session.get_or_insert_with(rand::random);
+    protocol::write_frame(&mut stream, &Message::Welcome { version: PROTOCOL_VERSION, session: id }).map_err(|e| e.to_string())?;
+    stream.set_read_timeout(None).map_err(|e| e.to_string())?;
+    Ok(rejoined)
+}
+
+///
+/// Opens (or after a drop, takes back) a lobby on the relay, then waits there until a player joins it.
+/// Returns the connection through the relay once someone does, or `None` to try again
+///
+fn open_lobby(relay: &str, code: &mut Option<String>, ends: &Ends) -> Result<Option<TcpStream>, String> {
+    let mut stream = match connect(relay) {
+        Ok(stream) => stream,
+        Err(_) => {
+            thread::sleep(RETRY);
+            return Ok(None);
+        }
+    };
+    let opened = stream.set_read_timeout(Some(HANDSHAKE)).is_ok()
+        && stream.set_write_timeout(Some(TIMEOUT)).is_ok()
+        && protocol::write_frame(&mut stream, &Message::OpenLobby { version: PROTOCOL_VERSION, code: code.clone() }).is_ok();
+    if !opened {
+        thread::sleep(RETRY);
+        return Ok(None);
+    }
+    match protocol::read_frame(&mut stream) {
+        Ok(Message::Lobby { code: given }) => {
+            if code.as_ref() != Some(&given) {
+                *code = Some(given.clone());
+                if ends.incoming.send(LinkEvent::Lobby { code: given }).is_err()
+                    || ends.incoming.send(LinkEvent::Status(LinkStatus::Waiting)).is_err() {
+                    return Ok(None);
+                }
+            }
+        }
+        Ok(Message::Rejected { reason }) => return Err(reason),
+        _ => {
+            thread::sleep(RETRY);
+            return Ok(None);
+        }
+    }
+    // The relay sends heartbeats while nobody has joined. Peeking instead of reading means a timeout
+    // can never land halfway through a frame
+    let mut last_heard = Instant::now();
+    loop {
+        if !drain(ends) || last_heard.elapsed() > TIMEOUT || stream.set_read_timeout(Some(TICK)).is_err() {
+            return Ok(None);
+        }
+        match stream.peek(&mut [0u8; 1]) {
+            Ok(0) => return Ok(None),
+            Ok(_) => {
+                if stream.set_read_timeout(Some(HANDSHAKE)).is_err() {
+                    return Ok(None);
+                }
+                match protocol::read_frame(&mut stream) {
+                    Ok(Message::Heartbeat) => last_heard = Instant::now(),
+                    Ok(Message::Paired) => return Ok(Some(stream)),
+                    _ => return Ok(None),
+                }
+            }
+            Err(e) if e.kind() == ErrorKind::WouldBlock || e.kind() == ErrorKind::TimedOut => {}
+            Err(_) => return Ok(None),
+        }
+    }
+}
+
+///
+/// Joins a host, reconnecting under the same session whenever the connection drops,
+/// until it can't get back in for `RECONNECT_WINDOW`. With a `code`, `address` is a relay to find the host through
+///
+fn run_join(address: String, code: Option<String>, greeting: Greeting, ends: Ends) {
+    let mut session: Option<u64> = None;
+    let mut give_up_at = Instant::now() + RECONNECT_WINDOW;
+    loop {
+        if !drain(&ends) {
+            return;
+        }
+        if Instant::now() > give_up_at {
+            ends.incoming.send(LinkEvent::Status(LinkStatus::Closed(format!("couldn't reach {}", address)))).ok();
+            return;
+        }
+        let stream = match connect(&address) {
+            Ok(stream) => stream,
+            Err(_) => {
+                thread::sleep(RETRY);
+                continue;
+            }
+        };
+        if let Some(code) = &code {
+            match join_lobby(&stream, code) {
+                Ok(()) => {}
+                // A relay that restarted doesn't know the code until the host takes it back, so only a first join gives up
+                Err(Some(reason)) if session.is_none() => {
+                    ends.incoming.send(LinkEvent::Status(LinkStatus::Closed(reason))).ok();
+                    return;
+                }
+                Err(_) => {
+                    thread::sleep(RETRY);
+                    continue;
+                }
+            }
+        }
+        let id = match join_handshake(&stream, session, &greeting) {
+            Ok(id) => id,
+            Err(Some(reason)) => {
+                ends.incoming.send(LinkEvent::Status(LinkStatus::Closed(reason))).ok();
+                return;
+            }
+            Err(None) => {
+                thread::sleep(RETRY);
+                continue;
+            }
+        };
+        let rejoined = session.is_some();
+        session = Some(id);
+        if ends.incoming.send(LinkEvent::Status(LinkStatus::Connected)).is_err()
+            || ends.incoming.send(LinkEvent::Joined { rejoined }).is_err() {
+            return;
+        }
+        match pump(stream, &ends) {
+            Ended::Quit => return,
+            Ended::Bye => {
+                ends.incoming.send(LinkEvent::Status(LinkStatus::Closed(String::from("the host left")))).ok();
+                return;
+            }
+            Ended::Dropped => {
+                if ends.incoming.send(LinkEvent::Status(LinkStatus::Reconnecting)).is_err() {
+                    return;
+                }
+                give_up_at = Instant::now() + RECONNECT_WINDOW;
+            }
+        }
+    }
+}
+
+///
+/// Opens a connection to `address` ("host:port"), trying each address it resolves to
+///
+fn connect(address: &str) -> Result<TcpStream, std::io::Error> {
+    let addresses: Vec<SocketAddr> = address.to_socket_addrs()?.collect();
+    let mut error = std::io::Error::new(std::io::ErrorKind::NotFound, format!("{} didn't resolve to anything", address));
+    for candidate in addresses {
+        match TcpStream::connect_timeout(&candidate, HANDSHAKE) {
+            Ok(stream) => return Ok(stream),
+            Err(e) => error = e,
+        }
+    }
+    Err(error)
+}
+
+///
+/// Asks the relay to put this connection through to the host of the lobby `code`.
+/// Errors without a reason (like the host being away for a moment) are worth retrying
+///
+fn join_lobby(stream: &TcpStream, code: &str) -> Result<(), Option<String>> {
+    let mut stream = stream;
+    stream.set_read_timeout(Some(HANDSHAKE)).map_err(|_| None)?;
+    stream.set_write_timeout(Some(TIMEOUT)).map_err(|_| None)?;
+    protocol::write_frame(&mut stream, &Message::JoinLobby { version: PROTOCOL_VERSION, code: code.to_owned() }).map_err(|_| None)?;
+    match protocol::read_frame(&mut stream) {
+        Ok(Message::Paired) => Ok(()),
+        Ok(Message::Rejected { reason }) => Err(Some(reason)),
+        _ => Err(None),
+    }
+}
+
+///
+/// Says hello to the host. Returns the session to reconnect with, or why the host said no.
+/// Errors without a reason are worth retrying
+///
+fn join_handshake(stream: &TcpStream, session: Option<u64>, greeting: &Greeting) -> Result<u64, Option<String>> {
+    let mut stream = stream;
+    stream.set_read_timeout(Some(HANDSHAKE)).map_err(|_| None)?;
+    stream.set_write_timeout(Some(TIMEOUT)).map_err(|_| None)?;
+    let hello = Message::Hello { version: PROTOCOL_VERSION, session, room: greeting.room.clone(), name: greeting.name.clone() };
+    protocol::write_frame(&mut stream, &hello).map_err(|_| None)?;
+    match protocol::read_frame(&mut stream) {
+        Ok(Message::Welcome { version, session }) if version == PROTOCOL_VERSION => {
+            stream.set_read_timeout(None).map_err(|_| None)?;
+            Ok(session)
+        }
+        Ok(Message::Welcome { version, .. }) => Err(Some(format!("version mismatch (host has {}, you have {})", version, PROTOCOL_VERSION))),
+        Ok(Message::Rejected { reason }) => Err(Some(reason)),
+        _ => Err(None),
+    }
+}
+
+///
+/// Moves messages both ways over a connection until it ends, sending heartbeats while things are quiet
+/// and treating a connection that has gone silent for `TIMEOUT` as dropped
+///
+fn pump(stream: TcpStream, ends: &Ends) -> Ended {
+    // Reads block, so they get their own thread
+    let (frames_sender, frames) = mpsc::channel();
+    let mut reader = match stream.try_clone() {
+        Ok(reader) => reader,
+        Err(_) => return Ended::Dropped,
+    };
+    thread::spawn(move || {
+        loop {
+            let frame = protocol::read_frame(&mut reader);
+            let failed = frame.is_err();
+            if frames_sender.send(frame).is_err() || failed {
+                return;
+            }
+        }
+    });
+    let mut writer = &stream;
+    let mut last_heard = Instant::now();
+    let mut last_sent = Instant::now();
+    let ended = loop {
+        match frames.recv_timeout(TICK) {
+            Ok(Ok(Message::Heartbeat)) => {
+                last_heard = Instant::now();
+            }
+            Ok(Ok(Message::Bye)) => break Ended::Bye,
+            Ok(Ok(message)) => {
+                last_heard = Instant::now();
+                if ends.incoming.send(LinkEvent::Message(message)).is_err() {
+                    break Ended::Quit;
+                }
+            }
+            Ok(Err(_)) | Err(RecvTimeoutError::Disconnected) => break Ended::Dropped,
+            Err(RecvTimeoutError::Timeout) => {}
+        }
+        let mut quit = false;
+        let mut failed = false;
+        loop {
+            match ends.outgoing.try_recv() {
+                Ok(message) => {
+                    if protocol::write_frame(&mut writer, &message).is_err() {
+                        failed = true;
+                        break;
+                    }
+                    last_sent = Instant::now();
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    quit = true;
+                    break;
+                }
+            }
+        }
+        if quit {
+            protocol::write_frame(&mut writer, &Message::Bye).ok();
+            break Ended::Quit;
+        }
+        if failed || last_heard.elapsed() > TIMEOUT {
+            break Ended::Dropped;
+        }
+        if last_sent.elapsed() >= HEARTBEAT {
+            if protocol::write_frame(&mut writer, &Message::Heartbeat).is_err() {
+                break Ended::Dropped;
+            }
+            last_sent = Instant::now();
+        }
+    };
+    // Unblocks the reader thread so it can finish
+    stream.shutdown(Shutdown::Both).ok();
+    ended
+}
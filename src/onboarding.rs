@@ -0,0 +1,141 @@
+//! The wizard on the very first launch, when there's no save yet: it asks which characters the board can be drawn
+//! with, which theme to use, which keys to play with, and whether to keep stats, then writes the first save from
+//! the answers. Each question shows what its answers look like, so a font without Nerd Font icons shows up as
+//! boxes here rather than on the board.
+
+use crate::saves::Save;
+use crate::term;
+use minesweeper::cast::{self, Frame, Theme};
+use minesweeper::engine::{GameState, Tile, VisibleBoard};
+use minesweeper::topology::SquareEight;
+
+use crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use std::io::Write;
+
+///
+/// The save a first launch starts from, and what's written as it is when the wizard can't be shown
+///
+pub(crate) const DEFAULT_SAVE: &str = "{\"g_played\": 0, \"g_won\": 0, \"total_playtime\": 0, \"total_clicks\": 0,
+    \"inner_fg\": \"37\", \"inner_bg\": \"100\", \"border_fg\": \"37\", \"border_bg\": \"40\",
+    \"inner_highlight\": \"97\", \"flag_char\": \"󰈿\", \"mine_char\": \"󰷚\", \"tile_char\": \"󰆢\",
+    \"m_count_fg\": [\"34\", \"32\", \"31\", \"35\", \"33\", \"36\", \"37\", \"30\"],
+    \"gamemode\": 0}";
+
+// Characters to draw covered spaces, flags, and mines with. The emoji are ones drawn as text by default,
+// since emoji drawn as pictures take up two columns and push the board's columns out of line
+const GLYPHS: [(&str, [&str; 3]); 3] = [
+    ("Nerd Font icons", ["󰆢", "󰈿", "󰷚"]),
+    ("ASCII", ["#", "F", "*"]),
+    ("Emoji", ["☐", "⚑", "☢"]),
+];
+
+// Key schemes, with the keys each one binds in the save
+const SCHEMES: [(&str, &[(&str, &str)]); 4] = [
+    ("q checks, w flags, space chords", &[]),
+    ("q checks and chords, w flags", &[]),
+    ("z checks, x flags, space chords", &[("check", "z"), ("flag", "x")]),
+    ("Vim: h/j/k/l move and y/u/b/n move diagonally, with q checking, w flagging, and space chording", &[]),
+];
+
+///
+/// Asks the first launch's questions and returns the save made from the answers. Esc skips whatever's left,
+/// keeping the usual answers for it
+///
+pub(crate) fn run() -> Result<Save, std::io::Error> {
+    let save: Save = serde_json::from_str(DEFAULT_SAVE).expect("the default save is valid");
+    let glyphs: Vec<String> = GLYPHS.iter()
+        .map(|(name, chars)| format!("{:<16} {}", name, chars.iter().map(|c| format!("[{}]", c)).collect::<String>()))
+        .collect();
+    let glyph = match ask(1, "Which of these look right? If a row shows boxes or question marks, your font doesn't have those characters", &glyphs)? {
+        Some(glyph) => glyph,
+        None => return Ok(finish(save, 0, 0, 0, true)),
+    };
+    let themes: Vec<String> = Theme::gallery().into_iter()
+        .map(|(name, theme)| format!("{}\r\n{}", name, sample(&with_glyphs(theme, glyph)).join("\r\n")))
+        .collect();
+    let theme = match ask(2, "Pick a theme. It can be changed later with `minesweeper theme`", &themes)? {
+        Some(theme) => theme,
+        None => return Ok(finish(save, glyph, 0, 0, true)),
+    };
+    let schemes: Vec<String> = SCHEMES.iter().map(|(name, _)| name.to_string()).collect();
+    let scheme = match ask(3, "Which keys? The arrow keys always move, and `k` in a game changes any of them", &schemes)? {
+        Some(scheme) => scheme,
+        None => return Ok(finish(save, glyph, theme, 0, true)),
+    };
+    let answers = [String::from("Yes"), String::from("No")];
+    let stats = ask(4, "Keep stats of finished games (games won, times, medals) and show them on the menu?", &answers)?;
+    Ok(finish(save, glyph, theme, scheme, stats != Some(1)))
+}
+
+///
+/// The save with the answers to the questions put in
+///
+fn finish(mut save: Save, glyph: usize, theme: usize, scheme: usize, stats: bool) -> Save {
+    let (_, theme) = Theme::gallery().swap_remove(theme);
+    save.set_theme(with_glyphs(theme, glyph));
+    save.combined_chord = scheme == 1;
+    save.vim_keys = scheme == 3;
+    for (action, key) in SCHEMES[scheme].1 {
+        save.keys.insert(action.to_string(), key.to_string());
+    }
+    save.stats = stats;
+    term::clear();
+    save
+}
+
+///
+/// A theme with its characters swapped for a set from `GLYPHS`
+///
+fn with_glyphs(theme: Theme, glyph: usize) -> Theme {
+    let [tile, flag, mine] = GLYPHS[glyph].1;
+    Theme { tile_char: tile.to_string(), flag_char: flag.to_string(), mine_char: mine.to_string(), ..theme }
+}
+
+///
+/// A one-row board drawn in a theme: a covered space, a flag, an empty space, a few numbers, and a mine
+///
+pub(crate) fn sample(theme: &Theme) -> Vec<String> {
+    let tiles = vec![Tile::Covered, Tile::Flagged, Tile::Revealed(0), Tile::Revealed(1), Tile::Revealed(2), Tile::Revealed(3), Tile::Covered];
+    let frame = Frame {
+        time_ms: 0,
+        board: VisibleBoard { width: 7, height: 1, m_count: 2, f_count: 1, state: GameState::Running, tiles: vec![tiles] },
+        mines: vec![(6, 0)],
+    };
+    cast::render_text(&frame, &SquareEight, theme)[1..4].to_vec()
+}
+
+///
+/// Asks one question, with its answers listed under it. Up and down or a number picks an answer and Enter takes it.
+/// Returns the answer's index, or `None` if the player pressed Esc to skip the rest
+///
+fn ask(step: u8, question: &str, answers: &[String]) -> Result<Option<usize>, std::io::Error> {
+    let mut selected: usize = 0;
+    loop {
+        term::clear();
+        print!("Welcome to Minesweeper! A few questions before the first game ({} of 4)\r\n\r\n", step);
+        print!("{}\r\n\r\n", question);
+        for (i, answer) in answers.iter().enumerate() {
+            let pointer = if i == selected { ">" } else { " " };
+            // Answers can run over a few lines, which are lined up under the first
+            print!("{} {}. {}\r\n", pointer, i + 1, answer.replace("\r\n", "\r\n     "));
+        }
+        print!("\r\nup/down - pick | enter - next | Esc - skip the rest and use the usual settings\r\n");
+        std::io::stdout().flush()?;
+        let key = match event::read()? {
+            Event::Key(key_event) if key_event.kind == KeyEventKind::Press => key_event.code,
+            _ => continue,
+        };
+        match key {
+            KeyCode::Up => selected = selected.saturating_sub(1),
+            KeyCode::Down => selected = (selected + 1).min(answers.len() - 1),
+            KeyCode::Char(c) => {
+                if let Some(n) = c.to_digit(10).filter(|n| (1..=answers.len() as u32).contains(n)) {
+                    selected = n as usize - 1;
+                }
+            }
+            KeyCode::Enter => return Ok(Some(selected)),
+            KeyCode::Esc => return Ok(None),
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,37 @@
+//! `--profile`: records where a session's time goes, from the spans around board generation, flood fill,
+//! the solver, and drawing, as a Chrome trace. Only built with the `profile` feature.
+
+use std::fs;
+use std::path::PathBuf;
+use tracing_chrome::{ChromeLayerBuilder, FlushGuard};
+use tracing_subscriber::Registry;
+use tracing_subscriber::layer::Layer;
+
+///
+/// A trace being recorded. Everything is written out once it's dropped at the end of the session
+///
+pub(crate) struct Profile {
+    _guard: FlushGuard,
+    path: PathBuf,
+}
+
+impl Drop for Profile {
+    fn drop(&mut self) {
+        // The guard is dropped after this, which is what writes the end of the file
+        eprintln!("Profile written to {}", self.path.display());
+    }
+}
+
+///
+/// Starts recording into the `profiles` folder next to the executable, named after when the session started.
+/// Returns the layer that records, for the caller to add to the subscriber, or `None` if the file couldn't be made
+///
+pub(crate) fn start() -> Option<(Box<dyn Layer<Registry> + Send + Sync>, Profile)> {
+    let dir = std::env::current_exe().ok()?.parent()?.join("profiles");
+    fs::create_dir_all(&dir).ok()?;
+    let started = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).ok()?.as_millis();
+    let path = dir.join(format!("{}.json", started));
+    let file = fs::File::create(&path).ok()?;
+    let (layer, guard) = ChromeLayerBuilder::new().writer(file).include_args(true).build();
+    Some((Box::new(layer), Profile { _guard: guard, path }))
+}
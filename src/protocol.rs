@@ -0,0 +1,117 @@
+use crate::engine::GameState;
+use crate::replay::Replay;
+
+use serde::{Deserialize, Serialize};
+use std::io::{Error, ErrorKind, Read, Write};
+
+///
+/// Version of the messages below. Peers with a different version are turned away when they say hello,
+/// so a change to any message has to bump it
+///
+pub const PROTOCOL_VERSION: u32 = 4;
+///
+/// Largest frame either side will read, so a bad length prefix can't make the other side allocate gigabytes
+///
+pub const MAX_FRAME: u32 = 1 << 20;
+///
+/// Longest chat message, in characters
+///
+pub const MAX_CHAT: usize = 80;
+
+///
+/// Everything networked games say to each other. Each one is sent as a single frame: a 4 byte big-endian length,
+/// then the message as JSON
+///
+#[derive(PartialEq, Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum Message {
+    // First thing a joining player sends, with the session to pick back up if reconnecting.
+    // Servers also take the room to join and the name to go on the leaderboard under; other hosts ignore them
+    Hello {
+        version: u32,
+        session: Option<u64>,
+        #[serde(default)]
+        room: Option<String>,
+        #[serde(default)]
+        name: Option<String>,
+    },
+    Welcome { version: u32, session: u64 },       // The host letting a player in, with the session to reconnect to
+    Rejected { reason: String },                  // The host turning a player away, e.g. for the wrong version
+    Start { board: Replay, start: (i16, i16) },   // The board to race on (as an empty replay) and the space everyone starts from
+    Progress(Progress),                           // How far along the sender is, sent after every move and on every reconnect
+    Finished { replay: Replay },                  // The sender's whole game once it's over, so a server can check it before ranking it
+    Chat { name: String, text: String },          // A line of chat, and who said it (servers fill in the name they know the player by)
+    Heartbeat,                                    // Sent when there's nothing else to say, so a dead connection gets noticed
+    Bye,                                          // The sender is leaving for good
+    // Said to a relay before the game's own hello, so players can find each other by code
+    OpenLobby { version: u32, code: Option<String> }, // A host asking the relay for a code, or to take its code back after a drop
+    Lobby { code: String },                           // The relay's answer, with the code for the other player to join with
+    JoinLobby { version: u32, code: String },         // A player asking the relay for the host with this code
+    Paired,                                           // The relay telling both sides they're connected; everything after goes straight through
+}
+
+///
+/// How far a player has gotten on their board
+///
+#[derive(PartialEq, Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct Progress {
+    pub uncovered: u32, // Safe spaces uncovered so far
+    pub safe: u32,      // Safe spaces on the board
+    pub state: GameState,
+    pub time_ms: u64,   // Game time, or how long the game took once it's over
+}
+
+impl Progress {
+    ///
+    /// Percentage of the safe spaces uncovered, rounded down
+    ///
+    pub fn percent(&self) -> u32 {
+        if self.safe == 0 {
+            return 0;
+        }
+        self.uncovered * 100 / self.safe
+    }
+}
+
+///
+/// Makes text from the other side safe to print: drops control characters (so nothing can move the cursor or
+/// recolor the screen), invisible formatting characters that could reorder the line, and outer whitespace,
+/// then cuts it down to `limit` characters
+///
+pub fn clean_text(text: &str, limit: usize) -> String {
+    let kept: String = text.chars()
+        .filter(|c| !c.is_control() && !matches!(c, '\u{200B}'..='\u{200F}' | '\u{202A}'..='\u{202E}' | '\u{2066}'..='\u{2069}' | '\u{FEFF}'))
+        .collect();
+    let cut: String = kept.trim().chars().take(limit).collect();
+    cut.trim_end().to_owned()
+}
+
+///
+/// Writes a message as a length-prefixed frame
+///
+pub fn write_frame<W: Write>(writer: &mut W, message: &Message) -> Result<(), Error> {
+    let body = serde_json::to_vec(message).map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+    if body.len() > MAX_FRAME as usize {
+        return Err(Error::new(ErrorKind::InvalidInput, format!("message is {} bytes, over the {} byte limit", body.len(), MAX_FRAME)));
+    }
+    let mut frame = (body.len() as u32).to_be_bytes().to_vec();
+    frame.extend(body);
+    writer.write_all(&frame)?;
+    writer.flush()?;
+    Ok(())
+}
+
+///
+/// Reads one length-prefixed frame and decodes the message in it. Blocks until the whole frame is in
+///
+pub fn read_frame<R: Read>(reader: &mut R) -> Result<Message, Error> {
+    let mut length = [0u8; 4];
+    reader.read_exact(&mut length)?;
+    let length = u32::from_be_bytes(length);
+    if length > MAX_FRAME {
+        return Err(Error::new(ErrorKind::InvalidData, format!("frame of {} bytes is over the {} byte limit", length, MAX_FRAME)));
+    }
+    let mut body = vec![0u8; length as usize];
+    reader.read_exact(&mut body)?;
+    serde_json::from_slice(&body).map_err(|e| Error::new(ErrorKind::InvalidData, e))
+}
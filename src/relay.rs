@@ -0,0 +1,165 @@
+use crate::net::{self, HANDSHAKE, HEARTBEAT};
+use minesweeper::protocol::{self, Message, PROTOCOL_VERSION};
+
+use std::collections::HashMap;
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// How long a lobby's code is kept after its host goes away, so the host can take it back
+const LOBBY_KEPT: Duration = Duration::from_secs(10 * 60);
+
+///
+/// A code handed out to a host
+///
+struct Lobby {
+    host: Option<TcpStream>, // The host's connection while it waits for someone to join
+    last_seen: Instant,      // When the host was last waiting or playing
+}
+
+type Lobbies = Arc<Mutex<HashMap<String, Lobby>>>;
+
+///
+/// Runs a relay on `port` until the process is stopped. Hosts get a code, players join with it, and from then on
+/// the relay just passes bytes back and forth, so games over a relay work exactly like direct ones.
+/// Run with `minesweeper relay [port]`
+///
+pub(crate) fn run(port: u16) -> Result<(), std::io::Error> {
+    let listener = TcpListener::bind(("0.0.0.0", port))?;
+    println!("Relay listening on port {}", port);
+    let lobbies: Lobbies = Arc::new(Mutex::new(HashMap::new()));
+    let kept = lobbies.clone();
+    thread::spawn(move || keep_lobbies(kept));
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(_) => continue,
+        };
+        let lobbies = lobbies.clone();
+        thread::spawn(move || welcome(stream, &lobbies));
+    }
+    Ok(())
+}
+
+///
+/// Handles a new connection's first message: a host opening a lobby, or a player joining one
+///
+fn welcome(mut stream: TcpStream, lobbies: &Lobbies) {
+    if stream.set_read_timeout(Some(HANDSHAKE)).is_err() || stream.set_write_timeout(Some(HANDSHAKE)).is_err() {
+        return;
+    }
+    match protocol::read_frame(&mut stream) {
+        Ok(Message::OpenLobby { version, code }) => {
+            if version != PROTOCOL_VERSION {
+                reject(stream, format!("version mismatch (relay has {}, you have {})", PROTOCOL_VERSION, version));
+                return;
+            }
+            let mut lobbies = lobbies.lock().unwrap();
+            // A host coming back after a drop keeps its code
+            let code = match code {
+                Some(code) if net::is_lobby_code(&code) => code,
+                _ => loop {
+                    let code = net::new_lobby_code();
+                    if !lobbies.contains_key(&code) {
+                        break code;
+                    }
+                },
+            };
+            if protocol::write_frame(&mut stream, &Message::Lobby { code: code.clone() }).is_err() {
+                return;
+            }
+            println!("Lobby {} is waiting for a player", code);
+            lobbies.insert(code, Lobby { host: Some(stream), last_seen: Instant::now() });
+        }
+        Ok(Message::JoinLobby { version, code }) => {
+            if version != PROTOCOL_VERSION {
+                reject(stream, format!("version mismatch (relay has {}, you have {})", PROTOCOL_VERSION, version));
+                return;
+            }
+            let host = match lobbies.lock().unwrap().get_mut(&code) {
+                Some(lobby) => {
+                    lobby.last_seen = Instant::now();
+                    Some(lobby.host.take())
+                }
+                None => None,
+            };
+            let mut host = match host {
+                Some(Some(host)) => host,
+                // The host is away for a moment; hanging up lets the player try again
+                Some(None) => return,
+                None => {
+                    reject(stream, format!("there's no game with the code {}", code));
+                    return;
+                }
+            };
+            if protocol::write_frame(&mut host, &Message::Paired).is_err() {
+                return;
+            }
+            if protocol::write_frame(&mut stream, &Message::Paired).is_err() {
+                host.shutdown(Shutdown::Both).ok();
+                return;
+            }
+            println!("Lobby {} has a player", code);
+            // Both sides keep their own heartbeats and timeouts from here on
+            if stream.set_read_timeout(None).is_ok() && host.set_read_timeout(None).is_ok()
+                && stream.set_write_timeout(None).is_ok() && host.set_write_timeout(None).is_ok() {
+                pipe(stream, host);
+            }
+            println!("Lobby {} lost its connection", code);
+            if let Some(lobby) = lobbies.lock().unwrap().get_mut(&code) {
+                lobby.last_seen = Instant::now();
+            }
+        }
+        _ => {}
+    }
+}
+
+///
+/// Tells a connection why it was turned away
+///
+fn reject(mut stream: TcpStream, reason: String) {
+    protocol::write_frame(&mut stream, &Message::Rejected { reason }).ok();
+}
+
+///
+/// Passes bytes both ways between two connections until either one closes
+///
+fn pipe(a: TcpStream, b: TcpStream) {
+    let (mut a_reader, mut b_reader) = match (a.try_clone(), b.try_clone()) {
+        (Ok(a_reader), Ok(b_reader)) => (a_reader, b_reader),
+        _ => return,
+    };
+    let (mut a_writer, mut b_writer) = (a, b);
+    let forward = thread::spawn(move || {
+        std::io::copy(&mut a_reader, &mut b_writer).ok();
+        // Closing both ends stops the copy going the other way too
+        a_reader.shutdown(Shutdown::Both).ok();
+        b_writer.shutdown(Shutdown::Both).ok();
+    });
+    std::io::copy(&mut b_reader, &mut a_writer).ok();
+    a_writer.shutdown(Shutdown::Both).ok();
+    b_reader.shutdown(Shutdown::Both).ok();
+    forward.join().ok();
+}
+
+///
+/// Sends heartbeats to waiting hosts so both sides notice a dead connection,
+/// and forgets lobbies whose host has been gone for `LOBBY_KEPT`
+///
+fn keep_lobbies(lobbies: Lobbies) {
+    loop {
+        thread::sleep(HEARTBEAT);
+        let mut lobbies = lobbies.lock().unwrap();
+        for (code, lobby) in lobbies.iter_mut() {
+            if let Some(host) = &mut lobby.host {
+                if protocol::write_frame(host, &Message::Heartbeat).is_err() {
+                    println!("Lobby {} lost its host", code);
+                    lobby.host = None;
+                }
+                lobby.last_seen = Instant::now();
+            }
+        }
+        lobbies.retain(|_, lobby| lobby.host.is_some() || lobby.last_seen.elapsed() < LOBBY_KEPT);
+    }
+}
@@ -0,0 +1,106 @@
+//! Drawing boards for frontends other than the terminal game, like a bot's log, a chat bot, or a window.
+//! A frontend implements `Renderer` and is handed the board as the player can see it after every move.
+
+use crate::engine::{GameState, Tile, VisibleBoard};
+
+use std::io::Write;
+
+///
+/// Something that shows a board to a player. It only ever gets what the player can see, so a frontend can't give
+/// the mines away by accident
+///
+/// ```
+/// use minesweeper::engine::{Board, Tile, VisibleBoard};
+/// use minesweeper::layout::Layout;
+/// use minesweeper::render::Renderer;
+///
+/// // Counts the spaces still covered each time it draws
+/// struct Covered(Vec<usize>);
+///
+/// impl Renderer for Covered {
+///     fn draw(&mut self, board: &VisibleBoard) {
+///         let covered = board.tiles.concat().iter().filter(|tile| !matches!(tile, Tile::Revealed(_))).count();
+///         self.0.push(covered);
+///     }
+/// }
+///
+/// let mut board = Board::from_layout(&Layout::from_art("*..\n...\n...").unwrap()).unwrap();
+/// let mut covered = Covered(vec![]);
+/// covered.draw(&board.visible_board());
+/// board.reveal(2, 2);
+/// covered.draw(&board.visible_board());
+/// assert_eq!(covered.0, vec![9, 1]);
+/// ```
+///
+pub trait Renderer {
+    ///
+    /// Shows the board as the player sees it. Called once before the first move, then after every move
+    ///
+    fn draw(&mut self, board: &VisibleBoard);
+}
+
+///
+/// Draws boards as plain text to anything written to, e.g. stdout or a log file: `#` for covered spaces, `F` for
+/// flags, `.` for empty spaces, and the numbers as digits, then a line with the flags left, and the result once
+/// the game is over
+///
+/// ```
+/// use minesweeper::engine::Board;
+/// use minesweeper::layout::Layout;
+/// use minesweeper::render::{Renderer, TextRenderer};
+///
+/// let mut board = Board::from_layout(&Layout::from_art("*..\n...").unwrap()).unwrap();
+/// board.reveal(2, 0);
+/// let mut text = TextRenderer::new(vec![]);
+/// text.draw(&board.visible_board());
+/// assert_eq!(String::from_utf8(text.into_inner()).unwrap(), "#1.\n#1.\nFlags left: 1\n");
+/// ```
+///
+pub struct TextRenderer<W: Write> {
+    out: W,
+}
+
+impl<W: Write> TextRenderer<W> {
+    pub fn new(out: W) -> TextRenderer<W> {
+        TextRenderer { out }
+    }
+    ///
+    /// Hands back what the boards were written to
+    ///
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+}
+
+impl<W: Write> Renderer for TextRenderer<W> {
+    fn draw(&mut self, board: &VisibleBoard) {
+        // Like the game's own drawing, a frontend that can't write just misses a frame
+        self.out.write_all(text(board).as_bytes()).ok();
+        self.out.flush().ok();
+    }
+}
+
+///
+/// A board as plain text, the way `TextRenderer` draws it
+///
+pub fn text(board: &VisibleBoard) -> String {
+    let mut text = String::new();
+    for row in &board.tiles {
+        for tile in row {
+            match tile {
+                Tile::Covered => text.push('#'),
+                Tile::Flagged => text.push('F'),
+                Tile::Revealed(0) => text.push('.'),
+                Tile::Revealed(n) => text.push_str(&n.to_string()),
+            }
+        }
+        text.push('\n');
+    }
+    text.push_str(&format!("Flags left: {}\n", board.m_count - board.f_count));
+    match board.state {
+        GameState::Win => text.push_str("Won!\n"),
+        GameState::Loss => text.push_str("Lost\n"),
+        _ => {}
+    }
+    text
+}
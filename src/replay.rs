@@ -0,0 +1,345 @@
+use crate::engine::{Board, Clock, GameState, ManualClock, SeedRng, VisibleBoard};
+#[cfg(feature = "solver")]
+use crate::grade::{self, Grade};
+use crate::generation::GenerationOptions;
+use crate::layout::Layout;
+#[cfg(feature = "solver")]
+use crate::review::Review;
+use crate::rules;
+use crate::topology;
+
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use std::time::Duration;
+
+///
+/// The kind of move a player made
+///
+#[derive(PartialEq, Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum ActionKind {
+    Reveal,
+    Flag,
+    Chord,
+}
+
+///
+/// A single move, along with how far into the game it was made
+///
+#[derive(PartialEq, Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct ReplayAction {
+    pub kind: ActionKind,
+    pub x: i16,
+    pub y: i16,
+    pub time_ms: u64, // Game time right after the move was made
+}
+
+///
+/// What a game is certified for, shown as badges when it's won and carried in its replay.
+/// Flags and no guess claims are checked when the replay is verified; assists aren't part of the recording,
+/// so that one is taken on trust
+///
+#[derive(PartialEq, Clone, Copy, Debug, Default, Serialize, Deserialize)]
+pub struct Badges {
+    #[serde(default)]
+    pub assist_free: bool, // No solver assists were used
+    #[serde(default)]
+    pub no_flags: bool,    // No flags were ever placed (NF)
+    #[serde(default)]
+    pub no_guess: bool,    // The board could be cleared from the first check without guessing
+    #[serde(default)]
+    pub zero_guess: bool,  // It was cleared without the player making a single guess
+}
+
+impl Badges {
+    ///
+    /// Every badge's label, along with whether it was earned, in the order they're shown
+    ///
+    pub fn labels(&self) -> [(&'static str, bool); 4] {
+        [("ASSIST-FREE", self.assist_free), ("NF", self.no_flags), ("NO GUESS", self.no_guess), ("0-GUESS", self.zero_guess)]
+    }
+}
+
+impl Display for Badges {
+    ///
+    /// The earned badges' labels, e.g. "ASSIST-FREE NF", or nothing if none were earned
+    ///
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
+        let earned: Vec<&str> = self.labels().iter().filter(|(_, earned)| *earned).map(|(label, _)| *label).collect();
+        write!(f, "{}", earned.join(" "))
+    }
+}
+
+///
+/// A recording of a whole game: the board's settings and seed, every move made, and the result the player claims.
+/// Boards are regenerated from the seed (or set up from their layout, for imported boards), so a replay is enough
+/// to re-play the game move for move
+///
+#[derive(PartialEq, Clone, Debug, Serialize, Deserialize)]
+pub struct Replay {
+    pub width: i16,
+    pub height: i16,
+    pub m_count: i16,
+    pub gamemode: u8,
+    pub seed: u64,
+    pub pre_start_flags: bool,
+    pub pre_flags_safe: bool,
+    #[serde(flatten)]
+    pub generation: GenerationOptions,
+    #[serde(default = "default_rules")]
+    pub rules: String, // Name of the rules the game was played by
+    #[serde(default = "default_topology")]
+    pub topology: String, // Name of the board's shape
+    #[serde(default = "default_rng")]
+    pub rng: String, // Generator the seed was fed to, "std" or "pcg". Replays from before there was a choice used "std"
+    #[serde(default)]
+    pub layout: Option<Layout>, // Where the mines are, for boards that weren't generated from the seed
+    #[serde(default)]
+    pub badges: Badges,         // Claimed badges
+    pub actions: Vec<ReplayAction>,
+    pub result: GameState, // Claimed result
+    pub time_ms: u64,      // Claimed game time
+}
+
+///
+/// What re-simulating a replay came out to
+///
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub struct Verification {
+    pub result: GameState,
+    pub time: Duration,
+}
+
+impl Replay {
+    ///
+    /// Starts an empty recording for a board with the given settings
+    ///
+    pub fn new(width: i16, height: i16, m_count: i16, gamemode: u8, seed: u64, pre_start_flags: bool, pre_flags_safe: bool) -> Replay {
+        Replay {
+            width,
+            height,
+            m_count,
+            gamemode,
+            seed,
+            pre_start_flags,
+            pre_flags_safe,
+            generation: GenerationOptions::default(),
+            rules: default_rules(),
+            topology: default_topology(),
+            rng: SeedRng::Pcg.name(),
+            layout: None,
+            badges: Badges::default(),
+            actions: vec![],
+            result: GameState::Starting,
+            time_ms: 0,
+        }
+    }
+    ///
+    /// Records a move made `time` into the game
+    ///
+    pub fn record(&mut self, kind: ActionKind, x: i16, y: i16, time: Duration) {
+        self.actions.push(ReplayAction { kind, x, y, time_ms: time.as_millis() as u64 });
+    }
+    ///
+    /// Records the result of the game once it's over
+    ///
+    pub fn finish(&mut self, result: GameState, time: Duration) {
+        self.result = result;
+        self.time_ms = time.as_millis() as u64;
+    }
+    ///
+    /// Re-plays every move against a board generated from the seed, checking that each move was possible
+    /// and that the game really ended with the claimed result and time.
+    /// Returns what the game came out to, or a description of the first thing that doesn't add up
+    ///
+    pub fn verify(&self) -> Result<Verification, String> {
+        let board = self.simulate(self.actions.len())?;
+        let now = self.actions.last().map(|a| a.time_ms).unwrap_or(0);
+        let verification = Verification { result: board.state(), time: board.elapsed() };
+        if verification.result != self.result {
+            return Err(format!("Claimed {:?} but the moves lead to {:?}", self.result, verification.result));
+        }
+        // A finished game's clock stops on its last move; an unfinished one can only be checked against its last move
+        let finished = verification.result == GameState::Win || verification.result == GameState::Loss;
+        // Bonuses can take the game's time back past when the last move was made
+        if (finished && verification.time.as_millis() as u64 != self.time_ms) || self.time_ms + (board.bonus_time().as_millis() as u64) < now {
+            return Err(format!("Claimed a time of {}ms but the moves take {}ms", self.time_ms, verification.time.as_millis()));
+        }
+        if self.badges.no_flags && self.actions.iter().any(|a| a.kind == ActionKind::Flag) {
+            return Err(String::from("Claimed no flags but the moves place flags"));
+        }
+        if self.badges.no_guess {
+            self.check_no_guess(&board)?;
+        }
+        if self.badges.zero_guess {
+            self.check_zero_guess(&board)?;
+        }
+        Ok(verification)
+    }
+    ///
+    /// Checks that the board could be cleared from the first check without guessing, by having the solver clear it
+    ///
+    #[cfg(feature = "solver")]
+    fn check_no_guess(&self, board: &Board) -> Result<(), String> {
+        if !board.rules().standard_numbers() || !board.topology().is_plain_grid() {
+            return Err(String::from("Claimed a no guess board, but the solver can't read this board"));
+        }
+        let start = match self.actions.iter().find(|a| a.kind != ActionKind::Flag) {
+            Some(action) => (action.x, action.y),
+            None => return Err(String::from("Claimed a no guess board, but nothing was ever checked")),
+        };
+        match grade::grade(board, start) {
+            Some(grade) if grade != Grade::Expert => Ok(()),
+            _ => Err(String::from("Claimed a no guess board, but it needs a guess")),
+        }
+    }
+    ///
+    /// Without the solver, there's nothing to check a no guess claim with
+    ///
+    #[cfg(not(feature = "solver"))]
+    fn check_no_guess(&self, _board: &Board) -> Result<(), String> {
+        Err(String::from("No guess boards can only be checked with the solver feature"))
+    }
+    ///
+    /// Checks that none of the moves were guesses, by having the solver look over each one
+    ///
+    #[cfg(feature = "solver")]
+    fn check_zero_guess(&self, board: &Board) -> Result<(), String> {
+        if !board.rules().standard_numbers() || !board.topology().is_plain_grid() {
+            return Err(String::from("Claimed a 0-guess clear, but the solver can't read this board"));
+        }
+        match Review::new(self)?.guesses() {
+            0 => Ok(()),
+            n => Err(format!("Claimed a 0-guess clear, but {} of the moves were guesses", n)),
+        }
+    }
+    ///
+    /// Without the solver, there's nothing to check a 0-guess claim with
+    ///
+    #[cfg(not(feature = "solver"))]
+    fn check_zero_guess(&self, _board: &Board) -> Result<(), String> {
+        Err(String::from("0-guess clears can only be checked with the solver feature"))
+    }
+    ///
+    /// Rebuilds the board as it was after the first `moves` moves, checking that each of them was possible
+    ///
+    pub fn simulate(&self, moves: usize) -> Result<Board, String> {
+        self.run(moves, |_| {})
+    }
+    ///
+    /// The board as the player saw it before the first move and after each move, checking every move along the way
+    ///
+    pub fn positions(&self) -> Result<Vec<VisibleBoard>, String> {
+        let mut positions: Vec<VisibleBoard> = vec![];
+        self.run(self.actions.len(), |board| positions.push(board.visible_board()))?;
+        Ok(positions)
+    }
+    ///
+    /// Sets up the board the replay was recorded on, before any moves, running on the given clock.
+    /// Fails if the settings couldn't have come from a real game
+    ///
+    pub fn new_board(&self, clock: Box<dyn Clock>) -> Result<Board, String> {
+        if self.gamemode > 1 {
+            return Err(format!("Gamemode {} boards can't be regenerated from a seed", self.gamemode));
+        }
+        if self.width < 1 || self.height < 1 {
+            return Err(format!("Impossible board size {}x{}", self.width, self.height));
+        }
+        if cfg!(not(feature = "solver")) && self.generation.hard_samples > 0 {
+            return Err(String::from("Hard boards can only be regenerated with the solver feature"));
+        }
+        let rules = match rules::from_name(&self.rules) {
+            Some(rules) => rules,
+            None => return Err(format!("Unknown rules \"{}\"", self.rules)),
+        };
+        let topology = match topology::from_name(&self.topology) {
+            Some(topology) => topology,
+            None => return Err(format!("Unknown topology \"{}\"", self.topology)),
+        };
+        let space_n = (self.width as i32) * (self.height as i32);
+        if self.m_count < 0 || self.m_count as i32 >= space_n {
+            return Err(format!("Impossible mine count {} for {} spaces", self.m_count, space_n));
+        }
+        let rng = match SeedRng::from_name(&self.rng) {
+            Some(rng) => rng.rng(self.seed),
+            None => return Err(format!("Unknown random number generator \"{}\"", self.rng)),
+        };
+        let mut board = Board::with_sources(self.width, self.height, self.m_count, self.gamemode, rng, clock);
+        board.set_pre_start_flags(self.pre_start_flags, self.pre_flags_safe);
+        board.set_rules(rules);
+        board.set_topology(topology);
+        if board.m_count() != self.m_count {
+            return Err(format!("Only {} mines fit on a {} board", board.m_count(), self.topology));
+        }
+        if let Some(layout) = &self.layout {
+            layout.validate()?;
+            if (layout.width, layout.height, layout.mines.len()) != (self.width, self.height, self.m_count as usize) {
+                return Err(format!("The layout is for a {}x{} board with {} mines", layout.width, layout.height, layout.mines.len()));
+            }
+            if let Some(&(x, y)) = layout.mines.iter().find(|&&(x, y)| !board.is_space(x, y)) {
+                return Err(format!("The layout has a mine at ({}, {}), which isn't on a {} board", x, y, self.topology));
+            }
+            board.set_mine_map(layout.mine_map());
+        }
+        Ok(board)
+    }
+    ///
+    /// Re-plays the first `moves` moves, handing the board to `on_position` before the first move and after each one
+    ///
+    fn run<F: FnMut(&Board)>(&self, moves: usize, mut on_position: F) -> Result<Board, String> {
+        let clock = ManualClock::new();
+        let mut board = self.new_board(Box::new(clock.clone()))?;
+        let mut now: u64 = 0;
+        on_position(&board);
+        for (i, action) in self.actions.iter().take(moves).enumerate() {
+            let n = i + 1;
+            if action.time_ms < now {
+                return Err(format!("Move {} happens before the move ahead of it", n));
+            }
+            if action.x < 0 || action.y < 0 || action.x >= self.width || action.y >= self.height || !board.is_space(action.x, action.y) {
+                return Err(format!("Move {} is off the board at ({}, {})", n, action.x, action.y));
+            }
+            if board.state() == GameState::Win || board.state() == GameState::Loss {
+                return Err(format!("Move {} was made after the game was over", n));
+            }
+            clock.advance(Duration::from_millis(action.time_ms - now));
+            now = action.time_ms;
+            // Generate around the first check the same way the game does
+            if !board.is_generated() && action.kind != ActionKind::Flag {
+                self.generation.generate(&mut board, (action.x, action.y));
+            }
+            match action.kind {
+                ActionKind::Reveal => {
+                    if board.is_generated() && (board.is_uncovered(action.x, action.y) || board.is_flagged(action.x, action.y)) {
+                        return Err(format!("Move {} checks ({}, {}), which can't be checked", n, action.x, action.y));
+                    }
+                    board.reveal(action.x, action.y);
+                }
+                ActionKind::Flag => {
+                    if !board.flag(action.x, action.y) {
+                        return Err(format!("Move {} flags ({}, {}), which can't be flagged", n, action.x, action.y));
+                    }
+                }
+                ActionKind::Chord => {
+                    if board.is_flagged(action.x, action.y) {
+                        return Err(format!("Move {} chords on the flag at ({}, {})", n, action.x, action.y));
+                    }
+                    board.chord(action.x, action.y);
+                }
+            }
+            on_position(&board);
+        }
+        Ok(board)
+    }
+}
+
+fn default_rules() -> String {
+    String::from("standard")
+}
+
+fn default_topology() -> String {
+    String::from("square8")
+}
+
+fn default_rng() -> String {
+    String::from("std")
+}
@@ -0,0 +1,231 @@
+use crate::engine::{GameState, VisibleBoard};
+use crate::replay::{ActionKind, Replay, ReplayAction};
+use crate::solver::{Solver, Tile};
+
+use std::fmt::{Display, Formatter};
+
+///
+/// What the solver makes of a move, looking only at what the player could see when making it
+///
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum Note {
+    ForcedGuess, // Opened a space that wasn't proven safe, when nothing on the board could be proven
+    Guess,       // Opened a space that wasn't proven safe, while something else could have been
+    WrongFlag,   // Flagged a space that was proven safe
+}
+
+impl Display for Note {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
+        match *self {
+            Note::ForcedGuess => {
+                write!(f, "forced guess")
+            }
+            Note::Guess => {
+                write!(f, "mistake: guessed while a safe move was available")
+            }
+            Note::WrongFlag => {
+                write!(f, "mistake: flagged a space that can't be a mine")
+            }
+        }
+    }
+}
+
+impl Note {
+    ///
+    /// Whether or not the move could have been avoided by playing only what was proven
+    ///
+    pub fn is_mistake(&self) -> bool {
+        *self != Note::ForcedGuess
+    }
+    ///
+    /// Whether or not the move opened a space that wasn't proven safe
+    ///
+    pub fn is_guess(&self) -> bool {
+        *self == Note::ForcedGuess || *self == Note::Guess
+    }
+}
+
+///
+/// A finished game laid out for stepping through: the board before and after every move,
+/// and the solver's note on each move, if it had one
+///
+#[derive(PartialEq, Clone, Debug)]
+pub struct Review {
+    pub actions: Vec<ReplayAction>,
+    pub positions: Vec<VisibleBoard>, // Before the first move, then after each move
+    pub notes: Vec<Option<Note>>,     // One per move
+    pub tip: Option<String>,          // For losses that could have been avoided, how to have seen it coming
+}
+
+impl Review {
+    ///
+    /// Re-plays a replay and annotates each of its moves
+    ///
+    pub fn new(replay: &Replay) -> Result<Review, String> {
+        let positions = replay.positions()?;
+        let mut notes: Vec<Option<Note>> = vec![];
+        for (i, action) in replay.actions.iter().enumerate() {
+            notes.push(annotate(&positions[i], action));
+        }
+        let tip = match replay.actions.last() {
+            Some(last) if replay.result == GameState::Loss => {
+                let board = replay.simulate(replay.actions.len())?;
+                let before = &positions[positions.len() - 2];
+                let fatal = opened(before, last).into_iter().find(|space| board.is_mine(space.0, space.1));
+                // Only the flags that really are mines are kept, so the tip can lean on them
+                let mut tiles = before.tiles.clone();
+                for i in 0..before.height {
+                    for j in 0..before.width {
+                        if tiles[i as usize][j as usize] == Tile::Flagged && !board.is_mine(j, i) {
+                            tiles[i as usize][j as usize] = Tile::Covered;
+                        }
+                    }
+                }
+                let solver = Solver::new(before.width, before.height, before.m_count, tiles).trusting_flags();
+                fatal.and_then(|fatal| coach(&solver, fatal))
+            }
+            _ => None,
+        };
+        Ok(Review { actions: replay.actions.clone(), positions, notes, tip })
+    }
+    ///
+    /// How many of the moves were guesses
+    ///
+    pub fn guesses(&self) -> usize {
+        self.notes.iter().filter(|note| note.is_some_and(|n| n.is_guess())).count()
+    }
+    ///
+    /// Index of the first move that could have been avoided
+    ///
+    pub fn first_mistake(&self) -> Option<usize> {
+        self.notes.iter().position(|note| note.is_some_and(|n| n.is_mistake()))
+    }
+    ///
+    /// Index of the first annotated move after move `after`, if any
+    ///
+    pub fn next_note(&self, after: Option<usize>) -> Option<usize> {
+        let start = after.map(|i| i + 1).unwrap_or(0);
+        (start..self.notes.len()).find(|&i| self.notes[i].is_some())
+    }
+    ///
+    /// Writes the review out as text: every move with its note, then what the solver could prove just before it
+    /// and why, and the tip at the end
+    ///
+    pub fn analysis(&self) -> String {
+        let mut out = String::new();
+        for (i, action) in self.actions.iter().enumerate() {
+            let verb = match action.kind {
+                ActionKind::Reveal => "check",
+                ActionKind::Flag => "flag",
+                ActionKind::Chord => "chord",
+            };
+            out.push_str(&format!("Move {} at {:.3}s: {} {}", i + 1, action.time_ms as f64 / 1000., verb, at((action.x, action.y))));
+            if let Some(note) = self.notes[i] {
+                out.push_str(&format!(" - {}", note));
+            }
+            out.push('\n');
+            let before = &self.positions[i];
+            if before.state != GameState::Running {
+                continue;
+            }
+            let solver = Solver::new(before.width, before.height, before.m_count, before.tiles.clone());
+            for inference in solver.infer() {
+                out.push_str(&format!("    {}\n", inference.reason));
+            }
+        }
+        if let Some(tip) = &self.tip {
+            out.push_str(&format!("Tip: {}\n", tip));
+        }
+        out
+    }
+}
+
+///
+/// Judges one move against the position it was made from. Moves made before the first check are never noted,
+/// since there was nothing to go on yet
+///
+pub fn annotate(before: &VisibleBoard, action: &ReplayAction) -> Option<Note> {
+    if before.state != GameState::Running {
+        return None;
+    }
+    let solver = Solver::new(before.width, before.height, before.m_count, before.tiles.clone());
+    let deduction = solver.next_deduction();
+    let mut proven_safe = solver.certain_safe();
+    if let Some(d) = &deduction {
+        proven_safe.extend(d.safe.iter().copied());
+    }
+    if action.kind == ActionKind::Flag {
+        let tile = before.tiles[action.y as usize][action.x as usize];
+        if tile == Tile::Covered && proven_safe.contains(&(action.x, action.y)) {
+            return Some(Note::WrongFlag);
+        }
+        return None;
+    }
+    if opened(before, action).iter().all(|space| proven_safe.contains(space)) {
+        return None;
+    }
+    match deduction {
+        Some(_) => Some(Note::Guess),
+        None => Some(Note::ForcedGuess),
+    }
+}
+
+///
+/// Gets the spaces a move opens up on its own, before any zeroes spread
+///
+fn opened(before: &VisibleBoard, action: &ReplayAction) -> Vec<(i16, i16)> {
+    let tile = before.tiles[action.y as usize][action.x as usize];
+    match action.kind {
+        ActionKind::Flag => vec![],
+        ActionKind::Reveal => vec![(action.x, action.y)],
+        ActionKind::Chord => {
+            let surrounding = surrounding(before, action.x, action.y);
+            let flags = surrounding.iter().filter(|s| before.tiles[s.1 as usize][s.0 as usize] == Tile::Flagged).count();
+            match tile {
+                Tile::Revealed(n) if n > 0 && flags == n as usize => {
+                    surrounding.into_iter().filter(|s| before.tiles[s.1 as usize][s.0 as usize] == Tile::Covered).collect()
+                }
+                // Chording a covered space just checks it
+                Tile::Covered => vec![(action.x, action.y)],
+                _ => vec![],
+            }
+        }
+    }
+}
+
+///
+/// Explains what would have avoided stepping on `fatal`: the proof that it was a mine if there was one,
+/// otherwise the proof of some other space that was safe to open instead
+///
+fn coach(solver: &Solver, fatal: (i16, i16)) -> Option<String> {
+    if let Some(proof) = solver.explain(fatal) {
+        return Some(solver.describe(&proof, &[fatal]));
+    }
+    let safe = *solver.next_deduction()?.safe.first()?;
+    let proof = solver.explain(safe)?;
+    Some(format!("Nothing proved {} safe, but {} was. {}", at(fatal), at(safe), solver.describe(&proof, &[safe])))
+}
+
+///
+/// Formats a space the way the game shows coordinates, counting from 1
+///
+fn at(space: (i16, i16)) -> String {
+    format!("({}, {})", space.0 + 1, space.1 + 1)
+}
+
+///
+/// Gets the spaces around a given space on a visible board
+///
+fn surrounding(board: &VisibleBoard, x: i16, y: i16) -> Vec<(i16, i16)> {
+    let mut surroundings: Vec<(i16, i16)> = vec![];
+    for dy in -1..=1 {
+        for dx in -1..=1 {
+            let nx = x + dx;
+            let ny = y + dy;
+            if (dx != 0 || dy != 0) && nx >= 0 && nx < board.width && ny >= 0 && ny < board.height {
+                surroundings.push((nx, ny));
+            }
+        }
+    }
+    surroundings
+}
@@ -0,0 +1,379 @@
+use crate::topology::Topology;
+use rand::{Rng, RngCore};
+use std::time::Duration;
+
+// Lives given by `lives` when no number is asked for
+const DEFAULT_LIVES: u8 = 3;
+// Time each bonus takes off the clock
+const BONUS_TIME: Duration = Duration::from_secs(5);
+// How long each bonus stays up, one after another: the first for the first 20 seconds, the second until 40, and so on
+const BONUS_WINDOW: Duration = Duration::from_secs(20);
+// Safe spaces on the board for every bonus on it, and the most bonuses a board gets
+const SPACES_PER_BONUS: usize = 25;
+const MAX_BONUSES: usize = 5;
+
+///
+/// The rules a board plays by: what its mines are worth, what its numbers show,
+/// and what happens when a mine gets checked or a game can be won early.
+/// Picked when the board is created, and named in replays so they can be played back the same way
+///
+pub trait RuleSet: Send {
+    ///
+    /// Name that `from_name` turns back into these rules
+    ///
+    fn name(&self) -> String;
+    ///
+    /// A copy of the rules as they stand, lives left and all, so a board can be put back how it was
+    ///
+    fn copy(&self) -> Box<dyn RuleSet>;
+    ///
+    /// Called whenever the mines are placed on a `width` x `height` board laid out by `topology`.
+    /// Returns how much each mine in `mines` adds to the numbers around it
+    ///
+    fn arrange(&mut self, _width: i16, _height: i16, _topology: &dyn Topology, mines: &[(i16, i16)], _rng: &mut dyn RngCore) -> Vec<i16> {
+        vec![1; mines.len()]
+    }
+    ///
+    /// The number shown on a safe space, given what the mines around it add up to
+    ///
+    fn shown_count(&self, _space: (i16, i16), count: i16) -> i16 {
+        count
+    }
+    ///
+    /// Called when a mine gets checked. Returns whether or not the player lives through it
+    ///
+    fn survive_mine(&mut self) -> bool {
+        false
+    }
+    ///
+    /// Whether or not uncovering `space` wins the game on the spot
+    ///
+    fn wins_on(&self, _space: (i16, i16)) -> bool {
+        false
+    }
+    ///
+    /// Whether or not the numbers mean what they do in standard minesweeper, so the solver can read them
+    ///
+    fn standard_numbers(&self) -> bool {
+        true
+    }
+    ///
+    /// Anything about the rules the player should be able to see, like lives left
+    ///
+    fn status(&self) -> Option<String> {
+        None
+    }
+    ///
+    /// The fewest and most mines the player is told a board with `mines` mines has,
+    /// or `None` if they aren't told anything about the total
+    ///
+    fn mine_range(&self, mines: i16) -> Option<(i16, i16)> {
+        Some((mines, mines))
+    }
+    ///
+    /// Called for every safe space that gets uncovered, `time` into the game.
+    /// Returns how much time uncovering it takes off the clock
+    ///
+    fn time_bonus(&mut self, _space: (i16, i16), _time: Duration) -> Duration {
+        Duration::ZERO
+    }
+    ///
+    /// Spaces with a time bonus that can still be had `time` into the game
+    ///
+    fn bonuses(&self, _time: Duration) -> Vec<(i16, i16)> {
+        vec![]
+    }
+}
+
+///
+/// Regular minesweeper: every mine counts once, and checking one loses the game
+///
+#[derive(Clone)]
+pub struct Standard;
+
+impl RuleSet for Standard {
+    fn name(&self) -> String {
+        String::from("standard")
+    }
+    fn copy(&self) -> Box<dyn RuleSet> {
+        Box::new(self.clone())
+    }
+}
+
+///
+/// Checking a mine costs a life instead of the game, until the lives run out.
+/// Mines that were lived through get flagged
+///
+#[derive(Clone)]
+pub struct Lives {
+    lives: u8, // Lives the game started with
+    left: u8,
+}
+
+impl RuleSet for Lives {
+    fn name(&self) -> String {
+        format!("lives:{}", self.lives)
+    }
+    fn copy(&self) -> Box<dyn RuleSet> {
+        Box::new(self.clone())
+    }
+    fn survive_mine(&mut self) -> bool {
+        if self.left > 1 {
+            self.left -= 1;
+            return true;
+        }
+        self.left = 0;
+        false
+    }
+    fn status(&self) -> Option<String> {
+        Some(format!("LIVES: {}", self.left))
+    }
+}
+
+///
+/// Every number is exactly one off from the truth, one way or the other
+///
+#[derive(Clone)]
+pub struct Liar {
+    lies: Vec<Vec<i16>>, // +1 or -1 for every space, indexed [y][x]
+}
+
+impl RuleSet for Liar {
+    fn name(&self) -> String {
+        String::from("liar")
+    }
+    fn copy(&self) -> Box<dyn RuleSet> {
+        Box::new(self.clone())
+    }
+    fn arrange(&mut self, width: i16, height: i16, _topology: &dyn Topology, mines: &[(i16, i16)], rng: &mut dyn RngCore) -> Vec<i16> {
+        self.lies = vec![vec![1; width as usize]; height as usize];
+        for i in 0..height {
+            for j in 0..width {
+                if rng.random_bool(0.5) {
+                    self.lies[i as usize][j as usize] = -1;
+                }
+            }
+        }
+        vec![1; mines.len()]
+    }
+    fn shown_count(&self, space: (i16, i16), count: i16) -> i16 {
+        let lie = self.lies.get(space.1 as usize).and_then(|row| row.get(space.0 as usize)).copied().unwrap_or(1);
+        // A zero can only lie upward
+        if count + lie < 0 {
+            return count + 1;
+        }
+        count + lie
+    }
+    fn standard_numbers(&self) -> bool {
+        false
+    }
+}
+
+///
+/// Some spaces hold two mines, counting twice in the numbers around them
+///
+#[derive(Clone)]
+pub struct MultiMine;
+
+impl RuleSet for MultiMine {
+    fn name(&self) -> String {
+        String::from("multi")
+    }
+    fn copy(&self) -> Box<dyn RuleSet> {
+        Box::new(self.clone())
+    }
+    fn arrange(&mut self, _width: i16, _height: i16, _topology: &dyn Topology, mines: &[(i16, i16)], rng: &mut dyn RngCore) -> Vec<i16> {
+        mines.iter().map(|_| if rng.random_ratio(1, 3) { 2 } else { 1 }).collect()
+    }
+    fn standard_numbers(&self) -> bool {
+        false
+    }
+}
+
+///
+/// Some mines are anti-mines, which take one away from the numbers around them. They still explode
+///
+#[derive(Clone)]
+pub struct AntiMine;
+
+impl RuleSet for AntiMine {
+    fn name(&self) -> String {
+        String::from("anti")
+    }
+    fn copy(&self) -> Box<dyn RuleSet> {
+        Box::new(self.clone())
+    }
+    fn arrange(&mut self, _width: i16, _height: i16, _topology: &dyn Topology, mines: &[(i16, i16)], rng: &mut dyn RngCore) -> Vec<i16> {
+        mines.iter().map(|_| if rng.random_ratio(1, 4) { -1 } else { 1 }).collect()
+    }
+    fn standard_numbers(&self) -> bool {
+        false
+    }
+}
+
+///
+/// A treasure is buried under one of the safe spaces, and uncovering it wins the game straight away
+///
+#[derive(Clone)]
+pub struct Treasure {
+    treasure: Option<(i16, i16)>,
+}
+
+impl RuleSet for Treasure {
+    fn name(&self) -> String {
+        String::from("treasure")
+    }
+    fn copy(&self) -> Box<dyn RuleSet> {
+        Box::new(self.clone())
+    }
+    fn arrange(&mut self, width: i16, height: i16, _topology: &dyn Topology, mines: &[(i16, i16)], rng: &mut dyn RngCore) -> Vec<i16> {
+        let mut is_mine = vec![vec![false; width as usize]; height as usize];
+        for mine in mines {
+            is_mine[mine.1 as usize][mine.0 as usize] = true;
+        }
+        let mut safe: Vec<(i16, i16)> = vec![];
+        for i in 0..height {
+            for j in 0..width {
+                if !is_mine[i as usize][j as usize] {
+                    safe.push((j, i));
+                }
+            }
+        }
+        self.treasure = match safe.len() {
+            0 => None,
+            n => Some(safe[rng.random_range(0..n)]),
+        };
+        vec![1; mines.len()]
+    }
+    fn wins_on(&self, space: (i16, i16)) -> bool {
+        self.treasure == Some(space)
+    }
+    fn status(&self) -> Option<String> {
+        Some(String::from("TREASURE HUNT"))
+    }
+}
+
+///
+/// The mine total is kept from the player, or only given as a range it falls somewhere in,
+/// so the board has to be cleared on its numbers alone
+///
+#[derive(Clone)]
+pub struct HiddenTotal {
+    spread: i16,               // How far apart the ends of the range are, or 0 to hide the total completely
+    range: Option<(i16, i16)>, // The range shown, once the mines are placed
+}
+
+impl RuleSet for HiddenTotal {
+    fn name(&self) -> String {
+        match self.spread {
+            0 => String::from("hidden"),
+            spread => format!("range:{}", spread),
+        }
+    }
+    fn copy(&self) -> Box<dyn RuleSet> {
+        Box::new(self.clone())
+    }
+    fn arrange(&mut self, width: i16, height: i16, _topology: &dyn Topology, mines: &[(i16, i16)], rng: &mut dyn RngCore) -> Vec<i16> {
+        if self.spread > 0 {
+            let mines = mines.len() as i16;
+            let fewest = (mines - rng.random_range(0..=self.spread)).max(0);
+            let most = fewest.saturating_add(self.spread).min(width.saturating_mul(height) - 1);
+            self.range = Some((fewest.min(mines), most.max(mines)));
+        }
+        vec![1; mines.len()]
+    }
+    fn status(&self) -> Option<String> {
+        match self.range {
+            Some((fewest, most)) => Some(format!("MINES: {}-{}", fewest, most)),
+            None => Some(String::from("MINES: ?")),
+        }
+    }
+    fn mine_range(&self, _mines: i16) -> Option<(i16, i16)> {
+        self.range
+    }
+}
+
+///
+/// A few safe spaces next to mines carry time bonuses, which take time off the clock if they're uncovered
+/// while they're up. Each one goes away for good once its window has passed
+///
+#[derive(Clone)]
+pub struct TimeBonus {
+    bonuses: Vec<((i16, i16), Duration)>, // Spaces with a bonus on them, and how far into the game each one lasts
+}
+
+impl RuleSet for TimeBonus {
+    fn name(&self) -> String {
+        String::from("bonus")
+    }
+    fn copy(&self) -> Box<dyn RuleSet> {
+        Box::new(self.clone())
+    }
+    fn arrange(&mut self, width: i16, height: i16, topology: &dyn Topology, mines: &[(i16, i16)], rng: &mut dyn RngCore) -> Vec<i16> {
+        let mut safe: Vec<(i16, i16)> = vec![];
+        let mut candidates: Vec<(i16, i16)> = vec![];
+        for i in 0..height {
+            for j in 0..width {
+                if mines.contains(&(j, i)) || !topology.contains(j, i, width, height) {
+                    continue;
+                }
+                safe.push((j, i));
+                // Spaces by a mine are numbers, so a bonus never sits in an opening the first check could give away
+                if topology.neighbors(j, i, width, height).iter().any(|n| mines.contains(n)) {
+                    candidates.push((j, i));
+                }
+            }
+        }
+        let count = (safe.len() / SPACES_PER_BONUS).clamp(1, MAX_BONUSES).min(candidates.len());
+        self.bonuses = vec![];
+        for n in 1..=count {
+            let space = candidates.swap_remove(rng.random_range(0..candidates.len()));
+            self.bonuses.push((space, BONUS_WINDOW * n as u32));
+        }
+        vec![1; mines.len()]
+    }
+    fn time_bonus(&mut self, space: (i16, i16), time: Duration) -> Duration {
+        let bonus = match self.bonuses.iter().find(|b| b.0 == space) {
+            Some(&(_, until)) if time < until => BONUS_TIME,
+            _ => Duration::ZERO,
+        };
+        self.bonuses.retain(|b| b.0 != space);
+        bonus
+    }
+    fn bonuses(&self, time: Duration) -> Vec<(i16, i16)> {
+        self.bonuses.iter().filter(|b| time < b.1).map(|b| b.0).collect()
+    }
+    fn status(&self) -> Option<String> {
+        Some(String::from("TIME BONUSES"))
+    }
+}
+
+///
+/// Makes the rules with the given name, e.g. "standard", "lives:3", "liar", "multi", "anti", "treasure", "hidden", "range:4", or "bonus".
+/// Returns `None` for names it doesn't know
+///
+pub fn from_name(name: &str) -> Option<Box<dyn RuleSet>> {
+    let (kind, arg) = match name.split_once(':') {
+        Some((kind, arg)) => (kind, Some(arg)),
+        None => (name, None),
+    };
+    match (kind, arg) {
+        ("standard", None) => Some(Box::new(Standard)),
+        ("lives", None) => Some(Box::new(Lives { lives: DEFAULT_LIVES, left: DEFAULT_LIVES })),
+        ("lives", Some(n)) => match n.parse::<u8>() {
+            Ok(lives) if lives > 0 => Some(Box::new(Lives { lives, left: lives })),
+            _ => None,
+        },
+        ("liar", None) => Some(Box::new(Liar { lies: vec![] })),
+        ("multi", None) => Some(Box::new(MultiMine)),
+        ("anti", None) => Some(Box::new(AntiMine)),
+        ("treasure", None) => Some(Box::new(Treasure { treasure: None })),
+        ("bonus", None) => Some(Box::new(TimeBonus { bonuses: vec![] })),
+        ("hidden", None) => Some(Box::new(HiddenTotal { spread: 0, range: None })),
+        ("range", Some(n)) => match n.parse::<i16>() {
+            Ok(spread) if spread > 0 => Some(Box::new(HiddenTotal { spread, range: None })),
+            _ => None,
+        },
+        _ => None,
+    }
+}
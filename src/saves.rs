@@ -1,85 +1,800 @@
-use serde::{Serialize, Deserialize};
-use std::fs;
-
-#[derive(Serialize, Deserialize)]
-pub struct Save {
-    // Statistics
-    pub g_played: u32,       // Number of games played
-    pub g_won: u32,          // Number of games won
-    pub total_playtime: u64, // Number of seconds of game played
-    pub total_clicks: u64,   // Total number of "check" / "chord" actions all time. This one is for fun
-    // Settings
-    // (ANSI color codes)
-    pub border_fg: String,       // Foreground color of map borders
-    pub border_bg: String,       // Background color of map borders
-    pub inner_fg: String,        // Foreground color of mine character and surrounding brackets
-    pub inner_highlight: String, // Foreground color for placed flags and mines exposed after loss
-    pub inner_bg: String,        // Background color of inner 
-    pub m_count_fg: Vec<String>, // Foreground color for all 8 mine counts (0 = blank)
-    // (Characters)
-    pub mine_char: String,
-    pub flag_char: String,
-    pub tile_char: String,
-    // (Gamemode)
-    // 0 - Vanilla
-    // 1 - CMD's QOL
-    // 2 - No Guessing
-    pub gamemode: u8
-}
-
-impl Save {
-    ///
-    /// Reads save data from the file `save.json`.
-    /// 
-    pub fn read_save() -> Save {
-        // Get file contents
-        let save_path = std::env::current_exe().unwrap().parent().unwrap().to_str().unwrap().to_owned();
-        let file = fs::read_to_string(format!("{}\\save.json", save_path));
-        match file {
-            Ok(_) => {}
-            Err(e) => { 
-                print!("Error while opening save file: {}\r\n", e);
-                std::process::exit(1);
-            }
-        }
-        let file_con: Result<Save, _> = serde_json::from_str(file.unwrap().as_str());
-        match file_con {
-            Ok(s) => {
-                return s;
-            }
-            Err(e) => {
-                print!("Error while opening save file: {}\r\n", e);
-                std::process::exit(1);
-            }
-        }
-    }
-    ///
-    /// Updates the stats of the Save object with those collected during the game
-    /// 
-    pub fn update_save(&mut self, won: bool, playtime: u64, clicks: u64) {
-        self.g_played += 1;
-        if won {
-            self.g_won += 1;
-        }
-        self.total_playtime += playtime;
-        self.total_clicks += clicks;
-    }
-    ///
-    /// Stores the Save data back into the file `save.json`.
-    /// 
-    pub fn write_save(&mut self) {
-        // TODO more error handling? It is a little pointless if the user <ctrl+c>'s 
-        // TODO and write errors are few and far between since we make sure the file exists
-        let new_save_data = serde_json::to_string(&self);
-        match new_save_data {
-            Ok(s) => {
-                let save_path = std::env::current_exe().unwrap().parent().unwrap().to_str().unwrap().to_owned();
-                fs::write(format!("{}\\save.json", save_path), s).ok();
-            }
-            Err(_) => {
-                // Could not write save data
-                return;
-            }
-        }
-    }
-}
\ No newline at end of file
+use minesweeper::cast::{self, Theme};
+use minesweeper::compact;
+use minesweeper::grade::Grade;
+use minesweeper::locale::Locale;
+use minesweeper::medal::Medal;
+use minesweeper::migrate::Imported;
+use minesweeper::replay::Replay;
+use minesweeper::snapshot::Snapshot;
+
+use serde::{Serialize, Deserialize};
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, SyncSender};
+use std::thread;
+use std::time::{Duration, Instant};
+
+///
+/// Stats kept for a single game category (beginner, expert, surprise, etc.)
+/// 
+#[derive(Serialize, Deserialize, Default)]
+pub struct CategoryStats {
+    pub g_played: u32,       // Number of games played in this category
+    pub g_won: u32,          // Number of games won in this category
+    pub total_playtime: u64, // Number of seconds played in this category
+    #[serde(default)]
+    pub best_time_ms: Option<u64>, // Fastest win in this category, in milliseconds
+}
+
+///
+/// Stats on guessing, to tell games won by logic from games won by luck.
+/// Only games the solver can read are counted, since it decides what was a guess
+///
+#[derive(Serialize, Deserialize, Default)]
+pub struct GuessStats {
+    pub g_played: u32,          // Number of games counted
+    pub g_won: u32,             // Number of those games won
+    pub zero_guess_wins: u32,   // Wins without a single guess
+    pub guesses: u64,           // Guesses made across every game counted
+    pub forced: u64,            // Guesses made when nothing on the board could be proven
+    pub survived: u64,          // Guesses that didn't hit a mine
+}
+
+impl GuessStats {
+    ///
+    /// Records a finished game's guesses
+    ///
+    pub fn update(&mut self, won: bool, guesses: u32, forced: u32, survived: u32) {
+        self.g_played += 1;
+        if won {
+            self.g_won += 1;
+            if guesses == 0 {
+                self.zero_guess_wins += 1;
+            }
+        }
+        self.guesses += guesses as u64;
+        self.forced += forced as u64;
+        self.survived += survived as u64;
+    }
+}
+
+///
+/// Stats for golf mode, where each board's par is its 3BV
+/// 
+#[derive(Serialize, Deserialize, Default)]
+pub struct GolfStats {
+    pub g_played: u32,        // Number of golf rounds played, including losses
+    pub g_finished: u32,      // Number of golf rounds won
+    pub under_par: u32,       // Rounds finished under par
+    pub at_par: u32,          // Rounds finished at par
+    pub over_par: u32,        // Rounds finished over par
+    pub total_vs_par: i64,    // Sum of strokes over (+) or under (-) par for finished rounds
+    pub best_vs_par: Option<i64>, // Best finished round relative to par
+}
+
+impl GolfStats {
+    ///
+    /// Records a finished round that was `diff` strokes over (or under, if negative) par
+    /// 
+    pub fn update(&mut self, diff: i64) {
+        self.g_played += 1;
+        self.g_finished += 1;
+        if diff < 0 {
+            self.under_par += 1;
+        } else if diff == 0 {
+            self.at_par += 1;
+        } else {
+            self.over_par += 1;
+        }
+        self.total_vs_par += diff;
+        if self.best_vs_par.is_none() || diff < self.best_vs_par.unwrap() {
+            self.best_vs_par = Some(diff);
+        }
+    }
+}
+
+///
+/// Par time medals won, for one category
+///
+#[derive(Serialize, Deserialize, Default)]
+pub struct MedalStats {
+    pub bronze: u32,
+    pub silver: u32,
+    pub gold: u32,
+}
+
+impl MedalStats {
+    ///
+    /// Records a medal won
+    ///
+    pub fn update(&mut self, medal: Medal) {
+        match medal {
+            Medal::Bronze => self.bronze += 1,
+            Medal::Silver => self.silver += 1,
+            Medal::Gold => self.gold += 1,
+        }
+    }
+}
+
+///
+/// A win on a high score table
+///
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct RecordEntry {
+    pub time_ms: u64,
+    pub date: String, // When it was won, as YYYY-MM-DD (UTC)
+    pub bbbv: u32,    // The board's 3BV, which says how much work the time was for
+}
+
+///
+/// A finished game's result, as it's written to the journal. Holds everything a game adds to the stats,
+/// so the save can be brought up to date from the journal alone
+///
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct GameRecord {
+    #[serde(default)]
+    pub seq: u64,                  // Numbers the records in the order they were written, so none is counted twice
+    pub category: String,
+    pub won: bool,
+    pub time_ms: u64,
+    pub clicks: u64,
+    #[serde(default)]
+    pub grade: Option<Grade>,
+    #[serde(default)]
+    pub start: Option<String>,     // Where the first check was, if the player picked it
+    #[serde(default)]
+    pub guesses: Option<[u32; 3]>, // Guesses, forced guesses, and guesses survived, in games the solver could follow
+    #[serde(default)]
+    pub golf_par: Option<u32>,     // The board's par, in golf rounds
+    #[serde(default)]
+    pub medal: Option<Medal>,
+    #[serde(default)]
+    pub board: Option<String>,     // The high score table a win goes on: a preset, or a custom size
+    #[serde(default)]
+    pub bbbv: u32,
+    #[serde(default)]
+    pub date: String,              // When the game ended, as YYYY-MM-DD (UTC)
+}
+
+///
+/// The settings the last game from the menu was started with, so it can be played again in one keypress
+///
+#[derive(Serialize, Deserialize, Clone)]
+pub struct LastGame {
+    pub category: String, // Menu category, e.g. "intermediate" or "custom"
+    pub width: i16,
+    pub height: i16,
+    pub mines: i16,
+    pub gamemode: u8,
+    pub rules: String,
+    pub topology: String,
+    #[serde(default)]
+    pub cursor: Option<(i16, i16)>, // Where the cursor was left, so playing the same size board again starts there
+}
+
+impl std::fmt::Display for LastGame {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
+        match self.category.as_str() {
+            "custom" => write!(f, "custom {}x{}, {} mines", self.width, self.height, self.mines),
+            category => write!(f, "{}", category),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Save {
+    // Statistics
+    pub g_played: u32,       // Number of games played
+    pub g_won: u32,          // Number of games won
+    pub total_playtime: u64, // Number of seconds of game played
+    pub total_clicks: u64,   // Total number of "check" / "chord" actions all time. This one is for fun
+    #[serde(default)]
+    pub category_stats: HashMap<String, CategoryStats>, // Per-category history, keyed by category name
+    #[serde(default)]
+    pub grade_stats: HashMap<String, CategoryStats>,    // Per-difficulty history, keyed by the solver's grade of the board
+    #[serde(default)]
+    pub start_stats: HashMap<String, CategoryStats>,    // Per-opening history, keyed by where the first check was: corner, edge, or middle
+    #[serde(default)]
+    pub practice_stats: HashMap<String, CategoryStats>, // Per-position practice history, keyed by a hash of the position
+    #[serde(default)]
+    pub journal_seq: u64,    // The last journal record counted in this file
+    // Settings
+    // (ANSI color codes)
+    pub border_fg: String,       // Foreground color of map borders
+    pub border_bg: String,       // Background color of map borders
+    pub inner_fg: String,        // Foreground color of mine character and surrounding brackets
+    pub inner_highlight: String, // Foreground color for placed flags and mines exposed after loss
+    pub inner_bg: String,        // Background color of inner 
+    pub m_count_fg: Vec<String>, // Foreground color for all 8 mine counts (0 = blank)
+    #[serde(default)]
+    pub m_count_chars: Vec<String>, // Characters for the mine counts 1 to 8, each one column wide, or empty for digits
+    #[serde(default)]
+    pub flag_bg: String,         // Background color of flagged spaces, or empty for the same as `inner_bg`
+    #[serde(default = "default_revealed")]
+    pub revealed: String,        // Colors of uncovered spaces' brackets and background
+    // (Characters)
+    pub mine_char: String,
+    pub flag_char: String,
+    pub tile_char: String,
+    #[serde(default = "default_empty_char")]
+    pub empty_char: String, // Shown on uncovered spaces with no number
+    #[serde(default = "default_bonus_char")]
+    pub bonus_char: String, // Shown on covered spaces with a time bonus still up, with the `bonus` rules
+    #[serde(default = "cast::default_number_brackets")]
+    pub number_brackets: Vec<String>, // Bracket pairs for the numbers 1 to 8, used when colors are off or `number_marks` is set
+    #[serde(default)]
+    pub number_marks: bool, // Put each number between its own brackets even with colors on
+    // (Timer)
+    // 0 - Counts up
+    // 1 - Counts down from `timer_target`
+    // 2 - Hidden until the game ends
+    #[serde(default)]
+    pub timer_mode: u8,
+    #[serde(default = "default_timer_target")]
+    pub timer_target: u64, // Number of seconds the countdown timer starts from
+    // (Locale)
+    #[serde(default)]
+    pub locale: String,      // How numbers are written, e.g. "de" or "fr_FR", or empty to go by the environment
+    #[serde(default = "default_time_format")]
+    pub time_format: String, // Times as "seconds" (65.3s) or on a "clock" (1:05.3)
+    // (Accessibility)
+    #[serde(default)]
+    pub reduced_motion: bool, // Turn off animations, like the spinner while a no guessing board is generated
+    // (Large print)
+    // 0 - Off
+    // 2 - Spaces drawn 2 rows tall and 6 columns wide, with big digits
+    // 3 - Spaces drawn 3 rows tall and 9 columns wide, with bigger digits
+    #[serde(default)]
+    pub large_print: u8,
+    // (Coordinates)
+    // 0 - Hidden
+    // 1 - Columns lettered and rows numbered, with row 1 at the top
+    // 2 - Columns lettered and rows numbered, with row 1 at the bottom
+    #[serde(default)]
+    pub coordinates: u8,
+    // (Gamemode)
+    // 0 - Vanilla
+    // 1 - CMD's QOL
+    // 2 - No Guessing
+    pub gamemode: u8,
+    #[serde(default)]
+    pub hard_boards: bool, // Prefer boards that need advanced deductions (but never a guess), in gamemodes 0 and 1
+    #[serde(default)]
+    pub opening_min: u16,  // Fewest spaces the first check should uncover, or 0 for no minimum
+    #[serde(default)]
+    pub opening_max: u16,  // Most spaces the first check should uncover, or 0 for no maximum
+    #[serde(default = "default_rules")]
+    pub rules: String,     // Variant rules, e.g. "standard", "lives:3", "liar", "multi", "anti", "treasure", "hidden", "range:4"
+    #[serde(default = "default_topology")]
+    pub topology: String,  // Board shape: "square8", "square4", "hex", "knight", "torus", or "masked"
+    #[serde(default = "default_rng")]
+    pub rng: String,       // Generator for boards without a chosen seed: "os" (rand's StdRng, seeded by the OS) or "pcg" (stable, so seeds can be shared)
+    // (QOL)
+    #[serde(default)]
+    pub combined_chord: bool, // Chord with the check key instead of a separate one
+    #[serde(default)]
+    pub safe_chord: bool, // Block chords when the solver can prove one of the surrounding flags is wrong
+    #[serde(default)]
+    pub pre_start_flags: bool, // Allow placing flags before the first check
+    #[serde(default)]
+    pub pre_flags_safe: bool,  // Keep spaces flagged before the first check free of mines
+    #[serde(default)]
+    pub flag_colors: Vec<String>, // Colors of extra kinds of flag, e.g. ["33"] for a yellow "suspected" flag. Flagging a flag again moves it to the next kind
+    #[serde(default = "default_question_marks")]
+    pub question_marks: bool, // Taking a flag off with the flag key leaves a question mark, which the next press clears
+    #[serde(default = "default_flag_debounce")]
+    pub flag_debounce_ms: u64, // Toggles of the same flag closer together than this are ignored as key bounce
+    #[serde(default)]
+    pub detailed_counters: bool, // Show flags and question marks placed next to the flags left, and point out misflags in gamemode 1
+    #[serde(default = "default_undo")]
+    pub undo: u8, // 0 = no undo, 1 = flags and checks can be taken back, 2 = mine hits too, in games that don't count
+    #[serde(default)]
+    pub keys: HashMap<String, String>, // Keys bound to "check", "flag", "chord", "up", "down", "left", "right", "reset", or "menu" on top of the usual ones, e.g. "j" or "space"
+    #[serde(default)]
+    pub vim_keys: bool, // h, j, k, and l move, and y, u, b, and n move diagonally, with the keys they take over moved to K, B, Y, and z
+    // (Scoring)
+    #[serde(default = "default_stats")]
+    pub stats: bool,           // Count finished games in the stats and show them on the menu
+    #[serde(default)]
+    pub golf: bool,            // Golf mode: finish each board in as few clicks as possible relative to its 3BV
+    #[serde(default)]
+    pub golf_stats: GolfStats, // Golf rounds are tracked separately from the normal stats
+    #[serde(default)]
+    pub guess_stats: GuessStats, // How often the player had to guess, in games the solver could follow
+    #[serde(default)]
+    pub metrics: Vec<String>,     // Stats shown after a win: "3bv", "3bv/s", "clicks", and "efficiency"
+    #[serde(default)]
+    pub medal_stats: HashMap<String, MedalStats>, // Par time medals won on the preset boards, keyed by category
+    #[serde(default)]
+    pub best_times: HashMap<String, Vec<RecordEntry>>, // Fastest wins, best first, keyed by preset ("beginner") or custom size ("20x20, 50 mines")
+    // (Network)
+    #[serde(default)]
+    pub relay: String, // Relay ("host:port") that hosts get lobby codes from and codes are joined through, or empty to host directly
+    #[serde(default)]
+    pub player_name: String, // Name to go on server leaderboards under, or empty to let the server pick one
+    #[serde(default)]
+    pub warmup: bool,        // Play a 5x5 throwaway board before joining a race or the daily board
+    // (Updates)
+    #[serde(default)]
+    pub update_check: bool, // Look for a newer release once a day and say so on the menu. Needs the `update-check` feature
+    #[serde(default = "default_update_url")]
+    pub update_url: String, // Where to ask for the latest release, answering like GitHub's latest release API
+    // (Menu)
+    #[serde(default)]
+    pub last_game: Option<LastGame>, // The last game started from the menu, for playing again with the same settings
+}
+
+// Wins kept on each high score table
+pub const BEST_TIMES: usize = 10;
+// Games journaled between writes of the whole save
+const JOURNAL_COMPACT_EVERY: u64 = 10;
+// Shortest time between two writes of the save by the writer thread. Saves asked for sooner are held, and only the newest is written
+const SAVE_INTERVAL: Duration = Duration::from_secs(2);
+// Writes that can wait on the writer thread before the game has to wait for it too
+const WRITE_QUEUE: usize = 64;
+
+// Where writes go while the writer thread is running. Without it, they're done straight away
+static WRITER: Mutex<Option<SyncSender<DiskWrite>>> = Mutex::new(None);
+
+///
+/// A write for the writer thread to do
+///
+enum DiskWrite {
+    Save { contents: String, seq: u64 }, // The whole save, and the last journal record it counts
+    Journal { line: String, seq: u64 },  // A record to append to the journal
+    File { path: PathBuf, contents: Vec<u8> },
+    Flush(mpsc::Sender<()>),             // Finish everything asked for so far, then answer
+}
+
+///
+/// Keeps the save, journal, replay, and snapshot writes off the game's thread while it's alive, so a slow disk
+/// (a network home folder, an SD card) can't hold up keys or frames. Whatever is still waiting is written out when it's dropped
+///
+pub struct Writer {
+    handle: Option<thread::JoinHandle<()>>,
+}
+
+impl Writer {
+    ///
+    /// Starts the writer thread. Writes are sent to it from here on
+    ///
+    pub fn start() -> Writer {
+        let (sender, receiver) = mpsc::sync_channel(WRITE_QUEUE);
+        let handle = thread::Builder::new().name(String::from("writer")).spawn(move || run_writer(receiver)).ok();
+        if handle.is_some() {
+            *WRITER.lock().unwrap() = Some(sender);
+        }
+        Writer { handle }
+    }
+}
+
+impl Drop for Writer {
+    fn drop(&mut self) {
+        // Hanging up lets the thread finish what it has and stop
+        WRITER.lock().unwrap().take();
+        if let Some(handle) = self.handle.take() {
+            handle.join().ok();
+        }
+    }
+}
+
+///
+/// Waits for every write asked for so far to be done, so the files on disk are up to date
+///
+pub fn flush() {
+    let sender = WRITER.lock().unwrap().clone();
+    if let Some(sender) = sender {
+        let (done, finished) = mpsc::channel();
+        if sender.send(DiskWrite::Flush(done)).is_ok() {
+            finished.recv().ok();
+        }
+    }
+}
+
+///
+/// Hands a write to the writer thread, or does it now if there isn't one
+///
+fn submit(write: DiskWrite) {
+    let sender = WRITER.lock().unwrap().clone();
+    let write = match sender {
+        Some(sender) => match sender.send(write) {
+            Ok(()) => return,
+            Err(mpsc::SendError(write)) => write,
+        },
+        None => write,
+    };
+    match write {
+        DiskWrite::Save { contents, .. } => store_save(&contents, true),
+        DiskWrite::Journal { line, .. } => append_journal(&line),
+        DiskWrite::File { path, contents } => write_file(&path, &contents),
+        DiskWrite::Flush(done) => {
+            done.send(()).ok();
+        }
+    }
+}
+
+///
+/// The writer thread: journal lines and files are written as they come, and the save at most once every `SAVE_INTERVAL`
+///
+fn run_writer(writes: Receiver<DiskWrite>) {
+    let mut pending: Option<(String, u64)> = None;
+    let mut last_save: Option<Instant> = None;
+    let mut journaled: u64 = 0; // The last journal record appended
+    loop {
+        let write = match (&pending, last_save) {
+            (Some(_), Some(last)) => match writes.recv_timeout(SAVE_INTERVAL.saturating_sub(last.elapsed())) {
+                Ok(write) => Some(write),
+                Err(RecvTimeoutError::Timeout) => None,
+                Err(RecvTimeoutError::Disconnected) => break,
+            },
+            _ => match writes.recv() {
+                Ok(write) => Some(write),
+                Err(_) => break,
+            },
+        };
+        let mut flushed = None;
+        match write {
+            Some(DiskWrite::Save { contents, seq }) => pending = Some((contents, seq)),
+            Some(DiskWrite::Journal { line, seq }) => {
+                append_journal(&line);
+                journaled = journaled.max(seq);
+            }
+            Some(DiskWrite::File { path, contents }) => write_file(&path, &contents),
+            Some(DiskWrite::Flush(done)) => flushed = Some(done),
+            None => {}
+        }
+        let due = last_save.is_none_or(|last| last.elapsed() >= SAVE_INTERVAL);
+        if (due || flushed.is_some()) && let Some((contents, seq)) = pending.take() {
+            // Records appended after this save was made aren't in it, so the journal has to stay for them
+            store_save(&contents, journaled <= seq);
+            last_save = Some(Instant::now());
+        }
+        if let Some(done) = flushed {
+            done.send(()).ok();
+        }
+    }
+    if let Some((contents, seq)) = pending {
+        store_save(&contents, journaled <= seq);
+    }
+}
+
+///
+/// Writes `save.json` next to the old one and then swaps it in, so a crash partway through leaves the old one whole.
+/// The journal is cleared afterwards if everything in it is in this save
+///
+fn store_save(contents: &str, clear_journal: bool) {
+    let save_path = std::env::current_exe().unwrap().parent().unwrap().to_str().unwrap().to_owned();
+    let temp_path = format!("{}\\save.json.tmp", save_path);
+    let written = fs::File::create(&temp_path).and_then(|mut file| {
+        file.write_all(contents.as_bytes())?;
+        file.sync_all()
+    });
+    if written.is_ok() && fs::rename(&temp_path, format!("{}\\save.json", save_path)).is_ok() && clear_journal {
+        // Records the save already counted are skipped by their numbers, so a crash before this is harmless
+        fs::remove_file(journal_path()).ok();
+    }
+}
+
+///
+/// Appends a line to the journal, making sure it's on disk before going on
+///
+fn append_journal(line: &str) {
+    if let Ok(mut journal) = OpenOptions::new().create(true).append(true).open(journal_path()) && journal.write_all(format!("{}\n", line).as_bytes()).is_ok() {
+        journal.sync_data().ok();
+    }
+}
+
+///
+/// Writes a replay or snapshot, making its folder if it isn't there yet
+///
+fn write_file(path: &PathBuf, contents: &[u8]) {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).ok();
+    }
+    fs::write(path, contents).ok();
+}
+
+fn default_timer_target() -> u64 {
+    300
+}
+
+fn default_stats() -> bool {
+    true
+}
+
+fn default_question_marks() -> bool {
+    true
+}
+
+fn default_flag_debounce() -> u64 {
+    150
+}
+
+fn default_undo() -> u8 {
+    1
+}
+
+fn default_time_format() -> String {
+    String::from("seconds")
+}
+
+fn default_revealed() -> String {
+    String::from("0;30")
+}
+
+fn default_empty_char() -> String {
+    String::from(" ")
+}
+
+fn default_bonus_char() -> String {
+    String::from("✦")
+}
+
+fn default_rules() -> String {
+    String::from("standard")
+}
+
+fn default_topology() -> String {
+    String::from("square8")
+}
+
+fn default_rng() -> String {
+    String::from("os")
+}
+
+fn default_update_url() -> String {
+    String::from("https://api.github.com/repos/cmdprompt117/minesweeper/releases/latest")
+}
+
+impl Save {
+    ///
+    /// Reads save data from the file `save.json`.
+    /// 
+    pub fn read_save() -> Save {
+        // Anything still on its way to the file has to get there first
+        flush();
+        // Get file contents
+        let save_path = std::env::current_exe().unwrap().parent().unwrap().to_str().unwrap().to_owned();
+        let file = fs::read_to_string(format!("{}\\save.json", save_path));
+        match file {
+            Ok(_) => {}
+            Err(e) => { 
+                print!("Error while opening save file: {}\r\n", e);
+                std::process::exit(1);
+            }
+        }
+        let file_con: Result<Save, _> = serde_json::from_str(file.unwrap().as_str());
+        match file_con {
+            Ok(mut s) => {
+                // Games that ended after the file was last written are still in the journal
+                s.replay_journal();
+                s
+            }
+            Err(e) => {
+                print!("Error while opening save file: {}\r\n", e);
+                std::process::exit(1);
+            }
+        }
+    }
+    ///
+    /// Records a finished game: counts it in the stats, and appends it to the journal straight away,
+    /// so a crash before the save is next written doesn't lose it
+    ///
+    pub fn record_game(&mut self, mut record: GameRecord) {
+        // With stats off, games aren't counted at all
+        if !self.stats {
+            return;
+        }
+        record.seq = self.journal_seq + 1;
+        self.apply_record(&record);
+        let line = match serde_json::to_string(&record) {
+            Ok(line) => line,
+            Err(_) => return,
+        };
+        submit(DiskWrite::Journal { line, seq: record.seq });
+        // Every so often the journal is folded into the save, so it doesn't grow for good between the save's other writes
+        if record.seq.is_multiple_of(JOURNAL_COMPACT_EVERY) {
+            self.write_save();
+        }
+    }
+    ///
+    /// Counts the games in the journal that this save doesn't have yet. A line cut off by a crash is skipped
+    ///
+    fn replay_journal(&mut self) {
+        let journal = match fs::read_to_string(journal_path()) {
+            Ok(journal) => journal,
+            Err(_) => return,
+        };
+        for line in journal.lines() {
+            if let Ok(record) = serde_json::from_str::<GameRecord>(line) && record.seq > self.journal_seq {
+                self.apply_record(&record);
+            }
+        }
+    }
+    ///
+    /// Updates the stats of the Save object with those collected during a game
+    /// 
+    fn apply_record(&mut self, record: &GameRecord) {
+        let won = record.won;
+        let playtime = record.time_ms / 1000;
+        self.g_played += 1;
+        if won {
+            self.g_won += 1;
+        }
+        self.total_playtime += playtime;
+        self.total_clicks += record.clicks;
+        // Also track the game under its category
+        let stats = self.category_stats.entry(record.category.clone()).or_default();
+        stats.g_played += 1;
+        if won {
+            stats.g_won += 1;
+            stats.best_time_ms = Some(stats.best_time_ms.map_or(record.time_ms, |best| best.min(record.time_ms)));
+        }
+        stats.total_playtime += playtime;
+        // And under the board's grade, if it was graded
+        if let Some(grade) = record.grade {
+            let stats = self.grade_stats.entry(grade.to_string()).or_default();
+            stats.g_played += 1;
+            if won {
+                stats.g_won += 1;
+            }
+            stats.total_playtime += playtime;
+        }
+        // And under where it was started from, if the player picked that
+        if let Some(start) = &record.start {
+            let stats = self.start_stats.entry(start.clone()).or_default();
+            stats.g_played += 1;
+            if won {
+                stats.g_won += 1;
+            }
+            stats.total_playtime += playtime;
+        }
+        if let Some([guesses, forced, survived]) = record.guesses {
+            self.guess_stats.update(won, guesses, forced, survived);
+        }
+        // Lost golf rounds don't get a score, but still count as played
+        if let Some(par) = record.golf_par {
+            if won {
+                self.golf_stats.update(record.clicks as i64 - par as i64);
+            } else {
+                self.golf_stats.g_played += 1;
+            }
+        }
+        if let Some(medal) = record.medal {
+            self.medal_stats.entry(record.category.clone()).or_default().update(medal);
+        }
+        if let Some(board) = record.board.as_ref().filter(|_| won) {
+            let entries = self.best_times.entry(board.clone()).or_default();
+            // Ties go to whoever got there first
+            let at = entries.partition_point(|entry| entry.time_ms <= record.time_ms);
+            entries.insert(at, RecordEntry { time_ms: record.time_ms, date: record.date.clone(), bbbv: record.bbbv });
+            entries.truncate(BEST_TIMES);
+        }
+        self.journal_seq = self.journal_seq.max(record.seq);
+    }
+    ///
+    /// The save's characters and colors as a theme, e.g. for drawing replays the way the game looks
+    ///
+    pub fn theme(&self) -> Theme {
+        Theme {
+            tile_char: self.tile_char.clone(),
+            flag_char: self.flag_char.clone(),
+            mine_char: self.mine_char.clone(),
+            border_fg: self.border_fg.clone(),
+            border_bg: self.border_bg.clone(),
+            inner_fg: self.inner_fg.clone(),
+            inner_bg: self.inner_bg.clone(),
+            inner_highlight: self.inner_highlight.clone(),
+            m_count_fg: self.m_count_fg.clone(),
+            m_count_chars: self.m_count_chars.clone(),
+            flag_bg: self.flag_bg().to_owned(),
+            revealed: self.revealed.clone(),
+            empty_char: self.empty_char.clone(),
+            number_brackets: self.number_brackets.clone(),
+        }
+    }
+    ///
+    /// Switches the save's characters and colors over to a theme's
+    ///
+    pub fn set_theme(&mut self, theme: Theme) {
+        self.tile_char = theme.tile_char;
+        self.flag_char = theme.flag_char;
+        self.mine_char = theme.mine_char;
+        self.border_fg = theme.border_fg;
+        self.border_bg = theme.border_bg;
+        self.inner_fg = theme.inner_fg;
+        self.inner_bg = theme.inner_bg;
+        self.inner_highlight = theme.inner_highlight;
+        self.m_count_fg = theme.m_count_fg;
+        self.m_count_chars = theme.m_count_chars;
+        self.flag_bg = theme.flag_bg;
+        self.revealed = theme.revealed;
+        self.empty_char = theme.empty_char;
+        self.number_brackets = theme.number_brackets;
+    }
+    ///
+    /// How numbers and times are written out, by the save's locale (or the environment's) and time format
+    ///
+    pub fn locale(&self) -> Locale {
+        let locale = if self.locale.is_empty() { Locale::from_env() } else { Locale::from_name(&self.locale) };
+        Locale { clock: self.time_format == "clock", ..locale }
+    }
+    ///
+    /// Background color of flagged spaces
+    ///
+    pub fn flag_bg(&self) -> &str {
+        if self.flag_bg.is_empty() { &self.inner_bg } else { &self.flag_bg }
+    }
+    ///
+    /// Takes on settings brought over from another client. Keys and colors replace whatever the save had
+    /// for the same thing, and stats to show are added to the ones already shown
+    ///
+    pub fn import(&mut self, imported: &Imported) {
+        for (action, key) in &imported.keys {
+            self.keys.insert(action.clone(), key.clone());
+        }
+        for (field, code) in &imported.colors {
+            match field.as_str() {
+                "border_fg" => self.border_fg = code.clone(),
+                "inner_bg" => self.inner_bg = code.clone(),
+                "inner_highlight" => self.inner_highlight = code.clone(),
+                _ => {
+                    // Number colors are "m_count_fg1" to "m_count_fg8"
+                    let n = field.strip_prefix("m_count_fg").and_then(|n| n.parse::<usize>().ok());
+                    if let Some(color) = n.and_then(|n| self.m_count_fg.get_mut(n.wrapping_sub(1))) {
+                        *color = code.clone();
+                    }
+                }
+            }
+        }
+        for metric in &imported.metrics {
+            if !self.metrics.contains(metric) {
+                self.metrics.push(metric.clone());
+            }
+        }
+    }
+    ///
+    /// Stores the Save data back into the file `save.json`, which takes in everything from the journal, so it's cleared.
+    /// With the writer thread running, the write happens there, and saves made in quick succession are only written once
+    ///
+    pub fn write_save(&mut self) {
+        if let Ok(contents) = serde_json::to_string(&self) {
+            submit(DiskWrite::Save { contents, seq: self.journal_seq });
+        }
+    }
+}
+///
+/// Where finished games are journaled until the save is next written
+///
+fn journal_path() -> String {
+    let save_path = std::env::current_exe().unwrap().parent().unwrap().to_str().unwrap().to_owned();
+    format!("{}\\journal.jsonl", save_path)
+}
+///
+/// Writes a finished game's replay into the `replays` folder next to the executable, packed as an `.msr` file and named
+/// after when the game ended.
+/// Returns where it's written to, or `None` if there's nowhere to put it
+///
+pub fn write_replay(replay: &Replay) -> Option<PathBuf> {
+    write_game_file("replays", "msr", &compact::encode(replay))
+}
+///
+/// Writes a lost game's snapshot into the `snapshots` folder next to the executable, the same way as replays
+///
+pub fn write_snapshot(snapshot: &Snapshot) -> Option<PathBuf> {
+    write_game_file("snapshots", "json", serde_json::to_string(snapshot).ok()?.as_bytes())
+}
+///
+/// Writes a file into a folder next to the executable, named after the current time.
+/// Returns where it goes; with the writer thread running, it gets there a moment later
+///
+fn write_game_file(folder: &str, extension: &str, contents: &[u8]) -> Option<PathBuf> {
+    let dir = std::env::current_exe().ok()?.parent()?.join(folder);
+    let ended = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).ok()?.as_millis();
+    let path = dir.join(format!("{}.{}", ended, extension));
+    submit(DiskWrite::File { path: path.clone(), contents: contents.to_vec() });
+    Some(path)
+}
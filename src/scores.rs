@@ -0,0 +1,78 @@
+//! The high scores screen from the main menu: the fastest wins on each preset board, and on each custom size
+//! that's been won, a table at a time.
+
+use crate::saves::Save;
+use crate::term;
+
+use crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use std::io::Write;
+use std::time::Duration;
+
+// The preset boards, which always get a table, even before they've been won
+const PRESETS: [&str; 3] = ["beginner", "intermediate", "expert"];
+
+///
+/// Shows the high score tables, one at a time. Left and right go through them, and Esc goes back to the menu
+///
+pub(crate) fn show(save: &Save) -> Result<(), std::io::Error> {
+    let boards = boards(save);
+    let locale = save.locale();
+    let mut page: usize = 0;
+    loop {
+        let board = &boards[page];
+        term::clear();
+        print!("High scores - {} ({} of {})\r\n\r\n", board, page + 1, boards.len());
+        let entries = save.best_times.get(board).map(Vec::as_slice).unwrap_or(&[]);
+        if entries.is_empty() {
+            print!("No wins yet\r\n");
+        } else {
+            print!("{:>3}  {:<12} {:>5} {:>7}  Date\r\n", "#", "Time", "3BV", "3BV/s");
+            for (i, entry) in entries.iter().enumerate() {
+                let time = Duration::from_millis(entry.time_ms);
+                let speed = if entry.time_ms > 0 { locale.decimal(entry.bbbv as f64 / time.as_secs_f64(), 2) } else { String::from("-") };
+                print!("{:>3}  {:<12} {:>5} {:>7}  {}\r\n", i + 1, locale.time(time, 3), entry.bbbv, speed, entry.date);
+            }
+        }
+        if !save.stats {
+            print!("\r\nStats are off in the save, so new wins aren't added\r\n");
+        }
+        print!("\r\nleft/right - other boards | Esc - back\r\n");
+        std::io::stdout().flush()?;
+        let key = match event::read()? {
+            Event::Key(key_event) if key_event.kind == KeyEventKind::Press => key_event.code,
+            _ => continue,
+        };
+        match key {
+            KeyCode::Left => page = page.checked_sub(1).unwrap_or(boards.len() - 1),
+            KeyCode::Right | KeyCode::Tab => page = (page + 1) % boards.len(),
+            KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('m') => break,
+            _ => {}
+        }
+    }
+    term::clear();
+    Ok(())
+}
+
+///
+/// Every table to show: the presets first, then the others that have wins, smallest board first
+///
+fn boards(save: &Save) -> Vec<String> {
+    let mut others: Vec<&String> = save.best_times.iter()
+        .filter(|(board, entries)| !PRESETS.contains(&board.as_str()) && !entries.is_empty())
+        .map(|(board, _)| board)
+        .collect();
+    others.sort_by_key(|board| (size(board), board.to_string()));
+    PRESETS.iter().map(|board| board.to_string()).chain(others.into_iter().cloned()).collect()
+}
+
+///
+/// How many spaces and mines a custom size's board has, going by its name ("20x20, 50 mines"), for putting them
+/// in order. Presets with bonuses come before every custom size
+///
+fn size(board: &str) -> (u32, u32) {
+    let numbers: Vec<u32> = board.split(|c: char| !c.is_ascii_digit()).filter_map(|n| n.parse().ok()).collect();
+    match numbers.as_slice() {
+        [width, height, mines, ..] => (width * height, *mines),
+        _ => (0, 0),
+    }
+}
@@ -0,0 +1,151 @@
+//! User scripts that react to game events. Scripts are `.rhai` files in a `scripts` folder next to the binary,
+//! and can define any of these handlers, each taking a map of the event's fields:
+//!
+//! - `on_start(ev)`: width, height, mines
+//! - `on_check(ev)` / `on_chord(ev)`: x, y, opened, safe_left, time_ms
+//! - `on_flag(ev)` / `on_unflag(ev)`: x, y, flags_left, time_ms
+//! - `on_win(ev)` / `on_loss(ev)`: time_ms, clicks
+//!
+//! Coordinates count from 1, like the game shows them. Handlers can only call `message(text)` (or `print`)
+//! to show a line under the board and `beep()`; they can't touch the board or the filesystem.
+//! Each script gets a map as `this` that lasts for the whole session, for keeping count of things:
+//!
+//! ```text
+//! fn on_check(ev) { if ev.safe_left == 10 { beep(); message("10 safe spaces left"); } }
+//! ```
+
+#[cfg(feature = "scripting")]
+use rhai::{AST, CallFnOptions, Dynamic, Engine, Map, Scope};
+#[cfg(feature = "scripting")]
+use std::cell::RefCell;
+#[cfg(feature = "scripting")]
+use std::rc::Rc;
+
+// Most operations a single handler call may run before it gets cut off
+#[cfg(feature = "scripting")]
+const MAX_OPERATIONS: u64 = 1_000_000;
+
+///
+/// Something a script asked the game to do
+///
+#[derive(PartialEq, Clone, Debug)]
+#[cfg_attr(not(feature = "scripting"), allow(dead_code))]
+pub(crate) enum ScriptOutput {
+    Message(String),
+    Beep,
+}
+
+///
+/// Every script that loaded, along with the engine they share
+///
+#[cfg(feature = "scripting")]
+struct Scripts {
+    engine: Engine,
+    scripts: Vec<Script>,
+    output: Rc<RefCell<Vec<ScriptOutput>>>, // Filled by `message` / `beep` while a handler runs
+}
+
+///
+/// A single loaded script
+///
+#[cfg(feature = "scripting")]
+struct Script {
+    name: String,
+    ast: AST,
+    this: Dynamic, // Kept between calls, so scripts can track things across events and games
+}
+
+#[cfg(feature = "scripting")]
+thread_local! {
+    // Loaded on the first event, then kept for the rest of the session
+    static SCRIPTS: RefCell<Option<Scripts>> = const { RefCell::new(None) };
+}
+
+#[cfg(feature = "scripting")]
+impl Scripts {
+    ///
+    /// Compiles every script in the `scripts` folder next to the binary. Scripts that fail to compile
+    /// are skipped, with the error shown as a message
+    ///
+    fn load() -> (Scripts, Vec<ScriptOutput>) {
+        let output: Rc<RefCell<Vec<ScriptOutput>>> = Rc::new(RefCell::new(vec![]));
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_OPERATIONS);
+        let sink = Rc::clone(&output);
+        engine.register_fn("message", move |text: &str| sink.borrow_mut().push(ScriptOutput::Message(text.to_owned())));
+        let sink = Rc::clone(&output);
+        engine.register_fn("beep", move || sink.borrow_mut().push(ScriptOutput::Beep));
+        // Printing straight to the terminal would draw over the board
+        let sink = Rc::clone(&output);
+        engine.on_print(move |text| sink.borrow_mut().push(ScriptOutput::Message(text.to_owned())));
+        engine.on_debug(|_, _, _| {});
+
+        let mut errors: Vec<ScriptOutput> = vec![];
+        let mut scripts: Vec<Script> = vec![];
+        let dir = std::env::current_exe().ok().and_then(|exe| exe.parent().map(|p| p.join("scripts")));
+        let mut paths: Vec<std::path::PathBuf> = match dir.and_then(|d| std::fs::read_dir(d).ok()) {
+            Some(entries) => entries.filter_map(|e| e.ok()).map(|e| e.path())
+                .filter(|p| p.extension().is_some_and(|ext| ext == "rhai")).collect(),
+            None => vec![],
+        };
+        paths.sort();
+        for path in paths {
+            let name = path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+            match engine.compile_file(path) {
+                Ok(ast) => scripts.push(Script { name, ast, this: Dynamic::from_map(Map::new()) }),
+                Err(e) => errors.push(ScriptOutput::Message(format!("Script {} didn't load: {}", name, e))),
+            }
+        }
+        (Scripts { engine, scripts, output }, errors)
+    }
+    ///
+    /// Calls the handler for `event` in every script that defines one, collecting what they asked for
+    ///
+    fn emit(&mut self, event: &str, fields: &[(&str, i64)]) -> Vec<ScriptOutput> {
+        let mut map = Map::new();
+        for (key, value) in fields {
+            map.insert((*key).into(), Dynamic::from_int(*value));
+        }
+        let mut errors: Vec<ScriptOutput> = vec![];
+        for script in &mut self.scripts {
+            if !script.ast.iter_functions().any(|f| f.name == event && f.params.len() == 1) {
+                continue;
+            }
+            let options = CallFnOptions::new().eval_ast(false).bind_this_ptr(&mut script.this);
+            let result = self.engine.call_fn_with_options::<Dynamic>(options, &mut Scope::new(), &script.ast, event, (map.clone(),));
+            if let Err(e) = result {
+                errors.push(ScriptOutput::Message(format!("Script {} failed in {}: {}", script.name, event, e)));
+            }
+        }
+        let mut output: Vec<ScriptOutput> = self.output.borrow_mut().drain(..).collect();
+        output.extend(errors);
+        output
+    }
+}
+
+///
+/// Runs every script's handler for `event`, loading the scripts first if this is the first event.
+/// Returns what the scripts asked the game to do, in order
+///
+#[cfg(feature = "scripting")]
+pub(crate) fn emit(event: &str, fields: &[(&str, i64)]) -> Vec<ScriptOutput> {
+    SCRIPTS.with(|cell| {
+        let mut scripts = cell.borrow_mut();
+        let mut output: Vec<ScriptOutput> = vec![];
+        if scripts.is_none() {
+            let (loaded, errors) = Scripts::load();
+            *scripts = Some(loaded);
+            output.extend(errors);
+        }
+        output.extend(scripts.as_mut().unwrap().emit(event, fields));
+        output
+    })
+}
+
+///
+/// Without the scripting feature there are no scripts, so nothing ever happens
+///
+#[cfg(not(feature = "scripting"))]
+pub(crate) fn emit(_event: &str, _fields: &[(&str, i64)]) -> Vec<ScriptOutput> {
+    vec![]
+}
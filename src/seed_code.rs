@@ -0,0 +1,70 @@
+//! Seed codes: a board's size, mine count, and seed in one short piece of text, like `16x16-40-3F9K2Q7`,
+//! so a board can be sent to someone else and played on exactly as it was.
+
+use std::fmt::{Display, Formatter};
+
+// Digits the seed is written in, base 36 so the code stays short
+const DIGITS: &[u8; 36] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+
+///
+/// Everything it takes to make the same board again from its seed. Only boards made through PCG come out the same
+/// everywhere, so codes are only given out for those
+///
+/// ```
+/// use minesweeper::seed_code::SeedCode;
+///
+/// let code = SeedCode { width: 16, height: 16, mines: 40, seed: 1_000_000 };
+/// assert_eq!(code.to_string(), "16x16-40-LFLS");
+/// assert_eq!(SeedCode::find("try this one: 16x16-40-lfls"), Some(code));
+/// ```
+///
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct SeedCode {
+    pub width: i16,
+    pub height: i16,
+    pub mines: i16,
+    pub seed: u64,
+}
+
+impl Display for SeedCode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
+        let mut digits: Vec<u8> = vec![];
+        let mut seed = self.seed;
+        loop {
+            digits.push(DIGITS[(seed % 36) as usize]);
+            seed /= 36;
+            if seed == 0 {
+                break;
+            }
+        }
+        digits.reverse();
+        write!(f, "{}x{}-{}-{}", self.width, self.height, self.mines, String::from_utf8(digits).unwrap())
+    }
+}
+
+impl SeedCode {
+    ///
+    /// Reads a code back, in either case. Returns `None` for anything that isn't one
+    ///
+    pub fn parse(text: &str) -> Option<SeedCode> {
+        let mut parts = text.trim().split('-');
+        let (width, height) = parts.next()?.to_ascii_lowercase().split_once('x').map(|(w, h)| (w.parse().ok(), h.parse().ok()))?;
+        let mines = parts.next()?.parse().ok()?;
+        let seed = parts.next()?;
+        if parts.next().is_some() || seed.is_empty() {
+            return None;
+        }
+        let mut value: u64 = 0;
+        for c in seed.chars() {
+            let digit = c.to_digit(36)? as u64;
+            value = value.checked_mul(36)?.checked_add(digit)?;
+        }
+        Some(SeedCode { width: width?, height: height?, mines, seed: value })
+    }
+    ///
+    /// Finds the first seed code in a longer piece of text, like a message it was pasted into
+    ///
+    pub fn find(text: &str) -> Option<SeedCode> {
+        text.split(|c: char| c.is_whitespace() || "()[],;:\"'".contains(c)).find_map(SeedCode::parse)
+    }
+}
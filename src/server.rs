@@ -0,0 +1,536 @@
+use crate::net::{self, HANDSHAKE, Link, LinkEvent, LinkStatus, RECONNECT_WINDOW};
+use minesweeper::engine::GameState;
+use minesweeper::locale;
+use minesweeper::protocol::{self, MAX_CHAT, Message, PROTOCOL_VERSION, Progress};
+use minesweeper::replay::{Badges, Replay};
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+// Room players end up in when they don't ask for one: the next two players to join race each other
+const DEFAULT_ROOM: &str = "race";
+// Room with one shared board a day, played alone against the day's best time
+const DAILY_ROOM: &str = "daily";
+// Longest player or room name kept
+const NAME_LENGTH: usize = 16;
+// Best times kept for each day
+const DAILY_KEPT: usize = 10;
+// Players shown in the status endpoint's race standings
+const STANDINGS_SHOWN: usize = 10;
+// Size and mine count of race boards
+const RACE_BOARD: (i16, i16, i16) = (16, 16, 40);
+// Size and mine count of the daily board
+const DAILY_BOARD: (i16, i16, i16) = (30, 16, 99);
+
+///
+/// Whether a room is a race between two players or the daily board
+///
+#[derive(PartialEq, Clone, Copy, Debug)]
+enum RoomKind {
+    Race,
+    Daily,
+}
+
+///
+/// Someone who joined the server. Players keep their place when their connection drops,
+/// and get it back by reconnecting with their session
+///
+struct Player {
+    name: String,
+    room: u64,
+    link: Option<Link>,              // None while disconnected
+    dropped_at: Option<Instant>,     // When the connection dropped, while waiting for them to come back
+    left: bool,                      // Gone for good
+    progress: Option<Progress>,      // Last heard progress
+    result: Option<(GameState, u64)>, // Result and game time, once a finished game checks out
+}
+
+///
+/// A game being played on the server
+///
+struct Room {
+    name: String,
+    kind: RoomKind,
+    players: Vec<u64>,
+    board: Option<(Replay, (i16, i16))>, // The board and start, once the game has started
+    over: bool,                          // Whether or not the result is in the leaderboard
+}
+
+///
+/// A finished daily board
+///
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct DailyTime {
+    name: String,
+    time_ms: u64,
+}
+
+///
+/// Results kept across server restarts
+///
+#[derive(Serialize, Deserialize, Default)]
+struct Leaderboard {
+    #[serde(default)]
+    races_played: u32,
+    #[serde(default)]
+    race_wins: BTreeMap<String, u32>,        // Races won, by player name
+    #[serde(default)]
+    daily: BTreeMap<String, Vec<DailyTime>>, // Best times on each day's board, fastest first, by date
+}
+
+///
+/// A connection that said hello, waiting to be let in
+///
+struct Arrival {
+    stream: TcpStream,
+    session: Option<u64>,
+    room: Option<String>,
+    name: Option<String>,
+}
+
+///
+/// Everything the server knows. Only the main loop touches it; connections hand their players over through a channel
+///
+struct Server {
+    players: HashMap<u64, Player>,
+    rooms: HashMap<u64, Room>,
+    next_room: u64,
+    leaderboard: Leaderboard,
+    leaderboard_path: PathBuf,
+    started: Instant,
+}
+
+///
+/// Runs a server on `port` until the process is stopped, keeping the leaderboard in `leaderboard_path`.
+/// Players connect the same way they join a race, and `GET` on the same port returns the server's status as JSON.
+/// Run with `minesweeper serve [--port N] [--leaderboard path]`
+///
+pub(crate) fn run(port: u16, leaderboard_path: PathBuf) -> Result<(), std::io::Error> {
+    let listener = TcpListener::bind(("0.0.0.0", port))?;
+    let leaderboard = match fs::read_to_string(&leaderboard_path) {
+        Ok(text) => match serde_json::from_str(&text) {
+            Ok(leaderboard) => leaderboard,
+            Err(e) => return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, format!("{}: {}", leaderboard_path.display(), e))),
+        },
+        Err(_) => Leaderboard::default(),
+    };
+    println!("Serving on port {}, leaderboard in {}", port, leaderboard_path.display());
+    let mut server = Server {
+        players: HashMap::new(),
+        rooms: HashMap::new(),
+        next_room: 0,
+        leaderboard,
+        leaderboard_path,
+        started: Instant::now(),
+    };
+    let status = Arc::new(Mutex::new(server.status().to_string()));
+    let (arrivals_sender, arrivals) = mpsc::channel();
+    let shown = status.clone();
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(_) => continue,
+            };
+            let arrivals = arrivals_sender.clone();
+            let status = shown.clone();
+            thread::spawn(move || greet(stream, arrivals, &status));
+        }
+    });
+    loop {
+        while let Ok(arrival) = arrivals.try_recv() {
+            server.arrive(arrival);
+        }
+        server.poll();
+        server.tidy();
+        *status.lock().unwrap() = server.status().to_string();
+        thread::sleep(net::TICK);
+    }
+}
+
+///
+/// Reads a new connection's first message. Hellos go to the main loop; a `GET` gets the status page
+///
+fn greet(mut stream: TcpStream, arrivals: Sender<Arrival>, status: &Mutex<String>) {
+    if stream.set_read_timeout(Some(HANDSHAKE)).is_err() || stream.set_write_timeout(Some(HANDSHAKE)).is_err() {
+        return;
+    }
+    // "GET " read as a frame length is far over the limit, so the two can't be mixed up
+    let mut start = [0u8; 4];
+    let deadline = Instant::now() + HANDSHAKE;
+    loop {
+        match stream.peek(&mut start) {
+            Ok(4) => break,
+            Ok(0) | Err(_) => return,
+            Ok(_) if Instant::now() > deadline => return,
+            Ok(_) => thread::sleep(net::TICK),
+        }
+    }
+    if &start == b"GET " {
+        let body = status.lock().unwrap().clone();
+        serve_status(stream, &body);
+        return;
+    }
+    if let Ok(Message::Hello { version, session, room, name }) = protocol::read_frame(&mut stream) {
+        if version != PROTOCOL_VERSION {
+            let reason = format!("version mismatch (server has {}, you have {})", PROTOCOL_VERSION, version);
+            protocol::write_frame(&mut stream, &Message::Rejected { reason }).ok();
+            return;
+        }
+        arrivals.send(Arrival { stream, session, room, name }).ok();
+    }
+}
+
+///
+/// Answers an HTTP request with the status JSON, whatever path it asked for
+///
+fn serve_status(mut stream: TcpStream, body: &str) {
+    // Only the request line matters, but the rest has to be read so closing doesn't reset the connection
+    let mut request = [0u8; 4096];
+    let mut read = 0;
+    while read < request.len() {
+        match stream.read(&mut request[read..]) {
+            Ok(0) | Err(_) => break,
+            Ok(n) => read += n,
+        }
+        if request[..read].windows(4).any(|w| w == b"\r\n\r\n") {
+            break;
+        }
+    }
+    let response = format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes()).ok();
+}
+
+///
+/// Keeps the letters, digits, spaces, dashes, and underscores of a name from a player, cut down to `NAME_LENGTH`
+///
+fn clean_name(name: &str) -> String {
+    let kept: String = name.chars().filter(|c| c.is_alphanumeric() || *c == ' ' || *c == '-' || *c == '_').take(NAME_LENGTH).collect();
+    kept.trim().to_owned()
+}
+
+///
+/// Today's date (UTC) as YYYY-MM-DD, and the number of days since 1970 it's counted from
+///
+fn today() -> (String, u64) {
+    let days = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs() / 86400).unwrap_or(0);
+    (locale::date(days), days)
+}
+
+impl Server {
+    ///
+    /// Lets a player in, or back in if they're reconnecting
+    ///
+    fn arrive(&mut self, arrival: Arrival) {
+        let mut stream = arrival.stream;
+        let session = match arrival.session {
+            Some(session) if self.players.get(&session).is_some_and(|p| !p.left) => session,
+            Some(_) => {
+                protocol::write_frame(&mut stream, &Message::Rejected { reason: String::from("that game is over") }).ok();
+                return;
+            }
+            None => rand::random(),
+        };
+        if protocol::write_frame(&mut stream, &Message::Welcome { version: PROTOCOL_VERSION, session }).is_err()
+            || stream.set_read_timeout(None).is_err() {
+            return;
+        }
+        let rejoined = self.players.contains_key(&session);
+        if !rejoined {
+            let name = match arrival.name.as_deref().map(clean_name) {
+                Some(name) if !name.is_empty() => name,
+                _ => format!("player-{:04}", session % 10000),
+            };
+            let room = self.place(session, arrival.room.as_deref().map(clean_name).unwrap_or_default());
+            println!("{} joined room {}", name, self.rooms[&room].name);
+            self.players.insert(session, Player {
+                name,
+                room,
+                link: None,
+                dropped_at: None,
+                left: false,
+                progress: None,
+                result: None,
+            });
+        }
+        let player = self.players.get_mut(&session).unwrap();
+        player.link = Some(Link::accepted(stream));
+        player.dropped_at = None;
+        let room = player.room;
+        self.start_if_ready(room);
+        self.catch_up(session);
+    }
+    ///
+    /// Puts a new player in the room they asked for, making it if it isn't there yet. Returns the room
+    ///
+    fn place(&mut self, session: u64, asked: String) -> u64 {
+        let asked = asked.to_lowercase();
+        let (name, kind) = match asked.as_str() {
+            "" => (String::from(DEFAULT_ROOM), RoomKind::Race),
+            DAILY_ROOM => (format!("{} {}", DAILY_ROOM, today().0), RoomKind::Daily),
+            _ => (asked, RoomKind::Race),
+        };
+        // Races only take players until they start; the next players to ask for the same room get a new one
+        let open = self.rooms.iter().find(|(_, room)| room.name == name && (room.kind == RoomKind::Daily || room.board.is_none()));
+        let id = match open {
+            Some((id, _)) => *id,
+            None => {
+                self.next_room += 1;
+                self.rooms.insert(self.next_room, Room { name, kind, players: vec![], board: None, over: false });
+                self.next_room
+            }
+        };
+        self.rooms.get_mut(&id).unwrap().players.push(session);
+        id
+    }
+    ///
+    /// Starts a room's game once it has everyone it needs: two players for a race, or anyone for the daily board
+    ///
+    fn start_if_ready(&mut self, id: u64) {
+        let room = self.rooms.get_mut(&id).unwrap();
+        if room.board.is_some() {
+            return;
+        }
+        let (size, seed) = match room.kind {
+            RoomKind::Race if room.players.len() >= 2 => (RACE_BOARD, rand::random()),
+            RoomKind::Race => return,
+            // Everyone gets the same board on the same day
+            RoomKind::Daily => (DAILY_BOARD, 0x4441_494C_5900_0000 ^ today().1),
+        };
+        let board = Replay::new(size.0, size.1, size.2, 1, seed, false, false);
+        room.board = Some((board, (size.0 / 2, size.1 / 2)));
+        println!("Room {} started", room.name);
+        for player in room.players.clone() {
+            self.catch_up(player);
+        }
+    }
+    ///
+    /// Sends a player the board and where everyone else is, e.g. after they (re)connect
+    ///
+    fn catch_up(&self, session: u64) {
+        let player = &self.players[&session];
+        let link = match &player.link {
+            Some(link) => link,
+            None => return,
+        };
+        let room = &self.rooms[&player.room];
+        let (board, start) = match &room.board {
+            Some(board) => board,
+            None => return,
+        };
+        link.send(Message::Start { board: board.clone(), start: *start });
+        match room.kind {
+            RoomKind::Race => {
+                for other in room.players.iter().filter(|&&p| p != session) {
+                    if let Some(progress) = self.players[other].progress {
+                        link.send(Message::Progress(progress));
+                    }
+                }
+            }
+            RoomKind::Daily => {
+                // The day's best time stands in for an opponent
+                let (date, _) = today();
+                if let Some(best) = self.leaderboard.daily.get(&date).and_then(|times| times.first()) {
+                    let safe = (DAILY_BOARD.0 * DAILY_BOARD.1 - DAILY_BOARD.2) as u32;
+                    link.send(Message::Progress(Progress { uncovered: safe, safe, state: GameState::Win, time_ms: best.time_ms }));
+                }
+            }
+        }
+    }
+    ///
+    /// Handles everything that came in from every player since the last poll
+    ///
+    fn poll(&mut self) {
+        let sessions: Vec<u64> = self.players.keys().copied().collect();
+        for session in sessions {
+            let events = match &mut self.players.get_mut(&session).unwrap().link {
+                Some(link) => link.poll(),
+                None => continue,
+            };
+            for event in events {
+                match event {
+                    LinkEvent::Status(LinkStatus::Reconnecting) => {
+                        let player = self.players.get_mut(&session).unwrap();
+                        player.link = None;
+                        player.dropped_at = Some(Instant::now());
+                    }
+                    LinkEvent::Status(LinkStatus::Closed(_)) => {
+                        let player = self.players.get_mut(&session).unwrap();
+                        player.link = None;
+                        player.left = true;
+                        println!("{} left", player.name);
+                    }
+                    LinkEvent::Message(Message::Progress(progress)) => {
+                        self.players.get_mut(&session).unwrap().progress = Some(progress);
+                        let room = &self.rooms[&self.players[&session].room];
+                        if room.kind == RoomKind::Race {
+                            for other in room.players.iter().filter(|&&p| p != session) {
+                                if let Some(link) = &self.players[other].link {
+                                    link.send(Message::Progress(progress));
+                                }
+                            }
+                        }
+                    }
+                    LinkEvent::Message(Message::Chat { text, .. }) => {
+                        // Everyone else in the room hears it, under the name the server knows the sender by
+                        let text = protocol::clean_text(&text, MAX_CHAT);
+                        let player = &self.players[&session];
+                        if text.is_empty() {
+                            continue;
+                        }
+                        for other in self.rooms[&player.room].players.iter().filter(|&&p| p != session) {
+                            if let Some(link) = &self.players[other].link {
+                                link.send(Message::Chat { name: player.name.clone(), text: text.clone() });
+                            }
+                        }
+                    }
+                    LinkEvent::Message(Message::Finished { replay }) => {
+                        self.finish(session, &replay);
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+    ///
+    /// Checks a player's finished game against the room's board, and ranks it if it holds up
+    ///
+    fn finish(&mut self, session: u64, replay: &Replay) {
+        let player = &self.players[&session];
+        let room = &self.rooms[&player.room];
+        let board = match &room.board {
+            Some((board, _)) => board,
+            None => return,
+        };
+        if player.result.is_some() {
+            return;
+        }
+        // Everything but the moves, the result, and the claimed badges has to match the board that was handed out
+        let played_on = Replay { actions: vec![], result: GameState::Starting, time_ms: 0, badges: Badges::default(), ..replay.clone() };
+        let verified = if played_on != *board {
+            Err(String::from("it was played on a different board"))
+        } else {
+            replay.verify()
+        };
+        let verification = match verified {
+            Ok(verification) => verification,
+            Err(e) => {
+                println!("Didn't count {}'s game: {}", player.name, e);
+                return;
+            }
+        };
+        let time_ms = verification.time.as_millis() as u64;
+        println!("{} finished room {}: {:?} in {:.3}s", player.name, room.name, verification.result, time_ms as f64 / 1000.);
+        let name = player.name.clone();
+        let kind = room.kind;
+        self.players.get_mut(&session).unwrap().result = Some((verification.result, time_ms));
+        if kind == RoomKind::Daily && verification.result == GameState::Win {
+            let times = self.leaderboard.daily.entry(today().0).or_default();
+            times.push(DailyTime { name, time_ms });
+            times.sort_by_key(|t| t.time_ms);
+            times.truncate(DAILY_KEPT);
+            self.write_leaderboard();
+        }
+    }
+    ///
+    /// Settles races whose players are all done, and forgets players and rooms nobody needs anymore
+    ///
+    fn tidy(&mut self) {
+        for player in self.players.values_mut() {
+            if player.dropped_at.is_some_and(|at| at.elapsed() > RECONNECT_WINDOW) {
+                player.left = true;
+                player.dropped_at = None;
+                println!("{} didn't come back", player.name);
+            }
+        }
+        let mut settled = false;
+        for room in self.rooms.values_mut() {
+            if room.kind != RoomKind::Race || room.board.is_none() || room.over {
+                continue;
+            }
+            if !room.players.iter().all(|p| self.players[p].result.is_some() || self.players[p].left) {
+                continue;
+            }
+            room.over = true;
+            // Fastest win takes it; if nobody won, nobody gets the point
+            let winner = room.players.iter()
+                .filter_map(|p| match self.players[p].result {
+                    Some((GameState::Win, time_ms)) => Some((time_ms, &self.players[p].name)),
+                    _ => None,
+                })
+                .min();
+            self.leaderboard.races_played += 1;
+            if let Some((_, name)) = winner {
+                *self.leaderboard.race_wins.entry(name.clone()).or_default() += 1;
+                println!("Room {} won by {}", room.name, name);
+            }
+            settled = true;
+        }
+        if settled {
+            self.write_leaderboard();
+        }
+        // Players who left stay in their race until it's settled, so it still counts them
+        let players = &self.players;
+        for room in self.rooms.values_mut() {
+            if room.over || room.board.is_none() || room.kind == RoomKind::Daily {
+                room.players.retain(|p| !players[p].left);
+            }
+        }
+        let rooms = &self.rooms;
+        self.players.retain(|session, p| rooms[&p.room].players.contains(session));
+        self.rooms.retain(|_, room| !room.players.is_empty());
+    }
+    ///
+    /// Writes the leaderboard out, so it survives a restart
+    ///
+    fn write_leaderboard(&self) {
+        if let Ok(text) = serde_json::to_string_pretty(&self.leaderboard) && let Err(e) = fs::write(&self.leaderboard_path, text) {
+            println!("Couldn't write the leaderboard to {}: {}", self.leaderboard_path.display(), e);
+        }
+    }
+    ///
+    /// What's going on, for the status endpoint
+    ///
+    fn status(&self) -> serde_json::Value {
+        let mut rooms: Vec<&Room> = self.rooms.values().collect();
+        rooms.sort_by(|a, b| a.name.cmp(&b.name));
+        let rooms: Vec<serde_json::Value> = rooms.iter().map(|room| json!({
+            "name": room.name,
+            "kind": if room.kind == RoomKind::Race { "race" } else { "daily" },
+            "started": room.board.is_some(),
+            "players": room.players.iter().map(|p| json!({
+                "name": self.players[p].name,
+                "connected": self.players[p].link.is_some(),
+                "percent": self.players[p].progress.map(|progress| progress.percent()),
+            })).collect::<Vec<serde_json::Value>>(),
+        })).collect();
+        let mut standings: Vec<(&String, &u32)> = self.leaderboard.race_wins.iter().collect();
+        standings.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+        standings.truncate(STANDINGS_SHOWN);
+        let (date, _) = today();
+        json!({
+            "version": PROTOCOL_VERSION,
+            "uptime_s": self.started.elapsed().as_secs(),
+            "players_online": self.players.values().filter(|p| p.link.is_some()).count(),
+            "rooms": rooms,
+            "leaderboard": {
+                "races_played": self.leaderboard.races_played,
+                "race_wins": standings.iter().map(|(name, wins)| json!({ "name": name, "wins": wins })).collect::<Vec<serde_json::Value>>(),
+                "daily": { "date": date, "times": self.leaderboard.daily.get(&date).cloned().unwrap_or_default() },
+            },
+        })
+    }
+}
@@ -0,0 +1,56 @@
+//! Lost games frozen at the moment they were lost, so the position outlives the next game:
+//! for looking back over in the review, or attaching to a bug report.
+
+use crate::engine::{Board, GameState, VisibleBoard};
+use crate::replay::Replay;
+
+use serde::{Deserialize, Serialize};
+
+///
+/// A lost game as it stood when it was lost: the whole recording, what the player could see,
+/// where every mine was, and the space that lost the game
+///
+#[derive(PartialEq, Clone, Debug, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub replay: Replay,
+    pub board: VisibleBoard,
+    pub mines: Vec<(i16, i16)>,
+    pub fatal: (i16, i16), // The mine that was checked, or chorded into
+}
+
+impl Snapshot {
+    ///
+    /// Freezes a lost board along with its finished replay. `fatal` is the mine that ended the game
+    ///
+    pub fn new(replay: &Replay, board: &Board, fatal: (i16, i16)) -> Snapshot {
+        let mut mines: Vec<(i16, i16)> = vec![];
+        for y in 0..board.height() {
+            for x in 0..board.width() {
+                if board.is_mine(x, y) {
+                    mines.push((x, y));
+                }
+            }
+        }
+        Snapshot { replay: replay.clone(), board: board.visible_board(), mines, fatal }
+    }
+    ///
+    /// Checks that the replay really leads to the frozen position: a loss on the same board, with the same mines,
+    /// ended by a mine. Returns what doesn't add up otherwise
+    ///
+    pub fn check(&self) -> Result<(), String> {
+        let board = self.replay.simulate(self.replay.actions.len())?;
+        if board.state() != GameState::Loss {
+            return Err(format!("The moves lead to {:?}, not a loss", board.state()));
+        }
+        if board.visible_board() != self.board {
+            return Err(String::from("The moves lead to a different position than the one saved"));
+        }
+        if Snapshot::new(&self.replay, &board, self.fatal).mines != self.mines {
+            return Err(String::from("The mines aren't where the replay puts them"));
+        }
+        if !board.is_mine(self.fatal.0, self.fatal.1) {
+            return Err(format!("The game was lost at ({}, {}), which isn't a mine", self.fatal.0 + 1, self.fatal.1 + 1));
+        }
+        Ok(())
+    }
+}
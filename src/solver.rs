@@ -0,0 +1,757 @@
+use crate::engine::Board;
+
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+// Tiles are part of what the engine shows players, but the solver is where most callers reach for them
+pub use crate::engine::Tile;
+
+// Largest region `certain_mines` / `certain_safe` will try to enumerate
+const MAX_REGION: usize = 32;
+// Most configurations kept per region before giving up on it
+const MAX_CONFIGURATIONS: usize = 20000;
+
+///
+/// The inference rules the solver knows, from simplest to most involved
+///
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug)]
+pub enum Rule {
+    SingleCell,  // One number on its own: all of its spaces are safe, or all are mines
+    Subset,      // Two numbers where one's spaces are contained in the other's
+    Enumeration, // Every configuration of a region checked against each other
+}
+
+///
+/// Safe spaces and mines proven by a single rule
+///
+pub struct Deduction {
+    pub rule: Rule,
+    pub safe: Vec<(i16, i16)>,
+    pub mines: Vec<(i16, i16)>,
+}
+
+///
+/// How a single space was proven to be a mine or safe: the rule used and the revealed numbers it was read from.
+/// For subsets the larger number comes first
+///
+#[derive(PartialEq, Clone, Debug)]
+pub struct Proof {
+    pub rule: Rule,
+    pub mine: bool,
+    pub numbers: Vec<(i16, i16)>,
+}
+
+///
+/// Spaces proven the same way, with the proof put into words, e.g. "The 3 at (4, 2) touches exactly 3 unopened spaces,
+/// so (3, 1), (4, 1), and (5, 1) are mines"
+///
+#[derive(PartialEq, Clone, Debug)]
+pub struct Inference {
+    pub proof: Proof,
+    pub spaces: Vec<(i16, i16)>,
+    pub reason: String,
+}
+
+///
+/// An independent region of the frontier and how many mines it can hold
+///
+pub struct RegionCount {
+    pub cells: Vec<(i16, i16)>,
+    pub mines: Option<(i16, i16)>, // Fewest and most possible mines, or `None` if the region is too large to enumerate
+}
+
+///
+/// Deduction engine that works only from the information visible to the player.
+/// Flags are treated as covered spaces, since the player may have placed them wrongly,
+/// unless the solver is told to trust them
+///
+pub struct Solver {
+    width: i16,           // Board width
+    height: i16,          // Board height
+    m_range: (i16, i16),  // Fewest and most mines on the board, which are the same unless the total is hidden
+    tiles: Vec<Vec<Tile>>, // Visible state of every space
+    trust_flags: bool,    // Whether flags are known to be mines
+}
+
+///
+/// A region's configurations counted up by how many mines they hold, as a share of all of them so a string of
+/// regions can't overflow
+///
+struct Tally {
+    configurations: Vec<f64>, // Share of the configurations holding each number of mines
+    mines: Vec<Vec<f64>>,     // For each number of mines, the share that has a mine on each of the region's spaces
+}
+
+impl Tally {
+    fn new(found: &[Vec<bool>]) -> Tally {
+        let size = found[0].len();
+        let share = 1.0 / found.len() as f64;
+        let mut tally = Tally { configurations: vec![0.0; size + 1], mines: vec![vec![0.0; size]; size + 1] };
+        for config in found {
+            let placed = config.iter().filter(|m| **m).count();
+            tally.configurations[placed] += share;
+            for (cell, mine) in config.iter().enumerate() {
+                if *mine {
+                    tally.mines[placed][cell] += share;
+                }
+            }
+        }
+        tally
+    }
+}
+
+///
+/// A single revealed number and the covered spaces around it
+///
+struct Constraint {
+    cells: Vec<usize>,    // Indices into the frontier
+    mines: i16,           // Number of mines that must be among `cells`
+    source: (i16, i16),   // The revealed number this comes from
+}
+
+impl Solver {
+    ///
+    /// Creates a solver for the given visible board
+    ///
+    pub fn new(width: i16, height: i16, m_count: i16, tiles: Vec<Vec<Tile>>) -> Solver {
+        Solver {
+            width,
+            height,
+            m_range: (m_count, m_count),
+            tiles,
+            trust_flags: false,
+        }
+    }
+    ///
+    /// Only assumes the board has somewhere from `min` to `max` mines, for boards that hide their total
+    ///
+    pub fn with_mine_range(mut self, min: i16, max: i16) -> Solver {
+        self.m_range = (min.min(max), max);
+        self
+    }
+    ///
+    /// Treats every flag as a proven mine. Only for callers that know their flags are right
+    ///
+    pub fn trusting_flags(mut self) -> Solver {
+        self.trust_flags = true;
+        self
+    }
+    ///
+    /// Gets the surrounding spaces of a given coordinate as a `Vec<(i16, i16)>`
+    ///
+    fn get_surrounding(&self, x: i16, y: i16) -> Vec<(i16, i16)> {
+        let mut surroundings: Vec<(i16, i16)> = vec![];
+        for dy in -1..=1 {
+            for dx in -1..=1 {
+                let nx = x + dx;
+                let ny = y + dy;
+                if (dx != 0 || dy != 0) && nx >= 0 && nx < self.width && ny >= 0 && ny < self.height {
+                    surroundings.push((nx, ny));
+                }
+            }
+        }
+        surroundings
+    }
+    ///
+    /// Whether or not the given space is still unknown: covered, or flagged when flags aren't trusted
+    ///
+    fn is_covered(&self, x: i16, y: i16) -> bool {
+        match self.tiles[y as usize][x as usize] {
+            Tile::Covered => true,
+            Tile::Flagged => !self.trust_flags,
+            Tile::Revealed(_) => false,
+        }
+    }
+    ///
+    /// Whether or not the given space is a flag known to be a mine
+    ///
+    fn is_known_mine(&self, x: i16, y: i16) -> bool {
+        self.trust_flags && self.tiles[y as usize][x as usize] == Tile::Flagged
+    }
+    ///
+    /// The fewest and most mines that aren't accounted for by trusted flags
+    ///
+    fn mines_left(&self) -> (i16, i16) {
+        let mut known: i16 = 0;
+        for i in 0..self.height {
+            for j in 0..self.width {
+                if self.is_known_mine(j, i) {
+                    known += 1;
+                }
+            }
+        }
+        (self.m_range.0 - known, self.m_range.1 - known)
+    }
+    ///
+    /// Gets every covered space that borders at least one revealed number
+    ///
+    pub fn get_frontier(&self) -> Vec<(i16, i16)> {
+        let mut frontier: Vec<(i16, i16)> = vec![];
+        for i in 0..self.height {
+            for j in 0..self.width {
+                if self.is_covered(j, i) {
+                    let borders_number = self.get_surrounding(j, i).iter()
+                        .any(|s| matches!(self.tiles[s.1 as usize][s.0 as usize], Tile::Revealed(_)));
+                    if borders_number {
+                        frontier.push((j, i));
+                    }
+                }
+            }
+        }
+        frontier
+    }
+    ///
+    /// Builds one constraint per revealed number that touches the frontier
+    ///
+    fn get_constraints(&self, frontier: &[(i16, i16)]) -> Vec<Constraint> {
+        let mut constraints: Vec<Constraint> = vec![];
+        for i in 0..self.height {
+            for j in 0..self.width {
+                if let Tile::Revealed(n) = self.tiles[i as usize][j as usize] {
+                    let surrounding = self.get_surrounding(j, i);
+                    let cells: Vec<usize> = surrounding.iter()
+                        .filter_map(|s| frontier.iter().position(|f| f == s))
+                        .collect();
+                    let known = surrounding.iter().filter(|s| self.is_known_mine(s.0, s.1)).count() as i16;
+                    if !cells.is_empty() {
+                        constraints.push(Constraint { cells, mines: n - known, source: (j, i) });
+                    }
+                }
+            }
+        }
+        constraints
+    }
+    ///
+    /// Lists every arrangement of mines on the frontier that agrees with the revealed numbers
+    /// and the total mine count. Each configuration is the list of frontier spaces holding a mine.
+    /// Returns `None` if the frontier is larger than `max_frontier`, or more than `limit` configurations exist
+    ///
+    pub fn enumerate_configurations(&self, max_frontier: usize, limit: usize) -> Option<Vec<Vec<(i16, i16)>>> {
+        let _span = tracing::info_span!("solver_configurations").entered();
+        let frontier = self.get_frontier();
+        if frontier.len() > max_frontier {
+            return None;
+        }
+        let found = self.enumerate(&frontier, true, limit)?;
+        let configurations = found.iter().map(|config| {
+            frontier.iter().zip(config).filter(|(_, mine)| **mine).map(|(space, _)| *space).collect()
+        }).collect();
+        Some(configurations)
+    }
+    ///
+    /// Splits the frontier into regions that share no revealed numbers, so each can be solved on its own
+    ///
+    pub fn get_regions(&self) -> Vec<Vec<(i16, i16)>> {
+        let frontier = self.get_frontier();
+        let constraints = self.get_constraints(&frontier);
+        let mut region_of: Vec<Option<usize>> = vec![None; frontier.len()];
+        let mut regions: Vec<Vec<(i16, i16)>> = vec![];
+        for start in 0..frontier.len() {
+            if region_of[start].is_some() {
+                continue;
+            }
+            // Flood out through every constraint that touches the region so far
+            let mut region: Vec<(i16, i16)> = vec![];
+            let mut to_visit: Vec<usize> = vec![start];
+            region_of[start] = Some(regions.len());
+            while let Some(cell) = to_visit.pop() {
+                region.push(frontier[cell]);
+                for constraint in constraints.iter().filter(|c| c.cells.contains(&cell)) {
+                    for other in &constraint.cells {
+                        if region_of[*other].is_none() {
+                            region_of[*other] = Some(regions.len());
+                            to_visit.push(*other);
+                        }
+                    }
+                }
+            }
+            regions.push(region);
+        }
+        regions
+    }
+    ///
+    /// Splits the frontier into regions and works out how many mines each one can hold.
+    ///
+    pub fn region_mine_counts(&self) -> Vec<RegionCount> {
+        let mut counts: Vec<RegionCount> = vec![];
+        let regions = self.get_regions();
+        let solved = self.each_region(&regions, |region| {
+            if region.len() > MAX_REGION { None } else { self.enumerate(region, false, MAX_CONFIGURATIONS) }
+        });
+        for (region, found) in regions.into_iter().zip(solved) {
+            let range = match found {
+                Some(f) if !f.is_empty() => {
+                    let mines: Vec<i16> = f.iter().map(|config| config.iter().filter(|m| **m).count() as i16).collect();
+                    Some((*mines.iter().min().unwrap(), *mines.iter().max().unwrap()))
+                }
+                _ => None,
+            };
+            counts.push(RegionCount { cells: region, mines: range });
+        }
+        counts
+    }
+    ///
+    /// Finds every covered space that is a mine in all possible configurations
+    ///
+    pub fn certain_mines(&self) -> Vec<(i16, i16)> {
+        self.get_certain(true)
+    }
+    ///
+    /// Finds every covered space that is safe in all possible configurations
+    ///
+    pub fn certain_safe(&self) -> Vec<(i16, i16)> {
+        self.get_certain(false)
+    }
+    ///
+    /// Proves what it can using the simplest rule that makes any progress: single numbers first,
+    /// then subsets, then enumeration. Returns `None` if nothing can be proven without guessing
+    ///
+    pub fn next_deduction(&self) -> Option<Deduction> {
+        let _span = tracing::info_span!("solver_deduction").entered();
+        let frontier = self.get_frontier();
+        let constraints = self.get_constraints(&frontier);
+        let mut safe: Vec<(i16, i16)> = vec![];
+        let mut mines: Vec<(i16, i16)> = vec![];
+        // Single numbers
+        for constraint in &constraints {
+            if constraint.mines == 0 {
+                safe.extend(constraint.cells.iter().map(|c| frontier[*c]));
+            } else if constraint.mines == constraint.cells.len() as i16 {
+                mines.extend(constraint.cells.iter().map(|c| frontier[*c]));
+            }
+        }
+        if let Some(deduction) = Solver::collect(Rule::SingleCell, safe, mines) {
+            return Some(deduction);
+        }
+        // Pairs of numbers where one's spaces are a subset of the other's
+        let mut safe: Vec<(i16, i16)> = vec![];
+        let mut mines: Vec<(i16, i16)> = vec![];
+        for small in &constraints {
+            for large in &constraints {
+                if small.cells.len() >= large.cells.len() || !small.cells.iter().all(|c| large.cells.contains(c)) {
+                    continue;
+                }
+                let rest: Vec<(i16, i16)> = large.cells.iter().filter(|c| !small.cells.contains(c)).map(|c| frontier[*c]).collect();
+                let rest_mines = large.mines - small.mines;
+                if rest_mines == 0 {
+                    safe.extend(rest);
+                } else if rest_mines == rest.len() as i16 {
+                    mines.extend(rest);
+                }
+            }
+        }
+        if let Some(deduction) = Solver::collect(Rule::Subset, safe, mines) {
+            return Some(deduction);
+        }
+        // Everything else
+        Solver::collect(Rule::Enumeration, self.certain_safe(), self.certain_mines())
+    }
+    ///
+    /// Proves whether the given space is a mine or safe with the simplest rule that can,
+    /// keeping track of which numbers the proof was read from. Returns `None` if it can't be proven either way
+    ///
+    pub fn explain(&self, space: (i16, i16)) -> Option<Proof> {
+        let frontier = self.get_frontier();
+        let index = frontier.iter().position(|f| *f == space)?;
+        let constraints = self.get_constraints(&frontier);
+        // Single numbers
+        for constraint in &constraints {
+            if !constraint.cells.contains(&index) {
+                continue;
+            }
+            if constraint.mines == 0 || constraint.mines == constraint.cells.len() as i16 {
+                return Some(Proof { rule: Rule::SingleCell, mine: constraint.mines > 0, numbers: vec![constraint.source] });
+            }
+        }
+        // Pairs of numbers where one's spaces are a subset of the other's
+        for small in &constraints {
+            for large in &constraints {
+                if !large.cells.contains(&index) || small.cells.contains(&index) || small.cells.len() >= large.cells.len()
+                    || !small.cells.iter().all(|c| large.cells.contains(c)) {
+                    continue;
+                }
+                let rest = (large.cells.len() - small.cells.len()) as i16;
+                let rest_mines = large.mines - small.mines;
+                if rest_mines == 0 || rest_mines == rest {
+                    return Some(Proof { rule: Rule::Subset, mine: rest_mines > 0, numbers: vec![large.source, small.source] });
+                }
+            }
+        }
+        // Everything else
+        let numbers: Vec<(i16, i16)> = constraints.iter().filter(|c| c.cells.contains(&index)).map(|c| c.source).collect();
+        if self.certain_mines().contains(&space) {
+            return Some(Proof { rule: Rule::Enumeration, mine: true, numbers });
+        }
+        if self.certain_safe().contains(&space) {
+            return Some(Proof { rule: Rule::Enumeration, mine: false, numbers });
+        }
+        None
+    }
+    ///
+    /// Everything `next_deduction` proves, grouped by proof and put into words, for showing the player why
+    ///
+    pub fn infer(&self) -> Vec<Inference> {
+        let deduction = match self.next_deduction() {
+            Some(d) => d,
+            None => return vec![],
+        };
+        let mut inferences: Vec<Inference> = vec![];
+        for space in deduction.mines.iter().chain(&deduction.safe) {
+            let proof = match self.explain(*space) {
+                Some(p) => p,
+                None => continue,
+            };
+            match inferences.iter_mut().find(|i| i.proof == proof) {
+                Some(inference) => inference.spaces.push(*space),
+                None => inferences.push(Inference { proof, spaces: vec![*space], reason: String::new() }),
+            }
+        }
+        for inference in &mut inferences {
+            inference.reason = self.describe(&inference.proof, &inference.spaces);
+        }
+        inferences
+    }
+    ///
+    /// Puts a proof of `spaces` into words. Coordinates count from 1, the way the game shows them
+    ///
+    pub fn describe(&self, proof: &Proof, spaces: &[(i16, i16)]) -> String {
+        let list: Vec<String> = spaces.iter().map(|s| at(*s)).collect();
+        let list = match list.len() {
+            0 | 1 => list.concat(),
+            2 => list.join(" and "),
+            n => format!("{}, and {}", list[..n - 1].join(", "), list[n - 1]),
+        };
+        let outcome = match (proof.mine, spaces.len()) {
+            (true, 1) => format!("so {} is a mine", list),
+            (true, _) => format!("so {} are mines", list),
+            (false, 1) => format!("so {} is safe", list),
+            (false, _) => format!("so {} are safe", list),
+        };
+        match proof.rule {
+            Rule::SingleCell => {
+                let number = proof.numbers[0];
+                let (n, flags, unopened) = self.around(number);
+                if !proof.mine && n == 0 {
+                    return format!("The 0 at {} has no mines around it, {}", at(number), outcome);
+                }
+                if !proof.mine && n == 1 && flags == 1 {
+                    return format!("The 1 at {} already has its mine flagged, {}", at(number), outcome);
+                }
+                if !proof.mine && flags == n {
+                    return format!("The {} at {} already has all {} of its mines flagged, {}", n, at(number), n, outcome);
+                }
+                if flags == 0 {
+                    return format!("The {} at {} touches exactly {} unopened {}, {}", n, at(number), unopened, plural(unopened, "space"), outcome);
+                }
+                format!("The {} at {} already has {} flagged and only {} other unopened {} for the rest, {}",
+                    n, at(number), flags, unopened, plural(unopened, "space"), outcome)
+            }
+            Rule::Subset => {
+                let (large, small) = (proof.numbers[0], proof.numbers[1]);
+                let (large_n, large_flags, _) = self.around(large);
+                let (small_n, small_flags, _) = self.around(small);
+                let extra = (large_n - large_flags) - (small_n - small_flags);
+                if extra == 0 {
+                    format!("Every unopened space next to the {} at {} is also next to the {} at {}, which has all its mines among them already, {}",
+                        small_n, at(small), large_n, at(large), outcome)
+                } else {
+                    format!("Every unopened space next to the {} at {} is also next to the {} at {}, which still needs {} more {} than it, {}",
+                        small_n, at(small), large_n, at(large), extra, plural(extra, "mine"), outcome)
+                }
+            }
+            Rule::Enumeration => {
+                let numbers: Vec<String> = proof.numbers.iter().map(|n| at(*n)).collect();
+                format!("Every way of fitting mines around the numbers at {} agrees, {}", numbers.join(", "), outcome)
+            }
+        }
+    }
+    ///
+    /// A revealed number, how many known mines are next to it, and how many unknown spaces are next to it.
+    /// Flags only count as mines when they're trusted
+    ///
+    fn around(&self, number: (i16, i16)) -> (i16, i16, i16) {
+        let n = match self.tiles[number.1 as usize][number.0 as usize] {
+            Tile::Revealed(n) => n,
+            _ => 0,
+        };
+        let surrounding = self.get_surrounding(number.0, number.1);
+        let flags = surrounding.iter().filter(|s| self.is_known_mine(s.0, s.1)).count() as i16;
+        let unopened = surrounding.iter().filter(|s| self.is_covered(s.0, s.1)).count() as i16;
+        (n, flags, unopened)
+    }
+    ///
+    /// The chance of each covered space being a mine, weighing every configuration of the frontier by the number of ways
+    /// the leftover mines can be spread over the rest of the board. Indexed [y][x], with `None` for revealed spaces.
+    /// Each region of the frontier is enumerated on its own, since only the mine total ties them together, so a long
+    /// frontier of small regions is quick. Returns `None` if a region is too large to enumerate
+    ///
+    pub fn probabilities(&self) -> Option<Vec<Vec<Option<f64>>>> {
+        let _span = tracing::info_span!("solver_probabilities").entered();
+        let regions = self.get_regions();
+        if regions.iter().any(|region| region.len() > MAX_REGION) {
+            return None;
+        }
+        let mut tallies: Vec<Tally> = vec![];
+        for found in self.each_region(&regions, |region| self.enumerate(region, false, MAX_CONFIGURATIONS)) {
+            match found {
+                Some(found) if !found.is_empty() => tallies.push(Tally::new(&found)),
+                _ => return None,
+            }
+        }
+        let left = self.mines_left();
+        let frontier: Vec<(i16, i16)> = regions.concat();
+        let mut interior: i16 = 0;
+        for i in 0..self.height {
+            for j in 0..self.width {
+                if self.is_covered(j, i) && !frontier.contains(&(j, i)) {
+                    interior += 1;
+                }
+            }
+        }
+        // Each total on the frontier counts once for every way the rest of the mines fit in the interior. Weights are
+        // kept relative to the heaviest total the board could have so they don't overflow
+        let ln_ways = ln_choose_all(interior);
+        let rests = |placed: usize| (left.0 - placed as i16).max(0)..=(left.1 - placed as i16).min(interior);
+        let heaviest = (0..=frontier.len()).flat_map(rests).map(|rest| ln_ways[rest as usize]).fold(f64::NEG_INFINITY, f64::max);
+        if heaviest == f64::NEG_INFINITY {
+            return None;
+        }
+        let weights: Vec<f64> = (0..=frontier.len()).map(|placed| rests(placed).map(|rest| (ln_ways[rest as usize] - heaviest).exp()).sum()).collect();
+        // Ways the regions before and after each one can hold each number of mines between them
+        let mut before: Vec<Vec<f64>> = vec![vec![1.0]];
+        for tally in &tallies {
+            before.push(convolve(before.last().unwrap(), &tally.configurations));
+        }
+        let mut after: Vec<Vec<f64>> = vec![vec![1.0]];
+        for tally in tallies.iter().rev() {
+            after.push(convolve(after.last().unwrap(), &tally.configurations));
+        }
+        after.reverse();
+        let all = &before[tallies.len()];
+        let total: f64 = all.iter().enumerate().map(|(placed, ways)| ways * weights[placed]).sum();
+        if total <= 0.0 {
+            return None;
+        }
+        let interior_mines: f64 = all.iter().enumerate()
+            .map(|(placed, ways)| ways * rests(placed).map(|rest| (ln_ways[rest as usize] - heaviest).exp() * rest as f64).sum::<f64>())
+            .sum();
+        let mut chances = vec![vec![None; self.width as usize]; self.height as usize];
+        for (index, (region, tally)) in regions.iter().zip(&tallies).enumerate() {
+            // Weight of each number of mines in this region, with the other regions and the interior taken into account
+            let others = convolve(&before[index], &after[index + 1]);
+            let weight: Vec<f64> = (0..tally.configurations.len())
+                .map(|mines| others.iter().enumerate().map(|(placed, ways)| ways * weights[placed + mines]).sum())
+                .collect();
+            for (cell, space) in region.iter().enumerate() {
+                let mine: f64 = tally.mines.iter().zip(&weight).map(|(mines, weight)| mines[cell] * weight).sum();
+                chances[space.1 as usize][space.0 as usize] = Some(mine / total);
+            }
+        }
+        for i in 0..self.height {
+            for j in 0..self.width {
+                if self.is_known_mine(j, i) {
+                    chances[i as usize][j as usize] = Some(1.0);
+                } else if self.is_covered(j, i) && chances[i as usize][j as usize].is_none() {
+                    chances[i as usize][j as usize] = Some(interior_mines / total / interior as f64);
+                }
+            }
+        }
+        Some(chances)
+    }
+    ///
+    /// Runs `solve` on each region, on every core at once when built with the `parallel` feature.
+    /// The results come back in the same order as the regions
+    ///
+    fn each_region<T, F>(&self, regions: &[Vec<(i16, i16)>], solve: F) -> Vec<T>
+    where
+        T: Send,
+        F: Fn(&Vec<(i16, i16)>) -> T + Sync + Send,
+    {
+        #[cfg(feature = "parallel")]
+        return regions.par_iter().map(solve).collect();
+        #[cfg(not(feature = "parallel"))]
+        return regions.iter().map(solve).collect();
+    }
+    ///
+    /// Removes duplicates, and returns `None` if the rule didn't find anything
+    ///
+    fn collect(rule: Rule, mut safe: Vec<(i16, i16)>, mut mines: Vec<(i16, i16)>) -> Option<Deduction> {
+        safe.sort();
+        safe.dedup();
+        mines.sort();
+        mines.dedup();
+        if safe.is_empty() && mines.is_empty() {
+            return None;
+        }
+        Some(Deduction { rule, safe, mines })
+    }
+    ///
+    /// Enumerates each region separately and keeps the spaces whose value never changes.
+    /// Regions too large to enumerate are skipped, so the result may be incomplete but is never wrong
+    ///
+    fn get_certain(&self, mines: bool) -> Vec<(i16, i16)> {
+        let _span = tracing::info_span!("solver_certain", mines = mines).entered();
+        let mut certain: Vec<(i16, i16)> = vec![];
+        let regions = self.get_regions();
+        let solved = self.each_region(&regions, |region| {
+            if region.len() > MAX_REGION { None } else { self.enumerate(region, false, MAX_CONFIGURATIONS) }
+        });
+        for (region, found) in regions.iter().zip(solved) {
+            let found = match found {
+                Some(f) if !f.is_empty() => f,
+                _ => continue,
+            };
+            for (index, space) in region.iter().enumerate() {
+                if found.iter().all(|config| config[index] == mines) {
+                    certain.push(*space);
+                }
+            }
+        }
+        tracing::debug!(mines = mines, found = certain.len(), "certain spaces");
+        certain
+    }
+    ///
+    /// Lists every mine assignment of `cells` that satisfies the revealed numbers touching them,
+    /// optionally also respecting the total mine count. Returns `None` past `limit` configurations
+    ///
+    fn enumerate(&self, cells: &[(i16, i16)], use_total: bool, limit: usize) -> Option<Vec<Vec<bool>>> {
+        let constraints = self.get_constraints(cells);
+        // Spaces that are covered but not being enumerated can hold any leftover mines
+        let mut interior: i16 = 0;
+        for i in 0..self.height {
+            for j in 0..self.width {
+                if self.is_covered(j, i) && !cells.contains(&(j, i)) {
+                    interior += 1;
+                }
+            }
+        }
+        let total = if use_total { Some((self.mines_left(), interior)) } else { None };
+        let mut assignment: Vec<Option<bool>> = vec![None; cells.len()];
+        let mut found: Vec<Vec<bool>> = vec![];
+        if !self.backtrack(0, &constraints, total, &mut assignment, &mut found, limit) {
+            return None;
+        }
+        Some(found)
+    }
+    ///
+    /// Recursively tries both values for the frontier space at `index`.
+    /// Returns false if the configuration limit was exceeded
+    ///
+    fn backtrack(&self, index: usize, constraints: &[Constraint], total: Option<((i16, i16), i16)>,
+        assignment: &mut Vec<Option<bool>>, found: &mut Vec<Vec<bool>>, limit: usize) -> bool {
+        // Prune on the revealed numbers
+        for constraint in constraints {
+            let mut mines = 0;
+            let mut unknown = 0;
+            for cell in &constraint.cells {
+                match assignment[*cell] {
+                    Some(true) => mines += 1,
+                    None => unknown += 1,
+                    _ => {}
+                }
+            }
+            if mines > constraint.mines || mines + unknown < constraint.mines {
+                return true;
+            }
+        }
+        // Prune on the total mine count, if it is being used
+        let placed = assignment.iter().filter(|a| **a == Some(true)).count() as i16;
+        if let Some(((_, most), _)) = total && placed > most {
+            return true;
+        }
+        if index == assignment.len() {
+            let fits = match total {
+                Some(((fewest, _), interior)) => fewest - placed <= interior,
+                None => true,
+            };
+            if fits {
+                if found.len() >= limit {
+                    return false;
+                }
+                found.push(assignment.iter().map(|a| *a == Some(true)).collect());
+            }
+            return true;
+        }
+        for value in [false, true] {
+            assignment[index] = Some(value);
+            if !self.backtrack(index + 1, constraints, total, assignment, found, limit) {
+                return false;
+            }
+        }
+        assignment[index] = None;
+        true
+    }
+}
+
+///
+/// Natural log of the number of ways to choose each number of things out of `n`, from 0 to `n`
+///
+fn ln_choose_all(n: i16) -> Vec<f64> {
+    let mut ln: Vec<f64> = vec![0.0];
+    for r in 1..=n {
+        ln.push(ln[r as usize - 1] + ((n - r + 1) as f64).ln() - (r as f64).ln());
+    }
+    ln
+}
+
+///
+/// Ways two independent sets of regions can hold each number of mines between them, from the ways each can on its own
+///
+fn convolve(a: &[f64], b: &[f64]) -> Vec<f64> {
+    let mut ways = vec![0.0; a.len() + b.len() - 1];
+    for (i, x) in a.iter().enumerate() {
+        for (j, y) in b.iter().enumerate() {
+            ways[i + j] += x * y;
+        }
+    }
+    ways
+}
+
+///
+/// Formats a space the way the game shows coordinates, counting from 1
+///
+fn at(space: (i16, i16)) -> String {
+    format!("({}, {})", space.0 + 1, space.1 + 1)
+}
+
+///
+/// A word with an "s" on the end unless there's exactly one
+///
+fn plural(count: i16, word: &str) -> String {
+    if count == 1 { String::from(word) } else { format!("{}s", word) }
+}
+
+///
+/// Finds every covered space on `board` that the player can prove is safe
+///
+/// ```
+/// use minesweeper::engine::Board;
+/// use minesweeper::layout::Layout;
+///
+/// // The bottom row reads 1 2 1, which only works out with mines in the corners
+/// let mut board = Board::from_layout(&Layout::from_art("*.*\n...").unwrap()).unwrap();
+/// for x in 0..3 {
+///     board.reveal(x, 1);
+/// }
+/// assert_eq!(minesweeper::solver::certain_safe(&board), vec![(1, 0)]);
+/// assert_eq!(minesweeper::solver::certain_mines(&board), vec![(0, 0), (2, 0)]);
+/// ```
+///
+pub fn certain_safe(board: &Board) -> Vec<(i16, i16)> {
+    board.get_solver().certain_safe()
+}
+
+///
+/// Finds every covered space on `board` that the player can prove is a mine
+///
+pub fn certain_mines(board: &Board) -> Vec<(i16, i16)> {
+    board.get_solver().certain_mines()
+}
+
+///
+/// The chance of each covered space on `board` being a mine, going only by what the player can see.
+/// See `Solver::probabilities`
+///
+pub fn probabilities(board: &Board) -> Option<Vec<Vec<Option<f64>>>> {
+    board.get_solver().probabilities()
+}
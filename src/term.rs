@@ -0,0 +1,305 @@
+use crossterm::{
+    cursor::{MoveTo, RestorePosition, SavePosition},
+    style::{Attribute, Color, ResetColor, SetAttribute, SetBackgroundColor, SetForegroundColor},
+    terminal::{BeginSynchronizedUpdate, Clear, ClearType, EndSynchronizedUpdate},
+    execute, queue
+};
+use ratatui::backend::{Backend, CrosstermBackend, FromCrossterm};
+use ratatui::buffer::Buffer;
+use ratatui::layout::{Position, Rect};
+use ratatui::style::{self as cell, Modifier, Style};
+
+use std::io::{Stdout, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+
+///
+/// The terminal multiplexer the game is running inside of, if any
+///
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub(crate) enum Multiplexer {
+    None,
+    Tmux,
+    Screen,
+}
+
+///
+/// Detects tmux / screen from the environment. Checked once, since it can't change while running
+///
+pub(crate) fn multiplexer() -> Multiplexer {
+    static DETECTED: OnceLock<Multiplexer> = OnceLock::new();
+    *DETECTED.get_or_init(|| {
+        if std::env::var_os("TMUX").is_some() {
+            return Multiplexer::Tmux;
+        }
+        if std::env::var_os("STY").is_some() {
+            return Multiplexer::Screen;
+        }
+        // Nested sessions (e.g. over SSH) only carry TERM across
+        let term = std::env::var("TERM").unwrap_or_default();
+        if term.starts_with("tmux") {
+            return Multiplexer::Tmux;
+        }
+        if term.starts_with("screen") {
+            return Multiplexer::Screen;
+        }
+        Multiplexer::None
+    })
+}
+
+///
+/// Whether the terminal understands the cursor shape sequence. Screen passes it through as garbage
+///
+pub(crate) fn supports_cursor_style() -> bool {
+    multiplexer() != Multiplexer::Screen
+}
+
+///
+/// Whether colors are turned off with `NO_COLOR`, which crossterm honors by leaving them out
+///
+pub(crate) fn no_color() -> bool {
+    std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty())
+}
+
+///
+/// The part of the screen the board is drawn in, through ratatui. `cells` holds what the board should look like
+/// and `shown` what was last sent, so only the cells that changed in between are drawn
+///
+struct BoardView {
+    backend: CrosstermBackend<Stdout>,
+    cells: Buffer,
+    shown: Buffer,
+    changed: Option<Rect>, // The part of `cells` drawn into since the last send
+}
+
+static BOARD_VIEW: Mutex<Option<BoardView>> = Mutex::new(None);
+// Whether a frame is being drawn, in which case the board's changes go out with the rest of it when it ends
+static IN_FRAME: AtomicBool = AtomicBool::new(false);
+
+///
+/// Starts a synchronized update so a full frame shows up all at once instead of tearing.
+/// tmux handles these itself (older versions ignore them); screen doesn't know them, so they are skipped there
+///
+pub(crate) fn begin_frame() {
+    IN_FRAME.store(true, Ordering::Relaxed);
+    if multiplexer() != Multiplexer::Screen {
+        queue!(std::io::stdout(), BeginSynchronizedUpdate).ok();
+    }
+}
+
+///
+/// Ends a synchronized update started with `begin_frame` and flushes it out
+///
+pub(crate) fn end_frame() {
+    IN_FRAME.store(false, Ordering::Relaxed);
+    flush_board();
+    if multiplexer() != Multiplexer::Screen {
+        queue!(std::io::stdout(), EndSynchronizedUpdate).ok();
+    }
+    std::io::stdout().flush().ok();
+}
+
+///
+/// Gives the board a new area of the screen to be drawn in, starting out blank, e.g. just after `clear`
+///
+pub(crate) fn board_view(area: Rect) {
+    let backend = CrosstermBackend::new(std::io::stdout());
+    *BOARD_VIEW.lock().unwrap() = Some(BoardView { backend, cells: Buffer::empty(area), shown: Buffer::empty(area), changed: None });
+}
+
+///
+/// Changes the board's cells in `area` with `draw`, then sends the ones that changed, or leaves them for the end
+/// of the frame if one is being drawn. Without a board on the screen there's nothing to draw into
+///
+pub(crate) fn draw_board<F: FnOnce(&mut Buffer)>(area: Rect, draw: F) {
+    if let Some(view) = BOARD_VIEW.lock().unwrap().as_mut() {
+        draw(&mut view.cells);
+        let area = area.intersection(view.cells.area);
+        view.changed = Some(view.changed.map_or(area, |changed| changed.union(area)));
+    }
+    if !IN_FRAME.load(Ordering::Relaxed) {
+        flush_board();
+    }
+}
+
+///
+/// Sends the board's cells that changed since they were last sent. Only the part drawn into is compared,
+/// so putting one space right doesn't go over the whole board. The cursor is put back where it was after,
+/// the same as if nothing had been drawn
+///
+fn flush_board() {
+    let mut view = BOARD_VIEW.lock().unwrap();
+    let Some(view) = view.as_mut() else {
+        return;
+    };
+    let Some(area) = view.changed.take() else {
+        return;
+    };
+    let (mut before, mut after) = (Buffer::empty(area), Buffer::empty(area));
+    for position in area.positions() {
+        before[position] = view.shown[position].clone();
+        after[position] = view.cells[position].clone();
+    }
+    queue!(std::io::stdout(), SavePosition).ok();
+    view.backend.draw(before.diff(&after).into_iter()).ok();
+    queue!(std::io::stdout(), RestorePosition).ok();
+    for position in area.positions() {
+        view.shown[position] = after[position].clone();
+    }
+}
+
+///
+/// Puts `text` in the board's cells from a column and row of the screen, in colors written as SGR codes the way
+/// `paint` prints them, and gives back the column just after it. Anything past the board's area is left off
+///
+pub(crate) fn put(cells: &mut Buffer, column: u16, row: u16, codes: &[&str], text: &str) -> u16 {
+    let area = cells.area;
+    if !area.contains(Position::new(column, row)) {
+        return column.saturating_add(text.chars().count() as u16);
+    }
+    cells.set_stringn(column, row, text, (area.right() - column) as usize, cell_style(codes)).0
+}
+
+///
+/// Prepares the terminal for drawing.
+/// On Windows this turns on virtual terminal processing where the console supports it;
+/// legacy consoles that don't are drawn through the console API by crossterm instead
+///
+pub(crate) fn init() {
+    #[cfg(windows)]
+    {
+        crossterm::ansi_support::supports_ansi();
+    }
+}
+
+///
+/// Clears the whole screen and moves the cursor to the top left
+///
+pub(crate) fn clear() {
+    // The board goes with everything else, until it's given an area again
+    *BOARD_VIEW.lock().unwrap() = None;
+    execute!(std::io::stdout(), MoveTo(0, 0), Clear(ClearType::All)).ok();
+}
+
+///
+/// Clears from the cursor to the end of the line
+///
+pub(crate) fn clear_line() {
+    queue!(std::io::stdout(), Clear(ClearType::UntilNewLine)).ok();
+}
+
+///
+/// Clears from the cursor to the end of the screen
+///
+#[cfg(feature = "solver")]
+pub(crate) fn clear_below() {
+    queue!(std::io::stdout(), Clear(ClearType::FromCursorDown)).ok();
+}
+
+///
+/// Rings the terminal bell
+///
+pub(crate) fn beep() {
+    print!("\x07");
+}
+
+///
+/// Applies colors written as SGR codes, the way they are stored in the save ("37", "100", "1;34").
+/// Goes through crossterm so the colors also work on consoles without ANSI support
+///
+pub(crate) fn style(codes: &[&str]) {
+    let mut stdout = std::io::stdout();
+    for sgr in sgr_codes(codes) {
+        match sgr {
+            Sgr::Reset => {
+                queue!(stdout, SetAttribute(Attribute::Reset), ResetColor).ok();
+            }
+            Sgr::Bold => {
+                queue!(stdout, SetAttribute(Attribute::Bold)).ok();
+            }
+            Sgr::Foreground(color) => {
+                queue!(stdout, SetForegroundColor(color)).ok();
+            }
+            Sgr::Background(color) => {
+                queue!(stdout, SetBackgroundColor(color)).ok();
+            }
+        }
+    }
+}
+
+///
+/// The same colors as a ratatui style, for cells drawn through it. Like `paint`, it starts from the terminal's defaults
+///
+pub(crate) fn cell_style(codes: &[&str]) -> Style {
+    sgr_codes(codes).fold(Style::reset(), |style, sgr| match sgr {
+        Sgr::Reset => Style::reset(),
+        Sgr::Bold => style.add_modifier(Modifier::BOLD),
+        Sgr::Foreground(color) => style.fg(cell::Color::from_crossterm(color)),
+        Sgr::Background(color) => style.bg(cell::Color::from_crossterm(color)),
+    })
+}
+
+///
+/// What one of the SGR codes the save uses does
+///
+enum Sgr {
+    Reset,
+    Bold,
+    Foreground(Color),
+    Background(Color),
+}
+
+///
+/// Reads SGR codes, skipping any the game doesn't draw with
+///
+fn sgr_codes<'a>(codes: &'a [&str]) -> impl Iterator<Item = Sgr> + 'a {
+    codes.iter().flat_map(|c| c.split(';')).filter_map(|code| {
+        match code.trim().parse::<u8>().ok()? {
+            0 => Some(Sgr::Reset),
+            1 => Some(Sgr::Bold),
+            n @ (30..=37 | 90..=97) => Some(Sgr::Foreground(sgr_color(n % 10, n >= 90))),
+            n @ (40..=47 | 100..=107) => Some(Sgr::Background(sgr_color(n % 10, n >= 100))),
+            _ => None,
+        }
+    })
+}
+
+///
+/// Resets the colors and attributes back to the terminal's defaults
+///
+pub(crate) fn reset() {
+    queue!(std::io::stdout(), SetAttribute(Attribute::Reset), ResetColor).ok();
+}
+
+///
+/// Prints `text` in the given colors, then resets them
+///
+pub(crate) fn paint(codes: &[&str], text: &str) {
+    style(codes);
+    print!("{}", text);
+    reset();
+}
+
+///
+/// Maps one of the 8 SGR color indices to a crossterm color
+///
+fn sgr_color(index: u8, bright: bool) -> Color {
+    match (index, bright) {
+        (0, false) => Color::Black,
+        (1, false) => Color::DarkRed,
+        (2, false) => Color::DarkGreen,
+        (3, false) => Color::DarkYellow,
+        (4, false) => Color::DarkBlue,
+        (5, false) => Color::DarkMagenta,
+        (6, false) => Color::DarkCyan,
+        (7, false) => Color::Grey,
+        (0, true) => Color::DarkGrey,
+        (1, true) => Color::Red,
+        (2, true) => Color::Green,
+        (3, true) => Color::Yellow,
+        (4, true) => Color::Blue,
+        (5, true) => Color::Magenta,
+        (6, true) => Color::Cyan,
+        _ => Color::White,
+    }
+}
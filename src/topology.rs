@@ -0,0 +1,221 @@
+///
+/// The shape of a board: which spaces exist, which spaces count as next to each other,
+/// and where each space goes on screen. Numbers, flood fills, chords, and generation all go through it
+///
+pub trait Topology: Send {
+    ///
+    /// Name that `from_name` turns back into this topology
+    ///
+    fn name(&self) -> String;
+    ///
+    /// Every space next to (x, y) on a `width` x `height` board, not counting (x, y) itself
+    ///
+    fn neighbors(&self, x: i16, y: i16, width: i16, height: i16) -> Vec<(i16, i16)>;
+    ///
+    /// Whether or not (x, y) is part of the board. Every space in the grid is, unless the board has holes in it
+    ///
+    fn contains(&self, _x: i16, _y: i16, _width: i16, _height: i16) -> bool {
+        true
+    }
+    ///
+    /// Where the space is drawn, as a column and row inside the board's border. Each space is 3 columns wide
+    ///
+    fn screen_offset(&self, x: i16, y: i16) -> (i16, i16) {
+        (3 * x, y)
+    }
+    ///
+    /// How many columns wide the inside of the board's border is
+    ///
+    fn screen_width(&self, width: i16, _height: i16) -> i16 {
+        3 * width
+    }
+    ///
+    /// Whether or not this is the plain grid with 8 neighbors and no holes, the only one the solver understands
+    ///
+    fn is_plain_grid(&self) -> bool {
+        false
+    }
+}
+
+///
+/// Keeps the spaces that fall on the board out of a list of (dx, dy) steps from (x, y)
+///
+fn steps(x: i16, y: i16, width: i16, height: i16, deltas: &[(i16, i16)]) -> Vec<(i16, i16)> {
+    let mut neighbors: Vec<(i16, i16)> = vec![];
+    for (dx, dy) in deltas {
+        let nx = x + dx;
+        let ny = y + dy;
+        if nx >= 0 && nx < width && ny >= 0 && ny < height {
+            neighbors.push((nx, ny));
+        }
+    }
+    neighbors
+}
+
+const KING_STEPS: [(i16, i16); 8] = [(-1, 0), (1, 0), (0, -1), (0, 1), (-1, -1), (1, -1), (-1, 1), (1, 1)];
+
+///
+/// The usual board: a grid where every space touches the 8 around it
+///
+pub struct SquareEight;
+
+impl Topology for SquareEight {
+    fn name(&self) -> String {
+        String::from("square8")
+    }
+    fn neighbors(&self, x: i16, y: i16, width: i16, height: i16) -> Vec<(i16, i16)> {
+        steps(x, y, width, height, &KING_STEPS)
+    }
+    fn is_plain_grid(&self) -> bool {
+        true
+    }
+}
+
+///
+/// A grid where spaces only touch the 4 they share a side with
+///
+pub struct SquareFour;
+
+impl Topology for SquareFour {
+    fn name(&self) -> String {
+        String::from("square4")
+    }
+    fn neighbors(&self, x: i16, y: i16, width: i16, height: i16) -> Vec<(i16, i16)> {
+        steps(x, y, width, height, &[(-1, 0), (1, 0), (0, -1), (0, 1)])
+    }
+}
+
+///
+/// Hexagons, laid out as rows with every odd row pushed over by half a space. Each space touches 6 others
+///
+pub struct Hex;
+
+impl Topology for Hex {
+    fn name(&self) -> String {
+        String::from("hex")
+    }
+    fn neighbors(&self, x: i16, y: i16, width: i16, height: i16) -> Vec<(i16, i16)> {
+        if y % 2 == 0 {
+            return steps(x, y, width, height, &[(-1, 0), (1, 0), (-1, -1), (0, -1), (-1, 1), (0, 1)]);
+        }
+        steps(x, y, width, height, &[(-1, 0), (1, 0), (0, -1), (1, -1), (0, 1), (1, 1)])
+    }
+    fn screen_offset(&self, x: i16, y: i16) -> (i16, i16) {
+        (3 * x + y % 2, y)
+    }
+    fn screen_width(&self, width: i16, height: i16) -> i16 {
+        if height > 1 { 3 * width + 1 } else { 3 * width }
+    }
+}
+
+///
+/// A grid where spaces touch the ones a knight's move away
+///
+pub struct Knight;
+
+impl Topology for Knight {
+    fn name(&self) -> String {
+        String::from("knight")
+    }
+    fn neighbors(&self, x: i16, y: i16, width: i16, height: i16) -> Vec<(i16, i16)> {
+        steps(x, y, width, height, &[(-2, -1), (-1, -2), (1, -2), (2, -1), (-2, 1), (-1, 2), (1, 2), (2, 1)])
+    }
+}
+
+///
+/// The usual 8 neighbors, but the edges wrap around to the other side
+///
+pub struct Torus;
+
+impl Topology for Torus {
+    fn name(&self) -> String {
+        String::from("torus")
+    }
+    fn neighbors(&self, x: i16, y: i16, width: i16, height: i16) -> Vec<(i16, i16)> {
+        let mut neighbors: Vec<(i16, i16)> = vec![];
+        for (dx, dy) in KING_STEPS {
+            let next = ((x + dx).rem_euclid(width), (y + dy).rem_euclid(height));
+            // Narrow boards wrap onto the same spaces more than once
+            if next != (x, y) && !neighbors.contains(&next) {
+                neighbors.push(next);
+            }
+        }
+        neighbors
+    }
+}
+
+///
+/// The usual 8 neighbors on a grid with holes in it. Holes can't hold mines and are never opened.
+/// Without a list of holes, the board is cut down to a round shape
+///
+pub struct Masked {
+    holes: Option<Vec<(i16, i16)>>,
+}
+
+impl Masked {
+    ///
+    /// A board with holes at the given spaces
+    ///
+    pub fn new(holes: Vec<(i16, i16)>) -> Masked {
+        Masked { holes: Some(holes) }
+    }
+    ///
+    /// A round board, filling as much of the grid as a circle (or oval) can
+    ///
+    pub fn round() -> Masked {
+        Masked { holes: None }
+    }
+}
+
+impl Topology for Masked {
+    fn name(&self) -> String {
+        match &self.holes {
+            Some(holes) => {
+                let holes: Vec<String> = holes.iter().map(|h| format!("{},{}", h.0, h.1)).collect();
+                format!("masked:{}", holes.join(";"))
+            }
+            None => String::from("masked"),
+        }
+    }
+    fn neighbors(&self, x: i16, y: i16, width: i16, height: i16) -> Vec<(i16, i16)> {
+        if !self.contains(x, y, width, height) {
+            return vec![];
+        }
+        steps(x, y, width, height, &KING_STEPS).into_iter().filter(|s| self.contains(s.0, s.1, width, height)).collect()
+    }
+    fn contains(&self, x: i16, y: i16, width: i16, height: i16) -> bool {
+        match &self.holes {
+            Some(holes) => !holes.contains(&(x, y)),
+            None => {
+                // Measured from the middle of each space, so the middle of the board is always kept
+                let dx = (x as f64 + 0.5 - width as f64 / 2.0) / (width as f64 / 2.0);
+                let dy = (y as f64 + 0.5 - height as f64 / 2.0) / (height as f64 / 2.0);
+                dx * dx + dy * dy <= 1.0
+            }
+        }
+    }
+}
+
+///
+/// Makes the topology with the given name: "square8", "square4", "hex", "knight", "torus", "masked",
+/// or "masked:x,y;x,y;..." for holes at the listed spaces. Returns `None` for names it doesn't know
+///
+pub fn from_name(name: &str) -> Option<Box<dyn Topology>> {
+    match name {
+        "square8" => Some(Box::new(SquareEight)),
+        "square4" => Some(Box::new(SquareFour)),
+        "hex" => Some(Box::new(Hex)),
+        "knight" => Some(Box::new(Knight)),
+        "torus" => Some(Box::new(Torus)),
+        "masked" => Some(Box::new(Masked::round())),
+        _ => {
+            let list = name.strip_prefix("masked:")?;
+            let mut holes: Vec<(i16, i16)> = vec![];
+            for hole in list.split(';').filter(|h| !h.is_empty()) {
+                let (x, y) = hole.split_once(',')?;
+                holes.push((x.trim().parse().ok()?, y.trim().parse().ok()?));
+            }
+            Some(Box::new(Masked::new(holes)))
+        }
+    }
+}
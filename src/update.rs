@@ -0,0 +1,121 @@
+//! Checking for a newer release, for players who turn `update_check` on in the save. The check is made at most once
+//! a day, off the game's thread, and never downloads anything: a newer version only gets a line on the menu with
+//! where to read what changed. Only built with the `update-check` feature.
+
+use crate::saves::Save;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// How long the answer to a check is kept before asking again
+const CHECK_EVERY: Duration = Duration::from_secs(24 * 60 * 60);
+// Longest a check can take before it's given up on until the next day
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+// The newer release, once a check has found one
+static NEWER: OnceLock<Release> = OnceLock::new();
+
+///
+/// The latest release, as the endpoint gave it
+///
+#[derive(Serialize, Deserialize, Clone)]
+struct Release {
+    version: String,   // e.g. "0.2.0", with or without a leading "v"
+    changelog: String, // Page saying what's in it
+}
+
+///
+/// The last check, kept in `update_check.json` next to the executable
+///
+#[derive(Serialize, Deserialize)]
+struct Cache {
+    checked: u64,            // When it was made, in seconds since the Unix epoch
+    url: String,             // The endpoint it asked, so changing `update_url` checks again
+    latest: Option<Release>, // What it found, or `None` if it couldn't tell
+}
+
+///
+/// Starts checking for a newer release in the background, if the save asks for it.
+/// Whatever turns up is shown by the menu through `notice`
+///
+pub(crate) fn start(save: &Save) {
+    if !save.update_check || save.update_url.is_empty() {
+        return;
+    }
+    let url = save.update_url.clone();
+    thread::Builder::new().name(String::from("update check")).spawn(move || {
+        if let Some(latest) = latest(&url) && is_newer(&latest.version, env!("CARGO_PKG_VERSION")) {
+            NEWER.set(latest).ok();
+        }
+    }).ok();
+}
+
+///
+/// The menu's line about a newer release, once one has been found
+///
+pub(crate) fn notice() -> Option<String> {
+    NEWER.get().map(|release| format!("Version {} is out (this is {}). What's new: {}",
+        release.version.trim_start_matches('v'), env!("CARGO_PKG_VERSION"), release.changelog))
+}
+
+///
+/// The latest release, from the cache if it was checked in the last day, otherwise asked for and cached.
+/// A check that fails is cached too, so an endpoint that's down isn't asked again every time the game starts
+///
+fn latest(url: &str) -> Option<Release> {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).ok()?.as_secs();
+    let path = cache_path()?;
+    let cached: Option<Cache> = fs::read_to_string(&path).ok().and_then(|text| serde_json::from_str(&text).ok());
+    if let Some(cache) = cached.filter(|c| c.url == url && now.saturating_sub(c.checked) < CHECK_EVERY.as_secs()) {
+        return cache.latest;
+    }
+    let latest = fetch(url);
+    let cache = Cache { checked: now, url: url.to_owned(), latest: latest.clone() };
+    if let Ok(text) = serde_json::to_string(&cache) {
+        fs::write(&path, text).ok();
+    }
+    latest
+}
+
+///
+/// Asks the endpoint for its latest release. It's expected to answer like GitHub's latest release API,
+/// with the version in `tag_name` and its page in `html_url`
+///
+fn fetch(url: &str) -> Option<Release> {
+    let agent = ureq::Agent::config_builder()
+        .timeout_global(Some(TIMEOUT))
+        .user_agent(concat!("minesweeper/", env!("CARGO_PKG_VERSION")))
+        .build()
+        .new_agent();
+    let body = agent.get(url).call().ok()?.body_mut().read_to_string().ok()?;
+    let answer: serde_json::Value = serde_json::from_str(&body).ok()?;
+    let version = answer.get("tag_name")?.as_str()?.to_owned();
+    let changelog = answer.get("html_url").and_then(|u| u.as_str()).unwrap_or(url).to_owned();
+    Some(Release { version, changelog })
+}
+
+///
+/// Whether `latest` is a later version than `current`, going by their dotted numbers. Anything after a number,
+/// like "-beta", is left out, and versions that aren't numbers at all never count as newer
+///
+fn is_newer(latest: &str, current: &str) -> bool {
+    let parts = |version: &str| -> Option<Vec<u64>> {
+        version.trim().trim_start_matches('v').split('.')
+            .map(|part| part.chars().take_while(|c| c.is_ascii_digit()).collect::<String>().parse().ok())
+            .collect()
+    };
+    match (parts(latest), parts(current)) {
+        (Some(latest), Some(current)) => latest > current,
+        _ => false,
+    }
+}
+
+///
+/// Where the cache goes: next to the executable, like the save
+///
+fn cache_path() -> Option<PathBuf> {
+    Some(std::env::current_exe().ok()?.parent()?.join("update_check.json"))
+}
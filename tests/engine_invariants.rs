@@ -0,0 +1,466 @@
+use minesweeper::engine::{Board, GameState, ManualClock};
+use minesweeper::layout::Layout;
+use minesweeper::rules;
+use minesweeper::topology;
+#[cfg(feature = "solver")]
+use minesweeper::solver::{self, Rule, Solver, Tile};
+use proptest::prelude::*;
+use std::time::Duration;
+
+#[derive(Clone, Debug)]
+enum Action {
+    Reveal(i16, i16),
+    Flag(i16, i16),
+    Chord(i16, i16),
+}
+
+///
+/// A random board size with a mine count that always leaves room for the first click
+///
+fn board_params() -> impl Strategy<Value = (i16, i16, i16, u64)> {
+    (1i16..12, 1i16..12).prop_flat_map(|(width, height)| {
+        (Just(width), Just(height), 0..(width * height), any::<u64>())
+    })
+}
+
+fn actions(width: i16, height: i16) -> impl Strategy<Value = Vec<Action>> {
+    let action = (0..3u8, 0..width, 0..height).prop_map(|(kind, x, y)| match kind {
+        0 => Action::Reveal(x, y),
+        1 => Action::Flag(x, y),
+        _ => Action::Chord(x, y),
+    });
+    prop::collection::vec(action, 0..80)
+}
+
+///
+/// Plays an action on a board, returning the spaces it uncovered
+///
+fn apply(board: &mut Board, action: &Action) -> Vec<(i16, i16)> {
+    match *action {
+        Action::Reveal(x, y) => board.reveal(x, y),
+        Action::Flag(x, y) => {
+            board.flag(x, y);
+            vec![]
+        }
+        Action::Chord(x, y) => board.chord(x, y),
+    }
+}
+
+fn game() -> impl Strategy<Value = ((i16, i16, i16, u64), Vec<Action>)> {
+    board_params().prop_flat_map(|params| (Just(params), actions(params.0, params.1)))
+}
+
+proptest! {
+    #[test]
+    fn random_play_keeps_board_consistent(((width, height, mines, seed), actions) in game()) {
+        let mut board = Board::deterministic(width, height, mines, 0, seed, ManualClock::new());
+        prop_assert_eq!(board.debug_validate(), Ok(()));
+        for action in actions {
+            let before = board.state();
+            let uncovered = apply(&mut board, &action);
+            prop_assert_eq!(board.debug_validate(), Ok(()));
+            // Nothing changes once the game is over
+            if before == GameState::Win || before == GameState::Loss {
+                prop_assert_eq!(board.state(), before);
+                prop_assert!(uncovered.is_empty());
+            }
+            // Flagged spaces are never uncovered
+            for space in &uncovered {
+                prop_assert!(!board.is_flagged(space.0, space.1));
+            }
+        }
+    }
+
+    #[test]
+    fn flag_kinds_play_the_same_as_plain_flags(((width, height, mines, seed), actions) in game(), kinds in prop::collection::vec(1u8..4, 80)) {
+        let mut plain = Board::deterministic(width, height, mines, 0, seed, ManualClock::new());
+        let mut kinded = Board::deterministic(width, height, mines, 0, seed, ManualClock::new());
+        for (action, kind) in actions.into_iter().zip(kinds) {
+            prop_assert_eq!(apply(&mut plain, &action), apply(&mut kinded, &action));
+            if let Action::Flag(x, y) = action && kinded.set_flag_kind(x, y, kind) {
+                prop_assert_eq!(kinded.flag_kind(x, y), kind);
+            }
+            prop_assert_eq!(kinded.debug_validate(), Ok(()));
+            prop_assert_eq!(kinded.visible_board(), plain.visible_board());
+        }
+    }
+
+    #[test]
+    fn variant_rules_keep_board_consistent(((width, height, mines, seed), actions) in game(),
+        name in prop::sample::select(vec!["standard", "lives:3", "liar", "multi", "anti", "treasure", "hidden", "range:3", "bonus"])) {
+        let mut board = Board::deterministic(width, height, mines, 0, seed, ManualClock::new());
+        board.set_rules(rules::from_name(name).unwrap());
+        for action in actions {
+            apply(&mut board, &action);
+            prop_assert_eq!(board.debug_validate(), Ok(()));
+        }
+    }
+
+    #[test]
+    fn restoring_a_checkpoint_takes_every_move_back(((width, height, mines, seed), actions) in game(),
+        name in prop::sample::select(vec!["standard", "lives:2"])) {
+        let mut board = Board::deterministic(width, height, mines, 0, seed, ManualClock::new());
+        board.set_rules(rules::from_name(name).unwrap());
+        board.reveal(0, 0);
+        let checkpoint = board.checkpoint();
+        let before = board.visible_board();
+        let status = board.rules().status();
+        for action in actions {
+            apply(&mut board, &action);
+        }
+        board.restore(&checkpoint);
+        prop_assert_eq!(board.visible_board(), before);
+        prop_assert_eq!(board.rules().status(), status);
+        prop_assert_eq!(board.debug_validate(), Ok(()));
+    }
+
+    #[test]
+    fn time_bonuses_come_off_the_clock_while_they_last((width, height, mines, seed) in board_params(), wait in 0u64..60) {
+        let clock = ManualClock::new();
+        let mut board = Board::deterministic(width, height, mines, 0, seed, clock.clone());
+        board.set_rules(rules::from_name("bonus").unwrap());
+        board.reveal(0, 0);
+        clock.advance(Duration::from_secs(wait));
+        let before = board.elapsed();
+        let bonuses = board.bonuses();
+        if let Some(&(x, y)) = bonuses.first() {
+            prop_assert!(!board.is_mine(x, y));
+            board.reveal(x, y);
+            prop_assert!(!board.bonuses().contains(&(x, y)));
+            prop_assert_eq!(before - board.elapsed(), Duration::from_secs(5).min(before));
+        }
+        // Bonuses only last so long
+        clock.advance(Duration::from_secs(1000));
+        prop_assert!(board.bonuses().is_empty());
+    }
+
+    #[test]
+    fn time_bonuses_sit_next_to_a_mine_on_every_topology((width, height, mines, seed) in board_params(),
+        name in prop::sample::select(vec!["square8", "square4", "hex", "knight", "torus", "masked:0,0;1,1"])) {
+        let mut board = Board::deterministic(width, height, mines, 0, seed, ManualClock::new());
+        board.set_topology(topology::from_name(name).unwrap());
+        board.set_rules(rules::from_name("bonus").unwrap());
+        let start = (0..height).flat_map(|y| (0..width).map(move |x| (x, y))).find(|&(x, y)| board.is_space(x, y));
+        if let Some((x, y)) = start {
+            board.reveal(x, y);
+        }
+        // Numbers are counted on the board's own neighbors, so a bonus always shows one
+        for (x, y) in board.bonuses() {
+            prop_assert!(board.is_space(x, y) && !board.is_mine(x, y));
+            prop_assert!(board.mine_count_at(x, y) > 0);
+        }
+    }
+
+    #[test]
+    fn topologies_keep_board_consistent(((width, height, mines, seed), actions) in game(),
+        name in prop::sample::select(vec!["square8", "square4", "hex", "knight", "torus", "masked", "masked:0,0;1,1"])) {
+        let mut board = Board::deterministic(width, height, mines, 0, seed, ManualClock::new());
+        board.set_topology(topology::from_name(name).unwrap());
+        // Being next to each other goes both ways
+        for i in 0..height {
+            for j in 0..width {
+                for (x, y) in board.get_surrounding(j, i) {
+                    prop_assert!(board.get_surrounding(x, y).contains(&(j, i)));
+                }
+            }
+        }
+        for action in actions {
+            apply(&mut board, &action);
+            prop_assert_eq!(board.debug_validate(), Ok(()));
+        }
+    }
+
+    #[test]
+    fn paused_time_is_not_counted(((width, height, mines, seed), actions) in game(),
+        stretches in prop::collection::vec((any::<bool>(), 0u64..5000), 80)) {
+        let clock = ManualClock::new();
+        let mut board = Board::deterministic(width, height, mines, 0, seed, clock.clone());
+        let mut expected = Duration::ZERO;
+        for (action, (paused, ms)) in actions.into_iter().zip(stretches) {
+            if paused {
+                board.pause();
+            } else {
+                board.resume();
+            }
+            clock.advance(Duration::from_millis(ms));
+            let over = board.state() == GameState::Win || board.state() == GameState::Loss;
+            if !paused && !over {
+                expected += Duration::from_millis(ms);
+            }
+            prop_assert_eq!(board.elapsed(), expected);
+            apply(&mut board, &action);
+            prop_assert_eq!(board.debug_validate(), Ok(()));
+            // Moves take no time, including the one that ends the game
+            prop_assert_eq!(board.elapsed(), expected);
+        }
+    }
+
+    #[test]
+    fn first_reveal_is_never_a_mine((width, height, mines, seed) in board_params(), x in 0i16..12, y in 0i16..12) {
+        let mut board = Board::deterministic(width, height, mines, 0, seed, ManualClock::new());
+        board.reveal(x % width, y % height);
+        prop_assert_ne!(board.state(), GameState::Loss);
+    }
+
+    #[test]
+    fn bbbv_progress_only_grows_and_finishes_with_a_win(((width, height, mines, seed), actions) in game()) {
+        let mut board = Board::deterministic(width, height, mines, 0, seed, ManualClock::new());
+        let mut last_done = 0;
+        for action in actions {
+            apply(&mut board, &action);
+            if !board.is_generated() || board.state() == GameState::Loss {
+                continue;
+            }
+            let (done, bbbv) = board.get_3bv_progress();
+            prop_assert_eq!(bbbv, board.get_3bv());
+            prop_assert!(done >= last_done && done <= bbbv);
+            prop_assert_eq!(done == bbbv, board.state() == GameState::Win);
+            last_done = done;
+        }
+    }
+
+    #[test]
+    fn layouts_rebuild_the_same_numbers((width, height, mines, seed) in board_params()) {
+        let mut board = Board::deterministic(width, height, mines, 0, seed, ManualClock::new());
+        board.generate((0, 0));
+        let rebuilt = Board::from_layout(&Layout::from_board(&board).unwrap()).unwrap();
+        for i in 0..height {
+            for j in 0..width {
+                prop_assert_eq!(rebuilt.is_mine(j, i), board.is_mine(j, i));
+                prop_assert_eq!(rebuilt.mine_count_at(j, i), board.mine_count_at(j, i));
+            }
+        }
+    }
+
+    #[test]
+    fn same_seed_generates_same_board((width, height, mines, seed) in board_params()) {
+        let mut first = Board::deterministic(width, height, mines, 0, seed, ManualClock::new());
+        let mut second = Board::deterministic(width, height, mines, 0, seed, ManualClock::new());
+        first.generate((0, 0));
+        second.generate((0, 0));
+        for i in 0..height {
+            for j in 0..width {
+                prop_assert_eq!(first.is_mine(j, i), second.is_mine(j, i));
+            }
+        }
+    }
+
+    #[cfg(feature = "solver")]
+    #[test]
+    fn solver_agrees_with_the_board(((width, height, mines, seed), actions) in game()) {
+        let mut board = Board::deterministic(width, height, mines, 0, seed, ManualClock::new());
+        for action in actions {
+            if board.state() == GameState::Win || board.state() == GameState::Loss {
+                break;
+            }
+            apply(&mut board, &action);
+        }
+        if board.state() != GameState::Running {
+            return Ok(());
+        }
+        let safe = solver::certain_safe(&board);
+        let certain = solver::certain_mines(&board);
+        for space in &safe {
+            prop_assert!(!board.is_mine(space.0, space.1));
+        }
+        for space in &certain {
+            prop_assert!(board.is_mine(space.0, space.1));
+        }
+        // Explained spaces are right too, and every explanation has something to say
+        for inference in board.get_solver().infer() {
+            prop_assert!(!inference.reason.is_empty());
+            for space in &inference.spaces {
+                prop_assert_eq!(board.is_mine(space.0, space.1), inference.proof.mine);
+            }
+        }
+        // Every covered space's chance adds up to the number of mines
+        if let Some(chances) = solver::probabilities(&board) {
+            let mut total = 0.0;
+            for i in 0..height {
+                for j in 0..width {
+                    match chances[i as usize][j as usize] {
+                        Some(chance) => {
+                            prop_assert!((-1e-9..=1.0 + 1e-9).contains(&chance));
+                            prop_assert!(!safe.contains(&(j, i)) || chance < 1e-9);
+                            prop_assert!(!certain.contains(&(j, i)) || chance > 1.0 - 1e-9);
+                            total += chance;
+                        }
+                        None => prop_assert!(board.is_uncovered(j, i)),
+                    }
+                }
+            }
+            prop_assert!((total - mines as f64).abs() < 1e-6, "{} != {}", total, mines);
+        }
+    }
+
+    #[cfg(feature = "solver")]
+    #[test]
+    fn chances_match_counting_every_way_the_mines_could_lie((width, height) in (2i16..7, 2i16..6), mines in 1i16..8, seed in any::<u64>(),
+        checks in prop::collection::vec((0i16..7, 0i16..6), 1..6)) {
+        // Small boards, so every way the mines could lie can be counted
+        let mines = mines.min(width * height / 3).max(1);
+        let mut board = Board::deterministic(width, height, mines, 0, seed, ManualClock::new());
+        for (x, y) in checks {
+            board.reveal(x % width, y % height);
+        }
+        let covered: Vec<(i16, i16)> = (0..height).flat_map(|y| (0..width).map(move |x| (x, y))).filter(|&(x, y)| !board.is_uncovered(x, y)).collect();
+        if board.state() != GameState::Running || covered.len() > 16 {
+            return Ok(());
+        }
+        // Every set of covered spaces that could hold the mines, going by the numbers and the total
+        let numbers: Vec<((i16, i16), i16)> = (0..height).flat_map(|y| (0..width).map(move |x| (x, y)))
+            .filter(|&(x, y)| board.is_uncovered(x, y))
+            .map(|(x, y)| ((x, y), board.mine_count_at(x, y)))
+            .collect();
+        let mut ways = 0.0;
+        let mut mine_ways = vec![0.0; covered.len()];
+        for set in 0u32..1 << covered.len() {
+            if set.count_ones() != mines as u32 {
+                continue;
+            }
+            let fits = numbers.iter().all(|&((x, y), n)| {
+                board.get_surrounding(x, y).iter().filter(|s| covered.iter().position(|c| c == *s).is_some_and(|i| set & (1 << i) != 0)).count() as i16 == n
+            });
+            if fits {
+                ways += 1.0;
+                for (i, chance) in mine_ways.iter_mut().enumerate() {
+                    if set & (1 << i) != 0 {
+                        *chance += 1.0;
+                    }
+                }
+            }
+        }
+        let chances = solver::probabilities(&board).unwrap();
+        for (i, &(x, y)) in covered.iter().enumerate() {
+            let chance = chances[y as usize][x as usize].unwrap();
+            prop_assert!((chance - mine_ways[i] / ways).abs() < 1e-9, "{} != {} at ({}, {})", chance, mine_ways[i] / ways, x, y);
+        }
+    }
+
+    #[cfg(feature = "solver")]
+    #[test]
+    fn solver_reads_boards_that_hide_their_total(((width, height, mines, seed), actions) in game(),
+        name in prop::sample::select(vec!["hidden", "range:3", "range:32767"])) {
+        let mut board = Board::deterministic(width, height, mines, 0, seed, ManualClock::new());
+        board.set_rules(rules::from_name(name).unwrap());
+        for action in actions {
+            if board.state() == GameState::Win || board.state() == GameState::Loss {
+                break;
+            }
+            apply(&mut board, &action);
+        }
+        // The range shown always holds the real total
+        let range = board.rules().mine_range(mines);
+        if let Some((fewest, most)) = range {
+            prop_assert!(fewest <= mines && mines <= most);
+            prop_assert_eq!(board.flag_limit(), most);
+        }
+        if board.state() != GameState::Running {
+            return Ok(());
+        }
+        for space in solver::certain_safe(&board) {
+            prop_assert!(!board.is_mine(space.0, space.1));
+        }
+        for space in solver::certain_mines(&board) {
+            prop_assert!(board.is_mine(space.0, space.1));
+        }
+        // The chances add up to somewhere in the range the board could hold
+        if let Some(chances) = solver::probabilities(&board) {
+            let total: f64 = chances.iter().flatten().flatten().sum();
+            let (fewest, most) = range.unwrap_or((0, width * height));
+            prop_assert!(fewest as f64 - 1e-6 <= total && total <= most as f64 + 1e-6, "{} not in {}-{}", total, fewest, most);
+        }
+    }
+}
+
+#[test]
+fn boards_drawn_as_text_count_their_mines() {
+    let layout = Layout::from_art("
+        ..*..
+        .*...
+    ").unwrap();
+    assert_eq!((layout.width, layout.height), (5, 2));
+    let mut board = Board::from_layout(&layout).unwrap();
+    assert_eq!(board.m_count(), 2);
+    assert_eq!(board.mine_count_at(0, 0), 1);
+    assert_eq!(board.mine_count_at(1, 0), 2);
+    assert_eq!(board.mine_count_at(4, 1), 0);
+    // Nothing gets generated around the first check, so it can go straight into a mine
+    board.reveal(2, 0);
+    assert_eq!(board.state(), GameState::Loss);
+}
+
+#[test]
+fn spaces_keep_the_time_they_were_uncovered() {
+    let mut board = Board::from_layout(&Layout::from_art(".*...\n.....").unwrap()).unwrap();
+    let clock = ManualClock::new();
+    board.set_clock(Box::new(clock.clone()));
+    clock.advance(Duration::from_secs(2));
+    let opened = board.reveal(4, 0);
+    // A flood's spaces all open with the check that started it
+    assert_eq!(opened.len(), 6);
+    for (x, y) in opened {
+        assert_eq!(board.revealed_at(x, y), Some(Duration::from_secs(2)));
+    }
+    let before = board.checkpoint();
+    clock.advance(Duration::from_secs(3));
+    board.reveal(0, 0);
+    assert_eq!(board.revealed_at(0, 0), Some(Duration::from_secs(5)));
+    assert_eq!(board.revealed_at(1, 1), None);
+    board.restore(&before);
+    assert_eq!(board.revealed_at(0, 0), None);
+}
+
+#[test]
+fn boards_with_more_spaces_than_an_i16_holds_keep_flags_clear() {
+    let mut board = Board::deterministic(200, 200, 10, 0, 1, ManualClock::new());
+    board.set_pre_start_flags(true, true);
+    assert!(board.flag(5, 5));
+    board.generate((0, 0));
+    assert!(board.is_generated() && !board.is_mine(5, 5));
+}
+
+#[test]
+fn ranges_wider_than_an_i16_holds_stop_at_the_board() {
+    // The range only starts above 0 on the odd board, so try a lot of them
+    for seed in 0..2000 {
+        let mut board = Board::deterministic(30, 16, 400, 0, seed, ManualClock::new());
+        board.set_rules(rules::from_name("range:32767").unwrap());
+        board.generate((0, 0));
+        let (fewest, most) = board.rules().mine_range(400).unwrap();
+        assert!(fewest <= 400 && most == 479, "{}-{}", fewest, most);
+    }
+}
+
+#[test]
+fn layouts_that_cant_be_played_are_refused() {
+    assert!(Layout::from_art("..*\n.*").is_err());
+    assert!(Layout::from_art("..x").is_err());
+    assert!(Layout::from_art("**\n**").is_err());
+    assert!(Layout::from_art("").is_err());
+    assert_eq!(Layout::from_grid(&[vec![false, true], vec![false, false]]).unwrap(), Layout::from_art(".*\n..").unwrap());
+}
+
+#[cfg(feature = "solver")]
+#[test]
+fn solver_explains_what_it_proves() {
+    // A column of three covered spaces beside a column of 1s, with the mine in the middle
+    let tiles = vec![
+        vec![Tile::Covered, Tile::Revealed(1)],
+        vec![Tile::Covered, Tile::Revealed(1)],
+        vec![Tile::Covered, Tile::Revealed(1)],
+    ];
+    let inferences = Solver::new(2, 3, 1, tiles).infer();
+    assert_eq!(inferences.len(), 2);
+    assert!(inferences.iter().all(|i| i.proof.rule == Rule::Subset && !i.proof.mine));
+    assert_eq!(inferences[0].spaces, vec![(0, 0)]);
+    assert_eq!(inferences[0].reason, "Every unopened space next to the 1 at (2, 3) is also next to the 1 at (2, 2), which has all its mines among them already, so (1, 1) is safe");
+    // With the middle opened up, one number covers the rest at once
+    let tiles = vec![
+        vec![Tile::Covered, Tile::Covered],
+        vec![Tile::Revealed(2), Tile::Revealed(2)],
+    ];
+    let inferences = Solver::new(2, 2, 2, tiles).infer();
+    assert_eq!(inferences.len(), 1);
+    assert_eq!(inferences[0].reason, "The 2 at (1, 2) touches exactly 2 unopened spaces, so (1, 1) and (2, 1) are mines");
+}
@@ -0,0 +1,38 @@
+use minesweeper::locale::{self, Locale};
+
+use std::time::Duration;
+
+#[test]
+fn numbers_follow_the_language() {
+    let english = Locale::from_name("en_US.UTF-8");
+    assert_eq!(english.number(1234567), "1,234,567");
+    assert_eq!(english.decimal(1234.5, 2), "1,234.50");
+    let german = Locale::from_name("de_DE.UTF-8");
+    assert_eq!(german.number(1234567), "1.234.567");
+    assert_eq!(german.decimal(12.5, 2), "12,50");
+    assert_eq!(Locale::from_name("de_CH").number(12000), "12'000");
+    assert_eq!(Locale::from_name("fr").decimal(-1000.26, 1), "-1 000,3");
+    // Anything unknown reads the English way
+    assert_eq!(Locale::from_name("C"), Locale::default());
+}
+
+#[test]
+fn times_read_as_seconds_or_on_a_clock() {
+    let seconds = Locale::from_name("en");
+    assert_eq!(seconds.time(Duration::from_millis(65_340), 1), "65.3s");
+    assert_eq!(seconds.time(Duration::from_secs(5), 0), "5s");
+    let clock = Locale { clock: true, ..Locale::from_name("de") };
+    assert_eq!(clock.time(Duration::from_millis(65_340), 1), "1:05,3");
+    assert_eq!(clock.time(Duration::from_millis(59_960), 1), "1:00,0");
+    assert_eq!(clock.time(Duration::from_secs(5), 0), "0:05");
+    assert_eq!(clock.seconds(-2.5, 1), "-0:02,5");
+}
+
+#[test]
+fn dates_count_days_from_1970() {
+    assert_eq!(locale::date(0), "1970-01-01");
+    // Leap days, and the turn of a century that isn't a leap year
+    assert_eq!(locale::date(11_016), "2000-02-29");
+    assert_eq!(locale::date(47_541), "2100-03-01");
+    assert_eq!(locale::date(20_743), "2026-10-17");
+}
@@ -0,0 +1,57 @@
+use minesweeper::migrate::{self, Imported};
+
+#[test]
+fn arbiter_style_settings_come_across() {
+    let ini = "\
+; Comments and unknown entries are fine
+[Options]
+Show3BV=1
+ShowIOE=0
+Skin=Classic
+
+[Keys]
+Open=74
+Flag=k
+Chord=Space
+MoveUp=38
+
+[Colors]
+Color1=16711680
+Color9=255
+BorderColor=#FF8000
+BackgroundColor=192, 192, 192
+";
+    let imported = migrate::import_ini(ini);
+    let expected = Imported {
+        keys: vec![
+            (String::from("check"), String::from("j")),
+            (String::from("flag"), String::from("k")),
+            (String::from("chord"), String::from("space")),
+            (String::from("up"), String::from("up")),
+        ],
+        colors: vec![
+            // Windows colors are stored blue first
+            (String::from("m_count_fg1"), String::from("38;2;0;0;255")),
+            (String::from("border_fg"), String::from("38;2;255;128;0")),
+            (String::from("inner_bg"), String::from("48;2;192;192;192")),
+        ],
+        metrics: vec![String::from("3bv")],
+        skipped: vec![String::from("[options] skin"), String::from("[colors] color9")],
+    };
+    assert_eq!(imported, expected);
+}
+
+#[test]
+fn later_entries_win() {
+    let imported = migrate::import_ini("[a]\nFlag=x\nShow3BVs=1\n[b]\nflag=y\nshow3bvs=no\n");
+    assert_eq!(imported.keys, vec![(String::from("flag"), String::from("y"))]);
+    assert!(imported.metrics.is_empty());
+}
+
+#[test]
+fn values_that_cant_be_read_are_skipped() {
+    let imported = migrate::import_ini("[keys]\nOpen=F13\nFlag=200\n[colors]\nColor2=#12345\nColor3=1,2\n");
+    assert_eq!(imported.keys, vec![]);
+    assert_eq!(imported.colors, vec![]);
+    assert_eq!(imported.skipped.len(), 4);
+}
@@ -0,0 +1,75 @@
+use minesweeper::engine::GameState;
+use minesweeper::protocol::{self, MAX_CHAT, MAX_FRAME, Message, PROTOCOL_VERSION, Progress};
+use minesweeper::replay::Replay;
+use proptest::prelude::*;
+use std::io::Cursor;
+
+fn message() -> impl Strategy<Value = Message> {
+    let state = prop::sample::select(vec![GameState::Starting, GameState::Running, GameState::Win, GameState::Loss]);
+    prop_oneof![
+        (any::<u32>(), any::<Option<u64>>(), prop::option::of("[a-z]{1,16}"), prop::option::of(".{0,16}"))
+            .prop_map(|(version, session, room, name)| Message::Hello { version, session, room, name }),
+        any::<u64>().prop_map(|session| Message::Welcome { version: PROTOCOL_VERSION, session }),
+        ".{0,40}".prop_map(|reason| Message::Rejected { reason }),
+        (1i16..30, 1i16..30, any::<u64>()).prop_map(|(width, height, seed)| Message::Start {
+            board: Replay::new(width, height, width * height / 5, 0, seed, false, false),
+            start: (width / 2, height / 2),
+        }),
+        (any::<u32>(), any::<u32>(), state, any::<u64>()).prop_map(|(uncovered, safe, state, time_ms)| {
+            Message::Progress(Progress { uncovered, safe, state, time_ms })
+        }),
+        (1i16..30, 1i16..30, any::<u64>()).prop_map(|(width, height, seed)| Message::Finished {
+            replay: Replay::new(width, height, width * height / 5, 1, seed, false, false),
+        }),
+        (".{0,16}", ".{0,80}").prop_map(|(name, text)| Message::Chat { name, text }),
+        Just(Message::Heartbeat),
+        Just(Message::Bye),
+        any::<Option<u32>>().prop_map(|code| Message::OpenLobby { version: PROTOCOL_VERSION, code: code.map(|c| format!("{:06}", c % 1_000_000)) }),
+        "[A-Z2-9]{6}".prop_map(|code| Message::Lobby { code }),
+        "[A-Z2-9]{6}".prop_map(|code| Message::JoinLobby { version: PROTOCOL_VERSION, code }),
+        Just(Message::Paired),
+    ]
+}
+
+#[test]
+fn hellos_without_a_room_still_parse() {
+    let hello: Message = serde_json::from_str(r#"{"type": "Hello", "version": 3, "session": null}"#).unwrap();
+    assert_eq!(hello, Message::Hello { version: 3, session: None, room: None, name: None });
+}
+
+proptest! {
+    #[test]
+    fn frames_round_trip(messages in prop::collection::vec(message(), 1..10)) {
+        let mut stream: Vec<u8> = vec![];
+        for message in &messages {
+            protocol::write_frame(&mut stream, message).unwrap();
+        }
+        // Frames back to back in one stream come apart again in order
+        let mut reader = Cursor::new(stream);
+        for message in &messages {
+            prop_assert_eq!(&protocol::read_frame(&mut reader).unwrap(), message);
+        }
+        prop_assert!(protocol::read_frame(&mut reader).is_err());
+    }
+
+    #[test]
+    fn cleaned_text_is_safe_to_print(text in any::<String>(), limit in 0usize..2 * MAX_CHAT) {
+        let cleaned = protocol::clean_text(&text, limit);
+        prop_assert!(cleaned.chars().count() <= limit);
+        let unsafe_chars = cleaned.chars().filter(|&c| c.is_control() || c == '\u{202E}').count();
+        prop_assert_eq!(unsafe_chars, 0);
+        // Text that was already clean comes through untouched
+        prop_assert_eq!(protocol::clean_text(&cleaned, limit), cleaned.clone());
+    }
+
+    #[test]
+    fn oversized_and_cut_off_frames_are_errors(message in message(), cut in 1usize..4) {
+        let mut oversized = (MAX_FRAME + 1).to_be_bytes().to_vec();
+        oversized.extend([0u8; 16]);
+        prop_assert!(protocol::read_frame(&mut Cursor::new(oversized)).is_err());
+        let mut frame: Vec<u8> = vec![];
+        protocol::write_frame(&mut frame, &message).unwrap();
+        frame.truncate(frame.len() - cut);
+        prop_assert!(protocol::read_frame(&mut Cursor::new(frame)).is_err());
+    }
+}
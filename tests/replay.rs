@@ -0,0 +1,380 @@
+use minesweeper::cast::{self, Theme};
+use minesweeper::compact;
+use minesweeper::engine::{Board, GameState, ManualClock};
+use minesweeper::generation::GenerationOptions;
+use minesweeper::history::MoveTree;
+use minesweeper::layout::Layout;
+use minesweeper::replay::{ActionKind, Badges, Replay};
+use minesweeper::rules;
+use minesweeper::snapshot::Snapshot;
+#[cfg(feature = "solver")]
+use minesweeper::{grade::{self, Grade}, review::Review};
+use proptest::prelude::*;
+use std::time::Duration;
+
+// A board's width, height, mine count, and seed
+type Params = (i16, i16, i16, u64);
+// A move, and how long to wait before making it
+type Move = (ActionKind, i16, i16, u64);
+
+///
+/// Random games along with the moves made in them and the time between each move
+///
+fn game() -> impl Strategy<Value = (Params, Vec<Move>)> {
+    (2i16..12, 2i16..12).prop_flat_map(|(width, height)| {
+        let params = (Just(width), Just(height), 0..(width * height - 1), any::<u64>());
+        let action = (0..3u8, 0..width, 0..height, 0u64..2000).prop_map(|(kind, x, y, wait)| {
+            let kind = match kind {
+                0 => ActionKind::Reveal,
+                1 => ActionKind::Flag,
+                _ => ActionKind::Chord,
+            };
+            (kind, x, y, wait)
+        });
+        (params, prop::collection::vec(action, 0..60))
+    })
+}
+
+///
+/// Plays the moves the way the game does, only recording the ones that are allowed
+///
+fn play(width: i16, height: i16, mines: i16, seed: u64, generation: GenerationOptions, actions: &[Move]) -> Replay {
+    play_by_rules("standard", width, height, mines, seed, generation, actions)
+}
+
+///
+/// Plays the moves the same way, by the rules with the given name
+///
+fn play_by_rules(rules: &str, width: i16, height: i16, mines: i16, seed: u64, generation: GenerationOptions, actions: &[Move]) -> Replay {
+    let clock = ManualClock::new();
+    let mut board = Board::deterministic(width, height, mines, 0, seed, clock.clone());
+    board.set_rules(rules::from_name(rules).unwrap());
+    let mut replay = Replay::new(width, height, mines, 0, seed, false, false);
+    replay.generation = generation;
+    replay.rules = String::from(rules);
+    for &(kind, x, y, wait) in actions {
+        if board.state() == GameState::Win || board.state() == GameState::Loss {
+            break;
+        }
+        clock.advance(Duration::from_millis(wait));
+        if !board.is_generated() && kind != ActionKind::Flag {
+            replay.generation.generate(&mut board, (x, y));
+        }
+        let allowed = match kind {
+            ActionKind::Reveal => {
+                if board.is_uncovered(x, y) || board.is_flagged(x, y) {
+                    false
+                } else {
+                    board.reveal(x, y);
+                    true
+                }
+            }
+            ActionKind::Flag => board.flag(x, y),
+            ActionKind::Chord => {
+                if board.is_flagged(x, y) {
+                    false
+                } else {
+                    board.chord(x, y);
+                    true
+                }
+            }
+        };
+        if allowed {
+            replay.record(kind, x, y, board.clock_time());
+        }
+    }
+    replay.finish(board.state(), board.elapsed());
+    replay
+}
+
+proptest! {
+    #[test]
+    fn recorded_games_verify(((width, height, mines, seed), actions) in game()) {
+        let replay = play(width, height, mines, seed, GenerationOptions::default(), &actions);
+        let verification = replay.verify();
+        prop_assert!(verification.is_ok(), "{:?}", verification);
+    }
+
+    #[test]
+    fn recorded_games_with_time_bonuses_verify(((width, height, mines, seed), actions) in game()) {
+        let replay = play_by_rules("bonus", width, height, mines, seed, GenerationOptions::default(), &actions);
+        let verification = replay.verify();
+        prop_assert!(verification.is_ok(), "{:?}", verification);
+    }
+
+    #[cfg(feature = "solver")]
+    #[test]
+    fn recorded_hard_games_verify(((width, height, mines, seed), actions) in game()) {
+        let generation = GenerationOptions { hard_samples: 5, ..GenerationOptions::default() };
+        let replay = play(width, height, mines, seed, generation, &actions);
+        let verification = replay.verify();
+        prop_assert!(verification.is_ok(), "{:?}", verification);
+    }
+
+    #[test]
+    fn tampered_results_are_rejected(((width, height, mines, seed), actions) in game()) {
+        let mut replay = play(width, height, mines, seed, GenerationOptions::default(), &actions);
+        replay.result = match replay.result {
+            GameState::Win => GameState::Loss,
+            _ => GameState::Win,
+        };
+        prop_assert!(replay.verify().is_err());
+    }
+}
+
+proptest! {
+    // Boards with limits that can't be met go through every attempt, so fewer cases keep this quick
+    #![proptest_config(ProptestConfig::with_cases(32))]
+
+    #[test]
+    fn recorded_games_with_opening_limits_verify(((width, height, mines, seed), actions) in game(), min in 0u16..20, max in 0u16..20) {
+        let generation = GenerationOptions { opening_min: min, opening_max: max, ..GenerationOptions::default() };
+        let replay = play(width, height, mines, seed, generation, &actions);
+        let verification = replay.verify();
+        prop_assert!(verification.is_ok(), "{:?}", verification);
+    }
+
+    #[test]
+    fn first_checks_open_up_when_the_mines_fit(((width, height, mines, seed), _) in game(), x in 0i16..12, y in 0i16..12, min in 0u16..20, max in 0u16..20) {
+        let (x, y) = (x % width, y % height);
+        let mut board = Board::deterministic(width, height, mines, 0, seed, ManualClock::new());
+        let generation = GenerationOptions { opening_min: min, opening_max: max, clear_opening: true, ..GenerationOptions::default() };
+        generation.generate(&mut board, (x, y));
+        let around = (x.max(1) - 1..=(x + 1).min(width - 1)).count() * (y.max(1) - 1..=(y + 1).min(height - 1)).count();
+        prop_assert!(!board.is_mine(x, y));
+        if (width * height) as usize - around >= mines as usize && (max == 0 || max as usize >= around) {
+            prop_assert_eq!(board.mine_count_at(x, y), 0);
+        }
+    }
+}
+
+proptest! {
+    #[test]
+    fn undoing_and_redoing_returns_to_the_same_board(((width, height, mines, seed), actions) in game(), back in 0usize..60) {
+        let replay = play(width, height, mines, seed, GenerationOptions::default(), &actions);
+        let mut tree = MoveTree::from_replay(&replay);
+        let end = tree.board().unwrap().visible_board();
+        let back = back.min(replay.actions.len());
+        for _ in 0..back {
+            prop_assert!(tree.undo());
+        }
+        prop_assert_eq!(tree.depth(), replay.actions.len() - back);
+        let earlier = replay.simulate(replay.actions.len() - back).unwrap().visible_board();
+        prop_assert_eq!(tree.board().unwrap().visible_board(), earlier);
+        tree.to_end();
+        prop_assert_eq!(tree.board().unwrap().visible_board(), end);
+        prop_assert!(!tree.redo());
+    }
+
+    #[test]
+    fn casts_have_a_timed_frame_per_move(((width, height, mines, seed), actions) in game(), speed in 0.5f64..4.) {
+        let replay = play(width, height, mines, seed, GenerationOptions::default(), &actions);
+        let text = cast::to_cast(&replay, &Theme::default(), "test", speed).unwrap();
+        let lines: Vec<serde_json::Value> = text.lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+        prop_assert_eq!(&lines[0]["version"], &serde_json::json!(2));
+        // The header, the board before the first move and after each one, then the hold on the last frame
+        prop_assert_eq!(lines.len(), replay.actions.len() + 3);
+        let times: Vec<f64> = lines[1..].iter().map(|event| event[0].as_f64().unwrap()).collect();
+        prop_assert!(times.windows(2).all(|pair| pair[0] <= pair[1]));
+        let last = (replay.actions.last().map(|a| a.time_ms).unwrap_or(0) as f64) / 1000. / speed;
+        prop_assert!((times[times.len() - 2] - last).abs() < 1e-9);
+    }
+
+    #[test]
+    fn badge_claims_are_checked(((width, height, mines, seed), actions) in game()) {
+        let replay = play(width, height, mines, seed, GenerationOptions::default(), &actions);
+        let flagged = replay.actions.iter().any(|a| a.kind == ActionKind::Flag);
+        let claims_nf = Replay { badges: Badges { no_flags: true, ..Badges::default() }, ..replay.clone() };
+        prop_assert_eq!(claims_nf.verify().is_ok(), !flagged);
+        // Assists aren't recorded, so claiming none never makes a replay fail
+        let claims_assist_free = Replay { badges: Badges { assist_free: true, ..Badges::default() }, ..replay.clone() };
+        prop_assert!(claims_assist_free.verify().is_ok());
+        #[cfg(feature = "solver")]
+        {
+            let board = replay.simulate(replay.actions.len()).unwrap();
+            let start = replay.actions.iter().find(|a| a.kind != ActionKind::Flag).map(|a| (a.x, a.y));
+            let no_guess = start.and_then(|start| grade::grade(&board, start)).is_some_and(|g| g != Grade::Expert);
+            let claims_no_guess = Replay { badges: Badges { no_guess: true, ..Badges::default() }, ..replay.clone() };
+            prop_assert_eq!(claims_no_guess.verify().is_ok(), no_guess);
+            let guessed = Review::new(&replay).unwrap().guesses() > 0;
+            let claims_zero_guess = Replay { badges: Badges { zero_guess: true, ..Badges::default() }, ..replay.clone() };
+            prop_assert_eq!(claims_zero_guess.verify().is_ok(), !guessed);
+        }
+    }
+
+    #[test]
+    fn games_replay_the_same_on_their_exported_layout(((width, height, mines, seed), actions) in game(), other_seed in any::<u64>()) {
+        let replay = play(width, height, mines, seed, GenerationOptions::default(), &actions);
+        let layout = match Layout::from_board(&replay.simulate(replay.actions.len()).unwrap()) {
+            Some(layout) => layout,
+            None => return Ok(()),
+        };
+        let bytes = layout.to_mbf().unwrap();
+        prop_assert_eq!(bytes.len(), 4 + 2 * mines as usize);
+        prop_assert!(Layout::from_mbf(&bytes[..bytes.len() - 1]).is_err());
+        let imported = Layout::from_mbf(&bytes).unwrap();
+        prop_assert_eq!(&imported, &layout);
+        // The seed doesn't matter once the mines are laid out
+        let on_layout = Replay { seed: other_seed, layout: Some(imported), ..replay.clone() };
+        prop_assert_eq!(on_layout.verify().unwrap().result, replay.result);
+        prop_assert_eq!(on_layout.positions().unwrap(), replay.positions().unwrap());
+    }
+
+    #[test]
+    fn snapshots_only_hold_up_for_the_loss_they_froze(((width, height, mines, seed), actions) in game()) {
+        let replay = play(width, height, mines, seed, GenerationOptions::default(), &actions);
+        let board = replay.simulate(replay.actions.len()).unwrap();
+        let fatal = (0..height).flat_map(|y| (0..width).map(move |x| (x, y))).find(|&(x, y)| board.is_mine(x, y)).unwrap_or((0, 0));
+        let snapshot = Snapshot::new(&replay, &board, fatal);
+        prop_assert_eq!(snapshot.check().is_ok(), replay.result == GameState::Loss);
+        // Without the move that lost it, the game never got to the frozen position
+        let mut cut = snapshot.clone();
+        cut.replay.actions.pop();
+        prop_assert!(cut.check().is_err());
+        let parsed: Snapshot = serde_json::from_str(&serde_json::to_string(&snapshot).unwrap()).unwrap();
+        prop_assert_eq!(parsed, snapshot);
+    }
+
+    #[test]
+    fn packed_replays_unpack_the_same(((width, height, mines, seed), actions) in game(), badges in any::<[bool; 4]>(), with_layout in any::<bool>(), clear_opening in any::<bool>()) {
+        let generation = GenerationOptions { clear_opening, ..GenerationOptions::default() };
+        let mut replay = play(width, height, mines, seed, generation, &actions);
+        let [assist_free, no_flags, no_guess, zero_guess] = badges;
+        replay.badges = Badges { assist_free, no_flags, no_guess, zero_guess };
+        if with_layout {
+            replay.layout = Layout::from_board(&replay.simulate(replay.actions.len()).unwrap());
+        }
+        let bytes = compact::encode(&replay);
+        prop_assert!(compact::is_compact(&bytes));
+        prop_assert_eq!(compact::decode(&bytes).unwrap(), replay.clone());
+        prop_assert!(bytes.len() <= serde_json::to_string(&replay).unwrap().len());
+        // Cut short anywhere, it's an error rather than a different replay
+        #[cfg(not(feature = "zstd"))]
+        for len in 0..bytes.len() {
+            prop_assert!(compact::decode(&bytes[..len]).is_err());
+        }
+    }
+
+    #[test]
+    fn damaged_packed_replays_fail_without_panicking(((width, height, mines, seed), actions) in game(), at in any::<prop::sample::Index>(), byte in any::<u8>(), junk in prop::collection::vec(any::<u8>(), 0..64)) {
+        let replay = play(width, height, mines, seed, GenerationOptions::default(), &actions);
+        let mut bytes = compact::pack(&replay);
+        // Any one byte changed, whether or not it still reads as a replay
+        let i = at.index(bytes.len());
+        bytes[i] = byte;
+        let _ = compact::decode(&bytes);
+        // Anything at all after the magic bytes, and anything at all
+        let _ = compact::decode(&[&bytes[..4], &junk[..]].concat());
+        let _ = compact::decode(&junk);
+    }
+
+    #[cfg(feature = "gif")]
+    #[test]
+    fn gifs_have_a_frame_per_move(((width, height, mines, seed), actions) in game()) {
+        let replay = play(width, height, mines, seed, GenerationOptions::default(), &actions);
+        let bytes = minesweeper::animation::to_gif(&replay, &Theme::default(), 1.).unwrap();
+        let mut decoder = gif::DecodeOptions::new().read_info(bytes.as_slice()).unwrap();
+        let mut delays: Vec<u64> = vec![];
+        while let Some(frame) = decoder.read_next_frame().unwrap() {
+            delays.push(frame.delay as u64);
+        }
+        prop_assert_eq!(delays.len(), replay.actions.len() + 1);
+        // Everything but the hold on the last frame adds up to when the last move was made
+        let last = replay.actions.last().map(|a| a.time_ms).unwrap_or(0);
+        prop_assert_eq!(delays[..delays.len() - 1].iter().sum::<u64>(), (last as f64 / 10.).round() as u64);
+    }
+
+    #[cfg(feature = "solver")]
+    #[test]
+    fn reviews_cover_every_move(((width, height, mines, seed), actions) in game()) {
+        let replay = play(width, height, mines, seed, GenerationOptions::default(), &actions);
+        let review = Review::new(&replay).unwrap();
+        prop_assert_eq!(review.positions.len(), replay.actions.len() + 1);
+        prop_assert_eq!(review.notes.len(), replay.actions.len());
+        prop_assert_eq!(review.positions.last().unwrap().state, replay.result);
+        prop_assert!(review.tip.is_none() || replay.result == GameState::Loss);
+        // Nothing can be held against moves made before the board existed
+        for (i, position) in review.positions.iter().take(replay.actions.len()).enumerate() {
+            if position.state == GameState::Starting {
+                prop_assert!(review.notes[i].is_none());
+            }
+        }
+        // The written analysis has a line for every move
+        let analysis = review.analysis();
+        prop_assert_eq!(analysis.lines().filter(|l| l.starts_with("Move ")).count(), replay.actions.len());
+    }
+}
+
+#[test]
+fn seeded_boards_stay_the_same() {
+    // Shared seeds have to make the same board in every version of the game, so this layout can never change
+    let replay = Replay::new(9, 9, 10, 0, 1234, false, false);
+    let mut board = replay.new_board(Box::new(ManualClock::new())).unwrap();
+    board.reveal(4, 4);
+    let mut mines: Vec<(i16, i16)> = vec![];
+    for y in 0..9 {
+        for x in 0..9 {
+            if board.is_mine(x, y) {
+                mines.push((x, y));
+            }
+        }
+    }
+    assert_eq!(mines, vec![(3, 1), (5, 1), (3, 2), (6, 2), (7, 3), (0, 4), (3, 4), (3, 6), (1, 8), (4, 8)]);
+}
+
+#[test]
+fn replays_from_before_the_generator_choice_use_std() {
+    let mut json = serde_json::to_value(Replay::new(9, 9, 10, 0, 1234, false, false)).unwrap();
+    json.as_object_mut().unwrap().remove("rng");
+    let replay: Replay = serde_json::from_value(json).unwrap();
+    assert_eq!(replay.rng, "std");
+}
+
+#[test]
+fn expert_replays_pack_into_a_kilobyte() {
+    // Clear an Expert board a space at a time, left to right and top to bottom, a move every so often
+    let clock = ManualClock::new();
+    let mut board = Board::deterministic(30, 16, 99, 0, 7, clock.clone());
+    let mut replay = Replay::new(30, 16, 99, 0, 7, false, false);
+    GenerationOptions::default().generate(&mut board, (0, 0));
+    for y in 0..16 {
+        for x in 0..30 {
+            if !board.is_mine(x, y) && !board.is_uncovered(x, y) {
+                clock.advance(Duration::from_millis(1234));
+                board.reveal(x, y);
+                replay.record(ActionKind::Reveal, x, y, board.elapsed());
+            }
+        }
+    }
+    replay.finish(board.state(), board.elapsed());
+    assert_eq!(replay.verify().unwrap().result, GameState::Win);
+    let bytes = compact::encode(&replay);
+    assert!(bytes.len() < 1024, "{} moves took {} bytes", replay.actions.len(), bytes.len());
+    assert_eq!(compact::decode(&bytes).unwrap(), replay);
+}
+
+#[test]
+fn packed_moves_that_run_off_the_end_of_an_i64_are_errors() {
+    let mut bytes = compact::pack(&Replay::new(9, 9, 10, 0, 1234, false, false));
+    // Swap the empty move list for two moves, a row down and then i64::MAX rows further
+    assert_eq!(bytes.pop(), Some(0));
+    bytes.extend_from_slice(&[2, 0, 2, 0]);
+    bytes.extend_from_slice(&[0, 0xFE, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x01, 0]);
+    assert_eq!(compact::decode(&bytes), Err(String::from("A move is off the board")));
+}
+
+#[test]
+fn positions_carry_on_with_another_clock() {
+    // Practice picks a replay's position back up on a different clock, which shouldn't lose or add any time
+    let clock = ManualClock::new();
+    let mut board = Board::deterministic(9, 9, 10, 0, 1, clock.clone());
+    board.reveal(0, 0);
+    clock.advance(Duration::from_millis(1500));
+    let other = ManualClock::new();
+    other.advance(Duration::from_secs(60));
+    board.set_clock(Box::new(other.clone()));
+    assert_eq!(board.elapsed(), Duration::from_millis(1500));
+    other.advance(Duration::from_millis(250));
+    clock.advance(Duration::from_secs(5));
+    assert_eq!(board.elapsed(), Duration::from_millis(1750));
+}
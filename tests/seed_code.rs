@@ -0,0 +1,26 @@
+use minesweeper::seed_code::SeedCode;
+use proptest::prelude::*;
+
+proptest! {
+    #[test]
+    fn codes_read_back_as_what_they_were_made_from(width in 1i16..=200, height in 1i16..=200, mines in 0i16..=9999, seed: u64) {
+        let code = SeedCode { width, height, mines, seed };
+        prop_assert_eq!(SeedCode::parse(&code.to_string()), Some(code));
+        prop_assert_eq!(SeedCode::parse(&code.to_string().to_lowercase()), Some(code));
+    }
+}
+
+#[test]
+fn codes_are_found_in_messages() {
+    let code = SeedCode { width: 16, height: 16, mines: 40, seed: 1_234_567_890 };
+    let message = format!("Won a 16x16 minesweeper board with 40 mines in 31.204s (seed code {})", code);
+    assert_eq!(SeedCode::find(&message), Some(code));
+    assert_eq!(SeedCode::find("no code here, just 12345"), None);
+}
+
+#[test]
+fn things_that_are_not_codes_are_turned_down() {
+    for text in ["", "12345", "16x16-40", "16x16-40-", "16-16-40-ABC", "16x16-40-AB!", "16x16-40-ABC-1", "axb-40-ABC", "16x16-40-ZZZZZZZZZZZZZZZZ"] {
+        assert_eq!(SeedCode::parse(text), None, "{:?}", text);
+    }
+}
@@ -0,0 +1,709 @@
+//! End to end tests of the game as it's played: the real binary runs in a pseudo-terminal, keys are typed into it,
+//! and what it draws is read back off a virtual terminal's screen. Boards come from seeds typed into the custom
+//! game form, so the engine can work out what the screen should show.
+
+use minesweeper::engine::{Board, GameState, ManualClock};
+use minesweeper::generation::GenerationOptions;
+use minesweeper::replay::Replay;
+#[cfg(feature = "solver")]
+use minesweeper::solver;
+use portable_pty::{Child, CommandBuilder, PtySize, native_pty_system};
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+const ROWS: u16 = 40;
+// Wide enough for the help line over the board to fit on one row
+const COLUMNS: u16 = 220;
+// How long the game gets to draw something before a test gives up on it
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+// What the default save draws on each kind of space
+const COVERED: &str = "󰆢";
+const FLAG: &str = "󰈿";
+const MINE: &str = "󰷚";
+
+///
+/// The game running in a pseudo-terminal of its own, with its own save in a scratch folder
+///
+struct Tui {
+    screen: Arc<Mutex<vt100::Parser>>,
+    writer: Box<dyn Write + Send>,
+    child: Box<dyn Child + Send + Sync>,
+    dir: PathBuf,
+    cursor: (i16, i16), // Where the game's cursor is on the board, followed along as keys are sent
+}
+
+impl Tui {
+    ///
+    /// Starts the game on its main menu. The binary is copied into a scratch folder first, since the save,
+    /// replays, and snapshots all go next to it
+    ///
+    fn start(name: &str) -> Tui {
+        Tui::start_with_env(name, &[])
+    }
+    ///
+    /// Starts the game the same way, with some extra environment variables set
+    ///
+    fn start_with_env(name: &str, env: &[(&str, &str)]) -> Tui {
+        let tui = Tui::launch(name, &[], env);
+        tui.wait_for("4. Custom");
+        tui
+    }
+    ///
+    /// Starts the game with some arguments, e.g. a subcommand. The first launch's questions are skipped,
+    /// which leaves the usual save, and nothing else is waited for
+    ///
+    fn launch(name: &str, args: &[&str], env: &[(&str, &str)]) -> Tui {
+        Tui::launch_at(name, args, env, COLUMNS)
+    }
+    ///
+    /// Starts the game the same way, in a terminal with some other number of columns
+    ///
+    fn launch_at(name: &str, args: &[&str], env: &[(&str, &str)], columns: u16) -> Tui {
+        let mut tui = Tui::first_launch_at(name, args, env, columns);
+        tui.wait_for("before the first game");
+        tui.keys("\x1b");
+        tui
+    }
+    ///
+    /// Starts the game with no save, so it opens on the first launch's questions
+    ///
+    fn first_launch(name: &str, args: &[&str], env: &[(&str, &str)]) -> Tui {
+        Tui::first_launch_at(name, args, env, COLUMNS)
+    }
+    fn first_launch_at(name: &str, args: &[&str], env: &[(&str, &str)], columns: u16) -> Tui {
+        let dir = std::env::temp_dir().join(format!("minesweeper-tui-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("bin")).unwrap();
+        let exe = dir.join("bin").join(if cfg!(windows) { "minesweeper.exe" } else { "minesweeper" });
+        std::fs::copy(env!("CARGO_BIN_EXE_minesweeper"), &exe).unwrap();
+        let pty = native_pty_system().openpty(PtySize { rows: ROWS, cols: columns, pixel_width: 0, pixel_height: 0 }).unwrap();
+        // The menus read keys without turning raw mode on, which only works if the terminal already hands keys over
+        // as they're pressed, the way the Windows console does. Elsewhere the terminal is put in raw mode first
+        let mut command = if cfg!(unix) {
+            let mut command = CommandBuilder::new("sh");
+            command.args(["-c", "stty raw -echo && exec \"$0\" \"$@\""]);
+            command.arg(&exe);
+            command
+        } else {
+            CommandBuilder::new(&exe)
+        };
+        command.args(args);
+        command.cwd(&dir);
+        command.env("TERM", "xterm-256color");
+        for (key, value) in env {
+            command.env(key, value);
+        }
+        let child = pty.slave.spawn_command(command).unwrap();
+        let screen = Arc::new(Mutex::new(vt100::Parser::new(ROWS, columns, 0)));
+        let mut reader = pty.master.try_clone_reader().unwrap();
+        let shared = screen.clone();
+        std::thread::spawn(move || {
+            let mut buffer = [0u8; 4096];
+            while let Ok(n) = reader.read(&mut buffer) {
+                if n == 0 {
+                    break;
+                }
+                shared.lock().unwrap().process(&buffer[..n]);
+            }
+        });
+        let writer = pty.master.take_writer().unwrap();
+        Tui { screen, writer, child, dir, cursor: (0, 0) }
+    }
+    ///
+    /// Everything on the screen, a line per row
+    ///
+    fn text(&self) -> String {
+        self.screen.lock().unwrap().screen().contents()
+    }
+    fn keys(&mut self, keys: &str) {
+        self.writer.write_all(keys.as_bytes()).unwrap();
+        self.writer.flush().unwrap();
+    }
+    ///
+    /// Waits until `done` is true of the screen, failing the test with what's on it if that takes too long
+    ///
+    fn wait_until<F: Fn(&Tui) -> bool>(&self, what: &str, done: F) {
+        let started = Instant::now();
+        while !done(self) {
+            if started.elapsed() > TIMEOUT {
+                panic!("Timed out waiting for {}. The screen shows:\n{}", what, self.text());
+            }
+            std::thread::sleep(Duration::from_millis(20));
+        }
+    }
+    fn wait_for(&self, text: &str) {
+        self.wait_until(&format!("\"{}\"", text), |tui| tui.text().contains(text));
+    }
+    ///
+    /// Sets up a custom game from the form and waits for its board to be drawn
+    ///
+    fn custom_game(&mut self, width: i16, height: i16, mines: i16, seed: u64) {
+        self.keys("4");
+        self.wait_for("Custom game");
+        // Each field starts out filled in, so it's cleared before typing
+        let clear = "\x7f".repeat(8);
+        self.keys(&format!("{clear}{width}\t{clear}{height}\t{clear}{mines}\t\t{clear}{seed}\r"));
+        self.wait_for("FLAGS LEFT");
+        self.cursor = (0, 0);
+    }
+    ///
+    /// Moves the cursor over to a space with the arrow keys
+    ///
+    fn move_to(&mut self, x: i16, y: i16) {
+        let (dx, dy) = (x - self.cursor.0, y - self.cursor.1);
+        let horizontal = if dx > 0 { "\x1b[C" } else { "\x1b[D" };
+        let vertical = if dy > 0 { "\x1b[B" } else { "\x1b[A" };
+        let keys = horizontal.repeat(dx.unsigned_abs() as usize) + &vertical.repeat(dy.unsigned_abs() as usize);
+        self.keys(&keys);
+        self.cursor = (x, y);
+    }
+    ///
+    /// Clicks the middle of a space with a mouse button (0 for left, 1 for middle, 2 for right), reported the way
+    /// xterm does it. The cursor goes wherever the click was
+    ///
+    fn click(&mut self, button: u8, x: i16, y: i16) {
+        // Spaces are 3 columns wide inside the border, with the board starting on the 4th row. Mouse reports count from 1
+        let (column, row) = (3 * x + 3, y + 4);
+        self.keys(&format!("\x1b[<{button};{column};{row}M\x1b[<{button};{column};{row}m"));
+        self.cursor = (x, y);
+    }
+    ///
+    /// The spaces of the board on the screen, as the text inside each one's brackets, indexed [y][x]
+    ///
+    fn board(&self) -> Vec<Vec<String>> {
+        let text = self.text();
+        let rows = text.lines().skip_while(|line| !line.starts_with('╔')).skip(1).take_while(|line| line.starts_with('║'));
+        rows.map(|row| {
+            row.trim_matches('║').split(']').filter(|cell| cell.starts_with('[')).map(|cell| cell[1..].to_string()).collect()
+        }).collect()
+    }
+    ///
+    /// A file the game keeps next to its binary. Paths are built with a backslash, so outside Windows
+    /// the file ends up beside the binary's folder with the backslash in its name
+    ///
+    fn data_file(&self, name: &str) -> PathBuf {
+        if cfg!(windows) { self.dir.join("bin").join(name) } else { self.dir.join(format!("bin\\{}", name)) }
+    }
+    ///
+    /// Waits for the board on the screen to match `board`
+    ///
+    fn wait_for_board(&self, board: &Board) {
+        let expected = drawn(board);
+        self.wait_until("the board to match the engine", |tui| tui.board() == expected);
+    }
+}
+
+impl Drop for Tui {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+///
+/// The board a seed makes once the first check at `start` generates it, the same way the game does
+///
+fn seeded_board(width: i16, height: i16, mines: i16, seed: u64, start: (i16, i16)) -> Board {
+    let replay = Replay::new(width, height, mines, 0, seed, false, false);
+    let mut board = replay.new_board(Box::new(ManualClock::new())).unwrap();
+    GenerationOptions { clear_opening: true, ..GenerationOptions::default() }.generate(&mut board, start);
+    board
+}
+
+///
+/// What the game should draw on each space of a board it's still playing, indexed [y][x]
+///
+fn drawn(board: &Board) -> Vec<Vec<String>> {
+    (0..board.height()).map(|y| {
+        (0..board.width()).map(|x| {
+            if board.is_flagged(x, y) {
+                String::from(FLAG)
+            } else if !board.is_uncovered(x, y) {
+                String::from(COVERED)
+            } else {
+                match board.mine_count_at(x, y) {
+                    0 => String::from(" "),
+                    n => n.to_string(),
+                }
+            }
+        }).collect()
+    }).collect()
+}
+
+#[test]
+fn cascade_then_chord_then_win() {
+    let mut tui = Tui::start("win");
+    // Checking the corner on this seed opens up a patch of the board
+    let mut board = seeded_board(9, 9, 10, 1, (0, 0));
+    tui.custom_game(9, 9, 10, 1);
+    tui.keys("q");
+    assert!(board.reveal(0, 0).len() > 1);
+    tui.wait_for_board(&board);
+    // Flag around a number on the edge of the opening, then chord it
+    let spaces: Vec<(i16, i16)> = (0..9).flat_map(|y| (0..9).map(move |x| (x, y))).collect();
+    let &(x, y) = spaces.iter().find(|&&(x, y)| {
+        board.is_uncovered(x, y) && board.mine_count_at(x, y) > 0
+            && board.get_surrounding(x, y).iter().any(|&(i, j)| !board.is_uncovered(i, j) && !board.is_mine(i, j))
+    }).unwrap();
+    let mines: Vec<(i16, i16)> = board.get_surrounding(x, y).into_iter().filter(|&(i, j)| board.is_mine(i, j)).collect();
+    for (i, j) in mines {
+        tui.move_to(i, j);
+        tui.keys("w");
+        board.flag(i, j);
+        tui.wait_for_board(&board);
+    }
+    tui.move_to(x, y);
+    tui.keys(" ");
+    assert!(!board.chord(x, y).is_empty());
+    tui.wait_for_board(&board);
+    // Then check everything that's left
+    for &(x, y) in &spaces {
+        if !board.is_mine(x, y) && !board.is_uncovered(x, y) {
+            tui.move_to(x, y);
+            tui.keys("q");
+            board.reveal(x, y);
+            tui.wait_for_board(&board);
+        }
+    }
+    assert_eq!(board.state(), GameState::Win);
+    tui.wait_for("Congrats! You won!");
+}
+
+#[test]
+fn wins_go_on_the_high_score_table_for_their_size() {
+    let mut tui = Tui::start("scores");
+    let mut board = seeded_board(9, 9, 10, 1, (0, 0));
+    tui.custom_game(9, 9, 10, 1);
+    for (x, y) in (0..9).flat_map(|y| (0..9).map(move |x| (x, y))) {
+        if !board.is_mine(x, y) && !board.is_uncovered(x, y) {
+            tui.move_to(x, y);
+            tui.keys("q");
+            board.reveal(x, y);
+        }
+    }
+    tui.wait_for("High score: #1 on 9x9, 10 mines");
+    tui.keys("m");
+    tui.wait_for("6. High scores");
+    tui.keys("6");
+    tui.wait_for("High scores - beginner (1 of 4)");
+    assert!(tui.text().contains("No wins yet"));
+    // Custom sizes come after the presets, so going left wraps around to it
+    tui.keys("\x1b[D");
+    tui.wait_for("High scores - 9x9, 10 mines (4 of 4)");
+    assert!(tui.text().lines().any(|line| line.trim_start().starts_with("1 ") && line.contains(&format!(" {} ", board.get_3bv()))));
+}
+
+#[test]
+fn the_heat_replay_colors_spaces_by_when_they_were_opened() {
+    let mut tui = Tui::start("heat");
+    let mut board = seeded_board(9, 9, 10, 1, (0, 0));
+    tui.custom_game(9, 9, 10, 1);
+    tui.keys("q");
+    board.reveal(0, 0);
+    tui.wait_for_board(&board);
+    let spaces: Vec<(i16, i16)> = (0..9).flat_map(|y| (0..9).map(move |x| (x, y))).collect();
+    // The last space is checked a while after the rest, so it's the only one in the last fifth of the game
+    let covered = |board: &Board| spaces.iter().copied().filter(|&(x, y)| !board.is_mine(x, y) && !board.is_uncovered(x, y)).collect::<Vec<_>>();
+    while covered(&board).len() > 1 {
+        let (x, y) = covered(&board)[0];
+        tui.move_to(x, y);
+        tui.keys("q");
+        board.reveal(x, y);
+    }
+    let last = covered(&board)[0];
+    tui.wait_for_board(&board);
+    std::thread::sleep(Duration::from_secs(2));
+    tui.move_to(last.0, last.1);
+    tui.keys("q");
+    tui.wait_for("Congrats! You won!");
+    tui.keys("h");
+    tui.wait_for("Heat replay");
+    assert!(tui.text().contains("100% "));
+    let heat = tui.board();
+    assert_eq!(heat[last.1 as usize][last.0 as usize], "5");
+    assert_eq!(heat[0][0], "1");
+    // Stepping all the way back leaves only the first check's opening
+    tui.keys(&"\x1b[D".repeat(10));
+    tui.wait_until("the replay to go back to the first check", |tui| tui.board()[last.1 as usize][last.0 as usize] == COVERED);
+    assert_eq!(tui.board()[0][0], "1");
+    tui.keys("h");
+    tui.wait_for("Game over | h - heat replay");
+}
+
+#[test]
+fn mouse_buttons_check_flag_and_chord() {
+    let mut tui = Tui::start("mouse");
+    let mut board = seeded_board(9, 9, 10, 1, (0, 0));
+    tui.custom_game(9, 9, 10, 1);
+    tui.click(0, 0, 0);
+    board.reveal(0, 0);
+    tui.wait_for_board(&board);
+    // Flag around a number on the edge of the opening with the right button, then chord it with the middle one
+    let spaces: Vec<(i16, i16)> = (0..9).flat_map(|y| (0..9).map(move |x| (x, y))).collect();
+    let &(x, y) = spaces.iter().find(|&&(x, y)| {
+        board.is_uncovered(x, y) && board.mine_count_at(x, y) > 0
+            && board.get_surrounding(x, y).iter().any(|&(i, j)| !board.is_uncovered(i, j) && !board.is_mine(i, j))
+    }).unwrap();
+    let mines: Vec<(i16, i16)> = board.get_surrounding(x, y).into_iter().filter(|&(i, j)| board.is_mine(i, j)).collect();
+    for (i, j) in mines {
+        tui.click(2, i, j);
+        board.flag(i, j);
+        tui.wait_for_board(&board);
+    }
+    tui.click(1, x, y);
+    assert!(!board.chord(x, y).is_empty());
+    tui.wait_for_board(&board);
+}
+
+#[test]
+fn numbers_get_brackets_of_their_own_without_color() {
+    let mut tui = Tui::start_with_env("no-color", &[("NO_COLOR", "1")]);
+    let mut board = seeded_board(9, 9, 10, 1, (0, 0));
+    tui.custom_game(9, 9, 10, 1);
+    tui.keys("q");
+    board.reveal(0, 0);
+    // Every number on the board shows up between its own brackets, e.g. (1) and [2]
+    let brackets = ["()", "[]", "{}", "<>", "||", "//", "::", "##"];
+    let numbers: Vec<String> = (0..9).flat_map(|y| (0..9).map(move |x| (x, y)))
+        .filter(|&(x, y)| board.is_uncovered(x, y) && board.mine_count_at(x, y) > 0)
+        .map(|(x, y)| {
+            let n = board.mine_count_at(x, y);
+            let pair: Vec<char> = brackets[n as usize - 1].chars().collect();
+            format!("{}{}{}", pair[0], n, pair[1])
+        }).collect();
+    assert!(!numbers.is_empty());
+    tui.wait_until("the numbers' brackets", |tui| numbers.iter().all(|n| tui.text().contains(n.as_str())));
+}
+
+#[test]
+fn undo_takes_moves_back_and_redo_plays_them_again() {
+    let mut tui = Tui::start("undo");
+    let mut board = seeded_board(9, 9, 10, 1, (0, 0));
+    tui.custom_game(9, 9, 10, 1);
+    tui.keys("q");
+    board.reveal(0, 0);
+    tui.wait_for_board(&board);
+    let opened = drawn(&board);
+    let mines: Vec<(i16, i16)> = (0..9).flat_map(|y| (0..9).map(move |x| (x, y))).filter(|&(x, y)| board.is_mine(x, y)).collect();
+    tui.move_to(mines[0].0, mines[0].1);
+    tui.keys("w");
+    board.flag(mines[0].0, mines[0].1);
+    tui.wait_for_board(&board);
+    tui.keys("u");
+    tui.wait_until("the flag to be taken back", |tui| tui.board() == opened);
+    tui.keys("U");
+    tui.wait_for_board(&board);
+    // A game with moves taken back doesn't count, win or lose
+    tui.move_to(mines[1].0, mines[1].1);
+    tui.keys("q");
+    tui.wait_for("Sorry! You lose.");
+    tui.wait_for("this game doesn't count");
+    tui.keys("m");
+    tui.wait_for("Games Played: 0");
+}
+
+#[test]
+fn the_flag_key_cycles_through_a_question_mark() {
+    let mut tui = Tui::start("marks");
+    let mut board = seeded_board(9, 9, 10, 1, (0, 0));
+    tui.custom_game(9, 9, 10, 1);
+    tui.keys("q");
+    board.reveal(0, 0);
+    tui.wait_for_board(&board);
+    let covered = drawn(&board);
+    let (x, y) = (0..9).flat_map(|y| (0..9).map(move |x| (x, y))).find(|&(x, y)| !board.is_uncovered(x, y)).unwrap();
+    tui.move_to(x, y);
+    let mut expected = covered.clone();
+    // Presses on the same space closer together than the debounce are dropped
+    for shown in [FLAG, "?", COVERED] {
+        std::thread::sleep(Duration::from_millis(200));
+        tui.keys("w");
+        expected[y as usize][x as usize] = String::from(shown);
+        tui.wait_until(&format!("{} at ({}, {})", shown, x, y), |tui| tui.board() == expected);
+    }
+    assert_eq!(tui.board(), covered);
+}
+
+#[test]
+fn keys_rebound_in_game_act_straight_away_and_are_saved() {
+    let mut tui = Tui::start("keys");
+    let mut board = seeded_board(9, 9, 10, 1, (0, 0));
+    tui.custom_game(9, 9, 10, 1);
+    tui.keys("q");
+    board.reveal(0, 0);
+    tui.wait_for_board(&board);
+    // The help line stays on one row, and the keys it leaves off are on the key screen
+    assert!(tui.text().lines().nth(1).is_some_and(|line| line.starts_with("FLAGS LEFT")));
+    // Flag is second on the list
+    tui.keys("k");
+    tui.wait_for("Flag a space");
+    tui.wait_for("b - overview");
+    tui.keys("\x1b[B\r");
+    tui.wait_for("Press the new key");
+    tui.keys("x");
+    tui.wait_until("x to be bound", |tui| tui.text().lines().any(|line| line.contains("Flag a space") && line.contains(" x ")));
+    tui.keys("\x1b");
+    tui.wait_for("FLAGS LEFT");
+    let (x, y) = (0..9).flat_map(|y| (0..9).map(move |x| (x, y))).find(|&(x, y)| !board.is_uncovered(x, y)).unwrap();
+    tui.move_to(x, y);
+    tui.keys("x");
+    board.flag(x, y);
+    tui.wait_for_board(&board);
+    // The save is written a moment later, on the writer thread
+    tui.wait_until("the binding to be saved", |tui| {
+        std::fs::read_to_string(tui.data_file("save.json")).is_ok_and(|save| save.contains("\"flag\":\"x\""))
+    });
+}
+
+#[test]
+fn the_console_shows_the_log_and_the_log_file_keeps_it() {
+    let mut tui = Tui::launch("log", &["--log-file", "game.log"], &[]);
+    tui.wait_for("4. Custom");
+    let mut board = seeded_board(9, 9, 10, 1, (0, 0));
+    tui.custom_game(9, 9, 10, 1);
+    tui.keys("q");
+    board.reveal(0, 0);
+    tui.wait_for_board(&board);
+    tui.keys("`");
+    tui.wait_for("game started");
+    assert!(tui.text().contains("uncovered"));
+    tui.keys("`");
+    tui.wait_for_board(&board);
+    // A relative path is taken from where the game was started
+    let log = std::fs::read_to_string(tui.dir.join("game.log")).unwrap();
+    assert!(log.contains("mines placed") && log.contains("game started"));
+}
+
+#[test]
+fn the_first_launch_writes_the_save_from_its_answers() {
+    let mut tui = Tui::first_launch("onboarding", &[], &[]);
+    tui.wait_for("1 of 4");
+    // ASCII, then the plain theme, z and x to check and flag, and no stats
+    tui.keys("2\r");
+    tui.wait_for("2 of 4");
+    tui.keys("\x1b[B\r");
+    tui.wait_for("3 of 4");
+    tui.keys("3\r");
+    tui.wait_for("4 of 4");
+    tui.keys("2\r");
+    tui.wait_for("4. Custom");
+    assert!(!tui.text().contains("Games Played"));
+    let save = std::fs::read_to_string(tui.data_file("save.json")).unwrap();
+    assert!(save.contains("\"tile_char\":\"#\"") && save.contains("\"stats\":false"));
+    let mut board = seeded_board(9, 9, 10, 1, (0, 0));
+    tui.custom_game(9, 9, 10, 1);
+    tui.keys("z");
+    board.reveal(0, 0);
+    // Covered spaces are drawn in ASCII, like the flag
+    let covered = |tui: &Tui| tui.board().concat().iter().filter(|space| *space == "#").count();
+    let left = (0..9).flat_map(|y| (0..9).map(move |x| (x, y))).filter(|&(x, y)| !board.is_uncovered(x, y)).count();
+    tui.wait_until("the opening", |tui| tui.board().len() == 9 && covered(tui) == left);
+    let x = (0..9).find(|&x| !board.is_uncovered(x, 8)).unwrap();
+    tui.move_to(x, 8);
+    tui.keys("x");
+    tui.wait_until("the flag", |tui| tui.board()[8][x as usize] == "F");
+}
+
+#[test]
+fn vim_keys_move_straight_and_diagonally() {
+    let mut tui = Tui::first_launch("vim", &[], &[]);
+    // The usual characters and theme, then the vim keys
+    tui.wait_for("1 of 4");
+    tui.keys("\r");
+    tui.wait_for("2 of 4");
+    tui.keys("\r");
+    tui.wait_for("3 of 4");
+    tui.keys("4\r");
+    tui.wait_for("4 of 4");
+    tui.keys("\r");
+    tui.wait_for("4. Custom");
+    tui.custom_game(9, 9, 10, 1);
+    assert!(tui.text().contains("K - keys"));
+    // Two steps down and to the right, then one right and one down, and back up and left
+    tui.keys("nnljyk");
+    tui.keys("q");
+    let mut board = seeded_board(9, 9, 10, 1, (2, 2));
+    board.reveal(2, 2);
+    tui.wait_for_board(&board);
+    // k moves, so the key screen is on K
+    tui.keys("K");
+    tui.wait_for("Flag a space");
+    tui.keys("\x1b");
+    tui.wait_for_board(&board);
+}
+
+#[test]
+fn hot_seat_players_take_turns_on_the_same_board() {
+    let mut tui = Tui::launch("hotseat", &["hotseat", "9x9-10-1"], &[]);
+    let spaces: Vec<(i16, i16)> = (0..9).flat_map(|y| (0..9).map(move |x| (x, y))).collect();
+    // Both players start from the middle, so the board comes out the same for each of them
+    let mut board = seeded_board(9, 9, 10, 1, (4, 4));
+    tui.wait_for("Player 1's turn");
+    tui.keys("\r");
+    tui.wait_for("PLAYER 1");
+    tui.cursor = (4, 4);
+    tui.keys("q");
+    board.reveal(4, 4);
+    tui.wait_for_board(&board);
+    let opened = drawn(&board);
+    for &(x, y) in &spaces {
+        if !board.is_mine(x, y) && !board.is_uncovered(x, y) {
+            tui.move_to(x, y);
+            tui.keys("q");
+            board.reveal(x, y);
+        }
+    }
+    tui.wait_for("Congrats! You won!");
+    tui.keys("\r");
+    // The second player gets a clean screen, and the same board
+    tui.wait_for("Player 2's turn");
+    assert!(!tui.text().contains("Congrats"));
+    tui.keys("\r");
+    tui.wait_for("PLAYER 2");
+    tui.cursor = (4, 4);
+    tui.keys("q");
+    tui.wait_until("the same opening", |tui| tui.board() == opened);
+    let mine = spaces.iter().copied().find(|&(x, y)| board.is_mine(x, y)).unwrap();
+    tui.move_to(mine.0, mine.1);
+    tui.keys("q");
+    tui.wait_for("Sorry! You lose.");
+    tui.keys("\r");
+    tui.wait_for("Player 1 wins!");
+    assert!(tui.text().contains("Player 2: hit a mine"));
+}
+
+#[test]
+fn the_line_under_the_board_fits_a_narrow_terminal() {
+    let mut tui = Tui::launch_at("narrow", &["hotseat", "9x9-10-1"], &[], 80);
+    tui.wait_for("Player 1's turn");
+    tui.keys("\r");
+    tui.wait_for("PLAYER 1");
+    // The player goes on its own row under the board, rather than wrapping over the board's top border
+    let text = tui.text();
+    let lines: Vec<&str> = text.lines().collect();
+    assert!(lines[2].starts_with('╔'), "{}", text);
+    let bottom = lines.iter().position(|line| line.starts_with('╚')).unwrap();
+    assert_eq!(lines[bottom + 1].trim_end(), "PLAYER 1");
+    assert_eq!(tui.board().len(), 9);
+}
+
+#[test]
+fn keys_pressed_while_waiting_for_a_race_are_played_once_it_starts() {
+    let port = (20_000 + std::process::id() % 20_000).to_string();
+    let mut host = Tui::launch("queue-host", &["host", &port, "beginner"], &[]);
+    host.wait_for("Hosting a race");
+    // Backspace drops what's queued so far, so the cursor stays on the X and the check starts the race
+    host.keys("\x1b[C");
+    host.wait_for("1 key queued");
+    host.keys("\x7f");
+    host.wait_until("the queue to be dropped", |tui| !tui.text().contains("queued"));
+    host.keys("q");
+    host.wait_for("1 key queued");
+    let _guest = Tui::launch("queue-guest", &["join", &format!("127.0.0.1:{}", port)], &[]);
+    host.wait_until("the queued check to open the board", |tui| {
+        tui.board().iter().flatten().any(|space| space == " " || space.parse::<u8>().is_ok())
+    });
+    assert!(!host.text().contains("starts from the X"));
+}
+
+#[test]
+fn losing_shows_every_mine() {
+    let mut tui = Tui::start("loss");
+    let mut board = seeded_board(9, 9, 10, 1, (0, 0));
+    tui.custom_game(9, 9, 10, 1);
+    tui.keys("q");
+    board.reveal(0, 0);
+    tui.wait_for_board(&board);
+    let mines: Vec<(i16, i16)> = (0..9).flat_map(|y| (0..9).map(move |x| (x, y))).filter(|&(x, y)| board.is_mine(x, y)).collect();
+    tui.move_to(mines[0].0, mines[0].1);
+    tui.keys("q");
+    tui.wait_for("Sorry! You lose.");
+    // Mines come up wherever they were, and everything else stays as it was
+    let mut expected = drawn(&board);
+    for &(x, y) in &mines {
+        expected[y as usize][x as usize] = String::from(MINE);
+    }
+    tui.wait_until("every mine to show", |tui| tui.board() == expected);
+}
+
+#[test]
+fn finished_games_are_journaled_until_the_save_is_written() {
+    let mut tui = Tui::start("journal");
+    let mut board = seeded_board(9, 9, 10, 1, (0, 0));
+    tui.custom_game(9, 9, 10, 1);
+    tui.keys("q");
+    board.reveal(0, 0);
+    tui.wait_for_board(&board);
+    let mine = (0..9).flat_map(|y| (0..9).map(move |x| (x, y))).find(|&(x, y)| board.is_mine(x, y)).unwrap();
+    tui.move_to(mine.0, mine.1);
+    tui.keys("q");
+    tui.wait_for("Sorry! You lose.");
+    // The loss is in the journal as soon as the game ends, which can be just after the message shows
+    tui.wait_until("the journal", |tui| tui.data_file("journal.jsonl").exists());
+    let journal = std::fs::read_to_string(tui.data_file("journal.jsonl")).unwrap();
+    assert_eq!(journal.lines().count(), 1);
+    assert!(journal.contains("\"won\":false"));
+    // Leaving the game writes the save, which takes the journal in
+    tui.keys("m");
+    tui.wait_for("Games Played: 1");
+    assert!(!tui.data_file("journal.jsonl").exists());
+    let save = std::fs::read_to_string(tui.data_file("save.json")).unwrap();
+    assert!(save.contains("\"g_played\":1,"));
+}
+
+#[test]
+fn surprise_games_hide_the_board_until_the_first_check() {
+    let mut tui = Tui::start("surprise");
+    tui.keys("5");
+    tui.wait_for("The board is a surprise too");
+    assert!(tui.text().contains("FLAGS LEFT: ?") && tui.board().is_empty());
+    // Moving first doesn't give the edges away, and the check still goes in the middle
+    tui.keys("\x1b[D\x1b[D\x1b[Aq");
+    tui.wait_for("SURPRISE: ");
+    let text = tui.text();
+    let size = text.split("SURPRISE: ").nth(1).and_then(|rest| rest.split(',').next()).unwrap();
+    let (width, height) = size.split_once('x').unwrap();
+    let (width, height): (usize, usize) = (width.parse().unwrap(), height.parse().unwrap());
+    tui.wait_until("the middle to be checked", |tui| {
+        let board = tui.board();
+        board.len() == height && board[height / 2].get(width / 2).is_some_and(|space| space != COVERED)
+    });
+    assert!(tui.board().iter().all(|row| row.len() == width));
+}
+
+#[test]
+#[cfg(feature = "solver")]
+fn flagging_certain_mines_takes_wrong_flags_off_first() {
+    let mut tui = Tui::start("certain");
+    let mut board = seeded_board(9, 9, 10, 1, (0, 0));
+    tui.custom_game(9, 9, 10, 1);
+    tui.keys("q");
+    board.reveal(0, 0);
+    tui.wait_for_board(&board);
+    // Every flag goes down, on safe spaces the numbers already give away where possible
+    let safe = solver::certain_safe(&board);
+    let mines = solver::certain_mines(&board);
+    let covered = (0..9).flat_map(|y| (0..9).map(move |x| (x, y))).filter(|&(x, y)| !board.is_uncovered(x, y));
+    let wrong: Vec<(i16, i16)> = safe.iter().copied().chain(covered.filter(|space| !mines.contains(space) && !safe.contains(space))).take(10).collect();
+    assert!(!safe.is_empty() && !mines.is_empty() && wrong.len() == 10);
+    for &(x, y) in &wrong {
+        tui.move_to(x, y);
+        tui.keys("w");
+        board.flag(x, y);
+        tui.wait_for_board(&board);
+    }
+    tui.keys("f");
+    for &(x, y) in &wrong {
+        if safe.contains(&(x, y)) {
+            board.flag(x, y);
+        }
+    }
+    for &(x, y) in &mines {
+        board.flag(x, y);
+    }
+    assert!(mines.iter().any(|&(x, y)| board.is_flagged(x, y)));
+    tui.wait_for_board(&board);
+}